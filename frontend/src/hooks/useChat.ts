@@ -15,6 +15,7 @@ import type { ChatRequest, Message } from '../types';
  * @returns {Object} Chat state and control functions
  * @returns {Message[]} messages - Array of confirmed chat messages (user and assistant)
  * @returns {string} currentResponse - Accumulated text of the currently streaming assistant response
+ * @returns {string} currentThinking - Reasoning the model streamed for the current response (empty if none)
  * @returns {boolean} isStreaming - Flag indicating if a response is currently being streamed
  * @returns {string | null} errorMessage - User-facing error message for non-auth errors, or null
  * @returns {Function} sendMessage - Function to send a message and start streaming the response
@@ -51,6 +52,9 @@ export function useChat(token: string, onAuthError?: () => void) {
   // Buffer for accumulating streamed assistant response (text events)
   const [currentResponse, setCurrentResponse] = useState('');
 
+  // Buffer for the model's reasoning (thinking events), kept out of the answer
+  const [currentThinking, setCurrentThinking] = useState('');
+
   // Error message to display in the UI (null when no error)
   const [errorMessage, setErrorMessage] = useState<string | null>(null);
 
@@ -66,6 +70,7 @@ export function useChat(token: string, onAuthError?: () => void) {
 
     setIsStreaming(true);
     setCurrentResponse('');
+    setCurrentThinking('');
 
     let accumulatedText = '';
     const request: ChatRequest = {
@@ -81,16 +86,29 @@ export function useChat(token: string, onAuthError?: () => void) {
           accumulatedText += event.content;
           break;
 
+        case 'thinking':
+          setCurrentThinking((prev) => prev + event.content);
+          break;
+
         case 'tool_use':
           setCurrentPhase(event.tool_name);
           break;
 
+        case 'warning':
+          console.warn(event.message);
+          break;
+
+        case 'source':
+          // 出典はサーバー側のセッションに記録される
+          break;
+
         case 'done':
           const assistantMessage: Message = { role: 'assistant', content: accumulatedText, timestamp: Date.now() };
           setMessages((prev) => [...prev, assistantMessage]);
           setSessionId(event.session_id);
           setIsStreaming(false);
           setCurrentResponse('');
+          setCurrentThinking('');
           setCurrentPhase(null);
           break;
 
@@ -118,8 +136,9 @@ export function useChat(token: string, onAuthError?: () => void) {
     abortControllerRef.current?.abort();
     setIsStreaming(false);
     setCurrentResponse('');
+    setCurrentThinking('');
     setCurrentPhase(null);
   };
 
-  return { messages, currentResponse, isStreaming, errorMessage, currentPhase, sendMessage, stopGeneration };
+  return { messages, currentResponse, currentThinking, isStreaming, errorMessage, currentPhase, sendMessage, stopGeneration };
 }
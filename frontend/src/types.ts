@@ -2,15 +2,41 @@
 export interface ChatRequest {
   session_id?: string;
   message: string;
+  provider?: string;
+  model?: string;
+  tools_enabled?: boolean;
+  // 'answer' は text / done / error イベントのみ返す（既定: 'full'）
+  verbosity?: 'answer' | 'full';
+  // メッセージ中のURLを先に取得し、ドキュメントとして添付して回答させる（既定: false）
+  ground?: boolean;
 }
 
+// エラー種別（Rust側のErrorCodeと対応）。rate_limited / provider_unavailable は再試行の余地あり
+export type ErrorCode =
+  | 'rate_limited'
+  | 'provider_unavailable'
+  | 'context_too_long'
+  | 'authentication_failed'
+  | 'session_not_found'
+  | 'unknown';
+
 // SSEイベント型（Rust側のSseEventDataと対応）
 // IMPORTANT: session_id は snake_case（Rust側のserdeがsnake_caseを使用）
 export type SseEvent =
   | { type: 'text'; content: string }
-  | { type: 'done'; session_id: string }
-  | { type: 'error'; message: string }
-  | { type: 'tool_use'; tool_name: string };
+  | { type: 'thinking'; content: string }
+  | { type: 'done'; session_id: string; ttft_ms?: number; truncated?: boolean }
+  | { type: 'error'; message: string; code?: ErrorCode }
+  | { type: 'tool_use'; tool_name: string }
+  | { type: 'warning'; message: string }
+  | { type: 'source'; url: string; title: string | null };
+
+// GET /api/sessions の各要素（Rust側のSessionSummaryと対応）
+export interface SessionSummary {
+  session_id: string;
+  title: string | null;
+  message_count: number;
+}
 
 // UIメッセージ型
 export interface Message {
@@ -1,4 +1,3 @@
-use std::env;
 use std::pin::Pin;
 
 use async_trait::async_trait;
@@ -6,18 +5,35 @@ use futures::Stream;
 use futures::StreamExt;
 use rig::agent::Agent;
 use rig::agent::MultiTurnStreamItem;
-use rig::completion::Message;
+use rig::agent::StreamingError;
+use rig::completion::message::AssistantContent;
+use rig::completion::{
+    Completion, CompletionModel, CompletionRequestBuilder, Document, GetTokenUsage, Message,
+};
+use rig::message::{ReasoningContent, ToolResultContent};
 use rig::providers::gemini;
 use rig::providers::ollama;
+use rig::providers::openai;
 use rig::providers::openai::responses_api::ResponsesCompletionModel;
 use rig::streaming::StreamedAssistantContent;
+use rig::streaming::StreamedUserContent;
 use rig::streaming::StreamingChat;
 
 use rig::completion::Prompt;
+use serde::Serialize;
 
+use super::cite::{citations_from_tool_output, CitedSources};
+use super::continuation::stopped_at_token_limit;
+use super::gemini_safety::explain_safety_block;
+use super::grounding::stream_grounded;
+use super::ollama_pull::{auto_pull_enabled, explain_missing_model};
+use super::structured::{output_schema, parse_structured, StructuredError};
+use super::tool_rounds::{rounds_for_turns, warn_on_tool_limit, ToolRoundLimit};
 use super::{
-    create_gemini_agent, create_ollama_agent, create_openai_agent, default_model, ChatAgent,
-    McpToolSet, WebFetch,
+    compact_for_context, create_gemini_agent, create_gemini_chat_agent, create_ollama_agent,
+    create_ollama_chat_agent, create_openai_agent, create_openai_chat_agent,
+    create_openai_compatible_agent, create_openai_compatible_chat_agent, AgentSettings, ChatAgent,
+    Citation, McpToolSet, ProviderConfig, WebFetch,
 };
 
 /// Provider-agnostic stream event emitted by `AnyAgent::stream_chat`.
@@ -27,8 +43,23 @@ use super::{
 pub enum ChatStreamEvent {
     /// A text fragment from the assistant's response
     TextDelta(String),
+    /// A fragment of the model's reasoning (e.g. qwen3 thinking, o-series
+    /// summaries), streamed before and separate from the response text
+    Thinking(String),
     /// The agent invoked a tool (e.g. web_search, web_fetch)
     ToolCall { name: String },
+    /// Text a tool returned to the agent (e.g. a fetched page as JSON)
+    ToolResult(String),
+    /// A tool fetched a page that can be cited as a source
+    Source(Citation),
+    /// Something the user should know about that didn't stop the response
+    /// (e.g. the tool-round limit was reached)
+    Warning(String),
+    /// Tokens the provider reported for the whole response (sent right before `Done`)
+    Usage { total_tokens: u64 },
+    /// The model stopped at the output token limit, so the answer is cut off
+    /// (sent before `Done`)
+    Truncated,
     /// The stream has completed successfully
     Done,
     /// An error occurred during streaming
@@ -41,27 +72,59 @@ pub enum AnyAgent {
     Ollama(Agent<ollama::CompletionModel>),
     Gemini(Agent<gemini::completion::CompletionModel>),
     OpenAi(Agent<ResponsesCompletionModel>),
+    /// An OpenAI-compatible server, spoken to over Chat Completions
+    OpenAiCompatible(Agent<openai::CompletionModel>),
 }
 
 impl AnyAgent {
-    /// Create an AnyAgent from environment configuration.
-    /// Reads LLM_PROVIDER and LLM_MODEL env vars plus provider-specific API keys.
-    pub fn from_env(web_fetch: WebFetch, mcp_tools: Vec<McpToolSet>) -> Self {
-        let provider = env::var("LLM_PROVIDER").unwrap_or_else(|_| "ollama".to_string());
-        let model = env::var("LLM_MODEL").unwrap_or_else(|_| default_model(&provider).to_string());
-
-        match provider.as_str() {
-            "openai" => {
-                let api_key =
-                    env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY required for OpenAI");
-                Self::OpenAi(create_openai_agent(&api_key, &model, web_fetch, mcp_tools))
+    /// Create an AnyAgent for an already-resolved provider configuration.
+    pub fn from_config(
+        config: ProviderConfig,
+        settings: &AgentSettings,
+        web_fetch: WebFetch,
+        mcp_tools: Vec<McpToolSet>,
+    ) -> Self {
+        let tools = settings.enabled_tools.build(&web_fetch, None);
+        match config {
+            ProviderConfig::OpenAi { api_key, model } => Self::OpenAi(create_openai_agent(
+                &api_key, &model, settings, tools, mcp_tools,
+            )),
+            ProviderConfig::OpenAiCompatible {
+                base_url,
+                api_key,
+                model,
+            } => Self::OpenAiCompatible(create_openai_compatible_agent(
+                &base_url, &api_key, &model, settings, tools, mcp_tools,
+            )),
+            ProviderConfig::Gemini { api_key, model } => Self::Gemini(create_gemini_agent(
+                &api_key, &model, settings, tools, mcp_tools,
+            )),
+            ProviderConfig::Ollama { host, model } => Self::Ollama(create_ollama_agent(
+                &host, &model, settings, tools, mcp_tools,
+            )),
+        }
+    }
+
+    /// Create a tool-less AnyAgent for plain conversation.
+    /// Used for chat requests that disable tools.
+    pub fn chat_only_from_config(config: ProviderConfig, settings: &AgentSettings) -> Self {
+        match config {
+            ProviderConfig::OpenAi { api_key, model } => {
+                Self::OpenAi(create_openai_chat_agent(&api_key, &model, settings))
+            }
+            ProviderConfig::OpenAiCompatible {
+                base_url,
+                api_key,
+                model,
+            } => Self::OpenAiCompatible(create_openai_compatible_chat_agent(
+                &base_url, &api_key, &model, settings,
+            )),
+            ProviderConfig::Gemini { api_key, model } => {
+                Self::Gemini(create_gemini_chat_agent(&api_key, &model, settings))
             }
-            "gemini" => {
-                let api_key =
-                    env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY required for Gemini");
-                Self::Gemini(create_gemini_agent(&api_key, &model, web_fetch, mcp_tools))
+            ProviderConfig::Ollama { host, model } => {
+                Self::Ollama(create_ollama_chat_agent(&host, &model, settings))
             }
-            _ => Self::Ollama(create_ollama_agent(&model, web_fetch, mcp_tools)),
         }
     }
 
@@ -72,7 +135,61 @@ impl AnyAgent {
             AnyAgent::Ollama(agent) => agent.prompt(text).await.map_err(anyhow::Error::from),
             AnyAgent::Gemini(agent) => agent.prompt(text).await.map_err(anyhow::Error::from),
             AnyAgent::OpenAi(agent) => agent.prompt(text).await.map_err(anyhow::Error::from),
+            AnyAgent::OpenAiCompatible(agent) => {
+                agent.prompt(text).await.map_err(anyhow::Error::from)
+            }
+        }
+    }
+
+    /// Ask for a single response as JSON conforming to `schema`, using the
+    /// provider's structured-output mode, and return it parsed.
+    /// The request is one completion; tools the model asks for are not run.
+    ///
+    /// # Errors
+    /// Returns `StructuredError::Validation` if the response doesn't match `schema`.
+    pub async fn complete_structured(
+        &self,
+        prompt: &str,
+        history: Vec<Message>,
+        schema: &serde_json::Value,
+    ) -> Result<serde_json::Value, StructuredError> {
+        let rig_schema = output_schema(schema)?;
+        let text = match self {
+            AnyAgent::Ollama(agent) => {
+                structured_text(structured_request(agent, prompt, history, rig_schema).await).await
+            }
+            AnyAgent::Gemini(agent) => {
+                structured_text(structured_request(agent, prompt, history, rig_schema).await).await
+            }
+            AnyAgent::OpenAi(agent) => {
+                structured_text(structured_request(agent, prompt, history, rig_schema).await).await
+            }
+            AnyAgent::OpenAiCompatible(agent) => {
+                structured_text(structured_request(agent, prompt, history, rig_schema).await).await
+            }
         }
+        .map_err(StructuredError::Completion)?;
+        parse_structured(&text, schema)
+    }
+
+    /// Name of the model this agent sends requests to
+    pub fn model_name(&self) -> &str {
+        match self {
+            AnyAgent::Ollama(agent) => &agent.model.model,
+            AnyAgent::Gemini(agent) => &agent.model.model,
+            AnyAgent::OpenAi(agent) => &agent.model.model,
+            AnyAgent::OpenAiCompatible(agent) => &agent.model.model,
+        }
+    }
+
+    /// Tool-call rounds allowed per response (see `MAX_TOOL_ROUNDS`)
+    fn max_tool_rounds(&self) -> usize {
+        rounds_for_turns(match self {
+            AnyAgent::Ollama(agent) => agent.default_max_turns,
+            AnyAgent::Gemini(agent) => agent.default_max_turns,
+            AnyAgent::OpenAi(agent) => agent.default_max_turns,
+            AnyAgent::OpenAiCompatible(agent) => agent.default_max_turns,
+        })
     }
 
     /// Stream a chat response, converting provider-specific stream items
@@ -86,35 +203,168 @@ impl AnyAgent {
         prompt: &str,
         history: Vec<Message>,
     ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+        let history = compact_for_context(history, prompt, self.model_name());
+        let max_rounds = self.max_tool_rounds();
+        let hook = CitedSources::new(ToolRoundLimit::new(max_rounds));
+        let stream = match self {
+            AnyAgent::Ollama(agent) => explain_missing_model(
+                Self::map_stream(
+                    agent.stream_chat(prompt, history).with_hook(hook).await,
+                    agent.max_tokens,
+                ),
+                &agent.model.model,
+                auto_pull_enabled(),
+            ),
+            AnyAgent::Gemini(agent) => explain_safety_block(Self::map_stream(
+                agent.stream_chat(prompt, history).with_hook(hook).await,
+                agent.max_tokens,
+            )),
+            AnyAgent::OpenAi(agent) => Self::map_stream(
+                agent.stream_chat(prompt, history).with_hook(hook).await,
+                agent.max_tokens,
+            ),
+            AnyAgent::OpenAiCompatible(agent) => Self::map_stream(
+                agent.stream_chat(prompt, history).with_hook(hook).await,
+                agent.max_tokens,
+            ),
+        };
+        warn_on_tool_limit(stream, max_rounds)
+    }
+
+    /// Stream a response to `prompt` with `documents` attached to the request,
+    /// e.g. pages fetched up front. The request carries no tools, so the
+    /// answer comes from the documents and the model's own knowledge.
+    pub async fn chat_with_documents(
+        &self,
+        prompt: &str,
+        history: Vec<Message>,
+        documents: Vec<Document>,
+    ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+        let history = compact_for_context(history, prompt, self.model_name());
         match self {
-            AnyAgent::Ollama(agent) => Self::map_stream(agent.stream_chat(prompt, history).await),
-            AnyAgent::Gemini(agent) => Self::map_stream(agent.stream_chat(prompt, history).await),
-            AnyAgent::OpenAi(agent) => Self::map_stream(agent.stream_chat(prompt, history).await),
+            AnyAgent::Ollama(agent) => explain_missing_model(
+                stream_grounded(agent, prompt, history, documents).await,
+                &agent.model.model,
+                auto_pull_enabled(),
+            ),
+            AnyAgent::Gemini(agent) => {
+                explain_safety_block(stream_grounded(agent, prompt, history, documents).await)
+            }
+            AnyAgent::OpenAi(agent) => stream_grounded(agent, prompt, history, documents).await,
+            AnyAgent::OpenAiCompatible(agent) => {
+                stream_grounded(agent, prompt, history, documents).await
+            }
         }
     }
 
-    fn map_stream<R: Send + 'static>(
+    fn map_stream<R: Serialize + GetTokenUsage + Send + 'static>(
         stream: rig::agent::StreamingResult<R>,
+        max_tokens: Option<u64>,
     ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
-        let mapped = stream.filter_map(|item| async {
-            match item {
-                Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(
-                    text,
-                ))) => Some(ChatStreamEvent::TextDelta(text.text)),
-                Ok(MultiTurnStreamItem::StreamAssistantItem(
-                    StreamedAssistantContent::ToolCall { tool_call, .. },
-                )) => Some(ChatStreamEvent::ToolCall {
-                    name: tool_call.function.name,
-                }),
-                Ok(MultiTurnStreamItem::FinalResponse(_)) => Some(ChatStreamEvent::Done),
-                Err(e) => Some(ChatStreamEvent::Error(e.to_string())),
-                _ => None,
-            }
-        });
+        let mapped =
+            stream.flat_map(move |item| futures::stream::iter(map_stream_item(item, max_tokens)));
         Box::pin(mapped)
     }
 }
 
+/// Build a completion request for `agent` that asks for output matching `schema`
+async fn structured_request<M: CompletionModel>(
+    agent: &Agent<M>,
+    prompt: &str,
+    history: Vec<Message>,
+    schema: schemars::Schema,
+) -> anyhow::Result<CompletionRequestBuilder<M>> {
+    Ok(agent
+        .completion(prompt, history)
+        .await?
+        .output_schema(schema))
+}
+
+/// Send a structured request and join the text parts of the response
+async fn structured_text<M: CompletionModel>(
+    request: anyhow::Result<CompletionRequestBuilder<M>>,
+) -> anyhow::Result<String> {
+    let response = request?.send().await?;
+    Ok(response
+        .choice
+        .iter()
+        .filter_map(|c| match c {
+            AssistantContent::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(""))
+}
+
+/// Convert one rig multi-turn stream item into zero or more `ChatStreamEvent`s.
+/// Shared by `AnyAgent` and `RouterAgent`.
+/// `max_tokens` is the agent's output token limit, used to tell whether a turn was cut off.
+pub(crate) fn map_stream_item<R: Serialize + GetTokenUsage>(
+    item: Result<MultiTurnStreamItem<R>, StreamingError>,
+    max_tokens: Option<u64>,
+) -> Vec<ChatStreamEvent> {
+    match item {
+        Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text))) => {
+            vec![ChatStreamEvent::TextDelta(text.text)]
+        }
+        Ok(MultiTurnStreamItem::StreamAssistantItem(
+            StreamedAssistantContent::ReasoningDelta { reasoning, .. },
+        )) => vec![ChatStreamEvent::Thinking(reasoning)],
+        // Summaries were already streamed as deltas and encrypted blocks aren't
+        // readable, so only plain reasoning text is new (e.g. Gemini's signed last chunk)
+        Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Reasoning(
+            reasoning,
+        ))) => reasoning
+            .content
+            .into_iter()
+            .filter_map(|content| match content {
+                ReasoningContent::Text { text, .. } => Some(ChatStreamEvent::Thinking(text)),
+                _ => None,
+            })
+            .collect(),
+        Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall {
+            tool_call,
+            ..
+        })) => vec![ChatStreamEvent::ToolCall {
+            name: tool_call.function.name,
+        }],
+        Ok(MultiTurnStreamItem::StreamUserItem(StreamedUserContent::ToolResult {
+            tool_result,
+            ..
+        })) => {
+            let texts: Vec<&str> = tool_result
+                .content
+                .iter()
+                .filter_map(|c| match c {
+                    ToolResultContent::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let mut events = vec![ChatStreamEvent::ToolResult(texts.join("\n"))];
+            events.extend(
+                texts
+                    .into_iter()
+                    .flat_map(citations_from_tool_output)
+                    .map(ChatStreamEvent::Source),
+            );
+            events
+        }
+        Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Final(response)))
+            if stopped_at_token_limit(&response, max_tokens) =>
+        {
+            vec![ChatStreamEvent::Truncated]
+        }
+        Ok(MultiTurnStreamItem::FinalResponse(response)) => vec![
+            ChatStreamEvent::Usage {
+                total_tokens: response.usage().total_tokens,
+            },
+            ChatStreamEvent::Done,
+        ],
+        Err(e) => vec![ChatStreamEvent::Error(e.to_string())],
+        _ => vec![],
+    }
+}
+
 #[async_trait]
 impl ChatAgent for AnyAgent {
     async fn stream_chat(
@@ -124,18 +374,33 @@ impl ChatAgent for AnyAgent {
     ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
         self.stream_chat_inner(prompt, history).await
     }
+
+    async fn stream_chat_with_documents(
+        &self,
+        prompt: &str,
+        history: Vec<Message>,
+        documents: Vec<Document>,
+    ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+        self.chat_with_documents(prompt, history, documents).await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::agent::ChatAgent;
+    use crate::agent::{ChatAgent, DEFAULT_OLLAMA_HOST};
+    use rig::message::Reasoning;
 
     #[tokio::test]
     #[ignore]
     async fn test_stream_chat_response() {
         let web_fetch = WebFetch::new();
-        let agent = AnyAgent::from_env(web_fetch, vec![]);
+        let agent = AnyAgent::from_config(
+            ProviderConfig::from_env().unwrap(),
+            &AgentSettings::default(),
+            web_fetch,
+            vec![],
+        );
 
         let mut stream = agent.stream_chat("hello", vec![]).await;
 
@@ -148,7 +413,13 @@ mod tests {
                     println!("{}", text);
                     got_text = true;
                 }
-                ChatStreamEvent::ToolCall { .. } => {}
+                ChatStreamEvent::Thinking(_)
+                | ChatStreamEvent::ToolCall { .. }
+                | ChatStreamEvent::ToolResult(_)
+                | ChatStreamEvent::Source(_)
+                | ChatStreamEvent::Warning(_)
+                | ChatStreamEvent::Usage { .. }
+                | ChatStreamEvent::Truncated => {}
                 ChatStreamEvent::Done => {
                     got_done = true;
                 }
@@ -161,4 +432,117 @@ mod tests {
         assert!(got_text, "Should have received at least one text delta");
         assert!(got_done, "Should have received Done event");
     }
+
+    fn assistant_item(content: StreamedAssistantContent<()>) -> Vec<ChatStreamEvent> {
+        map_stream_item(Ok(MultiTurnStreamItem::StreamAssistantItem(content)), None)
+    }
+
+    #[test]
+    fn test_reasoning_delta_maps_to_thinking() {
+        let events = assistant_item(StreamedAssistantContent::ReasoningDelta {
+            id: None,
+            reasoning: "The user wants".to_string(),
+        });
+
+        assert!(
+            matches!(events.as_slice(), [ChatStreamEvent::Thinking(t)] if t == "The user wants")
+        );
+    }
+
+    #[test]
+    fn test_reasoning_item_maps_text_but_not_summaries() {
+        let text = assistant_item(StreamedAssistantContent::Reasoning(Reasoning::new(
+            "so the answer is 4",
+        )));
+        // Summaries arrive as deltas first; mapping them again would repeat them
+        let summary = assistant_item(StreamedAssistantContent::Reasoning(Reasoning::summaries(
+            vec!["Adding numbers".to_string()],
+        )));
+        let encrypted = assistant_item(StreamedAssistantContent::Reasoning(Reasoning::encrypted(
+            "opaque",
+        )));
+
+        assert!(
+            matches!(text.as_slice(), [ChatStreamEvent::Thinking(t)] if t == "so the answer is 4")
+        );
+        assert!(summary.is_empty());
+        assert!(encrypted.is_empty());
+    }
+
+    #[test]
+    fn test_final_response_at_token_limit_maps_to_truncated() {
+        let final_item = || {
+            Ok(MultiTurnStreamItem::StreamAssistantItem(
+                StreamedAssistantContent::Final(ollama::StreamingCompletionResponse {
+                    done_reason: Some("length".to_string()),
+                    total_duration: None,
+                    load_duration: None,
+                    prompt_eval_count: None,
+                    prompt_eval_duration: None,
+                    eval_count: Some(256),
+                    eval_duration: None,
+                }),
+            ))
+        };
+
+        assert!(matches!(
+            map_stream_item(final_item(), None).as_slice(),
+            [ChatStreamEvent::Truncated]
+        ));
+        // A turn that finished normally maps to nothing
+        assert!(assistant_item(StreamedAssistantContent::Final(())).is_empty());
+    }
+
+    fn city_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "city": { "type": "string" },
+                "population": { "type": "integer" }
+            },
+            "required": ["city", "population"]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_structured_request_sets_output_schema() {
+        let agent =
+            create_ollama_chat_agent(DEFAULT_OLLAMA_HOST, "qwen3", &AgentSettings::default());
+
+        let request = structured_request(
+            &agent,
+            "Largest city in Japan?",
+            vec![],
+            output_schema(&city_schema()).unwrap(),
+        )
+        .await
+        .unwrap()
+        .build();
+
+        let schema = request.output_schema.expect("output schema is set");
+        assert_eq!(schema.as_value(), &city_schema());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_complete_structured_returns_conforming_json() {
+        let agent = AnyAgent::from_config(
+            ProviderConfig::from_env().unwrap(),
+            &AgentSettings::default(),
+            WebFetch::new(),
+            vec![],
+        );
+
+        let value = agent
+            .complete_structured(
+                "What is the largest city in Japan and its population?",
+                vec![],
+                &city_schema(),
+            )
+            .await
+            .unwrap();
+
+        assert!(value["city"].is_string());
+        assert!(value["population"].is_i64());
+    }
 }
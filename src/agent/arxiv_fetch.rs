@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use log::info;
+use rig::completion::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::cite::Citation;
+use super::content_filter::ContentFilter;
+use super::WebFetch;
+use crate::collectors::arxiv::{fetch_arxiv_paper, ArxivPaper};
+use crate::collectors::web::ReqwestClient;
+use crate::metrics::Metrics;
+
+/// Arguments for the ArxivFetch tool
+#[derive(Deserialize)]
+pub struct ArxivFetchArgs {
+    /// arXiv ID (e.g. "2301.07041") or arxiv.org abs/pdf URL
+    id: String,
+}
+
+/// Output from the ArxivFetch tool
+#[derive(Debug, Serialize)]
+pub struct ArxivFetchOutput {
+    id: String,
+    title: String,
+    authors: Vec<String>,
+    #[serde(rename = "abstract")]
+    summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<String>,
+    pdf_url: String,
+    citation: Citation,
+}
+
+impl ArxivFetchOutput {
+    fn from_paper(paper: ArxivPaper, citation: Citation) -> Self {
+        Self {
+            id: paper.id,
+            title: paper.title,
+            authors: paper.authors,
+            summary: paper.summary,
+            published: paper.published,
+            pdf_url: paper.pdf_url,
+            citation,
+        }
+    }
+}
+
+/// Error type for ArxivFetch tool
+#[derive(Debug, thiserror::Error)]
+pub enum ArxivFetchError {
+    #[error("Failed to fetch arXiv paper: {0}")]
+    FetchError(#[from] anyhow::Error),
+}
+
+/// Fetches an arXiv paper's title, authors, abstract and PDF link via the arXiv API.
+/// Shares the HTTP client, metrics and content filter with the `WebFetch` it was created from.
+#[derive(Clone)]
+pub struct ArxivFetch {
+    http: ReqwestClient,
+    metrics: Arc<Metrics>,
+    content_filter: ContentFilter,
+}
+
+impl ArxivFetch {
+    pub fn new(web_fetch: &WebFetch) -> Self {
+        Self {
+            http: web_fetch.http.clone(),
+            metrics: web_fetch.metrics.clone(),
+            content_filter: web_fetch.content_filter.clone(),
+        }
+    }
+}
+
+impl rig::tool::Tool for ArxivFetch {
+    const NAME: &'static str = "arxiv_fetch";
+    type Error = ArxivFetchError;
+    type Args = ArxivFetchArgs;
+    type Output = ArxivFetchOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Fetches an arXiv paper's title, authors, abstract and PDF link. \
+                          Prefer this over web_fetch for arXiv papers"
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "id": {
+                        "type": "string",
+                        "description": "The arXiv ID (e.g. 2301.07041) or an arxiv.org abs/pdf URL"
+                    }
+                },
+                "required": ["id"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        info!("Fetching arXiv paper {} ...", args.id);
+        // A paper refused by the content filter counts as a failed fetch
+        let paper = fetch_arxiv_paper(&self.http, &args.id)
+            .await
+            .and_then(|mut paper| {
+                self.content_filter.apply_all(
+                    [&mut paper.title, &mut paper.summary]
+                        .into_iter()
+                        .chain(paper.authors.iter_mut()),
+                )?;
+                Ok(paper)
+            });
+        self.metrics.record_fetch(paper.as_ref().err());
+        let paper = paper?;
+        let citation = Citation::now(&paper.abs_url, Some(paper.title.clone()));
+        Ok(ArxivFetchOutput::from_paper(paper, citation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arxiv_fetch_args_deserialize() {
+        let json = r#"{"id": "1706.03762"}"#;
+        let args: ArxivFetchArgs = serde_json::from_str(json).unwrap();
+        assert_eq!(args.id, "1706.03762");
+    }
+
+    #[test]
+    fn test_arxiv_fetch_output_from_paper() {
+        let paper = ArxivPaper {
+            id: "1706.03762v7".to_string(),
+            title: "Attention Is All You Need".to_string(),
+            authors: vec!["Ashish Vaswani".to_string()],
+            summary: "The dominant sequence transduction models ...".to_string(),
+            published: None,
+            abs_url: "http://arxiv.org/abs/1706.03762v7".to_string(),
+            pdf_url: "http://arxiv.org/pdf/1706.03762v7".to_string(),
+        };
+        let citation = Citation::now(&paper.abs_url, Some(paper.title.clone()));
+
+        let value = serde_json::to_value(ArxivFetchOutput::from_paper(paper, citation)).unwrap();
+
+        assert_eq!(value["title"], "Attention Is All You Need");
+        assert_eq!(value["authors"][0], "Ashish Vaswani");
+        assert_eq!(
+            value["abstract"],
+            "The dominant sequence transduction models ..."
+        );
+        assert_eq!(value["pdf_url"], "http://arxiv.org/pdf/1706.03762v7");
+        assert!(value.get("published").is_none());
+    }
+}
@@ -1,33 +1,146 @@
-use rig::agent::Agent;
+use rig::agent::{Agent, AgentBuilder, PromptHook};
 use rig::client::{CompletionClient, Nothing};
+use rig::completion::CompletionModel;
 use rig::providers::openai::responses_api::ResponsesCompletionModel;
 use rig::providers::{gemini, ollama, openai};
+use rig::tool::ToolDyn;
 
+use super::gemini_safety::GeminiSafety;
 use super::mcp::McpToolSet;
-use super::research_tool::ResearchTool;
-use super::{PdfRead, WebFetch, WebSearch};
+use super::response_style::ResponseStyle;
+use super::tool_rounds::max_turns;
+use super::{AgentParams, AgentSettings};
+
+/// Name shown in the CLI banner and `/health` when `ASSISTANT_NAME` is unset
+pub const DEFAULT_ASSISTANT_NAME: &str = "Copal";
+
+/// Placeholder in the preamble templates for how the assistant introduces itself
+const ASSISTANT_PLACEHOLDER: &str = "{assistant}";
+
+/// Fill the placeholder in `template` with `role` (e.g. "a research assistant"),
+/// introduced by `name` when one is configured: "Acme, a research assistant"
+fn render_preamble(template: &str, role: &str, name: Option<&str>) -> String {
+    let identity = match name {
+        Some(name) => format!("{name}, {role}"),
+        None => role.to_string(),
+    };
+    template.replace(ASSISTANT_PLACEHOLDER, &identity)
+}
+
+/// `preamble` followed by `style`'s formatting instruction (none for prose)
+fn with_response_style(preamble: String, style: ResponseStyle) -> String {
+    preamble + style.preamble_suffix()
+}
+
+const PREAMBLE: &str = "\
+You are {assistant} that helps users gather and summarize information from the web.\n\
+\n\
+Always cite the pages your answer relies on. Fetched pages include a citation \
+(url, title, fetched_at); call the cite tool with the URLs you used to list your sources.";
+
+fn research_preamble(settings: &AgentSettings) -> String {
+    let preamble = render_preamble(
+        PREAMBLE,
+        "a research assistant",
+        settings.assistant_name.as_deref(),
+    );
+    with_response_style(preamble, settings.response_style)
+}
+
+/// Apply the sampling settings that rig's builder supports directly, plus
+/// `extra` request fields for the rest (their names differ per provider).
+fn with_params<M, P, T>(
+    builder: AgentBuilder<M, P, T>,
+    params: &AgentParams,
+    extra: serde_json::Value,
+) -> AgentBuilder<M, P, T>
+where
+    M: CompletionModel,
+    P: PromptHook<M>,
+{
+    let mut builder = builder;
+    if let Some(temperature) = params.temperature {
+        builder = builder.temperature(temperature);
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        builder = builder.max_tokens(max_tokens);
+    }
+    if extra.as_object().is_some_and(|fields| !fields.is_empty()) {
+        builder = builder.additional_params(extra);
+    }
+    builder
+}
+
+/// Ollama takes sampling settings as model `options`; rig merges extra fields into them
+fn ollama_params(params: &AgentParams) -> serde_json::Value {
+    let mut options = serde_json::Map::new();
+    if let Some(top_p) = params.top_p {
+        options.insert("top_p".into(), top_p.into());
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        options.insert("num_predict".into(), max_tokens.into());
+    }
+    options.into()
+}
+
+/// Gemini only honours sampling settings inside `generationConfig`;
+/// safety thresholds go in a top-level `safetySettings`
+fn gemini_params(params: &AgentParams, safety: &GeminiSafety) -> serde_json::Value {
+    let mut fields = serde_json::Map::new();
+    if !safety.is_empty() {
+        fields.insert("safetySettings".into(), safety.to_json());
+    }
+    let mut config = serde_json::Map::new();
+    if let Some(temperature) = params.temperature {
+        config.insert("temperature".into(), temperature.into());
+    }
+    if let Some(top_p) = params.top_p {
+        config.insert("topP".into(), top_p.into());
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        config.insert("maxOutputTokens".into(), max_tokens.into());
+    }
+    if !config.is_empty() {
+        fields.insert("generationConfig".into(), config.into());
+    }
+    fields.into()
+}
 
-const PREAMBLE: &str =
-    "You are a research assistant that helps users gather and summarize information from the web";
+/// The OpenAI Responses API takes `top_p` at the top level
+fn openai_params(params: &AgentParams) -> serde_json::Value {
+    match params.top_p {
+        Some(top_p) => serde_json::json!({ "top_p": top_p }),
+        None => serde_json::json!({}),
+    }
+}
+
+/// Create an Ollama client for the server at `host` (see `OLLAMA_HOST`)
+fn ollama_client(host: &str) -> ollama::Client {
+    ollama::Client::builder()
+        .api_key(Nothing)
+        .base_url(host)
+        .build()
+        .expect("Failed to create Ollama client")
+}
 
 /// Create an Ollama-based research agent
 pub fn create_ollama_agent(
+    host: &str,
     model: &str,
-    web_fetch: WebFetch,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
     mcp_tools: Vec<McpToolSet>,
 ) -> Agent<ollama::CompletionModel> {
-    let client = ollama::Client::builder()
-        .api_key(Nothing)
-        .build()
-        .expect("Failed to create Ollama client");
+    let client = ollama_client(host);
 
-    let mut builder = client
-        .agent(model)
-        .preamble(PREAMBLE)
-        .default_max_turns(10)
-        .tool(web_fetch)
-        .tool(WebSearch)
-        .tool(PdfRead);
+    let mut builder = with_params(
+        client.agent(model),
+        &settings.params,
+        ollama_params(&settings.params),
+    )
+    .preamble(&research_preamble(settings))
+    .default_max_turns(max_turns(settings.max_tool_rounds))
+    .tools(tools);
 
     for mcp in mcp_tools {
         builder = builder.rmcp_tools(mcp.tools, mcp.sink);
@@ -40,18 +153,20 @@ pub fn create_ollama_agent(
 pub fn create_gemini_agent(
     api_key: &str,
     model: &str,
-    web_fetch: WebFetch,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
     mcp_tools: Vec<McpToolSet>,
 ) -> Agent<gemini::completion::CompletionModel> {
     let client = gemini::Client::new(api_key).expect("Failed to create Gemini client");
 
-    let mut builder = client
-        .agent(model)
-        .preamble(PREAMBLE)
-        .default_max_turns(10)
-        .tool(web_fetch)
-        .tool(WebSearch)
-        .tool(PdfRead);
+    let mut builder = with_params(
+        client.agent(model),
+        &settings.params,
+        gemini_params(&settings.params, &settings.gemini_safety),
+    )
+    .preamble(&research_preamble(settings))
+    .default_max_turns(max_turns(settings.max_tool_rounds))
+    .tools(tools);
 
     for mcp in mcp_tools {
         builder = builder.rmcp_tools(mcp.tools, mcp.sink);
@@ -60,23 +175,71 @@ pub fn create_gemini_agent(
     builder.build()
 }
 
+/// OpenAI client, sending requests to `base_url` instead of api.openai.com when given
+pub(crate) fn openai_client(
+    api_key: &str,
+    base_url: Option<&str>,
+) -> rig::http_client::Result<openai::Client> {
+    let builder = openai::Client::builder().api_key(api_key);
+    match base_url {
+        Some(base_url) => builder.base_url(base_url),
+        None => builder,
+    }
+    .build()
+}
+
+/// Client for an OpenAI-compatible server at `base_url` (vLLM, LM Studio, ...).
+/// Such servers implement Chat Completions (`/chat/completions`) rather than
+/// the Responses API that `openai_client` talks to.
+pub(crate) fn openai_compatible_client(
+    api_key: &str,
+    base_url: &str,
+) -> rig::http_client::Result<openai::CompletionsClient> {
+    Ok(openai_client(api_key, Some(base_url))?.completions_api())
+}
+
 /// Create an OpenAI-based research agent
 pub fn create_openai_agent(
     api_key: &str,
     model: &str,
-    web_fetch: WebFetch,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
     mcp_tools: Vec<McpToolSet>,
 ) -> Agent<ResponsesCompletionModel> {
-    let client: rig::client::Client<openai::OpenAIResponsesExt> =
-        openai::Client::new(api_key).expect("Failed to create OpenAI client");
+    let client = openai_client(api_key, None).expect("Failed to create OpenAI client");
+    openai_research_agent(&client, model, settings, tools, mcp_tools)
+}
 
-    let mut builder = client
-        .agent(model)
-        .preamble(PREAMBLE)
-        .default_max_turns(10)
-        .tool(web_fetch)
-        .tool(WebSearch)
-        .tool(PdfRead);
+/// Create a research agent for an OpenAI-compatible server at `base_url`
+/// (vLLM, LM Studio, ...), built like the OpenAI one
+pub fn create_openai_compatible_agent(
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
+    mcp_tools: Vec<McpToolSet>,
+) -> Agent<openai::CompletionModel> {
+    let client = openai_compatible_client(api_key, base_url)
+        .expect("Failed to create OpenAI-compatible client");
+    openai_research_agent(&client, model, settings, tools, mcp_tools)
+}
+
+fn openai_research_agent<C: CompletionClient>(
+    client: &C,
+    model: &str,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
+    mcp_tools: Vec<McpToolSet>,
+) -> Agent<C::CompletionModel> {
+    let mut builder = with_params(
+        client.agent(model),
+        &settings.params,
+        openai_params(&settings.params),
+    )
+    .preamble(&research_preamble(settings))
+    .default_max_turns(max_turns(settings.max_tool_rounds))
+    .tools(tools);
 
     for mcp in mcp_tools {
         builder = builder.rmcp_tools(mcp.tools, mcp.sink);
@@ -85,17 +248,107 @@ pub fn create_openai_agent(
     builder.build()
 }
 
+const CHAT_PREAMBLE: &str = "\
+You are {assistant}. You have no web access in this conversation, so answer \
+from your own knowledge and say so when you are unsure or the answer may be outdated.";
+
+fn chat_preamble(settings: &AgentSettings) -> String {
+    let preamble = render_preamble(
+        CHAT_PREAMBLE,
+        "a helpful assistant",
+        settings.assistant_name.as_deref(),
+    );
+    with_response_style(preamble, settings.response_style)
+}
+
+/// Create an Ollama-based agent without any tools (plain conversation)
+pub fn create_ollama_chat_agent(
+    host: &str,
+    model: &str,
+    settings: &AgentSettings,
+) -> Agent<ollama::CompletionModel> {
+    let client = ollama_client(host);
+
+    with_params(
+        client.agent(model),
+        &settings.params,
+        ollama_params(&settings.params),
+    )
+    .preamble(&chat_preamble(settings))
+    .build()
+}
+
+/// Create a Gemini-based agent without any tools (plain conversation)
+pub fn create_gemini_chat_agent(
+    api_key: &str,
+    model: &str,
+    settings: &AgentSettings,
+) -> Agent<gemini::completion::CompletionModel> {
+    let client = gemini::Client::new(api_key).expect("Failed to create Gemini client");
+
+    with_params(
+        client.agent(model),
+        &settings.params,
+        gemini_params(&settings.params, &settings.gemini_safety),
+    )
+    .preamble(&chat_preamble(settings))
+    .build()
+}
+
+/// Create an OpenAI-based agent without any tools (plain conversation)
+pub fn create_openai_chat_agent(
+    api_key: &str,
+    model: &str,
+    settings: &AgentSettings,
+) -> Agent<ResponsesCompletionModel> {
+    let client = openai_client(api_key, None).expect("Failed to create OpenAI client");
+    openai_chat_agent(&client, model, settings)
+}
+
+/// Create an agent without any tools for an OpenAI-compatible server at `base_url`
+pub fn create_openai_compatible_chat_agent(
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    settings: &AgentSettings,
+) -> Agent<openai::CompletionModel> {
+    let client = openai_compatible_client(api_key, base_url)
+        .expect("Failed to create OpenAI-compatible client");
+    openai_chat_agent(&client, model, settings)
+}
+
+fn openai_chat_agent<C: CompletionClient>(
+    client: &C,
+    model: &str,
+    settings: &AgentSettings,
+) -> Agent<C::CompletionModel> {
+    with_params(
+        client.agent(model),
+        &settings.params,
+        openai_params(&settings.params),
+    )
+    .preamble(&chat_preamble(settings))
+    .build()
+}
+
 /// System prompt that defines the RouterAgent's tool-selection strategy.
 ///
-/// The router sees all tools (research_tool, web_search, web_fetch, pdf_read)
+/// The router sees all tools (research_tool, web_search, web_fetch, web_fetch_many,
+/// sitemap_fetch, feed_fetch, wikipedia_fetch, arxiv_fetch, cite, pdf_read)
 /// and must choose the right one based on the user's intent:
 /// - Deep investigation → research_tool
 /// - Quick lookup      → web_search
 /// - Specific URL      → web_fetch
+/// - Several URLs      → web_fetch_many
+/// - Site discovery    → sitemap_fetch
+/// - News/blog feed    → feed_fetch
+/// - Wikipedia article → wikipedia_fetch
+/// - arXiv paper       → arxiv_fetch
+/// - Listing sources   → cite
 /// - PDF document      → pdf_read
 /// - General chat      → no tool
 const ROUTER_PREAMBLE: &str = "\
-You are an intelligent assistant that routes user requests to the most appropriate tool.\n\
+You are {assistant} that routes user requests to the most appropriate tool.\n\
 \n\
 Available tools and when to use them:\n\
 - research_tool: Use for in-depth research requiring multiple sources. \
@@ -105,33 +358,49 @@ Available tools and when to use them:\n\
   that don't require reading full pages.\n\
 - web_fetch: Use when the user provides a specific URL to read or when you need \
   to retrieve a known page.\n\
+- web_fetch_many: Use instead of web_fetch when several known URLs need to be read; \
+  they are fetched in parallel.\n\
+- sitemap_fetch: Use to discover which pages exist on a website before fetching them.\n\
+- feed_fetch: Use to read the latest entries of an RSS/Atom news or blog feed URL.\n\
+- wikipedia_fetch: Use instead of web_fetch for Wikipedia article URLs; returns clean article text.\n\
+- arxiv_fetch: Use for arXiv papers (ID or arxiv.org URL); returns title, authors, abstract and PDF link.\n\
+- cite: Use after fetching pages to list the sources (url, title, fetched_at) your answer relies on.\n\
 - pdf_read: Use when the user provides a path to a PDF file to read.\n\
 - Additional MCP tools may be available depending on configuration. \
   Use them when they match the user's request more precisely than the built-in tools above.\n\
 \n\
 For general conversation, questions you can answer from your knowledge, or simple \
-clarifications — respond directly without using any tool.";
+clarifications — respond directly without using any tool.\n\
+\n\
+When your answer uses fetched pages, cite their URLs.";
+
+fn router_preamble(settings: &AgentSettings) -> String {
+    let preamble = render_preamble(
+        ROUTER_PREAMBLE,
+        "an intelligent assistant",
+        settings.assistant_name.as_deref(),
+    );
+    with_response_style(preamble, settings.response_style)
+}
 
 /// Create an Ollama-based router agent with all routing tools
 pub fn create_ollama_router_agent(
+    host: &str,
     model: &str,
-    research_tool: ResearchTool,
-    web_fetch: WebFetch,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
     mcp_tools: Vec<McpToolSet>,
 ) -> Agent<ollama::CompletionModel> {
-    let client = ollama::Client::builder()
-        .api_key(Nothing)
-        .build()
-        .expect("Failed to create Ollama client");
+    let client = ollama_client(host);
 
-    let mut builder = client
-        .agent(model)
-        .preamble(ROUTER_PREAMBLE)
-        .default_max_turns(10)
-        .tool(research_tool)
-        .tool(web_fetch)
-        .tool(WebSearch)
-        .tool(PdfRead);
+    let mut builder = with_params(
+        client.agent(model),
+        &settings.params,
+        ollama_params(&settings.params),
+    )
+    .preamble(&router_preamble(settings))
+    .default_max_turns(max_turns(settings.max_tool_rounds))
+    .tools(tools);
 
     for mcp in mcp_tools {
         builder = builder.rmcp_tools(mcp.tools, mcp.sink);
@@ -144,20 +413,20 @@ pub fn create_ollama_router_agent(
 pub fn create_gemini_router_agent(
     api_key: &str,
     model: &str,
-    research_tool: ResearchTool,
-    web_fetch: WebFetch,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
     mcp_tools: Vec<McpToolSet>,
 ) -> Agent<gemini::completion::CompletionModel> {
     let client = gemini::Client::new(api_key).expect("Failed to create Gemini client");
 
-    let mut builder = client
-        .agent(model)
-        .preamble(ROUTER_PREAMBLE)
-        .default_max_turns(10)
-        .tool(research_tool)
-        .tool(web_fetch)
-        .tool(WebSearch)
-        .tool(PdfRead);
+    let mut builder = with_params(
+        client.agent(model),
+        &settings.params,
+        gemini_params(&settings.params, &settings.gemini_safety),
+    )
+    .preamble(&router_preamble(settings))
+    .default_max_turns(max_turns(settings.max_tool_rounds))
+    .tools(tools);
 
     for mcp in mcp_tools {
         builder = builder.rmcp_tools(mcp.tools, mcp.sink);
@@ -170,21 +439,43 @@ pub fn create_gemini_router_agent(
 pub fn create_openai_router_agent(
     api_key: &str,
     model: &str,
-    research_tool: ResearchTool,
-    web_fetch: WebFetch,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
     mcp_tools: Vec<McpToolSet>,
 ) -> Agent<ResponsesCompletionModel> {
-    let client: rig::client::Client<openai::OpenAIResponsesExt> =
-        openai::Client::new(api_key).expect("Failed to create OpenAI client");
-
-    let mut builder = client
-        .agent(model)
-        .preamble(ROUTER_PREAMBLE)
-        .default_max_turns(10)
-        .tool(research_tool)
-        .tool(web_fetch)
-        .tool(WebSearch)
-        .tool(PdfRead);
+    let client = openai_client(api_key, None).expect("Failed to create OpenAI client");
+    openai_router_agent(&client, model, settings, tools, mcp_tools)
+}
+
+/// Create a router agent with all routing tools for an OpenAI-compatible server at `base_url`
+pub fn create_openai_compatible_router_agent(
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
+    mcp_tools: Vec<McpToolSet>,
+) -> Agent<openai::CompletionModel> {
+    let client = openai_compatible_client(api_key, base_url)
+        .expect("Failed to create OpenAI-compatible client");
+    openai_router_agent(&client, model, settings, tools, mcp_tools)
+}
+
+fn openai_router_agent<C: CompletionClient>(
+    client: &C,
+    model: &str,
+    settings: &AgentSettings,
+    tools: Vec<Box<dyn ToolDyn>>,
+    mcp_tools: Vec<McpToolSet>,
+) -> Agent<C::CompletionModel> {
+    let mut builder = with_params(
+        client.agent(model),
+        &settings.params,
+        openai_params(&settings.params),
+    )
+    .preamble(&router_preamble(settings))
+    .default_max_turns(max_turns(settings.max_tool_rounds))
+    .tools(tools);
 
     for mcp in mcp_tools {
         builder = builder.rmcp_tools(mcp.tools, mcp.sink);
@@ -193,22 +484,106 @@ pub fn create_openai_router_agent(
     builder.build()
 }
 
-/// Get the default model name for a given provider
+/// Get the default model name for a given provider.
+/// An unknown provider gets Ollama's default model.
 pub fn default_model(provider: &str) -> &'static str {
+    known_default_model(provider).unwrap_or(OLLAMA_DEFAULT_MODEL)
+}
+
+/// Model used for Ollama when `LLM_MODEL` is unset
+const OLLAMA_DEFAULT_MODEL: &str = "qwen3";
+
+fn known_default_model(provider: &str) -> Option<&'static str> {
     match provider {
-        "gemini" => gemini::completion::GEMINI_2_5_FLASH,
-        "openai" => openai::completion::GPT_4_1_MINI,
-        _ => "qwen3",
+        "gemini" => Some(gemini::completion::GEMINI_2_5_FLASH),
+        "openai" => Some(openai::completion::GPT_4_1_MINI),
+        "ollama" => Some(OLLAMA_DEFAULT_MODEL),
+        _ => None,
+    }
+}
+
+/// Check whether `model` looks like a model served by `provider`.
+///
+/// Only a lightweight prefix check: Gemini models start with `gemini-`/`gemma-`,
+/// OpenAI models with `gpt-`, `chatgpt-` or `o<digit>` (e.g. `o3-mini`).
+/// Ollama serves arbitrary local model names, so any model is accepted.
+pub fn model_matches_provider(provider: &str, model: &str) -> bool {
+    let model = model.to_ascii_lowercase();
+    match provider {
+        "gemini" => model.starts_with("gemini-") || model.starts_with("gemma-"),
+        "openai" => {
+            model.starts_with("gpt-")
+                || model.starts_with("chatgpt-")
+                || (model.starts_with('o') && model[1..].starts_with(|c: char| c.is_ascii_digit()))
+        }
+        _ => true,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent::{EnabledTools, WebFetch, DEFAULT_OLLAMA_HOST};
     use dotenvy::dotenv;
     use rig::completion::Prompt;
     use rig::providers::{gemini, openai};
 
+    #[test]
+    fn test_render_preamble_keeps_default_without_name() {
+        let preamble = render_preamble(PREAMBLE, "a research assistant", None);
+
+        assert!(preamble.starts_with(
+            "You are a research assistant that helps users gather and summarize information"
+        ));
+        assert!(!preamble.contains(ASSISTANT_PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_render_preamble_substitutes_name() {
+        let research = render_preamble(PREAMBLE, "a research assistant", Some("Acme Scout"));
+        let chat = render_preamble(CHAT_PREAMBLE, "a helpful assistant", Some("Acme Scout"));
+        let router = render_preamble(
+            ROUTER_PREAMBLE,
+            "an intelligent assistant",
+            Some("Acme Scout"),
+        );
+
+        assert!(research.starts_with("You are Acme Scout, a research assistant that helps"));
+        assert!(chat.starts_with("You are Acme Scout, a helpful assistant. You have"));
+        assert!(router.starts_with("You are Acme Scout, an intelligent assistant that routes"));
+    }
+
+    #[test]
+    fn test_response_style_is_appended_to_preamble() {
+        let preamble = render_preamble(CHAT_PREAMBLE, "a helpful assistant", None);
+
+        assert_eq!(
+            with_response_style(preamble.clone(), ResponseStyle::Prose),
+            preamble
+        );
+        assert_eq!(
+            with_response_style(preamble.clone(), ResponseStyle::Steps),
+            format!("{}{}", preamble, ResponseStyle::Steps.preamble_suffix())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_settings_shape_research_agent() {
+        let settings = AgentSettings {
+            max_tool_rounds: 3,
+            assistant_name: Some("Acme".to_string()),
+            response_style: ResponseStyle::Bullets,
+            ..AgentSettings::default()
+        };
+
+        let agent = create_ollama_agent(DEFAULT_OLLAMA_HOST, "qwen3", &settings, vec![], vec![]);
+        let preamble = agent.preamble.unwrap();
+
+        assert!(preamble.starts_with("You are Acme, a research assistant"));
+        assert!(preamble.ends_with(ResponseStyle::Bullets.preamble_suffix()));
+        assert_eq!(agent.default_max_turns, Some(max_turns(3)));
+    }
+
     #[test]
     fn test_default_model_ollama() {
         assert_eq!(default_model("ollama"), "qwen3");
@@ -229,10 +604,215 @@ mod tests {
         assert_eq!(default_model("unknown"), "qwen3");
     }
 
+    #[test]
+    fn test_model_matches_provider_gemini() {
+        assert!(model_matches_provider("gemini", "gemini-2.5-flash"));
+        assert!(model_matches_provider("gemini", "gemma-3-27b-it"));
+        assert!(!model_matches_provider("gemini", "gpt-4.1-mini"));
+    }
+
+    #[test]
+    fn test_model_matches_provider_openai() {
+        assert!(model_matches_provider("openai", "gpt-4.1-mini"));
+        assert!(model_matches_provider("openai", "o3-mini"));
+        assert!(model_matches_provider("openai", "o1"));
+        assert!(!model_matches_provider("openai", "gemini-2.5-flash"));
+        assert!(!model_matches_provider("openai", "ollama-model"));
+    }
+
+    #[test]
+    fn test_model_matches_provider_ollama_accepts_any_model() {
+        assert!(model_matches_provider("ollama", "qwen3"));
+        assert!(model_matches_provider("ollama", "llama3.2:latest"));
+    }
+
+    #[test]
+    fn test_default_models_match_their_provider() {
+        for provider in ["ollama", "gemini", "openai"] {
+            assert!(model_matches_provider(provider, default_model(provider)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_tool_list_builds_tool_less_agent() {
+        let agent = create_ollama_agent(
+            DEFAULT_OLLAMA_HOST,
+            "qwen3",
+            &AgentSettings::default(),
+            EnabledTools::parse("")
+                .unwrap()
+                .build(&WebFetch::new(), None),
+            vec![],
+        );
+
+        let tools = agent.tool_server_handle.get_tool_defs(None).await.unwrap();
+
+        assert!(tools.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_enabled_tools_are_registered_with_agent() {
+        let agent = create_ollama_agent(
+            DEFAULT_OLLAMA_HOST,
+            "qwen3",
+            &AgentSettings::default(),
+            EnabledTools::parse("calculator,web_search")
+                .unwrap()
+                .build(&WebFetch::new(), None),
+            vec![],
+        );
+
+        let tools = agent.tool_server_handle.get_tool_defs(None).await.unwrap();
+        let mut names: Vec<String> = tools.into_iter().map(|tool| tool.name).collect();
+        names.sort();
+
+        assert_eq!(names, ["calculator", "web_search"]);
+    }
+
+    #[tokio::test]
+    async fn test_default_params_leave_requests_unchanged() {
+        let settings = AgentSettings::default();
+
+        let ollama = create_ollama_chat_agent(DEFAULT_OLLAMA_HOST, "qwen3", &settings);
+        let gemini = create_gemini_chat_agent("test-key", "gemini-2.5-flash", &settings);
+        let openai = create_openai_chat_agent("test-key", "gpt-4.1-mini", &settings);
+
+        assert_eq!(
+            (
+                ollama.temperature,
+                ollama.max_tokens,
+                ollama.additional_params
+            ),
+            (None, None, None)
+        );
+        assert_eq!(
+            (
+                gemini.temperature,
+                gemini.max_tokens,
+                gemini.additional_params
+            ),
+            (None, None, None)
+        );
+        assert_eq!(
+            (
+                openai.temperature,
+                openai.max_tokens,
+                openai.additional_params
+            ),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn test_gemini_params_include_safety_settings() {
+        let safety: GeminiSafety = "dangerous_content=block_only_high".parse().unwrap();
+        let params = AgentParams {
+            temperature: Some(0.3),
+            ..AgentParams::default()
+        };
+
+        assert_eq!(
+            gemini_params(&params, &safety),
+            serde_json::json!({
+                "safetySettings": [
+                    { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "BLOCK_ONLY_HIGH" }
+                ],
+                "generationConfig": { "temperature": 0.3 }
+            })
+        );
+        assert_eq!(
+            gemini_params(&AgentParams::default(), &GeminiSafety::default()),
+            serde_json::json!({})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_params_use_each_providers_field_names() {
+        let settings = AgentSettings {
+            params: AgentParams {
+                temperature: Some(0.3),
+                top_p: Some(0.9),
+                max_tokens: Some(512),
+            },
+            ..AgentSettings::default()
+        };
+
+        let ollama = create_ollama_chat_agent(DEFAULT_OLLAMA_HOST, "qwen3", &settings);
+        let gemini = create_gemini_chat_agent("test-key", "gemini-2.5-flash", &settings);
+        let openai = create_openai_chat_agent("test-key", "gpt-4.1-mini", &settings);
+
+        assert_eq!(ollama.temperature, Some(0.3));
+        assert_eq!(ollama.max_tokens, Some(512));
+        assert_eq!(
+            ollama.additional_params,
+            Some(serde_json::json!({ "top_p": 0.9, "num_predict": 512 }))
+        );
+        assert_eq!(
+            gemini.additional_params,
+            Some(serde_json::json!({
+                "generationConfig": { "temperature": 0.3, "topP": 0.9, "maxOutputTokens": 512 }
+            }))
+        );
+        assert_eq!(openai.temperature, Some(0.3));
+        assert_eq!(
+            openai.additional_params,
+            Some(serde_json::json!({ "top_p": 0.9 }))
+        );
+    }
+
+    #[test]
+    fn test_openai_client_uses_configured_base_url() {
+        let custom = openai_client("test-key", Some("http://localhost:8000/v1")).unwrap();
+        let default = openai_client("test-key", None).unwrap();
+
+        assert_eq!(custom.base_url(), "http://localhost:8000/v1");
+        assert!(default.base_url().starts_with("https://api.openai.com"));
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_agent_sends_requests_to_base_url() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 8192];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+        let agent = create_openai_compatible_chat_agent(
+            &base_url,
+            "local-key",
+            "qwen2.5-7b-instruct",
+            &AgentSettings::default(),
+        );
+
+        // Only the request matters; the stub server fails it
+        _ = agent.prompt("hi").await;
+
+        let request = server.await.unwrap();
+        assert!(
+            request.starts_with("post /v1/chat/completions "),
+            "{request}"
+        );
+        assert!(request.contains("authorization: bearer local-key"));
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_ollama_agent_with_web_fetch() {
-        let agent = create_ollama_agent("qwen3", WebFetch::new(), vec![]);
+        let agent = create_ollama_agent(
+            DEFAULT_OLLAMA_HOST,
+            "qwen3",
+            &AgentSettings::default(),
+            EnabledTools::all().build(&WebFetch::new(), None),
+            vec![],
+        );
         let response = agent
             .prompt("Fetch https://example.com and **summarize** it shortly")
             .await
@@ -251,7 +831,8 @@ mod tests {
         let agent = create_gemini_agent(
             &api_key,
             gemini::completion::GEMINI_2_5_FLASH,
-            WebFetch::new(),
+            &AgentSettings::default(),
+            EnabledTools::all().build(&WebFetch::new(), None),
             vec![],
         );
         let response = agent
@@ -272,7 +853,8 @@ mod tests {
         let agent = create_openai_agent(
             &api_key,
             openai::completion::GPT_4_1_MINI,
-            WebFetch::new(),
+            &AgentSettings::default(),
+            EnabledTools::all().build(&WebFetch::new(), None),
             vec![],
         );
         let response = agent
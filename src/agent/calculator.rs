@@ -0,0 +1,259 @@
+use log::info;
+use rig::completion::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Arguments for the Calculator tool
+#[derive(Deserialize)]
+pub struct CalculatorArgs {
+    /// An arithmetic expression, optionally followed by a conversion
+    /// such as `5 km to mi`
+    expression: String,
+}
+
+/// Output from the Calculator tool
+#[derive(Debug, Serialize)]
+pub struct CalculatorOutput {
+    expression: String,
+    result: f64,
+    /// Unit of `result`, for conversions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit: Option<String>,
+}
+
+/// Error type for Calculator tool
+#[derive(Debug, thiserror::Error)]
+pub enum CalculatorError {
+    #[error("Invalid expression: {0}")]
+    Invalid(String),
+    #[error("Division by zero or a result that is not a finite number")]
+    NotFinite,
+    #[error("'{0}' is not supported; only arithmetic and unit conversion are allowed")]
+    Unsupported(String),
+    #[error("Unknown unit '{0}'")]
+    UnknownUnit(String),
+    #[error("Cannot convert {from} to {to}")]
+    IncompatibleUnits { from: String, to: String },
+}
+
+/// What a unit measures; only units of the same dimension convert into each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Time,
+    Temperature,
+}
+
+/// Units by name: dimension and size in the dimension's base unit
+/// (metre, kilogram, litre, second). Temperatures are handled separately.
+const UNITS: &[(&str, Dimension, f64)] = &[
+    ("mm", Dimension::Length, 0.001),
+    ("cm", Dimension::Length, 0.01),
+    ("m", Dimension::Length, 1.0),
+    ("km", Dimension::Length, 1000.0),
+    ("in", Dimension::Length, 0.0254),
+    ("ft", Dimension::Length, 0.3048),
+    ("yd", Dimension::Length, 0.9144),
+    ("mi", Dimension::Length, 1609.344),
+    ("g", Dimension::Mass, 0.001),
+    ("kg", Dimension::Mass, 1.0),
+    ("t", Dimension::Mass, 1000.0),
+    ("oz", Dimension::Mass, 0.028_349_523_125),
+    ("lb", Dimension::Mass, 0.453_592_37),
+    ("ml", Dimension::Volume, 0.001),
+    ("l", Dimension::Volume, 1.0),
+    ("gal", Dimension::Volume, 3.785_411_784),
+    ("s", Dimension::Time, 1.0),
+    ("min", Dimension::Time, 60.0),
+    ("h", Dimension::Time, 3600.0),
+    ("day", Dimension::Time, 86_400.0),
+    ("c", Dimension::Temperature, 1.0),
+    ("f", Dimension::Temperature, 1.0),
+    ("k", Dimension::Temperature, 1.0),
+];
+
+fn lookup_unit(name: &str) -> Result<(&'static str, Dimension, f64), CalculatorError> {
+    let name = name.to_ascii_lowercase();
+    UNITS
+        .iter()
+        .find(|(unit, _, _)| *unit == name)
+        .copied()
+        .ok_or(CalculatorError::UnknownUnit(name))
+}
+
+/// Evaluate an arithmetic expression (`+ - * / % ^`, parentheses and math
+/// functions such as `sqrt`, `sin`, `log`, `pi()`). No variables exist, and the
+/// library's `print` function, which writes to stderr, is refused.
+pub(crate) fn evaluate(expression: &str) -> Result<f64, CalculatorError> {
+    if expression.to_ascii_lowercase().contains("print") {
+        return Err(CalculatorError::Unsupported("print".to_string()));
+    }
+    let result = fasteval::ez_eval(expression, &mut fasteval::EmptyNamespace)
+        .map_err(|e| CalculatorError::Invalid(e.to_string()))?;
+    if result.is_finite() {
+        Ok(result)
+    } else {
+        Err(CalculatorError::NotFinite)
+    }
+}
+
+/// Convert `value` between two units of the same dimension
+pub(crate) fn convert(value: f64, from: &str, to: &str) -> Result<f64, CalculatorError> {
+    let (from_name, from_dim, from_factor) = lookup_unit(from)?;
+    let (to_name, to_dim, to_factor) = lookup_unit(to)?;
+    if from_dim != to_dim {
+        return Err(CalculatorError::IncompatibleUnits {
+            from: from_name.to_string(),
+            to: to_name.to_string(),
+        });
+    }
+    if from_dim != Dimension::Temperature {
+        return Ok(value * from_factor / to_factor);
+    }
+    let kelvin = match from_name {
+        "c" => value + 273.15,
+        "f" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        _ => value,
+    };
+    Ok(match to_name {
+        "c" => kelvin - 273.15,
+        "f" => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+        _ => kelvin,
+    })
+}
+
+/// Split `<expression> <unit> to|in <unit>` into its parts
+fn parse_conversion(input: &str) -> Option<(&str, &str, &str)> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let [.., from, keyword, to] = words.as_slice() else {
+        return None;
+    };
+    if !matches!(*keyword, "to" | "in") || words.len() < 4 {
+        return None;
+    }
+    // Strip the last three words off the end, so a unit's letters inside an
+    // earlier word (the "m" of "mi") can't be mistaken for the unit
+    let value = input
+        .trim_end()
+        .strip_suffix(to)?
+        .trim_end()
+        .strip_suffix(keyword)?
+        .trim_end()
+        .strip_suffix(from)?;
+    Some((value.trim(), from, to))
+}
+
+/// Calculator tool for exact arithmetic and unit conversion
+pub struct Calculator;
+
+impl rig::tool::Tool for Calculator {
+    const NAME: &'static str = "calculator";
+    type Error = CalculatorError;
+    type Args = CalculatorArgs;
+    type Output = CalculatorOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Evaluates an arithmetic expression exactly, or converts units. \
+                          Use it instead of doing math yourself"
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "expression": {
+                        "type": "string",
+                        "description": "e.g. \"(3 + 4) * 2 ^ 10\", \"sqrt(2) / 3\" or \"5 km to mi\" \
+                                        (units: mm cm m km in ft yd mi g kg t oz lb ml l gal s min h day c f k)"
+                    }
+                },
+                "required": ["expression"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        info!("Calculating {} ...", args.expression);
+
+        let (result, unit) = match parse_conversion(&args.expression) {
+            Some((value, from, to)) => {
+                let value = evaluate(value)?;
+                (convert(value, from, to)?, Some(to.to_ascii_lowercase()))
+            }
+            None => (evaluate(&args.expression)?, None),
+        };
+        Ok(CalculatorOutput {
+            expression: args.expression,
+            result,
+            unit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::tool::Tool;
+
+    fn calculate(expression: &str) -> Result<CalculatorOutput, CalculatorError> {
+        let args = CalculatorArgs {
+            expression: expression.to_string(),
+        };
+        futures::executor::block_on(Calculator.call(args))
+    }
+
+    #[test]
+    fn test_evaluate_respects_precedence() {
+        assert_eq!(evaluate("2 + 3 * 4").unwrap(), 14.0);
+        assert_eq!(evaluate("(2 + 3) * 4").unwrap(), 20.0);
+        assert_eq!(evaluate("2 ^ 3 ^ 2").unwrap(), 512.0);
+        assert_eq!(evaluate("7 / 2").unwrap(), 3.5);
+    }
+
+    #[test]
+    fn test_division_by_zero_is_an_error() {
+        assert!(matches!(evaluate("1 / 0"), Err(CalculatorError::NotFinite)));
+    }
+
+    #[test]
+    fn test_rejects_print_and_garbage() {
+        assert!(matches!(
+            evaluate("print(\"hi\", 1)"),
+            Err(CalculatorError::Unsupported(_))
+        ));
+        assert!(matches!(
+            evaluate("2 +* 3"),
+            Err(CalculatorError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_unit_conversion() {
+        let output = calculate("10 km to mi").unwrap();
+
+        assert!((output.result - 6.213_711_922).abs() < 1e-6);
+        assert_eq!(output.unit.as_deref(), Some("mi"));
+        assert!((calculate("100 C in F").unwrap().result - 212.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unit_letters_inside_other_words_are_not_the_unit() {
+        assert_eq!(parse_conversion("5 m to mi"), Some(("5", "m", "mi")));
+        assert_eq!(parse_conversion("1 t to kg"), Some(("1", "t", "kg")));
+        assert_eq!(parse_conversion("1 l to gal"), Some(("1", "l", "gal")));
+
+        assert!((calculate("5 m to mi").unwrap().result - 5.0 / 1609.344).abs() < 1e-12);
+        assert!((calculate("1 t to kg").unwrap().result - 1000.0).abs() < 1e-9);
+        assert!((calculate("1 l to gal").unwrap().result - 1.0 / 3.785_411_784).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_incompatible_units_are_an_error() {
+        assert!(matches!(
+            calculate("3 kg to m"),
+            Err(CalculatorError::IncompatibleUnits { .. })
+        ));
+    }
+}
@@ -2,8 +2,9 @@ use std::pin::Pin;
 
 use async_trait::async_trait;
 use futures::Stream;
-use rig::completion::Message;
+use rig::completion::{Document, Message};
 
+use super::grounding::inline_documents;
 use super::ChatStreamEvent;
 
 /// Provider-agnostic chat interface.
@@ -22,4 +23,17 @@ pub trait ChatAgent: Send + Sync {
         prompt: &str,
         history: Vec<Message>,
     ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>;
+
+    /// Stream a response to `prompt` grounded in `documents`.
+    /// Agents that can't attach documents to a request get them inlined
+    /// before the prompt.
+    async fn stream_chat_with_documents(
+        &self,
+        prompt: &str,
+        history: Vec<Message>,
+        documents: Vec<Document>,
+    ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+        self.stream_chat(&inline_documents(prompt, &documents), history)
+            .await
+    }
 }
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{SecondsFormat, Utc};
+use rig::agent::{HookAction, PromptHook, ToolCallHookAction};
+use rig::completion::{CompletionModel, CompletionResponse, Message, ToolDefinition};
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// A stable reference to a fetched web page, returned alongside its content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    pub url: String,
+    pub title: Option<String>,
+    /// When the page was fetched (RFC 3339, UTC)
+    pub fetched_at: String,
+}
+
+impl Citation {
+    /// Create a citation for a page fetched just now
+    pub fn now(url: &str, title: Option<String>) -> Self {
+        Self {
+            url: url.to_string(),
+            title,
+            fetched_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+}
+
+/// Pages fetched during one response, keyed by URL.
+/// Clones share the same log via Arc, so each request's hook clones see the same pages.
+#[derive(Clone, Default)]
+struct SourceLog {
+    sources: Arc<Mutex<HashMap<String, Citation>>>,
+}
+
+impl SourceLog {
+    /// Record a fetched page (the latest fetch of a URL wins)
+    fn record(&self, citation: Citation) {
+        let mut locked = self.sources.lock().unwrap();
+        locked.insert(citation.url.clone(), citation);
+    }
+
+    /// Look up the citations for `urls`, listing the URLs never fetched separately
+    fn cite(&self, urls: Vec<String>) -> CiteOutput {
+        let locked = self.sources.lock().unwrap();
+        let (known, unknown_urls): (Vec<_>, Vec<_>) =
+            urls.into_iter().partition(|url| locked.contains_key(url));
+        CiteOutput {
+            sources: known.iter().map(|url| locked[url].clone()).collect(),
+            unknown_urls,
+        }
+    }
+}
+
+/// Extract citations from a tool's JSON output.
+///
+/// Recognizes a top-level `citation` object (web_fetch) and `citation` objects
+/// inside a `results` array (web_fetch_many). Non-JSON output yields nothing.
+pub(crate) fn citations_from_tool_output(output: &str) -> Vec<Citation> {
+    let value: serde_json::Value = match serde_json::from_str(output) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+
+    let mut candidates = vec![&value["citation"]];
+    if let Some(results) = value["results"].as_array() {
+        candidates.extend(results.iter().map(|r| &r["citation"]));
+    }
+
+    candidates
+        .into_iter()
+        .filter_map(|c| serde_json::from_value(c.clone()).ok())
+        .collect()
+}
+
+/// Arguments for the Cite tool
+#[derive(Deserialize)]
+pub struct CiteArgs {
+    urls: Vec<String>,
+}
+
+/// Output from the Cite tool
+#[derive(Serialize)]
+pub struct CiteOutput {
+    sources: Vec<Citation>,
+    /// URLs the model asked about that were never fetched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unknown_urls: Vec<String>,
+}
+
+/// Error type for Cite tool
+#[derive(Debug, thiserror::Error)]
+pub enum CiteError {
+    #[error("No URLs provided")]
+    NoUrls,
+    #[error("Sources are only known while answering a chat message")]
+    OutsideResponse,
+}
+
+/// Tool that returns citation objects for pages the agent has fetched.
+/// Lets the model list its sources with titles and fetch times.
+///
+/// The pages fetched are only known to the response that fetched them, so
+/// calls are answered by that response's `CitedSources` hook; the tool
+/// itself only carries the definition.
+#[derive(Clone, Default)]
+pub struct Cite;
+
+impl rig::tool::Tool for Cite {
+    const NAME: &'static str = "cite";
+    type Error = CiteError;
+    type Args = CiteArgs;
+    type Output = CiteOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Returns citations (url, title, fetched_at) for the fetched pages \
+                          used in the answer. Call it with the URLs you relied on."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "urls": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The URLs of the pages used in the answer"
+                    }
+                },
+                "required": ["urls"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.urls.is_empty() {
+            return Err(CiteError::NoUrls);
+        }
+        Err(CiteError::OutsideResponse)
+    }
+}
+
+/// Per-request hook that records the pages fetched while answering and
+/// answers `cite` calls from them, so one response never cites pages fetched
+/// for another chat. Every other hook call is passed on to `inner`.
+#[derive(Clone)]
+pub(crate) struct CitedSources<H> {
+    inner: H,
+    sources: SourceLog,
+}
+
+impl<H> CitedSources<H> {
+    pub(crate) fn new(inner: H) -> Self {
+        Self {
+            inner,
+            sources: SourceLog::default(),
+        }
+    }
+}
+
+impl<M: CompletionModel, H: PromptHook<M>> PromptHook<M> for CitedSources<H> {
+    async fn on_completion_call(&self, prompt: &Message, history: &[Message]) -> HookAction {
+        self.inner.on_completion_call(prompt, history).await
+    }
+
+    async fn on_completion_response(
+        &self,
+        prompt: &Message,
+        response: &CompletionResponse<M::Response>,
+    ) -> HookAction {
+        self.inner.on_completion_response(prompt, response).await
+    }
+
+    async fn on_tool_call(
+        &self,
+        tool_name: &str,
+        tool_call_id: Option<String>,
+        internal_call_id: &str,
+        args: &str,
+    ) -> ToolCallHookAction {
+        let action = self
+            .inner
+            .on_tool_call(tool_name, tool_call_id, internal_call_id, args)
+            .await;
+        if action != ToolCallHookAction::Continue || tool_name != Cite::NAME {
+            return action;
+        }
+        // A skipped call's reason is what the model gets as the tool result
+        let result = match serde_json::from_str::<CiteArgs>(args) {
+            Ok(args) if !args.urls.is_empty() => {
+                serde_json::to_string(&self.sources.cite(args.urls)).unwrap()
+            }
+            Ok(_) => CiteError::NoUrls.to_string(),
+            Err(e) => format!("Invalid cite arguments: {e}"),
+        };
+        ToolCallHookAction::skip(result)
+    }
+
+    async fn on_tool_result(
+        &self,
+        tool_name: &str,
+        tool_call_id: Option<String>,
+        internal_call_id: &str,
+        args: &str,
+        result: &str,
+    ) -> HookAction {
+        for citation in citations_from_tool_output(result) {
+            self.sources.record(citation);
+        }
+        self.inner
+            .on_tool_result(tool_name, tool_call_id, internal_call_id, args, result)
+            .await
+    }
+
+    async fn on_text_delta(&self, text_delta: &str, aggregated_text: &str) -> HookAction {
+        self.inner.on_text_delta(text_delta, aggregated_text).await
+    }
+
+    async fn on_tool_call_delta(
+        &self,
+        tool_call_id: &str,
+        internal_call_id: &str,
+        tool_name: Option<&str>,
+        tool_call_delta: &str,
+    ) -> HookAction {
+        self.inner
+            .on_tool_call_delta(tool_call_id, internal_call_id, tool_name, tool_call_delta)
+            .await
+    }
+
+    async fn on_stream_completion_response_finish(
+        &self,
+        prompt: &Message,
+        response: &M::StreamingResponse,
+    ) -> HookAction {
+        self.inner
+            .on_stream_completion_response_finish(prompt, response)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_citation_now_uses_rfc3339_utc() {
+        let citation = Citation::now("https://example.com", None);
+        assert!(citation.fetched_at.ends_with('Z'));
+        assert!(chrono::DateTime::parse_from_rfc3339(&citation.fetched_at).is_ok());
+    }
+
+    #[test]
+    fn test_citations_from_web_fetch_output() {
+        let output = r#"{"title":"T","content":"...","citation":{"url":"https://a.example.com","title":"T","fetched_at":"2024-01-01T00:00:00Z"}}"#;
+
+        let result = citations_from_tool_output(output);
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].url, "https://a.example.com");
+    }
+
+    #[test]
+    fn test_citations_from_web_fetch_many_output() {
+        let output = r#"{"results":[
+            {"url":"https://a.example.com","citation":{"url":"https://a.example.com","title":null,"fetched_at":"2024-01-01T00:00:00Z"}},
+            {"url":"https://b.example.com","error":"boom"}
+        ]}"#;
+
+        let result = citations_from_tool_output(output);
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].url, "https://a.example.com");
+    }
+
+    #[test]
+    fn test_citations_from_non_json_output_is_empty() {
+        assert!(citations_from_tool_output("plain text report").is_empty());
+    }
+
+    async fn tool_call(hook: &CitedSources<()>, name: &str, args: &str) -> ToolCallHookAction {
+        PromptHook::<rig::providers::ollama::CompletionModel>::on_tool_call(
+            hook, name, None, "id", args,
+        )
+        .await
+    }
+
+    async fn tool_result(hook: &CitedSources<()>, result: &str) {
+        PromptHook::<rig::providers::ollama::CompletionModel>::on_tool_result(
+            hook,
+            "web_fetch",
+            None,
+            "id",
+            "{}",
+            result,
+        )
+        .await;
+    }
+
+    const FETCHED_A: &str = r#"{"title":"A","content":"...","citation":{"url":"https://a.example.com","title":"A","fetched_at":"2024-01-01T00:00:00Z"}}"#;
+
+    #[tokio::test]
+    async fn test_cite_returns_sources_fetched_in_the_response_and_unknown_urls() {
+        let hook = CitedSources::new(());
+        tool_result(&hook, FETCHED_A).await;
+
+        let action = tool_call(
+            &hook,
+            "cite",
+            r#"{"urls":["https://a.example.com","https://never-fetched.example.com"]}"#,
+        )
+        .await;
+
+        let ToolCallHookAction::Skip { reason } = action else {
+            panic!("cite should be answered by the hook, got {action:?}");
+        };
+        let output: serde_json::Value = serde_json::from_str(&reason).unwrap();
+        assert_eq!(output["sources"][0]["title"], "A");
+        assert_eq!(
+            output["unknown_urls"],
+            json!(["https://never-fetched.example.com"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cite_does_not_see_pages_fetched_for_another_response() {
+        let other = CitedSources::new(());
+        tool_result(&other, FETCHED_A).await;
+        let hook = CitedSources::new(());
+
+        let ToolCallHookAction::Skip { reason } =
+            tool_call(&hook, "cite", r#"{"urls":["https://a.example.com"]}"#).await
+        else {
+            panic!("cite should be answered by the hook");
+        };
+
+        let output: serde_json::Value = serde_json::from_str(&reason).unwrap();
+        assert_eq!(output["unknown_urls"], json!(["https://a.example.com"]));
+    }
+
+    #[tokio::test]
+    async fn test_other_tool_calls_are_passed_on() {
+        let hook = CitedSources::new(());
+
+        assert_eq!(
+            tool_call(&hook, "web_fetch", "{}").await,
+            ToolCallHookAction::cont()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cite_tool_outside_a_response_is_an_error() {
+        let result = Cite
+            .call(CiteArgs {
+                urls: vec!["https://a.example.com".to_string()],
+            })
+            .await;
+
+        assert!(matches!(result, Err(CiteError::OutsideResponse)));
+    }
+}
@@ -0,0 +1,686 @@
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+
+use log::warn;
+use reqwest::Url;
+
+use super::{
+    default_model, model_matches_provider, EnabledTools, GeminiSafety, ResponseStyle,
+    DEFAULT_MAX_TOOL_ROUNDS,
+};
+
+/// Error returned when the LLM provider configuration is incomplete.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ConfigError {
+    #[error("{var} environment variable is required when LLM_PROVIDER={provider}")]
+    MissingApiKey {
+        provider: &'static str,
+        var: &'static str,
+    },
+    #[error("model '{model}' does not look like a {provider} model (unset LLM_MODEL_STRICT to only warn)")]
+    ModelProviderMismatch { provider: String, model: String },
+    #[error("OLLAMA_HOST '{0}' is not a valid http(s) URL (e.g. http://gpu-box:11434)")]
+    InvalidOllamaHost(String),
+    #[error("OPENAI_BASE_URL '{0}' is not a valid http(s) URL (e.g. http://localhost:8000/v1)")]
+    InvalidOpenAiBaseUrl(String),
+    #[error("unknown tool '{name}' in ENABLED_TOOLS (available: {available})")]
+    UnknownTool { name: String, available: String },
+    #[error("{0} environment variable is required")]
+    MissingVar(&'static str),
+    #[error("CONTENT_FILTER_WORDS must list at least one term when CONTENT_FILTER_MODE={0}")]
+    EmptyContentFilter(String),
+    #[error("{var}='{value}' is invalid; expected {expected}")]
+    InvalidValue {
+        var: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("{} configuration problems: {}", .0.len(), join_problems(.0))]
+    Multiple(Vec<ConfigError>),
+}
+
+fn join_problems(problems: &[ConfigError]) -> String {
+    problems
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Provider used when neither `LLM_PROVIDER` nor `LLM_DEFAULT_PROVIDER` is set
+pub const FALLBACK_PROVIDER: &str = "ollama";
+
+/// Providers `LLM_PROVIDER` and `LLM_DEFAULT_PROVIDER` may name
+const KNOWN_PROVIDERS: &[&str] = &["ollama", "gemini", "openai", "openai_compat"];
+
+/// Resolve the provider used when a request or the environment doesn't name
+/// one: `LLM_PROVIDER`, then `LLM_DEFAULT_PROVIDER`, then Ollama.
+/// Values are trimmed and matched case-insensitively.
+///
+/// # Errors
+/// Returns `ConfigError::InvalidValue` for the first set variable that doesn't
+/// name one of `KNOWN_PROVIDERS`.
+pub(crate) fn resolve_provider(
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, ConfigError> {
+    for var in ["LLM_PROVIDER", "LLM_DEFAULT_PROVIDER"] {
+        let Some(value) = lookup(var)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+        else {
+            continue;
+        };
+        let provider = value.to_ascii_lowercase();
+        return if KNOWN_PROVIDERS.contains(&provider.as_str()) {
+            Ok(provider)
+        } else {
+            Err(ConfigError::InvalidValue {
+                var,
+                value,
+                expected: "ollama, gemini, openai or openai_compat",
+            })
+        };
+    }
+    Ok(FALLBACK_PROVIDER.to_string())
+}
+
+/// Ollama endpoint used when `OLLAMA_HOST` is unset
+pub const DEFAULT_OLLAMA_HOST: &str = "http://localhost:11434";
+
+/// Resolve the Ollama base URL from the value of `OLLAMA_HOST`.
+/// Unset or blank falls back to `DEFAULT_OLLAMA_HOST`; a trailing `/` is dropped.
+pub(crate) fn resolve_ollama_host(value: Option<String>) -> Result<String, ConfigError> {
+    let Some(value) = value.filter(|v| !v.trim().is_empty()) else {
+        return Ok(DEFAULT_OLLAMA_HOST.to_string());
+    };
+    let host = value.trim().trim_end_matches('/');
+    if is_http_url(host) {
+        Ok(host.to_string())
+    } else {
+        Err(ConfigError::InvalidOllamaHost(value))
+    }
+}
+
+/// Resolve the base URL of an OpenAI-compatible server from `OPENAI_BASE_URL`.
+/// Required for `LLM_PROVIDER=openai_compat`; a trailing `/` is dropped.
+pub(crate) fn resolve_openai_base_url(value: Option<String>) -> Result<String, ConfigError> {
+    let Some(value) = value.filter(|v| !v.trim().is_empty()) else {
+        return Err(ConfigError::MissingVar("OPENAI_BASE_URL"));
+    };
+    let base_url = value.trim().trim_end_matches('/');
+    if is_http_url(base_url) {
+        Ok(base_url.to_string())
+    } else {
+        Err(ConfigError::InvalidOpenAiBaseUrl(value))
+    }
+}
+
+fn is_http_url(value: &str) -> bool {
+    Url::parse(value)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+}
+
+/// Provider selection resolved from `LLM_PROVIDER`, `LLM_MODEL` and the provider's
+/// API key (or `OLLAMA_HOST` for Ollama).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProviderConfig {
+    Ollama {
+        host: String,
+        model: String,
+    },
+    Gemini {
+        api_key: String,
+        model: String,
+    },
+    OpenAi {
+        api_key: String,
+        model: String,
+    },
+    /// Any server speaking the OpenAI API at `base_url` (vLLM, LM Studio, ...)
+    OpenAiCompatible {
+        base_url: String,
+        api_key: String,
+        model: String,
+    },
+}
+
+impl ProviderConfig {
+    /// Resolve the provider configuration from environment variables.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    /// Resolve the provider configuration using `lookup` to read variables.
+    /// Separated from `from_env` so tests don't have to mutate the process environment.
+    pub(crate) fn from_lookup(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let provider = resolve_provider(&lookup)?;
+        // An OpenAI-compatible server may serve any model, so there is no default to fall back on
+        if provider == "openai_compat" && lookup("LLM_MODEL").is_none() {
+            return Err(ConfigError::MissingVar("LLM_MODEL"));
+        }
+        let model = lookup("LLM_MODEL").unwrap_or_else(|| default_model(&provider).to_string());
+
+        if !model_matches_provider(&provider, &model) {
+            let strict = lookup("LLM_MODEL_STRICT").is_some_and(|v| v == "true" || v == "1");
+            if strict {
+                return Err(ConfigError::ModelProviderMismatch { provider, model });
+            }
+            warn!("Model '{model}' does not look like a {provider} model; requests may fail");
+        }
+
+        match provider.as_str() {
+            "openai" => {
+                let api_key = lookup("OPENAI_API_KEY").ok_or(ConfigError::MissingApiKey {
+                    provider: "openai",
+                    var: "OPENAI_API_KEY",
+                })?;
+                Ok(Self::OpenAi { api_key, model })
+            }
+            "openai_compat" => {
+                let base_url = resolve_openai_base_url(lookup("OPENAI_BASE_URL"))?;
+                let api_key = lookup("OPENAI_API_KEY").ok_or(ConfigError::MissingApiKey {
+                    provider: "openai_compat",
+                    var: "OPENAI_API_KEY",
+                })?;
+                Ok(Self::OpenAiCompatible {
+                    base_url,
+                    api_key,
+                    model,
+                })
+            }
+            "gemini" => {
+                let api_key = lookup("GEMINI_API_KEY").ok_or(ConfigError::MissingApiKey {
+                    provider: "gemini",
+                    var: "GEMINI_API_KEY",
+                })?;
+                Ok(Self::Gemini { api_key, model })
+            }
+            _ => {
+                let host = resolve_ollama_host(lookup("OLLAMA_HOST"))?;
+                Ok(Self::Ollama { host, model })
+            }
+        }
+    }
+}
+
+/// The configured provider plus every provider's endpoint and API key, read
+/// at startup so agents for other providers/models can be built later (see
+/// `AppState::agent_for`) without going back to the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderSettings {
+    /// Provider used when a request names only a model
+    pub provider: String,
+    ollama_host: Option<String>,
+    openai_api_key: Option<String>,
+    openai_base_url: Option<String>,
+    gemini_api_key: Option<String>,
+    model_strict: Option<String>,
+}
+
+impl Default for ProviderSettings {
+    fn default() -> Self {
+        Self {
+            provider: FALLBACK_PROVIDER.to_string(),
+            ollama_host: None,
+            openai_api_key: None,
+            openai_base_url: None,
+            gemini_api_key: None,
+            model_strict: None,
+        }
+    }
+}
+
+impl ProviderSettings {
+    /// Read the settings using `lookup` to read variables
+    pub(crate) fn from_lookup(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self {
+            provider: resolve_provider(&lookup)?,
+            ollama_host: lookup("OLLAMA_HOST"),
+            openai_api_key: lookup("OPENAI_API_KEY"),
+            openai_base_url: lookup("OPENAI_BASE_URL"),
+            gemini_api_key: lookup("GEMINI_API_KEY"),
+            model_strict: lookup("LLM_MODEL_STRICT"),
+        })
+    }
+
+    /// Resolve the configuration for `model` on `provider`, checked the same
+    /// way as `LLM_PROVIDER`/`LLM_MODEL` are at startup
+    pub fn provider_config(
+        &self,
+        provider: &str,
+        model: &str,
+    ) -> Result<ProviderConfig, ConfigError> {
+        ProviderConfig::from_lookup(|key| match key {
+            "LLM_PROVIDER" => Some(provider.to_string()),
+            "LLM_MODEL" => Some(model.to_string()),
+            "OLLAMA_HOST" => self.ollama_host.clone(),
+            "OPENAI_API_KEY" => self.openai_api_key.clone(),
+            "OPENAI_BASE_URL" => self.openai_base_url.clone(),
+            "GEMINI_API_KEY" => self.gemini_api_key.clone(),
+            "LLM_MODEL_STRICT" => self.model_strict.clone(),
+            _ => None,
+        })
+    }
+}
+
+/// Provider/model pairs a chat request is allowed to select (`CHAT_MODEL_ALLOWLIST`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelAllowlist {
+    entries: HashSet<(String, String)>,
+}
+
+impl FromStr for ModelAllowlist {
+    /// The first entry that isn't a `provider:model` pair
+    type Err = String;
+
+    /// Parse a comma-separated list of `provider:model` pairs
+    fn from_str(list: &str) -> Result<Self, Self::Err> {
+        let entries = list
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .split_once(':')
+                    .map(|(provider, model)| (provider.trim(), model.trim()))
+                    .filter(|(provider, model)| !provider.is_empty() && !model.is_empty())
+                    .map(|(provider, model)| (provider.to_string(), model.to_string()))
+                    .ok_or_else(|| entry.to_string())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
+    }
+}
+
+impl ModelAllowlist {
+    pub fn allows(&self, provider: &str, model: &str) -> bool {
+        self.entries
+            .contains(&(provider.to_string(), model.to_string()))
+    }
+}
+
+impl ProviderConfig {
+    /// Provider name as used in `LLM_PROVIDER`
+    pub fn provider_name(&self) -> &'static str {
+        match self {
+            Self::Ollama { .. } => "ollama",
+            Self::Gemini { .. } => "gemini",
+            Self::OpenAi { .. } => "openai",
+            Self::OpenAiCompatible { .. } => "openai_compat",
+        }
+    }
+
+    /// Model name passed to the provider
+    pub fn model(&self) -> &str {
+        match self {
+            Self::Ollama { model, .. }
+            | Self::Gemini { model, .. }
+            | Self::OpenAi { model, .. }
+            | Self::OpenAiCompatible { model, .. } => model,
+        }
+    }
+}
+
+/// Sampling settings applied to every agent the builders create.
+/// `None` leaves the provider's default in place, so `AgentParams::default()`
+/// changes nothing about the requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AgentParams {
+    /// `LLM_TEMPERATURE`, 0.0 to 2.0
+    pub temperature: Option<f64>,
+    /// `LLM_TOP_P`, 0.0 to 1.0
+    pub top_p: Option<f64>,
+    /// `LLM_MAX_TOKENS`, the maximum tokens per completion
+    pub max_tokens: Option<u64>,
+}
+
+/// Everything besides the provider that shapes the agents the builders
+/// create, taken from `Config` at startup. `AgentSettings::default()` is what
+/// an empty environment configures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentSettings {
+    /// Sampling settings
+    pub params: AgentParams,
+    /// `MAX_TOOL_ROUNDS`
+    pub max_tool_rounds: usize,
+    /// `ASSISTANT_NAME`, how the assistant introduces itself
+    pub assistant_name: Option<String>,
+    /// `RESPONSE_STYLE`
+    pub response_style: ResponseStyle,
+    /// `GEMINI_SAFETY`; used by Gemini agents only
+    pub gemini_safety: GeminiSafety,
+    /// `ENABLED_TOOLS`, the built-in tools each agent is given
+    pub enabled_tools: EnabledTools,
+}
+
+impl Default for AgentSettings {
+    fn default() -> Self {
+        Self {
+            params: AgentParams::default(),
+            max_tool_rounds: DEFAULT_MAX_TOOL_ROUNDS,
+            assistant_name: None,
+            response_style: ResponseStyle::default(),
+            gemini_safety: GeminiSafety::default(),
+            enabled_tools: EnabledTools::all(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_defaults_to_ollama() {
+        let result = ProviderConfig::from_lookup(lookup_from(&[]));
+
+        assert_eq!(
+            result,
+            Ok(ProviderConfig::Ollama {
+                host: DEFAULT_OLLAMA_HOST.to_string(),
+                model: "qwen3".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_default_provider_resolution() {
+        let cases: &[(&[(&str, &str)], &str)] = &[
+            (&[], "ollama"),
+            (&[("LLM_DEFAULT_PROVIDER", "openai")], "openai"),
+            (
+                &[
+                    ("LLM_PROVIDER", "gemini"),
+                    ("LLM_DEFAULT_PROVIDER", "openai"),
+                ],
+                "gemini",
+            ),
+            (
+                &[("LLM_PROVIDER", " "), ("LLM_DEFAULT_PROVIDER", "gemini")],
+                "gemini",
+            ),
+            (&[("LLM_PROVIDER", " OpenAI ")], "openai"),
+            (&[("LLM_DEFAULT_PROVIDER", "Gemini ")], "gemini"),
+        ];
+
+        for (vars, expected) in cases {
+            assert_eq!(
+                resolve_provider(lookup_from(vars)).as_deref(),
+                Ok(*expected),
+                "{vars:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_provider_is_an_error_naming_the_variable() {
+        for var in ["LLM_PROVIDER", "LLM_DEFAULT_PROVIDER"] {
+            let err =
+                ProviderConfig::from_lookup(lookup_from(&[(var, " anthropic ")])).unwrap_err();
+
+            assert_eq!(
+                err,
+                ConfigError::InvalidValue {
+                    var,
+                    value: "anthropic".to_string(),
+                    expected: "ollama, gemini, openai or openai_compat",
+                }
+            );
+            assert!(err.to_string().contains("openai_compat"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_provider_settings_build_other_providers_from_startup_values() {
+        let settings = ProviderSettings::from_lookup(lookup_from(&[
+            ("LLM_DEFAULT_PROVIDER", "gemini"),
+            ("GEMINI_API_KEY", "g-key"),
+            ("OLLAMA_HOST", "http://gpu-box:11434/"),
+        ]))
+        .unwrap();
+
+        assert_eq!(settings.provider, "gemini");
+        assert_eq!(
+            settings.provider_config("ollama", "llama3"),
+            Ok(ProviderConfig::Ollama {
+                host: "http://gpu-box:11434".to_string(),
+                model: "llama3".to_string()
+            })
+        );
+        assert_eq!(
+            settings.provider_config("openai", "gpt-4.1"),
+            Err(ConfigError::MissingApiKey {
+                provider: "openai",
+                var: "OPENAI_API_KEY"
+            })
+        );
+    }
+
+    #[test]
+    fn test_model_allowlist_parse() {
+        let allowlist: ModelAllowlist = " openai:gpt-4.1 ,, gemini:gemini-2.5-pro".parse().unwrap();
+
+        assert!(allowlist.allows("openai", "gpt-4.1"));
+        assert!(allowlist.allows("gemini", "gemini-2.5-pro"));
+        assert!(!allowlist.allows("openai", "gpt-4.1-mini"));
+    }
+
+    #[test]
+    fn test_model_allowlist_rejects_malformed_entries() {
+        for (list, bad) in [
+            ("openai:gpt-4.1,bogus", "bogus"),
+            ("openai:", "openai:"),
+            (":gpt-4.1", ":gpt-4.1"),
+        ] {
+            assert_eq!(
+                list.parse::<ModelAllowlist>(),
+                Err(bad.to_string()),
+                "{list}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_provider_selects_provider_and_model() {
+        let result = ProviderConfig::from_lookup(lookup_from(&[
+            ("LLM_DEFAULT_PROVIDER", "openai"),
+            ("OPENAI_API_KEY", "sk-test"),
+        ]));
+
+        assert_eq!(
+            result,
+            Ok(ProviderConfig::OpenAi {
+                api_key: "sk-test".to_string(),
+                model: "gpt-4.1-mini".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_ollama_uses_configured_host() {
+        let result =
+            ProviderConfig::from_lookup(lookup_from(&[("OLLAMA_HOST", "http://gpu-box:11434/")]));
+
+        assert_eq!(
+            result,
+            Ok(ProviderConfig::Ollama {
+                host: "http://gpu-box:11434".to_string(),
+                model: "qwen3".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_ollama_host_falls_back_to_default() {
+        assert_eq!(resolve_ollama_host(None).unwrap(), DEFAULT_OLLAMA_HOST);
+        assert_eq!(
+            resolve_ollama_host(Some("  ".to_string())).unwrap(),
+            DEFAULT_OLLAMA_HOST
+        );
+    }
+
+    #[test]
+    fn test_resolve_ollama_host_rejects_malformed_url() {
+        for value in ["gpu-box:11434", "ftp://gpu-box", "not a url"] {
+            assert_eq!(
+                resolve_ollama_host(Some(value.to_string())),
+                Err(ConfigError::InvalidOllamaHost(value.to_string())),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_openai_compat_uses_configured_base_url() {
+        let result = ProviderConfig::from_lookup(lookup_from(&[
+            ("LLM_PROVIDER", "openai_compat"),
+            ("OPENAI_BASE_URL", "http://localhost:8000/v1/"),
+            ("OPENAI_API_KEY", "local"),
+            ("LLM_MODEL", "meta-llama/Llama-3.1-8B-Instruct"),
+        ]));
+
+        assert_eq!(
+            result,
+            Ok(ProviderConfig::OpenAiCompatible {
+                base_url: "http://localhost:8000/v1".to_string(),
+                api_key: "local".to_string(),
+                model: "meta-llama/Llama-3.1-8B-Instruct".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_openai_compat_requires_base_url_and_model() {
+        let vars = [
+            ("LLM_PROVIDER", "openai_compat"),
+            ("OPENAI_BASE_URL", "http://localhost:8000/v1"),
+            ("OPENAI_API_KEY", "local"),
+            ("LLM_MODEL", "qwen2.5-7b-instruct"),
+        ];
+        let without = |var: &str| {
+            let vars: Vec<_> = vars.iter().copied().filter(|(k, _)| *k != var).collect();
+            ProviderConfig::from_lookup(lookup_from(&vars))
+        };
+
+        assert_eq!(
+            without("OPENAI_BASE_URL"),
+            Err(ConfigError::MissingVar("OPENAI_BASE_URL"))
+        );
+        assert_eq!(
+            without("LLM_MODEL"),
+            Err(ConfigError::MissingVar("LLM_MODEL"))
+        );
+        assert_eq!(
+            without("OPENAI_API_KEY"),
+            Err(ConfigError::MissingApiKey {
+                provider: "openai_compat",
+                var: "OPENAI_API_KEY"
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_openai_base_url_rejects_malformed_url() {
+        for value in ["localhost:8000/v1", "ftp://models.local", "not a url"] {
+            assert_eq!(
+                resolve_openai_base_url(Some(value.to_string())),
+                Err(ConfigError::InvalidOpenAiBaseUrl(value.to_string())),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_openai_without_api_key_is_error() {
+        let result = ProviderConfig::from_lookup(lookup_from(&[("LLM_PROVIDER", "openai")]));
+
+        assert_eq!(
+            result,
+            Err(ConfigError::MissingApiKey {
+                provider: "openai",
+                var: "OPENAI_API_KEY"
+            })
+        );
+    }
+
+    #[test]
+    fn test_gemini_without_api_key_is_error() {
+        let result = ProviderConfig::from_lookup(lookup_from(&[("LLM_PROVIDER", "gemini")]));
+
+        assert_eq!(
+            result,
+            Err(ConfigError::MissingApiKey {
+                provider: "gemini",
+                var: "GEMINI_API_KEY"
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_api_key_error_names_variable() {
+        let err =
+            ProviderConfig::from_lookup(lookup_from(&[("LLM_PROVIDER", "gemini")])).unwrap_err();
+
+        assert!(err.to_string().contains("GEMINI_API_KEY"));
+    }
+
+    #[test]
+    fn test_model_mismatch_only_warns_by_default() {
+        let result = ProviderConfig::from_lookup(lookup_from(&[
+            ("LLM_PROVIDER", "gemini"),
+            ("GEMINI_API_KEY", "key"),
+            ("LLM_MODEL", "gpt-4.1-mini"),
+        ]));
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_model_mismatch_is_error_in_strict_mode() {
+        let result = ProviderConfig::from_lookup(lookup_from(&[
+            ("LLM_PROVIDER", "gemini"),
+            ("GEMINI_API_KEY", "key"),
+            ("LLM_MODEL", "gpt-4.1-mini"),
+            ("LLM_MODEL_STRICT", "true"),
+        ]));
+
+        assert_eq!(
+            result,
+            Err(ConfigError::ModelProviderMismatch {
+                provider: "gemini".to_string(),
+                model: "gpt-4.1-mini".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_openai_with_api_key_and_model() {
+        let result = ProviderConfig::from_lookup(lookup_from(&[
+            ("LLM_PROVIDER", "openai"),
+            ("OPENAI_API_KEY", "sk-test"),
+            ("LLM_MODEL", "gpt-4.1"),
+        ]));
+
+        assert_eq!(
+            result,
+            Ok(ProviderConfig::OpenAi {
+                api_key: "sk-test".to_string(),
+                model: "gpt-4.1".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::str::FromStr;
+
+use crate::collectors::web::PageContent;
+
+/// Replacement for redacted terms
+const REDACTED: &str = "[redacted]";
+
+/// What `ContentFilter` does with fetched text that contains a listed term
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentFilterMode {
+    /// Pass text through unchanged
+    #[default]
+    Off,
+    /// Replace each listed term with `[redacted]`
+    Redact,
+    /// Refuse the whole page
+    Block,
+}
+
+impl FromStr for ContentFilterMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "redact" => Ok(Self::Redact),
+            "block" => Ok(Self::Block),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Returned when a page is refused in `Block` mode
+#[derive(Debug, thiserror::Error)]
+#[error("page contains terms blocked by the content filter")]
+pub struct ContentBlocked;
+
+/// Scans fetched text against a wordlist.
+/// Matching is ASCII case-insensitive; terms starting or ending with an ASCII letter
+/// or digit only match whole words, so "ass" doesn't match "class".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContentFilter {
+    mode: ContentFilterMode,
+    words: Vec<String>,
+}
+
+impl ContentFilter {
+    pub fn new(mode: ContentFilterMode, words: Vec<String>) -> Self {
+        let words = words
+            .into_iter()
+            .map(|w| w.trim().to_string())
+            .filter(|w| !w.is_empty())
+            .collect();
+        Self { mode, words }
+    }
+
+    /// Whether the wordlist has no terms, so nothing is ever filtered
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Filter `text` according to the mode.
+    /// Returns the (possibly redacted) text, or `ContentBlocked` in `Block` mode.
+    pub fn apply(&self, text: String) -> Result<String, ContentBlocked> {
+        if self.mode == ContentFilterMode::Off || self.words.is_empty() {
+            return Ok(text);
+        }
+        let matches = self.find_matches(&text);
+        if matches.is_empty() {
+            return Ok(text);
+        }
+        if self.mode == ContentFilterMode::Block {
+            return Err(ContentBlocked);
+        }
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end) in matches {
+            redacted.push_str(&text[last..start]);
+            redacted.push_str(REDACTED);
+            last = end;
+        }
+        redacted.push_str(&text[last..]);
+        Ok(redacted)
+    }
+
+    /// Filter each of `texts` in place.
+    /// In `Block` mode, fails as soon as any of them contains a listed term.
+    pub(crate) fn apply_all<'a>(
+        &self,
+        texts: impl IntoIterator<Item = &'a mut String>,
+    ) -> Result<(), ContentBlocked> {
+        for text in texts {
+            *text = self.apply(std::mem::take(text))?;
+        }
+        Ok(())
+    }
+
+    /// Filter the text, title, headings, link text, metadata and raw HTML of a fetched page
+    pub(crate) fn apply_page(&self, mut page: PageContent) -> Result<PageContent, ContentBlocked> {
+        self.apply_all(
+            std::iter::once(&mut page.text)
+                .chain(page.title.as_mut())
+                .chain(page.headings.iter_mut().map(|h| &mut h.text))
+                .chain(page.links.iter_mut().map(|l| &mut l.text))
+                .chain(page.metadata.values_mut())
+                .chain(page.raw_html.as_mut()),
+        )?;
+        Ok(page)
+    }
+
+    /// Byte ranges of non-overlapping matches, in order
+    fn find_matches(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        let mut pos = 0;
+        while pos < text.len() {
+            let found = self.words.iter().find_map(|word| {
+                let candidate = text.get(pos..pos + word.len())?;
+                let end = pos + word.len();
+                (candidate.eq_ignore_ascii_case(word) && is_boundary(text, pos, end, word))
+                    .then_some(end)
+            });
+            match found {
+                Some(end) => {
+                    matches.push((pos, end));
+                    pos = end;
+                }
+                None => pos += text[pos..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        matches
+    }
+}
+
+/// Whether `text[start..end]` (a match of `word`) stands on word boundaries.
+/// Only checked on sides where `word` itself begins/ends with an ASCII alphanumeric
+/// character, so terms in scripts without spaces (e.g. Japanese) still match.
+fn is_boundary(text: &str, start: usize, end: usize, word: &str) -> bool {
+    let is_alnum = |c: char| c.is_ascii_alphanumeric();
+    let starts_alnum = word.chars().next().is_some_and(is_alnum);
+    let ends_alnum = word.chars().next_back().is_some_and(is_alnum);
+    let before_ok = !starts_alnum || !text[..start].chars().next_back().is_some_and(is_alnum);
+    let after_ok = !ends_alnum || !text[end..].chars().next().is_some_and(is_alnum);
+    before_ok && after_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(mode: ContentFilterMode) -> ContentFilter {
+        ContentFilter::new(mode, vec!["darn".to_string(), "暴力".to_string()])
+    }
+
+    #[test]
+    fn test_redact_replaces_whole_words_case_insensitively() {
+        let result = filter(ContentFilterMode::Redact)
+            .apply("Darn it, darnation. darn!".to_string())
+            .unwrap();
+
+        assert_eq!(result, "[redacted] it, darnation. [redacted]!");
+    }
+
+    #[test]
+    fn test_redact_matches_inside_text_without_spaces() {
+        let result = filter(ContentFilterMode::Redact)
+            .apply("この映画は暴力的です".to_string())
+            .unwrap();
+
+        assert_eq!(result, "この映画は[redacted]的です");
+    }
+
+    #[test]
+    fn test_block_refuses_matching_text() {
+        let sut = filter(ContentFilterMode::Block);
+
+        assert!(sut.apply("well, darn".to_string()).is_err());
+        assert_eq!(sut.apply("all clean".to_string()).unwrap(), "all clean");
+    }
+
+    #[test]
+    fn test_apply_all_redacts_every_text() {
+        let mut title = "Darn title".to_string();
+        let mut summary = "clean, then darn".to_string();
+
+        filter(ContentFilterMode::Redact)
+            .apply_all([&mut title, &mut summary])
+            .unwrap();
+
+        assert_eq!(title, "[redacted] title");
+        assert_eq!(summary, "clean, then [redacted]");
+    }
+
+    #[test]
+    fn test_apply_page_blocks_on_a_match_outside_the_text() {
+        let page = PageContent {
+            url: "https://example.com".to_string(),
+            title: Some("All clean".to_string()),
+            text: "Nothing to see".to_string(),
+            links: vec![],
+            headings: vec![],
+            metadata: [("description".to_string(), "darn".to_string())].into(),
+            detected_language: None,
+            raw_html: None,
+            access_wall: None,
+        };
+
+        assert!(filter(ContentFilterMode::Block).apply_page(page).is_err());
+    }
+
+    #[test]
+    fn test_off_passes_text_through() {
+        let result = filter(ContentFilterMode::Off)
+            .apply("darn".to_string())
+            .unwrap();
+
+        assert_eq!(result, "darn");
+    }
+}
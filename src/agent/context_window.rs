@@ -0,0 +1,183 @@
+use log::info;
+use rig::completion::Message;
+
+use crate::session::{message_text, summary_line, summary_turn};
+
+/// Context window (in tokens) assumed for models missing from `CONTEXT_WINDOWS`
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8_192;
+
+/// Context windows by model name prefix. More specific prefixes come first,
+/// since the first match wins (e.g. `gpt-4.1` before `gpt-4`).
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    // OpenAI
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-5", 400_000),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    // Gemini
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-", 1_048_576),
+    ("gemma-3", 131_072),
+    // Common Ollama models
+    ("qwen3", 40_960),
+    ("qwen2.5", 32_768),
+    ("llama3.1", 131_072),
+    ("llama3.2", 131_072),
+    ("llama3", 8_192),
+    ("mistral", 32_768),
+    ("gemma3", 131_072),
+    ("phi4", 16_384),
+];
+
+/// Share of the context window the request may fill before history is compacted;
+/// the rest is left for the reply, the preamble and tool output
+const COMPACTION_THRESHOLD: f64 = 0.8;
+
+/// Characters per token assumed by `estimate_tokens`
+const CHARS_PER_TOKEN: usize = 4;
+
+/// The context window of `model` in tokens, or `DEFAULT_CONTEXT_WINDOW` if unknown.
+/// Ollama tags (`qwen3:14b`) and case are ignored when matching.
+pub fn context_window(model: &str) -> usize {
+    let model = model.to_ascii_lowercase();
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map_or(DEFAULT_CONTEXT_WINDOW, |(_, tokens)| *tokens)
+}
+
+/// Rough token count of `text` (about four characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Whether a request of `estimated_tokens` is close enough to `window` to compact
+pub(crate) fn needs_compaction(estimated_tokens: usize, window: usize) -> bool {
+    estimated_tokens as f64 > window as f64 * COMPACTION_THRESHOLD
+}
+
+fn history_tokens(history: &[Message]) -> usize {
+    history
+        .iter()
+        .filter_map(message_text)
+        .map(|text| estimate_tokens(&text))
+        .sum()
+}
+
+/// Fit `history` into `model`'s context window before a request.
+///
+/// If the prompt plus history is estimated to exceed the compaction threshold,
+/// the oldest turns are folded into an extractive summary turn (as with
+/// `TrimStrategy::Summarize`) until it fits. Otherwise `history` is returned as is.
+pub fn compact_for_context(history: Vec<Message>, prompt: &str, model: &str) -> Vec<Message> {
+    let window = context_window(model);
+    let prompt_tokens = estimate_tokens(prompt);
+    let before = prompt_tokens + history_tokens(&history);
+    if !needs_compaction(before, window) {
+        return history;
+    }
+
+    let mut remaining = history.as_slice();
+    let mut kept_tokens = history_tokens(remaining);
+    let mut summary: Vec<String> = Vec::new();
+    let mut summary_tokens = 0;
+    while !remaining.is_empty()
+        && needs_compaction(prompt_tokens + summary_tokens + kept_tokens, window)
+    {
+        // Drop whole turns so the kept history still starts with a user message
+        let (dropped, rest) = remaining.split_at(remaining.len().min(2));
+        summary.extend(dropped.iter().filter_map(summary_line));
+        summary_tokens = summary_turn(&summary)
+            .iter()
+            .filter_map(message_text)
+            .map(|text| estimate_tokens(&text))
+            .sum();
+        kept_tokens -= history_tokens(dropped);
+        remaining = rest;
+    }
+
+    info!(
+        "Compacted history for {model} ({window} token window): {} of {} messages summarized, ~{before} -> ~{} tokens",
+        history.len() - remaining.len(),
+        history.len(),
+        prompt_tokens + summary_tokens + kept_tokens
+    );
+    let mut compacted = Vec::with_capacity(remaining.len() + 2);
+    if !summary.is_empty() {
+        compacted.extend(summary_turn(&summary));
+    }
+    compacted.extend_from_slice(remaining);
+    compacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turns(count: usize, chars: usize) -> Vec<Message> {
+        (0..count)
+            .flat_map(|i| {
+                [
+                    Message::user(format!("q{i} {}", "x".repeat(chars))),
+                    Message::assistant(format!("a{i} {}", "y".repeat(chars))),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_context_window_lookup() {
+        assert_eq!(context_window("gpt-4.1-mini"), 1_047_576);
+        assert_eq!(context_window("gpt-4o-mini"), 128_000);
+        assert_eq!(context_window("gpt-4"), 8_192);
+        assert_eq!(context_window("Gemini-2.5-Flash"), 1_048_576);
+        assert_eq!(context_window("gemini-1.5-pro-002"), 2_097_152);
+        assert_eq!(context_window("qwen3:14b"), 40_960);
+        assert_eq!(context_window("my-finetune"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn test_needs_compaction_threshold() {
+        assert!(!needs_compaction(800, 1_000));
+        assert!(needs_compaction(801, 1_000));
+        assert!(!needs_compaction(0, 1_000));
+    }
+
+    #[test]
+    fn test_estimate_tokens_rounds_up() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_history_within_window_is_unchanged() {
+        let history = turns(3, 100);
+
+        let compacted = compact_for_context(history.clone(), "hi", "gpt-4.1");
+
+        assert_eq!(compacted, history);
+    }
+
+    #[test]
+    fn test_history_near_window_is_summarized_from_the_oldest_turn() {
+        // ~10 turns of ~2000 tokens each against an 8192 token window
+        let history = turns(10, 4_000);
+
+        let compacted = compact_for_context(history.clone(), "next question", "unknown-model");
+
+        let tokens = estimate_tokens("next question") + history_tokens(&compacted);
+        assert!(
+            !needs_compaction(tokens, DEFAULT_CONTEXT_WINDOW),
+            "{tokens}"
+        );
+        let summary = message_text(&compacted[0]).unwrap();
+        assert!(summary.contains("- User: q0 "), "{summary}");
+        assert_eq!(compacted.last(), history.last());
+        assert!(compacted.len() < history.len());
+    }
+}
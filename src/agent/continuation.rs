@@ -0,0 +1,387 @@
+//! Answers cut off at the output token limit: detecting them from the
+//! provider's final response, and optionally asking the model to continue.
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::{stream, Stream, StreamExt};
+use log::info;
+use rig::completion::{GetTokenUsage, Message};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{ChatAgent, ChatStreamEvent};
+
+/// Follow-up sent to the model to get the rest of a cut-off answer
+pub const CONTINUE_PROMPT: &str = "Your previous answer was cut off. Continue exactly where it \
+     stopped, without repeating any of it and without an introduction.";
+
+/// Characters of a continuation buffered before checking whether it repeats
+/// the end of the previous part
+const STITCH_WINDOW_CHARS: usize = 200;
+
+/// Shortest repeated passage that is dropped when stitching; shorter matches
+/// (e.g. a repeated letter or word) are likely to be coincidental
+const MIN_OVERLAP_CHARS: usize = 10;
+
+/// Whether a provider's final response for one turn stopped at the token limit.
+///
+/// rig doesn't pass finish reasons through, so the raw response is checked for
+/// one (Ollama's `done_reason: "length"`, `finish_reason: "length"`,
+/// `stop_reason: "max_tokens"`, ...); failing that, a turn whose output used
+/// the whole `max_tokens` budget counts as cut off.
+pub(crate) fn stopped_at_token_limit<R: Serialize + GetTokenUsage>(
+    response: &R,
+    max_tokens: Option<u64>,
+) -> bool {
+    let reported = serde_json::to_value(response)
+        .map(|value| finish_reason_is_length(&value))
+        .unwrap_or(false);
+    let used_budget = match (max_tokens, response.token_usage()) {
+        (Some(max), Some(usage)) => max > 0 && usage.output_tokens >= max,
+        _ => false,
+    };
+    reported || used_budget
+}
+
+fn finish_reason_is_length(response: &Value) -> bool {
+    [
+        "done_reason",
+        "finish_reason",
+        "finishReason",
+        "stop_reason",
+    ]
+    .iter()
+    .filter_map(|key| response.get(key).and_then(Value::as_str))
+    .any(|reason| {
+        reason.eq_ignore_ascii_case("length") || reason.eq_ignore_ascii_case("max_tokens")
+    })
+}
+
+/// The part of `continuation` to append to `previous`: models asked to
+/// continue often start by repeating the last words they sent, so the longest
+/// start of `continuation` that `previous` ends with is dropped.
+pub(crate) fn stitch<'a>(previous: &str, continuation: &'a str) -> &'a str {
+    let overlap = continuation
+        .char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .rev()
+        .find(|&end| previous.ends_with(&continuation[..end]))
+        .filter(|&end| continuation[..end].chars().count() >= MIN_OVERLAP_CHARS);
+    match overlap {
+        Some(end) => &continuation[end..],
+        None => continuation,
+    }
+}
+
+/// Continue an answer cut off at the token limit up to `max_continuations`
+/// times, sending `CONTINUE_PROMPT` with the answer so far and stitching each
+/// continuation onto it. The result reads as one answer: `Truncated` and
+/// `Done` are only passed on for the last part.
+///
+/// `prompt` and `history` must be the ones `events` was started with.
+/// With `max_continuations` of 0 the stream is passed through unchanged.
+pub fn continue_truncated(
+    agent: Arc<dyn ChatAgent>,
+    prompt: &str,
+    history: Vec<Message>,
+    events: Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>,
+    max_continuations: usize,
+) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+    if max_continuations == 0 {
+        return events;
+    }
+    let state = Continuation {
+        agent,
+        prompt: prompt.to_string(),
+        history,
+        events: Some(events),
+        answer: String::new(),
+        opening: None,
+        truncated: false,
+        left: max_continuations,
+    };
+    Box::pin(
+        stream::unfold(state, |mut state| async move {
+            let out = state.next_events().await?;
+            Some((out, state))
+        })
+        .flat_map(stream::iter),
+    )
+}
+
+struct Continuation {
+    agent: Arc<dyn ChatAgent>,
+    prompt: String,
+    history: Vec<Message>,
+    /// The part being streamed; `None` once the answer is finished
+    events: Option<Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>>,
+    /// Answer text sent so far, across all parts
+    answer: String,
+    /// Start of a continuation, held back until it can be stitched
+    opening: Option<String>,
+    /// Whether the current part was cut off
+    truncated: bool,
+    /// Continuations still allowed
+    left: usize,
+}
+
+impl Continuation {
+    /// Events to pass on for the next item of the current part;
+    /// `None` when the answer is finished
+    async fn next_events(&mut self) -> Option<Vec<ChatStreamEvent>> {
+        let event = self.events.as_mut()?.next().await;
+        let mut out = Vec::new();
+        match event {
+            Some(ChatStreamEvent::TextDelta(text)) => match &mut self.opening {
+                Some(opening) => {
+                    opening.push_str(&text);
+                    if opening.chars().count() >= STITCH_WINDOW_CHARS {
+                        out.extend(self.flush_opening());
+                    }
+                }
+                None => {
+                    self.answer.push_str(&text);
+                    out.push(ChatStreamEvent::TextDelta(text));
+                }
+            },
+            Some(ChatStreamEvent::Truncated) => self.truncated = true,
+            Some(ChatStreamEvent::Done) => {
+                out.extend(self.flush_opening());
+                if self.truncated && self.left > 0 {
+                    self.left -= 1;
+                    self.truncated = false;
+                    info!("Response hit the token limit; asking the model to continue");
+                    let mut history = self.history.clone();
+                    history.push(Message::user(&self.prompt));
+                    history.push(Message::assistant(&self.answer));
+                    self.events = Some(self.agent.stream_chat(CONTINUE_PROMPT, history).await);
+                    self.opening = Some(String::new());
+                } else {
+                    if self.truncated {
+                        out.push(ChatStreamEvent::Truncated);
+                    }
+                    out.push(ChatStreamEvent::Done);
+                    self.events = None;
+                }
+            }
+            Some(ChatStreamEvent::Error(e)) => {
+                out.extend(self.flush_opening());
+                out.push(ChatStreamEvent::Error(e));
+            }
+            Some(other) => out.push(other),
+            None => {
+                out.extend(self.flush_opening());
+                self.events = None;
+            }
+        }
+        Some(out)
+    }
+
+    /// Stitch the held-back start of a continuation onto the answer
+    fn flush_opening(&mut self) -> Option<ChatStreamEvent> {
+        let opening = self.opening.take()?;
+        let text = stitch(&self.answer, &opening).to_string();
+        self.answer.push_str(&text);
+        (!text.is_empty()).then_some(ChatStreamEvent::TextDelta(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::MockAgent;
+    use rig::completion::Usage;
+    use serde_json::json;
+
+    /// A provider response with an optional finish reason and output token count
+    #[derive(Serialize)]
+    struct FinalResponse {
+        #[serde(flatten)]
+        fields: Value,
+        #[serde(skip)]
+        output_tokens: Option<u64>,
+    }
+
+    impl GetTokenUsage for FinalResponse {
+        fn token_usage(&self) -> Option<Usage> {
+            let output_tokens = self.output_tokens?;
+            let mut usage = Usage::new();
+            usage.output_tokens = output_tokens;
+            Some(usage)
+        }
+    }
+
+    fn response(fields: Value, output_tokens: Option<u64>) -> FinalResponse {
+        FinalResponse {
+            fields,
+            output_tokens,
+        }
+    }
+
+    #[test]
+    fn test_finish_reason_length_is_detected() {
+        let cases = [
+            json!({ "done_reason": "length" }),
+            json!({ "finish_reason": "length" }),
+            json!({ "finishReason": "MAX_TOKENS" }),
+            json!({ "stop_reason": "max_tokens" }),
+        ];
+
+        for fields in cases {
+            assert!(
+                stopped_at_token_limit(&response(fields.clone(), None), None),
+                "{fields}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_normal_stop_is_not_truncation() {
+        let stop = response(json!({ "done_reason": "stop" }), Some(120));
+        let no_reason = response(json!({}), None);
+
+        assert!(!stopped_at_token_limit(&stop, Some(512)));
+        assert!(!stopped_at_token_limit(&no_reason, Some(512)));
+    }
+
+    #[test]
+    fn test_output_using_whole_budget_is_truncation() {
+        let full = response(json!({}), Some(512));
+
+        assert!(stopped_at_token_limit(&full, Some(512)));
+        // Without a configured limit the token count says nothing
+        assert!(!stopped_at_token_limit(&full, None));
+    }
+
+    #[test]
+    fn test_stitch_drops_repeated_tail() {
+        let previous = "Rust guarantees memory safety without a garbage";
+        let continuation = "without a garbage collector, using ownership.";
+
+        assert_eq!(
+            stitch(previous, continuation),
+            " collector, using ownership."
+        );
+    }
+
+    #[test]
+    fn test_stitch_keeps_short_coincidental_overlap() {
+        // "e" ends the previous part and starts the continuation by chance
+        assert_eq!(stitch("The answer is the", "e end"), "e end");
+        assert_eq!(stitch("First part. ", "Second part."), "Second part.");
+    }
+
+    #[test]
+    fn test_stitch_handles_multibyte_text() {
+        let previous = "所有権と借用によってメモリ安全性を";
+        let continuation = "借用によってメモリ安全性を保証します。";
+
+        assert_eq!(stitch(previous, continuation), "保証します。");
+    }
+
+    fn text_events(text: &str, truncated: bool) -> Vec<ChatStreamEvent> {
+        let mut events = vec![ChatStreamEvent::TextDelta(text.to_string())];
+        if truncated {
+            events.push(ChatStreamEvent::Truncated);
+        }
+        events.push(ChatStreamEvent::Done);
+        events
+    }
+
+    async fn run(
+        agent: Arc<MockAgent>,
+        first: Vec<ChatStreamEvent>,
+        max_continuations: usize,
+    ) -> Vec<ChatStreamEvent> {
+        continue_truncated(
+            agent,
+            "Explain ownership",
+            vec![],
+            Box::pin(stream::iter(first)),
+            max_continuations,
+        )
+        .collect()
+        .await
+    }
+
+    fn text_of(events: &[ChatStreamEvent]) -> String {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                ChatStreamEvent::TextDelta(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_truncated_answer_is_continued_and_stitched() {
+        let agent = Arc::new(MockAgent::new(vec![text_events(
+            "each value has one owner. When the owner goes out of scope, it is dropped.",
+            false,
+        )]));
+
+        let events = run(
+            agent.clone(),
+            text_events("Ownership means each value has one owner.", true),
+            1,
+        )
+        .await;
+
+        assert_eq!(
+            text_of(&events),
+            "Ownership means each value has one owner. When the owner goes out of scope, it is dropped."
+        );
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, ChatStreamEvent::Truncated)));
+        let done = events
+            .iter()
+            .filter(|e| matches!(e, ChatStreamEvent::Done))
+            .count();
+        assert_eq!(done, 1);
+        assert!(matches!(events.last(), Some(ChatStreamEvent::Done)));
+        // The continuation request carries the question and the answer so far
+        assert_eq!(
+            agent.received_histories(),
+            [vec![
+                Message::user("Explain ownership"),
+                Message::assistant("Ownership means each value has one owner."),
+            ]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_continuations_stop_at_the_limit() {
+        let agent = Arc::new(MockAgent::new(vec![
+            text_events(" second", true),
+            text_events(" third", true),
+        ]));
+
+        let events = run(agent, text_events("first", true), 1).await;
+
+        assert_eq!(text_of(&events), "first second");
+        assert!(matches!(
+            events.as_slice(),
+            [.., ChatStreamEvent::Truncated, ChatStreamEvent::Done]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_complete_answer_and_zero_limit_pass_through() {
+        let no_replies = || Arc::new(MockAgent::new(vec![]));
+        let complete = run(no_replies(), text_events("done.", false), 3).await;
+        let disabled = run(no_replies(), text_events("cut", true), 0).await;
+
+        assert_eq!(text_of(&complete), "done.");
+        assert!(matches!(
+            disabled.as_slice(),
+            [
+                ChatStreamEvent::TextDelta(_),
+                ChatStreamEvent::Truncated,
+                ChatStreamEvent::Done
+            ]
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use log::info;
+use rig::completion::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::content_filter::ContentFilter;
+use super::WebFetch;
+use crate::collectors::feed::{fetch_feed, FeedEntry};
+use crate::collectors::robots::RobotsCache;
+use crate::collectors::web::ReqwestClient;
+use crate::metrics::Metrics;
+
+/// Arguments for the FeedFetch tool
+#[derive(Deserialize)]
+pub struct FeedFetchArgs {
+    url: String,
+}
+
+/// Output from the FeedFetch tool
+#[derive(Serialize)]
+pub struct FeedFetchOutput {
+    entries: Vec<FeedEntry>,
+}
+
+/// Error type for FeedFetch tool
+#[derive(Debug, thiserror::Error)]
+pub enum FeedFetchError {
+    #[error("Failed to fetch feed: {0}")]
+    FetchError(#[from] anyhow::Error),
+}
+
+/// Reads the entries of an RSS or Atom feed, e.g. for news research.
+/// Shares the HTTP client, robots.txt cache, metrics and content filter
+/// with the `WebFetch` it was created from.
+#[derive(Clone)]
+pub struct FeedFetch {
+    http: ReqwestClient,
+    robots_cache: RobotsCache,
+    metrics: Arc<Metrics>,
+    content_filter: ContentFilter,
+}
+
+impl FeedFetch {
+    pub fn new(web_fetch: &WebFetch) -> Self {
+        Self {
+            http: web_fetch.http.clone(),
+            robots_cache: web_fetch.robots_cache.clone(),
+            metrics: web_fetch.metrics.clone(),
+            content_filter: web_fetch.content_filter.clone(),
+        }
+    }
+}
+
+impl rig::tool::Tool for FeedFetch {
+    const NAME: &'static str = "feed_fetch";
+    type Error = FeedFetchError;
+    type Args = FeedFetchArgs;
+    type Output = FeedFetchOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Reads an RSS or Atom feed and lists its entries (title, link, \
+                          published date, summary). Use for news and blog updates"
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "The RSS or Atom feed URL"
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        info!("Fetching feed {} ...", args.url);
+        // A feed refused by the content filter counts as a failed fetch
+        let entries = fetch_feed(&self.http, &args.url, &self.robots_cache)
+            .await
+            .and_then(|mut entries| {
+                self.content_filter
+                    .apply_all(entries.iter_mut().flat_map(|entry| {
+                        [&mut entry.title, &mut entry.link]
+                            .into_iter()
+                            .chain(entry.summary.as_mut())
+                    }))?;
+                Ok(entries)
+            });
+        self.metrics.record_fetch(entries.as_ref().err());
+        let entries = entries?;
+        Ok(FeedFetchOutput { entries })
+    }
+}
@@ -0,0 +1,239 @@
+//! Gemini safety-filter thresholds (`GEMINI_SAFETY`) and a readable error for
+//! answers the filters blocked, which Gemini otherwise reports as an empty
+//! or unexplained response.
+
+use std::pin::Pin;
+use std::str::FromStr;
+
+use futures::{Stream, StreamExt};
+use serde_json::json;
+
+use super::ChatStreamEvent;
+
+/// Harm categories Gemini accepts thresholds for, without the `HARM_CATEGORY_` prefix
+const HARM_CATEGORIES: &[&str] = &[
+    "HARASSMENT",
+    "HATE_SPEECH",
+    "SEXUALLY_EXPLICIT",
+    "DANGEROUS_CONTENT",
+    "CIVIC_INTEGRITY",
+];
+
+/// Block thresholds, from least to most permissive
+const THRESHOLDS: &[&str] = &[
+    "BLOCK_LOW_AND_ABOVE",
+    "BLOCK_MEDIUM_AND_ABOVE",
+    "BLOCK_ONLY_HIGH",
+    "BLOCK_NONE",
+    "OFF",
+];
+
+/// Finish and block reasons Gemini gives for content it refused to return
+const BLOCK_REASONS: &[&str] = &[
+    "safety",
+    "prohibitedcontent",
+    "prohibited_content",
+    "blocklist",
+    "spii",
+];
+
+/// Thresholds for Gemini's safety filters, sent as `safetySettings`.
+/// Categories that aren't listed keep Gemini's defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeminiSafety {
+    /// (category, threshold) pairs with the API's names, in category order
+    settings: Vec<(String, &'static str)>,
+}
+
+impl FromStr for GeminiSafety {
+    /// The first entry that isn't a known `category=threshold`
+    type Err = String;
+
+    /// Parse a `GEMINI_SAFETY` value: a comma-separated list of
+    /// `category=threshold`, e.g. `dangerous_content=block_only_high,harassment=block_none`.
+    /// Names are case-insensitive and the `HARM_CATEGORY_` prefix is optional;
+    /// `all` sets every category, and later entries override earlier ones.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut settings: Vec<(String, &'static str)> = Vec::new();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (categories, threshold) = parse_entry(entry).ok_or_else(|| entry.to_string())?;
+            for category in categories {
+                settings.retain(|(c, _)| *c != category);
+                settings.push((category, threshold));
+            }
+        }
+        settings.sort();
+        Ok(Self { settings })
+    }
+}
+
+impl GeminiSafety {
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+    }
+
+    /// The `safetySettings` request field
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        self.settings
+            .iter()
+            .map(|(category, threshold)| json!({ "category": category, "threshold": threshold }))
+            .collect()
+    }
+}
+
+/// The full category names and threshold of one `category=threshold` entry
+fn parse_entry(entry: &str) -> Option<(Vec<String>, &'static str)> {
+    let (category, threshold) = entry.split_once('=')?;
+    let threshold = threshold.trim().to_uppercase();
+    let threshold = THRESHOLDS.iter().find(|t| **t == threshold)?;
+    let category = category.trim().to_uppercase();
+    let category = category.strip_prefix("HARM_CATEGORY_").unwrap_or(&category);
+    let categories = if category == "ALL" {
+        HARM_CATEGORIES.to_vec()
+    } else {
+        vec![*HARM_CATEGORIES.iter().find(|c| **c == category)?]
+    };
+    let categories = categories
+        .into_iter()
+        .map(|c| format!("HARM_CATEGORY_{c}"))
+        .collect();
+    Some((categories, threshold))
+}
+
+/// Message shown instead of the provider's error or empty reply for a blocked answer
+pub(crate) const SAFETY_BLOCK_MESSAGE: &str = "Gemini's safety filters blocked this response. \
+Try rephrasing the request, or relax the thresholds with GEMINI_SAFETY \
+(e.g. GEMINI_SAFETY=all=block_only_high).";
+
+/// Whether a provider error reports content blocked by Gemini's filters,
+/// e.g. `Gemini candidate missing content (finish_reason=Safety, ...)`
+/// or a `promptFeedback` with `"blockReason": "SAFETY"`
+pub(crate) fn is_safety_block(message: &str) -> bool {
+    let message = message.to_lowercase().replace([' ', '"'], "");
+    ["finish_reason=", "blockreason:", "block_reason:"]
+        .iter()
+        .any(|key| {
+            BLOCK_REASONS
+                .iter()
+                .any(|reason| message.contains(&format!("{key}{reason}")))
+        })
+}
+
+/// Replace provider errors in a Gemini agent's stream that name a block reason
+/// with `SAFETY_BLOCK_MESSAGE`. A turn that merely ends without text is passed
+/// through unchanged: without a finish or block reason it may have other causes.
+pub(crate) fn explain_safety_block(
+    stream: Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>,
+) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+    Box::pin(stream.map(|event| match event {
+        ChatStreamEvent::Error(e) if is_safety_block(&e) => {
+            ChatStreamEvent::Error(SAFETY_BLOCK_MESSAGE.to_string())
+        }
+        event => event,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    fn safety(value: &str) -> GeminiSafety {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parses_category_thresholds() {
+        let parsed =
+            safety("dangerous_content=block_only_high, HARM_CATEGORY_HARASSMENT=BLOCK_NONE");
+
+        assert_eq!(
+            parsed.to_json(),
+            json!([
+                { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "BLOCK_ONLY_HIGH" },
+                { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_NONE" },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_all_sets_every_category_and_later_entries_win() {
+        let parsed = safety("all=block_only_high,hate_speech=off");
+
+        assert_eq!(parsed.settings.len(), HARM_CATEGORIES.len());
+        assert!(parsed
+            .settings
+            .contains(&("HARM_CATEGORY_HATE_SPEECH".to_string(), "OFF")));
+        assert!(parsed
+            .settings
+            .contains(&("HARM_CATEGORY_HARASSMENT".to_string(), "BLOCK_ONLY_HIGH")));
+    }
+
+    #[test]
+    fn test_invalid_entries_are_rejected() {
+        for value in [
+            "violence=block_none",
+            "harassment=lenient",
+            "harassment",
+            "off",
+        ] {
+            assert_eq!(value.parse::<GeminiSafety>(), Err(value.to_string()));
+        }
+        assert!(safety("").is_empty());
+    }
+
+    #[test]
+    fn test_detects_block_errors() {
+        assert!(is_safety_block(
+            "ResponseError: Gemini candidate missing content (finish_reason=Safety, finish_message=no finish message provided)"
+        ));
+        assert!(is_safety_block(
+            r#"{"promptFeedback": {"blockReason": "PROHIBITED_CONTENT"}}"#
+        ));
+        assert!(!is_safety_block(
+            "Gemini candidate missing content (finish_reason=MaxTokens, ...)"
+        ));
+        assert!(!is_safety_block("429 Too Many Requests"));
+    }
+
+    #[tokio::test]
+    async fn test_block_error_is_explained() {
+        let events = stream::iter(vec![ChatStreamEvent::Error(
+            "Gemini candidate missing content (finish_reason=Safety, finish_message=none)"
+                .to_string(),
+        )]);
+
+        let events: Vec<_> = explain_safety_block(Box::pin(events)).collect().await;
+
+        assert!(matches!(&events[..], [ChatStreamEvent::Error(e)] if e == SAFETY_BLOCK_MESSAGE));
+    }
+
+    #[tokio::test]
+    async fn test_empty_answer_without_block_reason_is_not_reported_as_block() {
+        let events = stream::iter(vec![
+            ChatStreamEvent::ToolCall {
+                name: "web_fetch".to_string(),
+            },
+            ChatStreamEvent::Usage { total_tokens: 12 },
+            ChatStreamEvent::Done,
+        ]);
+
+        let events: Vec<_> = explain_safety_block(Box::pin(events)).collect().await;
+
+        assert!(matches!(events.last(), Some(ChatStreamEvent::Done)));
+    }
+
+    #[tokio::test]
+    async fn test_answered_turn_and_other_errors_pass_through() {
+        let events = stream::iter(vec![
+            ChatStreamEvent::TextDelta("Hello".to_string()),
+            ChatStreamEvent::Done,
+            ChatStreamEvent::Error("connection refused".to_string()),
+        ]);
+
+        let events: Vec<_> = explain_safety_block(Box::pin(events)).collect().await;
+
+        assert!(matches!(events[1], ChatStreamEvent::Done));
+        assert!(matches!(&events[2], ChatStreamEvent::Error(e) if e == "connection refused"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Grounded answers: pages fetched up front are attached to the completion
+//! request as rig documents, which providers treat as reference material,
+//! instead of reaching the model as tool output.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+
+use futures::{stream, Stream, StreamExt};
+use reqwest::Url;
+use rig::agent::{Agent, MultiTurnStreamItem};
+use rig::completion::{
+    CompletionModel, CompletionRequestBuilder, Document, GetTokenUsage, Message,
+};
+use rig::streaming::StreamedAssistantContent;
+use serde::Serialize;
+
+use super::any_agent::map_stream_item;
+use super::{ChatStreamEvent, WebFetch};
+
+/// Most URLs of one message fetched as documents
+pub const MAX_GROUNDING_DOCUMENTS: usize = 5;
+
+/// Appended to the agent's preamble for grounded requests, which carry no tools
+const GROUNDING_INSTRUCTIONS: &str = "\n\nAnswer from the attached documents. \
+Cite the document ids (their URLs) you rely on, and say so when the documents \
+don't contain the answer.";
+
+/// The distinct http(s) URLs in `message`, in order of appearance, at most
+/// `MAX_GROUNDING_DOCUMENTS`. Surrounding brackets, quotes and trailing
+/// punctuation are not part of a URL.
+pub fn urls_in_message(message: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for word in message.split_whitespace() {
+        let word = word
+            .trim_start_matches(['(', '<', '[', '"', '\''])
+            .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '>', ']', '"', '\'']);
+        if !(word.starts_with("http://") || word.starts_with("https://")) {
+            continue;
+        }
+        if let Ok(url) = Url::parse(word) {
+            let url = url.to_string();
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        if urls.len() == MAX_GROUNDING_DOCUMENTS {
+            break;
+        }
+    }
+    urls
+}
+
+/// Fetch the URLs in `message` with `web_fetch` as documents to ground the answer in.
+/// Also returns a `Source` event per fetched page and a `Warning` per page that
+/// couldn't be fetched, for the caller to pass on.
+pub async fn fetch_grounding_documents(
+    web_fetch: &WebFetch,
+    message: &str,
+) -> (Vec<Document>, Vec<ChatStreamEvent>) {
+    let fetches = urls_in_message(message)
+        .into_iter()
+        .map(|url| async move { (web_fetch.fetch_document(&url).await, url) });
+    let mut documents = Vec::new();
+    let mut events = Vec::new();
+    for (fetched, url) in futures::future::join_all(fetches).await {
+        match fetched {
+            Ok((document, citation)) => {
+                documents.push(document);
+                events.push(ChatStreamEvent::Source(citation));
+            }
+            Err(e) => events.push(ChatStreamEvent::Warning(format!(
+                "Could not attach {url} as a document: {e}"
+            ))),
+        }
+    }
+    (documents, events)
+}
+
+/// A document for a fetched page: its URL as the id and its title, if any, as metadata
+pub(crate) fn page_document(url: &str, title: Option<&str>, text: String) -> Document {
+    let additional_props = title
+        .map(|title| HashMap::from([("title".to_string(), title.to_string())]))
+        .unwrap_or_default();
+    Document {
+        id: url.to_string(),
+        text,
+        additional_props,
+    }
+}
+
+/// `prompt` preceded by `documents` in rig's `<file>` format, for agents
+/// that can't attach documents to a request
+pub(crate) fn inline_documents(prompt: &str, documents: &[Document]) -> String {
+    let documents: String = documents.iter().map(Document::to_string).collect();
+    format!("{documents}\n{prompt}")
+}
+
+/// A completion request for `agent` carrying `documents`. It keeps the
+/// agent's preamble and sampling settings but has no tools, so the model
+/// answers from the documents in a single completion.
+pub(crate) fn grounded_request<M: CompletionModel>(
+    agent: &Agent<M>,
+    prompt: &str,
+    history: Vec<Message>,
+    documents: Vec<Document>,
+) -> CompletionRequestBuilder<M> {
+    let preamble = agent.preamble.clone().unwrap_or_default();
+    CompletionRequestBuilder::new((*agent.model).clone(), prompt)
+        .preamble(format!("{preamble}{GROUNDING_INSTRUCTIONS}"))
+        .messages(history)
+        .documents(agent.static_context.clone())
+        .documents(documents)
+        .temperature_opt(agent.temperature)
+        .max_tokens_opt(agent.max_tokens)
+        .additional_params_opt(agent.additional_params.clone())
+}
+
+/// Stream `agent`'s answer to `prompt` grounded in `documents`
+pub(crate) async fn stream_grounded<M>(
+    agent: &Agent<M>,
+    prompt: &str,
+    history: Vec<Message>,
+    documents: Vec<Document>,
+) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>
+where
+    M: CompletionModel + 'static,
+    M::StreamingResponse: Serialize + Send,
+{
+    let max_tokens = agent.max_tokens;
+    let response = match grounded_request(agent, prompt, history, documents)
+        .stream()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return Box::pin(stream::iter([ChatStreamEvent::Error(e.to_string())])),
+    };
+    // Ends with `Done`, unless an error ends it first
+    let events = stream::unfold(Some(Box::pin(response)), move |response| async move {
+        let mut response = response?;
+        match response.next().await {
+            Some(Ok(content)) => Some((content_events(content, max_tokens), Some(response))),
+            Some(Err(e)) => Some((vec![ChatStreamEvent::Error(e.to_string())], None)),
+            None => Some((vec![ChatStreamEvent::Done], None)),
+        }
+    });
+    Box::pin(events.flat_map(stream::iter))
+}
+
+/// Events for one item of a single-completion stream
+fn content_events<R: Serialize + GetTokenUsage>(
+    content: StreamedAssistantContent<R>,
+    max_tokens: Option<u64>,
+) -> Vec<ChatStreamEvent> {
+    let usage = match &content {
+        StreamedAssistantContent::Final(response) => response.token_usage(),
+        _ => None,
+    };
+    let mut events = map_stream_item(
+        Ok(MultiTurnStreamItem::StreamAssistantItem(content)),
+        max_tokens,
+    );
+    if let Some(usage) = usage {
+        events.push(ChatStreamEvent::Usage {
+            total_tokens: usage.total_tokens,
+        });
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{create_ollama_chat_agent, AgentSettings, DEFAULT_OLLAMA_HOST};
+    use rig::message::UserContent;
+
+    #[test]
+    fn test_urls_in_message() {
+        let urls = urls_in_message(
+            "Compare (https://example.com/a) with <https://example.org/b>, \
+             and https://example.com/a again. Not ftp://x or example.net.",
+        );
+
+        assert_eq!(urls, ["https://example.com/a", "https://example.org/b"]);
+    }
+
+    #[test]
+    fn test_urls_in_message_are_capped() {
+        let message: Vec<String> = (0..10).map(|i| format!("https://e.com/{i}")).collect();
+
+        assert_eq!(
+            urls_in_message(&message.join(" ")).len(),
+            MAX_GROUNDING_DOCUMENTS
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grounded_request_carries_documents_and_no_tools() {
+        let agent =
+            create_ollama_chat_agent(DEFAULT_OLLAMA_HOST, "qwen3", &AgentSettings::default());
+        let document = page_document(
+            "https://example.com/a",
+            Some("Example"),
+            "Page text".to_string(),
+        );
+
+        let request = grounded_request(
+            &agent,
+            "What does the page say?",
+            vec![Message::user("earlier")],
+            vec![document],
+        )
+        .build();
+
+        assert_eq!(request.documents.len(), 1);
+        assert_eq!(request.documents[0].id, "https://example.com/a");
+        assert_eq!(request.documents[0].text, "Page text");
+        assert_eq!(request.documents[0].additional_props["title"], "Example");
+        assert!(request.tools.is_empty());
+        assert!(request
+            .preamble
+            .as_deref()
+            .unwrap()
+            .ends_with(GROUNDING_INSTRUCTIONS));
+        // History first, then the prompt
+        assert_eq!(request.chat_history.len(), 2);
+        let Message::User { content } = request.chat_history.last() else {
+            panic!("the prompt should be the last message");
+        };
+        assert!(matches!(
+            content.first(),
+            UserContent::Text(text) if text.text == "What does the page say?"
+        ));
+    }
+
+    #[test]
+    fn test_inline_documents_prefixes_prompt() {
+        let documents = [page_document(
+            "https://example.com/a",
+            None,
+            "Text".to_string(),
+        )];
+
+        assert_eq!(
+            inline_documents("Question?", &documents),
+            "<file id: https://example.com/a>\nText\n</file>\n\nQuestion?"
+        );
+    }
+}
@@ -11,10 +11,12 @@ use super::{ChatAgent, ChatStreamEvent};
 ///
 /// Each call to `stream_chat` pops and returns the next `Vec<ChatStreamEvent>`
 /// from the internal queue. Use the constructor helpers to set up expected
-/// responses before running tests.
+/// responses before running tests. The history passed to each call is recorded.
 pub struct MockAgent {
     /// Queue of response event sequences. Each element is one full response.
     responses: Mutex<Vec<Vec<ChatStreamEvent>>>,
+    /// History received by each `stream_chat` call, in call order
+    histories: Mutex<Vec<Vec<Message>>>,
 }
 
 impl MockAgent {
@@ -23,6 +25,7 @@ impl MockAgent {
     pub fn new(responses: Vec<Vec<ChatStreamEvent>>) -> Self {
         Self {
             responses: Mutex::new(responses),
+            histories: Mutex::new(Vec::new()),
         }
     }
 
@@ -38,6 +41,11 @@ impl MockAgent {
     pub fn with_error(msg: &str) -> Self {
         Self::new(vec![vec![ChatStreamEvent::Error(msg.to_string())]])
     }
+
+    /// The history passed to each `stream_chat` call so far
+    pub fn received_histories(&self) -> Vec<Vec<Message>> {
+        self.histories.lock().unwrap().clone()
+    }
 }
 
 #[async_trait]
@@ -45,8 +53,9 @@ impl ChatAgent for MockAgent {
     async fn stream_chat(
         &self,
         _prompt: &str,
-        _history: Vec<Message>,
+        history: Vec<Message>,
     ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+        self.histories.lock().unwrap().push(history);
         let events = {
             let mut locked = self.responses.lock().unwrap();
             if locked.is_empty() {
@@ -1,27 +1,82 @@
 pub mod any_agent;
+mod arxiv_fetch;
 mod builder;
+mod calculator;
 mod chat_agent;
+mod cite;
+mod config;
+mod content_filter;
+mod context_window;
+mod continuation;
+mod feed_fetch;
+mod gemini_safety;
+mod grounding;
 pub mod mcp;
+mod ollama_pull;
 mod pdf_read;
+mod prompt_limit;
+mod relevance;
 pub(crate) mod research_tool;
+mod response_limit;
+mod response_style;
 pub mod router_agent;
+mod sitemap_fetch;
+mod structured;
+mod tool_registry;
+mod tool_rounds;
+mod truncation;
+mod turn_timing;
+mod warmup;
 mod web_fetch;
+mod web_fetch_many;
 mod web_search;
+mod wikipedia_fetch;
 
 #[cfg(test)]
 pub mod mock_agent;
 
 pub use any_agent::{AnyAgent, ChatStreamEvent};
+pub use arxiv_fetch::ArxivFetch;
 pub use builder::{
-    create_gemini_agent, create_gemini_router_agent, create_ollama_agent,
-    create_ollama_router_agent, create_openai_agent, create_openai_router_agent, default_model,
+    create_gemini_agent, create_gemini_chat_agent, create_gemini_router_agent, create_ollama_agent,
+    create_ollama_chat_agent, create_ollama_router_agent, create_openai_agent,
+    create_openai_chat_agent, create_openai_compatible_agent, create_openai_compatible_chat_agent,
+    create_openai_compatible_router_agent, create_openai_router_agent, default_model,
+    model_matches_provider, DEFAULT_ASSISTANT_NAME,
 };
+pub(crate) use builder::{openai_client, openai_compatible_client};
+pub use calculator::Calculator;
 pub use chat_agent::ChatAgent;
+pub use cite::{Citation, Cite};
+pub use config::{
+    AgentParams, AgentSettings, ConfigError, ModelAllowlist, ProviderConfig, ProviderSettings,
+    DEFAULT_OLLAMA_HOST, FALLBACK_PROVIDER,
+};
+pub use content_filter::{ContentBlocked, ContentFilter, ContentFilterMode};
+pub use context_window::{
+    compact_for_context, context_window, estimate_tokens, DEFAULT_CONTEXT_WINDOW,
+};
+pub use continuation::{continue_truncated, CONTINUE_PROMPT};
+pub use feed_fetch::FeedFetch;
+pub use gemini_safety::GeminiSafety;
+pub use grounding::{fetch_grounding_documents, urls_in_message, MAX_GROUNDING_DOCUMENTS};
 pub use mcp::McpToolSet;
 pub use pdf_read::PdfRead;
+pub use prompt_limit::{check_prompt_length, PromptTooLong, DEFAULT_MAX_PROMPT_CHARS};
+pub use response_limit::limit_response_chars;
+pub use response_style::ResponseStyle;
 pub use router_agent::RouterAgent;
+pub use sitemap_fetch::SitemapFetch;
+pub use structured::StructuredError;
+pub use tool_registry::{EnabledTools, TOOL_NAMES};
+pub use tool_rounds::DEFAULT_MAX_TOOL_ROUNDS;
+pub(crate) use truncation::truncate_at_boundary;
+pub use turn_timing::TurnTiming;
+pub use warmup::{run_warmup, warm_up, WarmupError, WARMUP_TIMEOUT};
 pub use web_fetch::WebFetch;
+pub use web_fetch_many::WebFetchMany;
 pub use web_search::{WebSearch, WebSearchArgs};
+pub use wikipedia_fetch::WikipediaFetch;
 
 #[cfg(test)]
 pub use mock_agent::MockAgent;
@@ -0,0 +1,147 @@
+//! Friendlier handling of Ollama's "model not found" error, which is what a
+//! request for a model that hasn't been pulled yet fails with.
+
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use log::info;
+use serde_json::json;
+
+use super::config::resolve_ollama_host;
+use super::ChatStreamEvent;
+
+/// Upper bound on an automatic pull; models are several gigabytes
+const PULL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Error type for pulling an Ollama model
+#[derive(Debug, thiserror::Error)]
+pub enum PullError {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Ollama responded with {0}")]
+    Status(reqwest::StatusCode),
+}
+
+/// Whether automatic pulls are enabled (`OLLAMA_AUTO_PULL=1` or `true`)
+pub(crate) fn auto_pull_enabled() -> bool {
+    auto_pull_from_lookup(|key| std::env::var(key).ok())
+}
+
+fn auto_pull_from_lookup(lookup: impl Fn(&str) -> Option<String>) -> bool {
+    lookup("OLLAMA_AUTO_PULL").is_some_and(|v| matches!(v.trim(), "1" | "true"))
+}
+
+/// Whether a provider error is Ollama reporting that the model isn't pulled,
+/// e.g. `model "qwen3" not found, try pulling it first`
+pub(crate) fn is_model_not_found(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("model") && message.contains("not found")
+}
+
+/// Message telling the user how to make `model` available
+pub(crate) fn model_not_pulled_message(model: &str) -> String {
+    format!("Ollama model \"{model}\" is not available. Run `ollama pull {model}` and try again.")
+}
+
+/// Ask the Ollama server at `host` to pull `model`, waiting until it's done
+pub(crate) async fn pull_model(host: &str, model: &str) -> Result<(), PullError> {
+    info!("Pulling Ollama model {} ...", model);
+    let response = reqwest::Client::new()
+        .post(format!("{host}/api/pull"))
+        .timeout(PULL_TIMEOUT)
+        .json(&json!({ "model": model, "stream": false }))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(PullError::Status(response.status()));
+    }
+    info!("Pulled Ollama model {}", model);
+    Ok(())
+}
+
+/// Replace "model not found" errors in an Ollama agent's stream with a
+/// message saying how to pull the model. With `auto_pull` (see
+/// `auto_pull_enabled`) the model is pulled first, so resending the message works.
+pub(crate) fn explain_missing_model(
+    stream: Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>,
+    model: &str,
+    auto_pull: bool,
+) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+    let model = model.to_string();
+    Box::pin(stream.then(move |event| {
+        let model = model.clone();
+        async move {
+            match event {
+                ChatStreamEvent::Error(e) if is_model_not_found(&e) => {
+                    ChatStreamEvent::Error(missing_model_error(&model, auto_pull).await)
+                }
+                event => event,
+            }
+        }
+    }))
+}
+
+async fn missing_model_error(model: &str, auto_pull: bool) -> String {
+    if !auto_pull {
+        return model_not_pulled_message(model);
+    }
+    let host = resolve_ollama_host(std::env::var("OLLAMA_HOST").ok())
+        .unwrap_or_else(|_| super::DEFAULT_OLLAMA_HOST.to_string());
+    match pull_model(&host, model).await {
+        Ok(()) => format!(
+            "Ollama model \"{model}\" was not available, so it has been pulled. Send your message again."
+        ),
+        Err(e) => format!("Pulling Ollama model \"{model}\" failed ({e}). {}", model_not_pulled_message(model)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[test]
+    fn test_detects_model_not_found_errors() {
+        assert!(is_model_not_found(
+            r#"CompletionError: ProviderError: {"error":"model \"qwen3\" not found, try pulling it first"}"#
+        ));
+        assert!(is_model_not_found("Model 'llama3' Not Found"));
+        assert!(!is_model_not_found("429 Too Many Requests"));
+        assert!(!is_model_not_found("404 page not found"));
+    }
+
+    #[test]
+    fn test_model_not_pulled_message_names_pull_command() {
+        let message = model_not_pulled_message("qwen3:8b");
+
+        assert!(message.contains("`ollama pull qwen3:8b`"), "{message}");
+    }
+
+    #[test]
+    fn test_auto_pull_flag() {
+        assert!(auto_pull_from_lookup(|_| Some("1".to_string())));
+        assert!(auto_pull_from_lookup(|_| Some("true".to_string())));
+        assert!(!auto_pull_from_lookup(|_| Some("0".to_string())));
+        assert!(!auto_pull_from_lookup(|_| None));
+    }
+
+    #[tokio::test]
+    async fn test_missing_model_error_is_replaced_other_events_kept() {
+        let events = stream::iter(vec![
+            ChatStreamEvent::TextDelta("hi".to_string()),
+            ChatStreamEvent::Error(r#"model "qwen3" not found, try pulling it first"#.to_string()),
+            ChatStreamEvent::Error("connection refused".to_string()),
+        ]);
+
+        let events: Vec<_> = explain_missing_model(Box::pin(events), "qwen3", false)
+            .collect()
+            .await;
+
+        assert!(matches!(&events[0], ChatStreamEvent::TextDelta(t) if t == "hi"));
+        assert!(
+            matches!(&events[1], ChatStreamEvent::Error(e) if *e == model_not_pulled_message("qwen3"))
+        );
+        assert!(matches!(&events[2], ChatStreamEvent::Error(e) if e == "connection refused"));
+    }
+}
@@ -3,12 +3,23 @@ use rig::completion::ToolDefinition;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+use super::content_filter::{ContentBlocked, ContentFilter};
+use super::truncation::truncate_at_boundary;
 use crate::collectors::pdf::read_pdf;
 
+/// Characters of PDF text returned per chunk
+const PDF_CHUNK_CHARS: usize = 20_000;
+
 /// Arguments for the PdfRead tool
 #[derive(Deserialize)]
 pub struct PdfReadArgs {
     file_path: String,
+    /// Which chunk of the text to return (default: 0, the first)
+    #[serde(default)]
+    chunk_index: usize,
+    /// Cut the chunk to at most this many characters, at a sentence or paragraph end
+    #[serde(default)]
+    max_chars: Option<usize>,
 }
 
 /// Output from the PdfRead tool
@@ -17,6 +28,13 @@ pub struct PdfReadOutput {
     path: String,
     title: Option<String>,
     content: String,
+    chunk_index: usize,
+    total_chunks: usize,
+    /// Index to pass as `chunk_index` for the rest of the document (None on the last chunk)
+    next_chunk: Option<usize>,
+    /// Whether `content` was cut short by `max_chars`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
 }
 
 /// Error type for PdfRead tool
@@ -26,10 +44,45 @@ pub enum PdfReadError {
     ReadError(#[from] anyhow::Error),
     #[error("Blocking task failed: {0}")]
     JoinError(#[from] tokio::task::JoinError),
+    #[error("chunk_index {index} is out of range; the document has {total} chunks")]
+    ChunkOutOfRange { index: usize, total: usize },
+    #[error("The PDF was refused by the content filter; do not read it again")]
+    ContentBlocked(#[from] ContentBlocked),
+}
+
+/// Number of `chunk_chars`-sized chunks in `text` (at least 1, even when empty)
+fn chunk_count(text: &str, chunk_chars: usize) -> usize {
+    text.chars().count().div_ceil(chunk_chars).max(1)
+}
+
+/// The `index`-th chunk of `text`, cut every `chunk_chars` characters.
+/// Boundaries depend only on the character count, so the same document always
+/// splits the same way. Returns None if `index` is past the last chunk.
+fn text_chunk(text: &str, chunk_chars: usize, index: usize) -> Option<String> {
+    if index >= chunk_count(text, chunk_chars) {
+        return None;
+    }
+    Some(
+        text.chars()
+            .skip(index * chunk_chars)
+            .take(chunk_chars)
+            .collect(),
+    )
 }
 
 /// PdfRead tool for extracting text from local PDF files
-pub struct PdfRead;
+#[derive(Default)]
+pub struct PdfRead {
+    content_filter: ContentFilter,
+}
+
+impl PdfRead {
+    /// Run the extracted title and text through `filter`
+    pub fn with_content_filter(mut self, filter: ContentFilter) -> Self {
+        self.content_filter = filter;
+        self
+    }
+}
 
 impl rig::tool::Tool for PdfRead {
     const NAME: &'static str = "pdf_read";
@@ -40,13 +93,23 @@ impl rig::tool::Tool for PdfRead {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: self.name(),
-            description: "Reads a local PDF file and extracts its text content".to_string(),
+            description: "Reads a local PDF file and extracts its text content. Long documents \
+                          are returned in chunks; pass next_chunk as chunk_index to continue"
+                .to_string(),
             parameters: json!({
                 "type": "object",
                 "properties": {
                     "file_path": {
                         "type": "string",
                         "description": "The local file path to the PDF"
+                    },
+                    "chunk_index": {
+                        "type": "integer",
+                        "description": "Which chunk to read (default: 0)"
+                    },
+                    "max_chars": {
+                        "type": "integer",
+                        "description": "Cut the chunk to at most this many characters, ending at a sentence or paragraph (default: no limit)"
                     }
                 },
                 "required": ["file_path"]
@@ -58,10 +121,30 @@ impl rig::tool::Tool for PdfRead {
         info!("Reading PDF {} ...", args.file_path);
 
         let pdf_content = tokio::task::spawn_blocking(move || read_pdf(&args.file_path)).await??;
+        let total_chunks = chunk_count(&pdf_content.text, PDF_CHUNK_CHARS);
+        let content = text_chunk(&pdf_content.text, PDF_CHUNK_CHARS, args.chunk_index).ok_or(
+            PdfReadError::ChunkOutOfRange {
+                index: args.chunk_index,
+                total: total_chunks,
+            },
+        )?;
+        let next_chunk = Some(args.chunk_index + 1).filter(|&next| next < total_chunks);
+        let kept = args.max_chars.map_or(content.len(), |max| {
+            truncate_at_boundary(&content, max).len()
+        });
+        let truncated = kept < content.len();
+        let mut title = pdf_content.title;
+        let mut content = content[..kept].to_string();
+        self.content_filter
+            .apply_all(std::iter::once(&mut content).chain(title.as_mut()))?;
         Ok(PdfReadOutput {
             path: pdf_content.path,
-            title: pdf_content.title,
-            content: pdf_content.text,
+            title,
+            content,
+            chunk_index: args.chunk_index,
+            total_chunks,
+            next_chunk,
+            truncated,
         })
     }
 }
@@ -76,6 +159,30 @@ mod tests {
         let json = r#"{"file_path": "/tmp/test.pdf"}"#;
         let args: PdfReadArgs = serde_json::from_str(json).unwrap();
         assert_eq!(args.file_path, "/tmp/test.pdf");
+        assert_eq!(args.chunk_index, 0);
+    }
+
+    #[test]
+    fn test_chunks_are_contiguous_and_cursor_terminates() {
+        // 25 characters with multi-byte ones mixed in, split every 10
+        let text = "日本語のPDFテキストを分割するテスト: abcdefghij";
+        let mut chunks = Vec::new();
+        let mut cursor = Some(0);
+        while let Some(index) = cursor {
+            chunks.push(text_chunk(text, 10, index).unwrap());
+            cursor = Some(index + 1).filter(|&next| next < chunk_count(text, 10));
+        }
+
+        assert_eq!(chunks.len(), chunk_count(text, 10));
+        assert!(chunks.iter().all(|c| c.chars().count() <= 10));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn test_text_chunk_out_of_range() {
+        assert_eq!(chunk_count("", 10), 1);
+        assert_eq!(text_chunk("", 10, 0).as_deref(), Some(""));
+        assert_eq!(text_chunk("0123456789", 10, 1), None);
     }
 
     #[test]
@@ -84,6 +191,10 @@ mod tests {
             path: "/tmp/test.pdf".to_string(),
             title: Some("Test PDF".to_string()),
             content: "Hello PDF".to_string(),
+            chunk_index: 0,
+            total_chunks: 1,
+            next_chunk: None,
+            truncated: false,
         };
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("Test PDF"));
@@ -93,8 +204,10 @@ mod tests {
     async fn test_pdf_read_call_file_not_found() {
         let args = PdfReadArgs {
             file_path: "/nonexistent/path/to/file.pdf".to_string(),
+            chunk_index: 0,
+            max_chars: None,
         };
-        let sut = PdfRead;
+        let sut = PdfRead::default();
         let result = sut.call(args).await;
         assert!(result.is_err());
     }
@@ -0,0 +1,60 @@
+//! Rejecting user messages too long to send, before they reach the provider
+//! and fail there with an opaque context-length error.
+
+/// Prompt length allowed when `MAX_PROMPT_CHARS` is unset
+pub const DEFAULT_MAX_PROMPT_CHARS: usize = 32_000;
+
+/// Returned for a message longer than the prompt limit
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "Message is too long ({chars} characters, limit {max}). \
+     Please split it into smaller messages."
+)]
+pub struct PromptTooLong {
+    pub chars: usize,
+    pub max: usize,
+}
+
+/// Check `prompt` against `max_chars` characters; `None` allows any length
+pub fn check_prompt_length(prompt: &str, max_chars: Option<usize>) -> Result<(), PromptTooLong> {
+    let Some(max) = max_chars else {
+        return Ok(());
+    };
+    let chars = prompt.chars().count();
+    if chars > max {
+        Err(PromptTooLong { chars, max })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_at_limit_is_accepted_and_one_over_is_rejected() {
+        assert_eq!(check_prompt_length("abcde", Some(5)), Ok(()));
+        assert_eq!(
+            check_prompt_length("abcdef", Some(5)),
+            Err(PromptTooLong { chars: 6, max: 5 })
+        );
+        // Counted in characters, not bytes
+        assert_eq!(check_prompt_length("日本語です", Some(5)), Ok(()));
+    }
+
+    #[test]
+    fn test_no_limit_accepts_anything() {
+        assert_eq!(check_prompt_length(&"a".repeat(100_000), None), Ok(()));
+    }
+
+    #[test]
+    fn test_error_message_suggests_splitting() {
+        let message = PromptTooLong { chars: 6, max: 5 }.to_string();
+
+        assert_eq!(
+            message,
+            "Message is too long (6 characters, limit 5). Please split it into smaller messages."
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Narrowing long page text down to the paragraphs most relevant to a query,
+//! ranked by embedding similarity.
+
+use anyhow::{bail, Result};
+
+use crate::llm::Embedder;
+
+/// Paragraphs kept when a fetch asks to `focus` on a query
+pub(crate) const FOCUS_TOP_K: usize = 5;
+
+/// Keep the `top_k` paragraphs of `text` most similar to `query`, in page order.
+/// Text with at most `top_k` paragraphs is returned unchanged without embedding.
+pub(crate) async fn focus_paragraphs(
+    embedder: &dyn Embedder,
+    text: &str,
+    query: &str,
+    top_k: usize,
+) -> Result<String> {
+    let paragraphs: Vec<&str> = text
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    if paragraphs.len() <= top_k {
+        return Ok(text.to_string());
+    }
+
+    let mut inputs = vec![query.to_string()];
+    inputs.extend(paragraphs.iter().map(|p| p.to_string()));
+    let vectors = embedder.embed(inputs).await?;
+    let Some((query_vector, paragraph_vectors)) = vectors.split_first() else {
+        bail!("Embedder returned no vectors");
+    };
+    if paragraph_vectors.len() != paragraphs.len() {
+        bail!(
+            "Embedder returned {} vectors for {} paragraphs",
+            paragraph_vectors.len(),
+            paragraphs.len()
+        );
+    }
+
+    let mut ranked: Vec<(usize, f64)> = paragraph_vectors
+        .iter()
+        .map(|v| cosine_similarity(query_vector, v))
+        .enumerate()
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut kept: Vec<usize> = ranked.into_iter().take(top_k).map(|(i, _)| i).collect();
+    kept.sort_unstable();
+
+    Ok(kept
+        .into_iter()
+        .map(|i| paragraphs[i])
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}
+
+/// Cosine similarity of two vectors; 0 when either is all zeros
+fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// Vocabulary of `KeywordEmbedder`; one dimension per word
+    const VOCABULARY: &[&str] = &["rust", "cargo", "borrow", "weather", "rain", "recipe"];
+
+    /// Embeds a text as the counts of `VOCABULARY` words in it, so similarity
+    /// is keyword overlap
+    pub(crate) struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    VOCABULARY
+                        .iter()
+                        .map(|word| text.matches(word).count() as f64)
+                        .collect()
+                })
+                .collect())
+        }
+    }
+
+    const PAGE: &str = "Today's weather brings rain.\n\n\
+        Rust has a borrow checker.\n\n\
+        A recipe for bread.\n\n\
+        Cargo builds Rust code.\n\n\
+        More rain and weather later.";
+
+    #[tokio::test]
+    async fn test_keeps_most_relevant_paragraphs_in_page_order() {
+        let focused = focus_paragraphs(&KeywordEmbedder, PAGE, "rust cargo borrow", 2)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            focused,
+            "Rust has a borrow checker.\n\nCargo builds Rust code."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_short_text_is_returned_unchanged() {
+        let text = "Only one paragraph about rain.";
+
+        let focused = focus_paragraphs(&KeywordEmbedder, text, "rust", 3)
+            .await
+            .unwrap();
+
+        assert_eq!(focused, text);
+    }
+
+    struct FailingEmbedder;
+
+    #[async_trait]
+    impl Embedder for FailingEmbedder {
+        async fn embed(&self, _texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_vectors_are_an_error() {
+        let result = focus_paragraphs(&FailingEmbedder, PAGE, "rust", 2).await;
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-9);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+}
@@ -0,0 +1,108 @@
+use std::pin::Pin;
+
+use futures::{future, stream, Stream, StreamExt};
+use log::warn;
+
+use super::ChatStreamEvent;
+
+/// Stop a chat stream once its text exceeds `max_chars` characters.
+///
+/// The text delta crossing the limit is cut at the limit, followed by `Done`;
+/// the rest of the underlying stream is never polled. `None` passes the stream
+/// through (see `MAX_RESPONSE_CHARS`).
+pub fn limit_response_chars(
+    events: Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>,
+    max_chars: Option<usize>,
+) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+    let Some(max_chars) = max_chars else {
+        return events;
+    };
+
+    let limited = events
+        .scan((0usize, false), move |(seen, stopped), event| {
+            if *stopped {
+                return future::ready(None);
+            }
+            let out = match event {
+                ChatStreamEvent::TextDelta(text) => {
+                    let len = text.chars().count();
+                    if *seen + len <= max_chars {
+                        *seen += len;
+                        vec![ChatStreamEvent::TextDelta(text)]
+                    } else {
+                        warn!("Response exceeded MAX_RESPONSE_CHARS={max_chars}; truncating");
+                        *stopped = true;
+                        let remaining: String = text.chars().take(max_chars - *seen).collect();
+                        let mut out = Vec::new();
+                        if !remaining.is_empty() {
+                            out.push(ChatStreamEvent::TextDelta(remaining));
+                        }
+                        out.push(ChatStreamEvent::Done);
+                        out
+                    }
+                }
+                other => vec![other],
+            };
+            future::ready(Some(out))
+        })
+        .flat_map(stream::iter);
+    Box::pin(limited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(deltas: &[&str]) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+        let mut events: Vec<ChatStreamEvent> = deltas
+            .iter()
+            .map(|d| ChatStreamEvent::TextDelta(d.to_string()))
+            .collect();
+        events.push(ChatStreamEvent::Done);
+        Box::pin(stream::iter(events))
+    }
+
+    async fn collect_text(
+        stream: Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>,
+    ) -> (String, usize) {
+        let collected: Vec<ChatStreamEvent> = stream.collect().await;
+        let mut text = String::new();
+        let mut done = 0;
+        for event in collected {
+            match event {
+                ChatStreamEvent::TextDelta(t) => text.push_str(&t),
+                ChatStreamEvent::Done => done += 1,
+                _ => {}
+            }
+        }
+        (text, done)
+    }
+
+    #[tokio::test]
+    async fn test_limit_stops_at_threshold() {
+        let limited = limit_response_chars(events(&["hello", " world", "!!!"]), Some(8));
+
+        assert_eq!(collect_text(limited).await, ("hello wo".to_string(), 1));
+    }
+
+    #[tokio::test]
+    async fn test_limit_counts_characters_not_bytes() {
+        let limited = limit_response_chars(events(&["こんにちは", "世界"]), Some(6));
+
+        assert_eq!(collect_text(limited).await, ("こんにちは世".to_string(), 1));
+    }
+
+    #[tokio::test]
+    async fn test_response_within_limit_is_unchanged() {
+        let limited = limit_response_chars(events(&["hello", " world"]), Some(11));
+
+        assert_eq!(collect_text(limited).await, ("hello world".to_string(), 1));
+    }
+
+    #[tokio::test]
+    async fn test_no_limit_passes_stream_through() {
+        let limited = limit_response_chars(events(&["hello", " world"]), None);
+
+        assert_eq!(collect_text(limited).await, ("hello world".to_string(), 1));
+    }
+}
@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+/// Default shape of answers, set with `RESPONSE_STYLE`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseStyle {
+    /// Paragraphs; the models' own default, so the preamble is left as is
+    #[default]
+    Prose,
+    /// A short bulleted summary
+    Bullets,
+    /// Numbered steps
+    Steps,
+}
+
+impl FromStr for ResponseStyle {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "prose" => Ok(Self::Prose),
+            "bullets" => Ok(Self::Bullets),
+            "steps" => Ok(Self::Steps),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ResponseStyle {
+    /// Formatting instruction appended to the preamble; empty for `Prose`
+    pub fn preamble_suffix(self) -> &'static str {
+        match self {
+            Self::Prose => "",
+            Self::Bullets => {
+                "\n\nFormat answers as a short bulleted list of key points, \
+                 unless the user asks for a different format."
+            }
+            Self::Steps => {
+                "\n\nFormat answers as numbered steps in the order they should be followed, \
+                 unless the user asks for a different format."
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(value: &str) -> ResponseStyle {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_prose_leaves_preamble_unchanged() {
+        assert_eq!(ResponseStyle::Prose.preamble_suffix(), "");
+    }
+
+    #[test]
+    fn test_bullets_suffix() {
+        let suffix = ResponseStyle::Bullets.preamble_suffix();
+
+        assert!(suffix.starts_with("\n\n"));
+        assert!(suffix.contains("bulleted list"));
+    }
+
+    #[test]
+    fn test_steps_suffix() {
+        let suffix = ResponseStyle::Steps.preamble_suffix();
+
+        assert!(suffix.starts_with("\n\n"));
+        assert!(suffix.contains("numbered steps"));
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(style("bullets"), ResponseStyle::Bullets);
+        assert_eq!(style(" Steps "), ResponseStyle::Steps);
+        assert_eq!(style("prose"), ResponseStyle::Prose);
+        assert!("haiku".parse::<ResponseStyle>().is_err());
+    }
+}
@@ -1,4 +1,3 @@
-use std::env;
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -6,21 +5,28 @@ use async_trait::async_trait;
 use futures::Stream;
 use futures::StreamExt;
 use rig::agent::Agent;
-use rig::agent::MultiTurnStreamItem;
-use rig::completion::Message;
+use rig::completion::{Document, GetTokenUsage, Message};
 use rig::providers::gemini;
 use rig::providers::ollama;
+use rig::providers::openai;
 use rig::providers::openai::responses_api::ResponsesCompletionModel;
-use rig::streaming::StreamedAssistantContent;
 use rig::streaming::StreamingChat;
+use serde::Serialize;
 
-use super::any_agent::AnyAgent;
+use super::any_agent::{map_stream_item, AnyAgent};
+use super::cite::CitedSources;
+use super::gemini_safety::explain_safety_block;
+use super::grounding::stream_grounded;
 use super::mcp::load_mcp_tools;
+use super::ollama_pull::{auto_pull_enabled, explain_missing_model};
 use super::research_tool::ResearchTool;
+use super::tool_rounds::{rounds_for_turns, warn_on_tool_limit, ToolRoundLimit};
 use super::{
-    create_gemini_router_agent, create_ollama_router_agent, create_openai_router_agent,
-    default_model, ChatAgent, ChatStreamEvent, McpToolSet, WebFetch,
+    compact_for_context, create_gemini_router_agent, create_ollama_router_agent,
+    create_openai_compatible_router_agent, create_openai_router_agent, AgentSettings, ChatAgent,
+    ChatStreamEvent, McpToolSet, ProviderConfig, WebFetch,
 };
+use crate::llm::{RigClient, RigEmbedder};
 
 /// A RouterAgent that orchestrates specialized tools (including a ResearchTool sub-agent).
 ///
@@ -32,78 +38,94 @@ pub enum RouterAgent {
     Ollama(Agent<ollama::CompletionModel>),
     Gemini(Agent<gemini::completion::CompletionModel>),
     OpenAi(Agent<ResponsesCompletionModel>),
+    /// An OpenAI-compatible server, spoken to over Chat Completions
+    OpenAiCompatible(Agent<openai::CompletionModel>),
 }
 
 impl RouterAgent {
-    /// Create a RouterAgent from environment configuration.
+    /// Create a RouterAgent for an explicit provider configuration.
     ///
     /// Builds two-level agent hierarchy:
     /// 1. Inner AnyAgent (web_search + web_fetch + pdf_read + MCP tools) for deep research
     /// 2. Outer RouterAgent with ResearchTool wrapping the inner agent (+ MCP tools)
     ///
     /// Async because MCP server connections are established at startup.
-    pub async fn from_env() -> Self {
-        let provider = env::var("LLM_PROVIDER").unwrap_or_else(|_| "ollama".to_string());
-        let model = env::var("LLM_MODEL").unwrap_or_else(|_| default_model(&provider).to_string());
-
+    ///
+    /// `web_fetch` is shared with the inner research agent; pass a clone of a
+    /// fetcher used elsewhere to share its robots.txt cache.
+    pub async fn from_config(
+        config: ProviderConfig,
+        settings: &AgentSettings,
+        web_fetch: WebFetch,
+    ) -> Self {
         // Load MCP tools once; clone to share between inner and outer agents
         let mcp_tools: Vec<McpToolSet> = load_mcp_tools().await;
 
         // Build inner research agent (shares the same provider/model and MCP tools)
-        let web_fetch = WebFetch::new();
-        let inner_agent = AnyAgent::from_env(web_fetch.clone(), mcp_tools.clone());
+        // Pages requested with `summarize: true` are condensed by the same provider
+        // and, with `EMBEDDING_MODEL` set, `focus` ranks paragraphs with its embeddings
+        let mut web_fetch =
+            web_fetch.with_summarizer(RigClient::summarizer(config.clone(), &settings.params));
+        if let Some(embedder) = RigEmbedder::from_env(config.clone()) {
+            web_fetch = web_fetch.with_embedder(embedder);
+        }
+        let inner_agent = AnyAgent::from_config(
+            config.clone(),
+            settings,
+            web_fetch.clone(),
+            mcp_tools.clone(),
+        );
         let research_tool = ResearchTool::new(Arc::new(inner_agent));
 
-        match provider.as_str() {
-            "openai" => {
-                let api_key =
-                    env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY required for OpenAI");
-                Self::OpenAi(create_openai_router_agent(
-                    &api_key,
-                    &model,
-                    research_tool,
-                    web_fetch,
-                    mcp_tools,
-                ))
-            }
-            "gemini" => {
-                let api_key =
-                    env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY required for Gemini");
-                Self::Gemini(create_gemini_router_agent(
-                    &api_key,
-                    &model,
-                    research_tool,
-                    web_fetch,
-                    mcp_tools,
-                ))
-            }
-            _ => Self::Ollama(create_ollama_router_agent(
-                &model,
-                research_tool,
-                web_fetch,
-                mcp_tools,
+        let tools = settings
+            .enabled_tools
+            .build(&web_fetch, Some(research_tool));
+        match config {
+            ProviderConfig::OpenAi { api_key, model } => Self::OpenAi(create_openai_router_agent(
+                &api_key, &model, settings, tools, mcp_tools,
+            )),
+            ProviderConfig::OpenAiCompatible {
+                base_url,
+                api_key,
+                model,
+            } => Self::OpenAiCompatible(create_openai_compatible_router_agent(
+                &base_url, &api_key, &model, settings, tools, mcp_tools,
             )),
+            ProviderConfig::Gemini { api_key, model } => Self::Gemini(create_gemini_router_agent(
+                &api_key, &model, settings, tools, mcp_tools,
+            )),
+            ProviderConfig::Ollama { host, model } => Self::Ollama(create_ollama_router_agent(
+                &host, &model, settings, tools, mcp_tools,
+            )),
+        }
+    }
+
+    /// Name of the model this agent sends requests to
+    pub fn model_name(&self) -> &str {
+        match self {
+            RouterAgent::Ollama(agent) => &agent.model.model,
+            RouterAgent::Gemini(agent) => &agent.model.model,
+            RouterAgent::OpenAi(agent) => &agent.model.model,
+            RouterAgent::OpenAiCompatible(agent) => &agent.model.model,
         }
     }
 
-    fn map_stream<R: Send + 'static>(
+    /// Tool-call rounds allowed per response (see `MAX_TOOL_ROUNDS`)
+    fn max_tool_rounds(&self) -> usize {
+        rounds_for_turns(match self {
+            RouterAgent::Ollama(agent) => agent.default_max_turns,
+            RouterAgent::Gemini(agent) => agent.default_max_turns,
+            RouterAgent::OpenAi(agent) => agent.default_max_turns,
+            RouterAgent::OpenAiCompatible(agent) => agent.default_max_turns,
+        })
+    }
+
+    fn map_stream<R: Serialize + GetTokenUsage + Send + 'static>(
         stream: rig::agent::StreamingResult<R>,
+        max_tokens: Option<u64>,
     ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
-        let mapped = stream.filter_map(|item| async {
-            match item {
-                Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(
-                    text,
-                ))) => Some(ChatStreamEvent::TextDelta(text.text)),
-                Ok(MultiTurnStreamItem::StreamAssistantItem(
-                    StreamedAssistantContent::ToolCall { tool_call, .. },
-                )) => Some(ChatStreamEvent::ToolCall {
-                    name: tool_call.function.name,
-                }),
-                Ok(MultiTurnStreamItem::FinalResponse(_)) => Some(ChatStreamEvent::Done),
-                Err(e) => Some(ChatStreamEvent::Error(e.to_string())),
-                _ => None,
-            }
-        });
+        let mapped =
+            stream.flat_map(move |item| futures::stream::iter(map_stream_item(item, max_tokens)));
         Box::pin(mapped)
     }
 }
@@ -115,15 +137,55 @@ impl ChatAgent for RouterAgent {
         prompt: &str,
         history: Vec<Message>,
     ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+        let history = compact_for_context(history, prompt, self.model_name());
+        let max_rounds = self.max_tool_rounds();
+        let hook = CitedSources::new(ToolRoundLimit::new(max_rounds));
+        let stream = match self {
+            RouterAgent::Ollama(agent) => explain_missing_model(
+                Self::map_stream(
+                    agent.stream_chat(prompt, history).with_hook(hook).await,
+                    agent.max_tokens,
+                ),
+                &agent.model.model,
+                auto_pull_enabled(),
+            ),
+            RouterAgent::Gemini(agent) => explain_safety_block(Self::map_stream(
+                agent.stream_chat(prompt, history).with_hook(hook).await,
+                agent.max_tokens,
+            )),
+            RouterAgent::OpenAi(agent) => Self::map_stream(
+                agent.stream_chat(prompt, history).with_hook(hook).await,
+                agent.max_tokens,
+            ),
+            RouterAgent::OpenAiCompatible(agent) => Self::map_stream(
+                agent.stream_chat(prompt, history).with_hook(hook).await,
+                agent.max_tokens,
+            ),
+        };
+        warn_on_tool_limit(stream, max_rounds)
+    }
+
+    /// Documents are attached to the router's own request; with them at hand
+    /// there is nothing to delegate to the research agent.
+    async fn stream_chat_with_documents(
+        &self,
+        prompt: &str,
+        history: Vec<Message>,
+        documents: Vec<Document>,
+    ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+        let history = compact_for_context(history, prompt, self.model_name());
         match self {
-            RouterAgent::Ollama(agent) => {
-                Self::map_stream(agent.stream_chat(prompt, history).await)
-            }
+            RouterAgent::Ollama(agent) => explain_missing_model(
+                stream_grounded(agent, prompt, history, documents).await,
+                &agent.model.model,
+                auto_pull_enabled(),
+            ),
             RouterAgent::Gemini(agent) => {
-                Self::map_stream(agent.stream_chat(prompt, history).await)
+                explain_safety_block(stream_grounded(agent, prompt, history, documents).await)
             }
-            RouterAgent::OpenAi(agent) => {
-                Self::map_stream(agent.stream_chat(prompt, history).await)
+            RouterAgent::OpenAi(agent) => stream_grounded(agent, prompt, history, documents).await,
+            RouterAgent::OpenAiCompatible(agent) => {
+                stream_grounded(agent, prompt, history, documents).await
             }
         }
     }
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use log::info;
+use rig::completion::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::content_filter::ContentFilter;
+use super::WebFetch;
+use crate::collectors::robots::RobotsCache;
+use crate::collectors::sitemap::fetch_sitemap;
+use crate::collectors::web::ReqwestClient;
+use crate::metrics::Metrics;
+
+/// Arguments for the SitemapFetch tool
+#[derive(Deserialize)]
+pub struct SitemapFetchArgs {
+    url: String,
+}
+
+/// Output from the SitemapFetch tool
+#[derive(Serialize)]
+pub struct SitemapFetchOutput {
+    urls: Vec<String>,
+}
+
+/// Error type for SitemapFetch tool
+#[derive(Debug, thiserror::Error)]
+pub enum SitemapFetchError {
+    #[error("Failed to fetch sitemap: {0}")]
+    FetchError(#[from] anyhow::Error),
+}
+
+/// Lists page URLs from a site's sitemap, discovered via robots.txt.
+/// Shares the HTTP client, robots.txt cache, metrics and content filter
+/// with the `WebFetch` it was created from.
+#[derive(Clone)]
+pub struct SitemapFetch {
+    http: ReqwestClient,
+    robots_cache: RobotsCache,
+    metrics: Arc<Metrics>,
+    content_filter: ContentFilter,
+}
+
+impl SitemapFetch {
+    pub fn new(web_fetch: &WebFetch) -> Self {
+        Self {
+            http: web_fetch.http.clone(),
+            robots_cache: web_fetch.robots_cache.clone(),
+            metrics: web_fetch.metrics.clone(),
+            content_filter: web_fetch.content_filter.clone(),
+        }
+    }
+}
+
+impl rig::tool::Tool for SitemapFetch {
+    const NAME: &'static str = "sitemap_fetch";
+    type Error = SitemapFetchError;
+    type Args = SitemapFetchArgs;
+    type Output = SitemapFetchOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Lists page URLs from a website's sitemap. Accepts any URL on the site \
+                          or a sitemap XML URL directly"
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "A URL on the site, or the sitemap URL"
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        info!("Fetching sitemap for {} ...", args.url);
+        // A sitemap refused by the content filter counts as a failed fetch
+        let urls = fetch_sitemap(&self.http, &args.url, &self.robots_cache)
+            .await
+            .and_then(|mut urls| {
+                self.content_filter.apply_all(urls.iter_mut())?;
+                Ok(urls)
+            });
+        self.metrics.record_fetch(urls.as_ref().err());
+        let urls = urls?;
+        Ok(SitemapFetchOutput { urls })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sitemap_fetch_args_deserialize() {
+        let json = r#"{"url": "https://example.com"}"#;
+        let args: SitemapFetchArgs = serde_json::from_str(json).unwrap();
+        assert_eq!(args.url, "https://example.com");
+    }
+
+    #[test]
+    fn test_sitemap_fetch_output_serialize() {
+        let output = SitemapFetchOutput {
+            urls: vec!["https://example.com/a".to_string()],
+        };
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(value["urls"][0], "https://example.com/a");
+    }
+}
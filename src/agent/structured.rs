@@ -0,0 +1,197 @@
+//! Structured output: asking the provider for JSON matching a schema and
+//! checking that the reply really does.
+
+use serde_json::Value;
+
+/// Error type for `AnyAgent::complete_structured`
+#[derive(Debug, thiserror::Error)]
+pub enum StructuredError {
+    #[error("Invalid JSON schema: {0}")]
+    InvalidSchema(String),
+    #[error("Completion failed: {0}")]
+    Completion(anyhow::Error),
+    #[error("Response is not JSON: {0}")]
+    NotJson(#[from] serde_json::Error),
+    #[error("Response does not match the schema: {0}")]
+    Validation(String),
+}
+
+/// Convert a JSON schema into the form rig passes to the provider
+pub(crate) fn output_schema(schema: &Value) -> Result<schemars::Schema, StructuredError> {
+    schemars::Schema::try_from(schema.clone())
+        .map_err(|_| StructuredError::InvalidSchema("expected an object or boolean".to_string()))
+}
+
+/// Parse a structured response and check it against `schema`.
+/// A surrounding Markdown code fence (```json ... ```) is tolerated.
+pub(crate) fn parse_structured(text: &str, schema: &Value) -> Result<Value, StructuredError> {
+    let value: Value = serde_json::from_str(strip_code_fence(text))?;
+    validate(&value, schema, "$").map_err(StructuredError::Validation)?;
+    Ok(value)
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim();
+    text.strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|inner| inner.trim_start_matches("json").trim())
+        .unwrap_or(text)
+}
+
+/// Check `value` against the subset of JSON Schema providers accept for
+/// structured output: `type`, `enum`, `properties`, `required`,
+/// `additionalProperties: false` and `items`. Other keywords are ignored.
+fn validate(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        // `true` (or a non-object) accepts anything; `false` accepts nothing
+        return match schema {
+            Value::Bool(false) => Err(format!("{path}: no value is allowed here")),
+            _ => Ok(()),
+        };
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            return Err(format!("{path}: expected {}", types.join(" or ")));
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            return Err(format!("{path}: {value} is not one of the allowed values"));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(Value::Array(required)) = schema.get("required") {
+            if let Some(missing) = required
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|key| !object.contains_key(*key))
+            {
+                return Err(format!("{path}: missing required property \"{missing}\""));
+            }
+        }
+        for (key, item) in object {
+            match properties.and_then(|p| p.get(key)) {
+                Some(property) => validate(item, property, &format!("{path}.{key}"))?,
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    return Err(format!("{path}: unexpected property \"{key}\""));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate(item, item_schema, &format!("{path}[{i}]"))?;
+        }
+    }
+    Ok(())
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "role": { "enum": ["admin", "user"] }
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_parse_structured_accepts_conforming_json() {
+        let value = parse_structured(
+            r#"{"name": "Ada", "age": 36, "tags": ["math"], "role": "admin"}"#,
+            &person_schema(),
+        )
+        .unwrap();
+
+        assert_eq!(value["name"], "Ada");
+        assert_eq!(value["age"], 36);
+    }
+
+    #[test]
+    fn test_parse_structured_strips_code_fence() {
+        let value = parse_structured(
+            "```json\n{\"name\": \"Ada\", \"age\": 36}\n```",
+            &person_schema(),
+        )
+        .unwrap();
+
+        assert_eq!(value["name"], "Ada");
+    }
+
+    #[test]
+    fn test_parse_structured_reports_violations_with_path() {
+        let cases = [
+            (r#"{"name": "Ada"}"#, "missing required property \"age\""),
+            (r#"{"name": "Ada", "age": "36"}"#, "$.age: expected integer"),
+            (
+                r#"{"name": "Ada", "age": 36, "tags": [1]}"#,
+                "$.tags[0]: expected string",
+            ),
+            (r#"{"name": "Ada", "age": 36, "role": "root"}"#, "$.role:"),
+            (
+                r#"{"name": "Ada", "age": 36, "email": "a@x"}"#,
+                "unexpected property \"email\"",
+            ),
+            (r#"["Ada", 36]"#, "$: expected object"),
+        ];
+
+        for (text, expected) in cases {
+            match parse_structured(text, &person_schema()) {
+                Err(StructuredError::Validation(message)) => {
+                    assert!(message.contains(expected), "{text}: {message}")
+                }
+                other => panic!("{text}: expected a validation error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_structured_rejects_prose() {
+        let result = parse_structured("Ada is 36 years old.", &person_schema());
+
+        assert!(matches!(result, Err(StructuredError::NotJson(_))));
+    }
+
+    #[test]
+    fn test_output_schema_rejects_non_object_schema() {
+        assert!(output_schema(&person_schema()).is_ok());
+        assert!(matches!(
+            output_schema(&json!("object")),
+            Err(StructuredError::InvalidSchema(_))
+        ));
+    }
+}
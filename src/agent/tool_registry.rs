@@ -0,0 +1,169 @@
+use rig::tool::ToolDyn;
+
+use super::research_tool::ResearchTool;
+use super::{
+    ArxivFetch, Calculator, Cite, ConfigError, FeedFetch, PdfRead, SitemapFetch, WebFetch,
+    WebFetchMany, WebSearch, WikipediaFetch,
+};
+
+/// Names accepted in `ENABLED_TOOLS`, in the order tools are registered
+pub const TOOL_NAMES: &[&str] = &[
+    "research_tool",
+    "web_fetch_many",
+    "sitemap_fetch",
+    "feed_fetch",
+    "cite",
+    "wikipedia_fetch",
+    "arxiv_fetch",
+    "web_fetch",
+    "web_search",
+    "pdf_read",
+    "calculator",
+];
+
+/// The built-in tools an agent is given, by name.
+///
+/// Read from `ENABLED_TOOLS` as comma-separated names (see `TOOL_NAMES`) by
+/// `Config`, which passes it to the agent builders in `AgentSettings`.
+/// Unset or blank enables every tool; `none` enables none.
+/// MCP tools are configured separately and are not affected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnabledTools {
+    names: Vec<String>,
+}
+
+impl EnabledTools {
+    /// Every built-in tool
+    pub fn all() -> Self {
+        Self {
+            names: TOOL_NAMES.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Read the enabled tools from `ENABLED_TOOLS` using `lookup` to read variables.
+    pub(crate) fn from_lookup(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        match lookup("ENABLED_TOOLS").filter(|v| !v.trim().is_empty()) {
+            None => Ok(Self::all()),
+            Some(spec) if spec.trim().eq_ignore_ascii_case("none") => Self::parse(""),
+            Some(spec) => Self::parse(&spec),
+        }
+    }
+
+    /// Parse comma-separated tool names. An empty list enables no tools.
+    ///
+    /// # Errors
+    /// Returns `ConfigError::UnknownTool` for a name not in `TOOL_NAMES`.
+    pub fn parse(spec: &str) -> Result<Self, ConfigError> {
+        let mut names: Vec<String> = Vec::new();
+        for name in spec.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            if !TOOL_NAMES.contains(&name) {
+                return Err(ConfigError::UnknownTool {
+                    name: name.to_string(),
+                    available: TOOL_NAMES.join(", "),
+                });
+            }
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        Ok(Self { names })
+    }
+
+    /// Whether the tool called `name` is enabled
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
+
+    /// Instantiate the enabled tools. Fetch tools share `web_fetch`'s caches;
+    /// `research_tool` is only added when one is given (router agents).
+    pub(crate) fn build(
+        &self,
+        web_fetch: &WebFetch,
+        research_tool: Option<ResearchTool>,
+    ) -> Vec<Box<dyn ToolDyn>> {
+        let mut research_tool = research_tool;
+        TOOL_NAMES
+            .iter()
+            .filter(|name| self.contains(name))
+            .filter_map(|name| -> Option<Box<dyn ToolDyn>> {
+                match *name {
+                    "research_tool" => Some(Box::new(research_tool.take()?)),
+                    "web_fetch_many" => Some(Box::new(WebFetchMany::new(web_fetch))),
+                    "sitemap_fetch" => Some(Box::new(SitemapFetch::new(web_fetch))),
+                    "feed_fetch" => Some(Box::new(FeedFetch::new(web_fetch))),
+                    "cite" => Some(Box::new(Cite)),
+                    "wikipedia_fetch" => Some(Box::new(WikipediaFetch::new(web_fetch))),
+                    "arxiv_fetch" => Some(Box::new(ArxivFetch::new(web_fetch))),
+                    "web_fetch" => Some(Box::new(web_fetch.clone())),
+                    "web_search" => Some(Box::new(WebSearch)),
+                    "pdf_read" => Some(Box::new(
+                        PdfRead::default().with_content_filter(web_fetch.content_filter.clone()),
+                    )),
+                    "calculator" => Some(Box::new(Calculator)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_names(tools: &[Box<dyn ToolDyn>]) -> Vec<String> {
+        tools.iter().map(|tool| tool.name()).collect()
+    }
+
+    #[test]
+    fn test_every_tool_name_builds_a_tool_of_that_name() {
+        let tools = EnabledTools::all().build(&WebFetch::new(), None);
+
+        let expected: Vec<&str> = TOOL_NAMES
+            .iter()
+            .copied()
+            .filter(|name| *name != "research_tool")
+            .collect();
+        assert_eq!(tool_names(&tools), expected);
+    }
+
+    #[test]
+    fn test_parse_selects_listed_tools() {
+        let enabled = EnabledTools::parse("web_search, calculator,web_search").unwrap();
+
+        let tools = enabled.build(&WebFetch::new(), None);
+
+        assert_eq!(tool_names(&tools), ["web_search", "calculator"]);
+    }
+
+    #[test]
+    fn test_empty_list_builds_no_tools() {
+        let enabled = EnabledTools::parse("").unwrap();
+
+        assert!(enabled.build(&WebFetch::new(), None).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_tool_name_is_an_error() {
+        let err = EnabledTools::parse("web_search,web_serch").unwrap_err();
+
+        assert!(matches!(&err, ConfigError::UnknownTool { name, .. } if name == "web_serch"));
+        assert!(err.to_string().contains("calculator"), "{err}");
+    }
+
+    #[test]
+    fn test_from_lookup_blank_enables_all_and_none_disables_all() {
+        let lookup = |value: &'static str| move |_: &str| Some(value.to_string());
+
+        assert_eq!(
+            EnabledTools::from_lookup(lookup("")).unwrap(),
+            EnabledTools::all()
+        );
+        assert_eq!(
+            EnabledTools::from_lookup(lookup("none")).unwrap(),
+            EnabledTools::parse("").unwrap()
+        );
+    }
+}
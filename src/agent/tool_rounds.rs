@@ -0,0 +1,189 @@
+//! Cap on tool-call rounds per response, so a confused model can't keep
+//! searching and fetching forever.
+//!
+//! A round is one model turn that calls tools. Once `MAX_TOOL_ROUNDS` rounds
+//! have run, further tool calls are skipped: the model gets
+//! `TOOL_LIMIT_REACHED` as the tool result and answers with what it has.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use rig::agent::{HookAction, PromptHook, ToolCallHookAction};
+use rig::completion::{CompletionModel, Message};
+
+use super::ChatStreamEvent;
+
+/// Tool-call rounds allowed per response when `MAX_TOOL_ROUNDS` is unset
+pub const DEFAULT_MAX_TOOL_ROUNDS: usize = 8;
+
+/// Tool result returned to the model for calls over the limit
+pub(crate) const TOOL_LIMIT_REACHED: &str = "Tool call limit reached for this response; no more \
+    tools can be used. Answer now with the information gathered so far.";
+
+/// Model turns an agent may take for `max_rounds` tool rounds: the tool
+/// rounds, the round whose calls are skipped, and the final answer
+pub(crate) fn max_turns(max_rounds: usize) -> usize {
+    max_rounds + 2
+}
+
+/// Tool rounds of an agent built with `default_max_turns(max_turns(rounds))`;
+/// agents built without a turn cap (the tool-less ones) get the default
+pub(crate) fn rounds_for_turns(turns: Option<usize>) -> usize {
+    turns.map_or(DEFAULT_MAX_TOOL_ROUNDS, |turns| {
+        turns.saturating_sub(max_turns(0))
+    })
+}
+
+/// Per-request hook that skips tool calls once `max_rounds` rounds have run
+#[derive(Debug, Clone)]
+pub(crate) struct ToolRoundLimit {
+    max_rounds: usize,
+    /// Model turns started so far in this request (shared by clones)
+    turns: Arc<AtomicUsize>,
+}
+
+impl ToolRoundLimit {
+    pub(crate) fn new(max_rounds: usize) -> Self {
+        Self {
+            max_rounds,
+            turns: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl<M: CompletionModel> PromptHook<M> for ToolRoundLimit {
+    async fn on_completion_call(&self, _prompt: &Message, _history: &[Message]) -> HookAction {
+        self.turns.fetch_add(1, Ordering::SeqCst);
+        HookAction::cont()
+    }
+
+    async fn on_tool_call(
+        &self,
+        tool_name: &str,
+        _tool_call_id: Option<String>,
+        _internal_call_id: &str,
+        _args: &str,
+    ) -> ToolCallHookAction {
+        // Tool calls of turn N belong to round N
+        if self.turns.load(Ordering::SeqCst) > self.max_rounds {
+            log::warn!(
+                "Skipping {} call: tool round limit ({}) reached",
+                tool_name,
+                self.max_rounds
+            );
+            return ToolCallHookAction::skip(TOOL_LIMIT_REACHED);
+        }
+        ToolCallHookAction::cont()
+    }
+}
+
+/// Replace the results of tool calls skipped by `ToolRoundLimit` with a
+/// single `Warning` event for the response
+pub(crate) fn warn_on_tool_limit(
+    stream: Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>>,
+    max_rounds: usize,
+) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+    let mut warned = false;
+    Box::pin(stream.filter_map(move |event| {
+        let event = match event {
+            ChatStreamEvent::ToolResult(output) if output == TOOL_LIMIT_REACHED => {
+                (!std::mem::replace(&mut warned, true)).then(|| {
+                    ChatStreamEvent::Warning(format!(
+                        "Stopped using tools after {max_rounds} rounds; answering with what was found so far"
+                    ))
+                })
+            }
+            event => Some(event),
+        };
+        futures::future::ready(event)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    fn tool_round(name: &str, result: &str) -> Vec<ChatStreamEvent> {
+        vec![
+            ChatStreamEvent::ToolCall {
+                name: name.to_string(),
+            },
+            ChatStreamEvent::ToolResult(result.to_string()),
+            ChatStreamEvent::TextDelta(String::new()),
+        ]
+    }
+
+    #[test]
+    fn test_rounds_for_turns_inverts_max_turns() {
+        assert_eq!(rounds_for_turns(Some(max_turns(3))), 3);
+        assert_eq!(rounds_for_turns(None), DEFAULT_MAX_TOOL_ROUNDS);
+    }
+
+    #[tokio::test]
+    async fn test_hook_skips_tool_calls_after_limit() {
+        let hook = ToolRoundLimit::new(2);
+        let prompt = Message::user("hi");
+        let mut actions = Vec::new();
+        for _ in 0..4 {
+            PromptHook::<rig::providers::ollama::CompletionModel>::on_completion_call(
+                &hook,
+                &prompt,
+                &[],
+            )
+            .await;
+            actions.push(
+                PromptHook::<rig::providers::ollama::CompletionModel>::on_tool_call(
+                    &hook,
+                    "web_search",
+                    None,
+                    "id",
+                    "{}",
+                )
+                .await,
+            );
+        }
+
+        assert_eq!(
+            actions,
+            [
+                ToolCallHookAction::cont(),
+                ToolCallHookAction::cont(),
+                ToolCallHookAction::skip(TOOL_LIMIT_REACHED),
+                ToolCallHookAction::skip(TOOL_LIMIT_REACHED),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_over_limit_warns_once_instead_of_skipped_results() {
+        let mut events = Vec::new();
+        for _ in 0..2 {
+            events.extend(tool_round("web_search", "results"));
+        }
+        // A confused model keeps calling tools; these calls were skipped
+        for _ in 0..3 {
+            events.extend(tool_round("web_fetch", TOOL_LIMIT_REACHED));
+        }
+        events.push(ChatStreamEvent::TextDelta("answer".to_string()));
+        events.push(ChatStreamEvent::Done);
+
+        let events: Vec<_> = warn_on_tool_limit(Box::pin(stream::iter(events)), 2)
+            .collect()
+            .await;
+
+        let tool_results = events
+            .iter()
+            .filter(|e| matches!(e, ChatStreamEvent::ToolResult(_)))
+            .count();
+        let warnings = events
+            .iter()
+            .filter(|e| matches!(e, ChatStreamEvent::Warning(_)))
+            .count();
+        assert_eq!(tool_results, 2);
+        assert_eq!(warnings, 1);
+        assert!(matches!(events.last(), Some(ChatStreamEvent::Done)));
+    }
+}
@@ -0,0 +1,105 @@
+//! Truncating tool output to a character budget without leaving a fragment
+//! of a sentence at the end.
+
+/// A boundary earlier than this fraction of the limit is not worth keeping;
+/// the next finer boundary is tried instead, so little of the budget is lost
+const MIN_KEPT_FRACTION: f64 = 0.5;
+
+/// Characters that end a sentence without needing a following space (CJK)
+const CJK_SENTENCE_ENDS: &[char] = &['。', '！', '？'];
+
+/// Characters that end a sentence when followed by whitespace
+const SENTENCE_ENDS: &[char] = &['.', '!', '?'];
+
+/// The longest prefix of `text` with at most `max_chars` characters that ends
+/// at a paragraph break, else a sentence end, else a word boundary, else a
+/// character boundary (e.g. CJK text without punctuation). Boundaries in the
+/// first half of the budget are skipped in favour of the next finer kind.
+/// Returns `text` itself when it already fits.
+pub(crate) fn truncate_at_boundary(text: &str, max_chars: usize) -> &str {
+    let limit = match text.char_indices().nth(max_chars) {
+        Some((byte_index, _)) => byte_index,
+        None => return text,
+    };
+    let prefix = &text[..limit];
+    let min_chars = (max_chars as f64 * MIN_KEPT_FRACTION) as usize;
+    let long_enough = |end: usize| prefix[..end].chars().count() >= min_chars;
+
+    let paragraph = prefix.rfind("\n\n");
+    let sentence = prefix
+        .char_indices()
+        .zip(text.chars().skip(1))
+        .filter(|&((_, c), next)| {
+            CJK_SENTENCE_ENDS.contains(&c) || (SENTENCE_ENDS.contains(&c) && next.is_whitespace())
+        })
+        .map(|((i, c), _)| i + c.len_utf8())
+        .last();
+    let word = prefix.rfind(char::is_whitespace);
+
+    let end = [paragraph, sentence, word]
+        .into_iter()
+        .flatten()
+        .find(|&end| long_enough(end))
+        .unwrap_or(limit);
+    prefix[..end].trim_end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGLISH: &str = "Rust is a systems language. It is fast! Is it safe? Yes.\n\n\
+        Cargo builds Rust code and manages dependencies for it.";
+
+    #[test]
+    fn test_text_within_limit_is_unchanged() {
+        assert_eq!(truncate_at_boundary(ENGLISH, 1000), ENGLISH);
+        assert_eq!(truncate_at_boundary("", 0), "");
+    }
+
+    #[test]
+    fn test_cuts_at_paragraph_break() {
+        assert_eq!(
+            truncate_at_boundary(ENGLISH, 80),
+            "Rust is a systems language. It is fast! Is it safe? Yes."
+        );
+    }
+
+    #[test]
+    fn test_cuts_at_last_sentence_end_within_limit() {
+        assert_eq!(
+            truncate_at_boundary(ENGLISH, 45),
+            "Rust is a systems language. It is fast!"
+        );
+        // A period inside a word (e.g. a version number) is not a sentence end
+        assert_eq!(
+            truncate_at_boundary("Install version 1.2 now. Then run the tool again", 30),
+            "Install version 1.2 now."
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_word_boundary_without_sentence_end() {
+        assert_eq!(
+            truncate_at_boundary("one two three four five six seven", 16),
+            "one two three"
+        );
+    }
+
+    #[test]
+    fn test_cjk_cuts_after_sentence_end() {
+        let text = "東京は晴れです。大阪は雨の予報です。週末は全国的に曇りでしょう。";
+
+        assert_eq!(
+            truncate_at_boundary(text, 20),
+            "東京は晴れです。大阪は雨の予報です。"
+        );
+    }
+
+    #[test]
+    fn test_cjk_without_boundaries_falls_back_to_characters() {
+        let text = "日本語の文章には単語の間に空白がありません";
+
+        assert_eq!(truncate_at_boundary(text, 8), "日本語の文章には");
+    }
+}
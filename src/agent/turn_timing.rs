@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::ChatStreamEvent;
+
+/// Timestamps of one chat turn, fed with the turn's stream events.
+///
+/// Separates time spent waiting for the provider from time spent in tools:
+/// a tool runs from its `ToolCall` event until the matching `ToolResult`.
+#[derive(Debug, Clone)]
+pub struct TurnTiming {
+    started: Instant,
+    first_token: Option<Instant>,
+    finished: Option<Instant>,
+    tools: usize,
+    tool_time: Duration,
+    /// Start times of tool calls whose results have not arrived yet, in call order
+    pending_tools: VecDeque<Instant>,
+}
+
+impl TurnTiming {
+    /// Start timing a turn now; call this before requesting the stream
+    pub fn start() -> Self {
+        Self::started_at(Instant::now())
+    }
+
+    fn started_at(started: Instant) -> Self {
+        Self {
+            started,
+            first_token: None,
+            finished: None,
+            tools: 0,
+            tool_time: Duration::ZERO,
+            pending_tools: VecDeque::new(),
+        }
+    }
+
+    /// Record a stream event as received now
+    pub fn observe(&mut self, event: &ChatStreamEvent) {
+        self.observe_at(event, Instant::now());
+    }
+
+    fn observe_at(&mut self, event: &ChatStreamEvent, now: Instant) {
+        match event {
+            ChatStreamEvent::TextDelta(_) => {
+                self.first_token.get_or_insert(now);
+            }
+            ChatStreamEvent::ToolCall { .. } => {
+                self.tools += 1;
+                self.pending_tools.push_back(now);
+            }
+            ChatStreamEvent::ToolResult(_) => {
+                if let Some(called) = self.pending_tools.pop_front() {
+                    self.tool_time += now - called;
+                }
+            }
+            ChatStreamEvent::Done | ChatStreamEvent::Error(_) => {
+                self.finished.get_or_insert(now);
+            }
+            ChatStreamEvent::Thinking(_)
+            | ChatStreamEvent::Source(_)
+            | ChatStreamEvent::Warning(_)
+            | ChatStreamEvent::Usage { .. }
+            | ChatStreamEvent::Truncated => {}
+        }
+    }
+
+    /// Time from the start of the turn to the first text delta
+    pub fn ttft(&self) -> Option<Duration> {
+        self.first_token.map(|at| at - self.started)
+    }
+
+    /// Whole turn duration; up to now if the stream has not finished
+    pub fn total(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
+
+    /// Number of tool calls made during the turn
+    pub fn tools(&self) -> usize {
+        self.tools
+    }
+
+    /// Time spent between tool calls and their results
+    pub fn tool_time(&self) -> Duration {
+        self.tool_time
+    }
+
+    /// One-line `key=value` summary for the logs
+    pub fn log_line(&self) -> String {
+        let ttft = self
+            .ttft()
+            .map_or_else(|| "-".to_string(), |d| d.as_millis().to_string());
+        format!(
+            "turn_ms={} ttft_ms={} tools={} tool_ms={}",
+            self.total().as_millis(),
+            ttft,
+            self.tools,
+            self.tool_time.as_millis()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_records_first_token_tools_and_total() {
+        let start = Instant::now();
+        let mut timing = TurnTiming::started_at(start);
+
+        let call = ChatStreamEvent::ToolCall {
+            name: "web_fetch".to_string(),
+        };
+        timing.observe_at(&call, start + ms(100));
+        timing.observe_at(&ChatStreamEvent::ToolResult("page".into()), start + ms(400));
+        timing.observe_at(&ChatStreamEvent::TextDelta("Hi".into()), start + ms(600));
+        timing.observe_at(
+            &ChatStreamEvent::TextDelta(" there".into()),
+            start + ms(700),
+        );
+        timing.observe_at(&ChatStreamEvent::Done, start + ms(900));
+
+        assert_eq!(timing.ttft(), Some(ms(600)));
+        assert_eq!(timing.tools(), 1);
+        assert_eq!(timing.tool_time(), ms(300));
+        assert_eq!(timing.total(), ms(900));
+        assert_eq!(
+            timing.log_line(),
+            "turn_ms=900 ttft_ms=600 tools=1 tool_ms=300"
+        );
+    }
+
+    #[test]
+    fn test_turn_without_text_has_no_ttft() {
+        let start = Instant::now();
+        let mut timing = TurnTiming::started_at(start);
+
+        timing.observe_at(&ChatStreamEvent::Error("boom".into()), start + ms(50));
+        timing.observe_at(&ChatStreamEvent::Done, start + ms(80));
+
+        assert_eq!(timing.ttft(), None);
+        assert_eq!(timing.total(), ms(50));
+        assert_eq!(timing.log_line(), "turn_ms=50 ttft_ms=- tools=0 tool_ms=0");
+    }
+
+    #[test]
+    fn test_parallel_tool_calls_are_matched_in_order() {
+        let start = Instant::now();
+        let mut timing = TurnTiming::started_at(start);
+        let call = ChatStreamEvent::ToolCall {
+            name: "web_fetch".to_string(),
+        };
+
+        timing.observe_at(&call, start);
+        timing.observe_at(&call, start + ms(10));
+        timing.observe_at(&ChatStreamEvent::ToolResult("a".into()), start + ms(200));
+        timing.observe_at(&ChatStreamEvent::ToolResult("b".into()), start + ms(210));
+
+        assert_eq!(timing.tools(), 2);
+        assert_eq!(timing.tool_time(), ms(400));
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use log::{info, warn};
+
+use super::{ChatAgent, ChatStreamEvent};
+
+/// Throwaway prompt sent to load the model; kept short so the reply is cheap
+const WARMUP_PROMPT: &str = "Reply with OK.";
+
+/// Upper bound on the warm-up so a hung provider can't keep the task alive
+pub const WARMUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Error type for the startup warm-up
+#[derive(Debug, thiserror::Error)]
+pub enum WarmupError {
+    #[error("agent returned an error: {0}")]
+    Agent(String),
+    #[error("no response within {0:?}")]
+    TimedOut(Duration),
+}
+
+/// Send a tiny prompt through `agent` and discard the reply, so the provider
+/// connection and model load happen before the first real request.
+/// Returns how long the round trip took.
+pub async fn warm_up(agent: &dyn ChatAgent, timeout: Duration) -> Result<Duration, WarmupError> {
+    let started = Instant::now();
+    let round_trip = async {
+        let mut stream = agent.stream_chat(WARMUP_PROMPT, vec![]).await;
+        while let Some(event) = stream.next().await {
+            match event {
+                ChatStreamEvent::Done => break,
+                ChatStreamEvent::Error(e) => return Err(WarmupError::Agent(e)),
+                _ => {}
+            }
+        }
+        Ok(())
+    };
+    tokio::time::timeout(timeout, round_trip)
+        .await
+        .map_err(|_| WarmupError::TimedOut(timeout))??;
+    Ok(started.elapsed())
+}
+
+/// Run the warm-up and log the outcome. Failures are only logged: the server
+/// keeps running and the first request simply pays the cold start instead.
+pub async fn run_warmup(agent: &dyn ChatAgent) {
+    info!("Warming up the model ...");
+    match warm_up(agent, WARMUP_TIMEOUT).await {
+        Ok(latency) => info!("Warm-up finished in {} ms", latency.as_millis()),
+        Err(e) => warn!("Warm-up failed (continuing without it): {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+
+    use async_trait::async_trait;
+    use futures::Stream;
+    use rig::completion::Message;
+
+    use super::*;
+    use crate::agent::MockAgent;
+
+    /// Agent that never answers, like a provider stuck loading the model
+    struct StalledAgent;
+
+    #[async_trait]
+    impl ChatAgent for StalledAgent {
+        async fn stream_chat(
+            &self,
+            _prompt: &str,
+            _history: Vec<Message>,
+        ) -> Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+            Box::pin(futures::stream::pending())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_sends_one_prompt_without_history() {
+        let agent = MockAgent::with_response("OK");
+
+        let result = warm_up(&agent, WARMUP_TIMEOUT).await;
+
+        assert!(result.is_ok());
+        let histories = agent.received_histories();
+        assert_eq!(histories.len(), 1);
+        assert!(histories[0].is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_reports_agent_error() {
+        let agent = MockAgent::with_error("connection refused");
+
+        let result = warm_up(&agent, WARMUP_TIMEOUT).await;
+
+        assert!(matches!(result, Err(WarmupError::Agent(e)) if e == "connection refused"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_warm_up_times_out_on_stalled_agent() {
+        let result = warm_up(&StalledAgent, Duration::from_secs(5)).await;
+
+        assert!(matches!(result, Err(WarmupError::TimedOut(_))));
+    }
+
+    #[tokio::test]
+    async fn test_run_warmup_failure_is_not_fatal() {
+        // Returns normally instead of panicking
+        run_warmup(&MockAgent::with_error("model not found")).await;
+    }
+}
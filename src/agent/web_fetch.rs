@@ -1,36 +1,200 @@
-use log::info;
-use rig::completion::ToolDefinition;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use futures::future::{BoxFuture, Shared};
+use futures::{FutureExt, TryFutureExt};
+use log::{info, warn};
+use rig::completion::{Document, ToolDefinition};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::collectors::robots::RobotsCache;
-use crate::collectors::web::fetch_url;
+use super::cite::Citation;
+use super::content_filter::{ContentBlocked, ContentFilter};
+use super::grounding::page_document;
+use super::relevance::{focus_paragraphs, FOCUS_TOP_K};
+use super::truncation::truncate_at_boundary;
+use crate::collectors::access_wall::AccessWall;
+use crate::collectors::fetch_limit::{FetchLimiter, DEFAULT_MAX_CONCURRENT_FETCHES};
+use crate::collectors::rate_limit::{DomainRateLimiter, DEFAULT_FETCH_RATE_PER_SEC};
+use crate::collectors::robots::{
+    RobotsCache, DEFAULT_ROBOTS_CACHE_TTL, DEFAULT_ROBOTS_FETCH_TIMEOUT,
+};
+use crate::collectors::web::{
+    fetch_url, FetchFailure, Heading, Link, PageContent, ReqwestClient, DEFAULT_FETCH_CONCURRENCY,
+};
+use crate::config::Config;
+use crate::llm::{Embedder, LlmClient, RigClient};
+use crate::metrics::Metrics;
+use crate::sync::lock;
+
+/// Instruction prepended to page text when `summarize` is requested
+const SUMMARIZE_PROMPT: &str = "Summarize the following web page in a few short paragraphs. \
+Keep concrete facts, names, numbers and dates. Reply with the summary only.\n\n";
 
 /// Arguments for the WebFetch tool
 #[derive(Deserialize)]
 pub struct WebFetchArgs {
     url: String,
+    /// Include the page's outbound links in the output (off by default to keep output small)
+    #[serde(default)]
+    include_links: bool,
+    /// Include the page's h1-h3 headings as a table of contents (off by default)
+    #[serde(default)]
+    include_toc: bool,
+    /// Return a short LLM-generated summary instead of the full page text (off by default)
+    #[serde(default)]
+    summarize: bool,
+    /// Also return the page's raw HTML, for debugging extraction (off by default)
+    #[serde(default)]
+    include_raw: bool,
+    /// Cut the content to at most this many characters, at a sentence or paragraph end
+    #[serde(default)]
+    max_chars: Option<usize>,
+    /// Keep only the paragraphs most relevant to this query (needs an embedder)
+    #[serde(default)]
+    focus: Option<String>,
 }
 
 /// Output from the WebFetch tool
 #[derive(Serialize)]
 pub struct WebFetchOutput {
     title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published_at: Option<String>,
+    /// ISO 639-1 code of the page's language, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detected_language: Option<String>,
     content: String,
+    /// Whether `content` was cut short by `max_chars`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    links: Option<Vec<Link>>,
+    /// The page's headings, when `include_toc` was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    toc: Option<Vec<Heading>>,
+    /// The page's HTML, when `include_raw` was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_html: Option<String>,
+    citation: Citation,
 }
 
-/// Error type for WebFetch tool
+/// Error type for WebFetch tool.
+/// Messages are phrased so the model can decide whether retrying makes sense.
 #[derive(Debug, thiserror::Error)]
 pub enum WebFetchError {
+    #[error("Access to {0} is disallowed by robots.txt; do not retry this URL")]
+    RobotsDisallowed(String),
+    #[error("Timed out fetching the page; the site may be slow or unreachable")]
+    Timeout,
+    #[error("The server responded with HTTP status {0}")]
+    HttpStatus(u16),
+    #[error("The URL is not an HTML page (content type: {0})")]
+    UnsupportedContentType(String),
+    #[error("Network error while fetching the page: {0}")]
+    Network(String),
+    #[error("The site returned a {0} instead of the page's content; look for the information in another source")]
+    AccessWall(AccessWall),
+    #[error("The page had almost no text ({0} characters); it is probably rendered with JavaScript, so look for the information in another source")]
+    TooLittleText(usize),
+    #[error("{0} was refused by the content filter; do not retry this URL")]
+    ContentBlocked(String),
     #[error("Failed to fetch URL: {0}")]
-    FetchError(#[from] anyhow::Error),
+    FetchError(anyhow::Error),
+    #[error("Failed to summarize page: {0}")]
+    SummarizeError(anyhow::Error),
+}
+
+impl WebFetchError {
+    /// Map a collector or content filter error for `url` to the matching variant.
+    /// Other errors become `FetchError`.
+    pub(crate) fn from_fetch(e: &anyhow::Error, url: &str) -> Self {
+        if e.is::<ContentBlocked>() {
+            return Self::ContentBlocked(url.to_string());
+        }
+        match e.downcast_ref::<FetchFailure>() {
+            Some(FetchFailure::RobotsDisallowed(url)) => Self::RobotsDisallowed(url.clone()),
+            Some(FetchFailure::Timeout) => Self::Timeout,
+            Some(FetchFailure::HttpStatus(status)) => Self::HttpStatus(*status),
+            Some(FetchFailure::UnsupportedContentType(ct)) => {
+                Self::UnsupportedContentType(ct.clone())
+            }
+            Some(FetchFailure::Network(msg)) => Self::Network(msg.clone()),
+            Some(FetchFailure::AccessWall(wall)) => Self::AccessWall(*wall),
+            Some(FetchFailure::TooLittleText(chars)) => Self::TooLittleText(*chars),
+            None => Self::FetchError(anyhow!("{:#}", e)),
+        }
+    }
+}
+
+/// A fetch that may be awaited by several callers at once.
+/// The error is wrapped in `Arc` because `Shared` requires a `Clone` output.
+type SharedFetch = Shared<BoxFuture<'static, Result<PageContent, Arc<anyhow::Error>>>>;
+
+/// Fetches currently in progress, keyed by URL.
+/// A second request for a URL that is still being fetched awaits the first
+/// fetch instead of starting another one.
+#[derive(Clone, Default)]
+pub(crate) struct InFlightFetches {
+    pending: Arc<Mutex<HashMap<String, SharedFetch>>>,
+}
+
+impl InFlightFetches {
+    /// Run `fetch` for `url`, or join the fetch already running for the same URL.
+    /// The error is shared between all callers, so it is returned behind an `Arc`.
+    pub(crate) async fn run<F>(
+        &self,
+        url: &str,
+        fetch: F,
+    ) -> Result<PageContent, Arc<anyhow::Error>>
+    where
+        F: Future<Output = anyhow::Result<PageContent>> + Send + 'static,
+    {
+        let shared = {
+            let mut pending = lock(&self.pending);
+            pending
+                .entry(url.to_string())
+                .or_insert_with(|| fetch.map_err(Arc::new).boxed().shared())
+                .clone()
+        };
+
+        let result = shared.clone().await;
+
+        // Only remove our own entry; a newer fetch may have replaced it already
+        let mut pending = lock(&self.pending);
+        if pending.get(url).is_some_and(|p| p.ptr_eq(&shared)) {
+            pending.remove(url);
+        }
+        result
+    }
 }
 
 /// Web page fetcher with shared robots.txt cache.
 /// Clone shares the same cache via Arc, enabling cache reuse across agents.
+/// Pages are requested through one shared HTTP client unless `with_http_client` injects another.
+/// Clones and the tools created from them share one bound on requests in flight,
+/// so a server's concurrent chats can't open more than `MAX_CONCURRENT_FETCHES` connections.
+/// Identical URLs requested while a fetch is in progress share that fetch.
+/// Fetch outcomes are counted in shared `Metrics`.
+/// Page text passes through a `ContentFilter` (read from `CONTENT_FILTER_MODE`).
+/// With a summarizer attached, `summarize: true` condenses the page text through `L`.
+/// With an embedder attached, `focus` keeps only the paragraphs relevant to a query.
 #[derive(Clone)]
-pub struct WebFetch {
-    robots_cache: RobotsCache,
+pub struct WebFetch<L = RigClient> {
+    pub(crate) http: ReqwestClient,
+    pub(crate) robots_cache: RobotsCache,
+    pub(crate) metrics: Arc<Metrics>,
+    pub(crate) content_filter: ContentFilter,
+    /// Pages the `WebFetchMany` built from this fetcher fetches at once
+    pub(crate) many_concurrency: usize,
+    in_flight: InFlightFetches,
+    summarizer: Option<L>,
+    embedder: Option<Arc<dyn Embedder>>,
 }
 
 impl Default for WebFetch {
@@ -40,14 +204,220 @@ impl Default for WebFetch {
 }
 
 impl WebFetch {
+    /// Create a fetcher with the default settings: robots.txt is respected and
+    /// cached for `DEFAULT_ROBOTS_CACHE_TTL`, each origin gets at most
+    /// `DEFAULT_FETCH_RATE_PER_SEC` page requests per second, and at most
+    /// `DEFAULT_MAX_CONCURRENT_FETCHES` requests are in flight at once.
+    /// Use `from_config` for the settings of a deployment.
     pub fn new() -> Self {
+        Self::with_robots_settings(
+            true,
+            DEFAULT_ROBOTS_CACHE_TTL,
+            DEFAULT_ROBOTS_FETCH_TIMEOUT,
+            DEFAULT_FETCH_RATE_PER_SEC,
+            FetchLimiter::new(DEFAULT_MAX_CONCURRENT_FETCHES),
+        )
+    }
+
+    /// Create a fetcher with the robots.txt, rate-limit, concurrency, User-Agent,
+    /// timeout, body limit and minimum page text settings of an already checked `Config`
+    pub fn from_config(config: &Config) -> Self {
+        let mut fetch = Self::with_robots_settings(
+            config.respect_robots,
+            config.robots_cache_ttl,
+            config.robots_timeout,
+            config.fetch_rate_per_sec,
+            FetchLimiter::new(config.max_concurrent_fetches),
+        );
+        fetch.robots_cache = fetch.robots_cache.with_user_agent(&config.user_agent);
+        fetch.http = ReqwestClient::with_settings(&config.user_agent, config.fetch_timeout)
+            .with_max_bytes(config.fetch_max_bytes)
+            .with_min_text_chars(config.fetch_min_text_chars)
+            .with_credentials(config.fetch_credentials.clone())
+            .with_content_selectors(config.content_selectors.clone())
+            .with_limiter(fetch.http.limiter().clone());
+        fetch.many_concurrency = config.fetch_concurrency;
+        fetch.content_filter = config.content_filter.clone();
+        fetch
+    }
+
+    fn with_robots_settings(
+        respect_robots: bool,
+        ttl: Duration,
+        fetch_timeout: Duration,
+        fetch_rate_per_sec: f64,
+        limiter: FetchLimiter,
+    ) -> Self {
+        if !respect_robots {
+            warn!("RESPECT_ROBOTS=false: robots.txt will be ignored for all fetches");
+        }
+        let metrics = Arc::<Metrics>::default();
+        let robots_cache = RobotsCache::new()
+            .with_respect_robots(respect_robots)
+            .with_ttl(ttl)
+            .with_fetch_timeout(fetch_timeout)
+            .with_rate_limiter(DomainRateLimiter::new(fetch_rate_per_sec))
+            .with_stats(metrics.robots_stats());
         Self {
-            robots_cache: RobotsCache::new(),
+            http: ReqwestClient::shared().clone().with_limiter(limiter),
+            robots_cache,
+            metrics,
+            content_filter: ContentFilter::default(),
+            many_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            in_flight: InFlightFetches::default(),
+            summarizer: None,
+            embedder: None,
+        }
+    }
+}
+
+impl<L> WebFetch<L> {
+    /// Use `llm` to summarize pages fetched with `summarize: true`.
+    /// Without a summarizer, such requests return the full page text.
+    pub fn with_summarizer<M: LlmClient>(self, llm: M) -> WebFetch<M> {
+        WebFetch {
+            http: self.http,
+            robots_cache: self.robots_cache,
+            metrics: self.metrics,
+            content_filter: self.content_filter,
+            many_concurrency: self.many_concurrency,
+            in_flight: self.in_flight,
+            summarizer: Some(llm),
+            embedder: self.embedder,
+        }
+    }
+
+    /// Send requests through `client`, e.g. to set a proxy or different timeouts.
+    /// The tools created from this fetcher use the same client.
+    /// The bound on concurrent requests is kept.
+    pub fn with_http_client(mut self, client: Arc<reqwest::Client>) -> Self {
+        self.http = ReqwestClient::with_client(client).with_limiter(self.http.limiter().clone());
+        self
+    }
+
+    /// Use `embedder` to rank paragraphs for fetches with `focus`.
+    /// Without an embedder, such requests return the full page text.
+    pub fn with_embedder(mut self, embedder: impl Embedder + 'static) -> Self {
+        self.embedder = Some(Arc::new(embedder));
+        self
+    }
+
+    /// Enable or disable robots.txt enforcement.
+    /// Disabling is intended only for authorized crawling of internal sites.
+    pub fn with_respect_robots(mut self, respect_robots: bool) -> Self {
+        self.robots_cache = self.robots_cache.with_respect_robots(respect_robots);
+        self
+    }
+
+    /// Filter fetched page text through `filter` (see `CONTENT_FILTER_MODE`); unfiltered by default
+    pub fn with_content_filter(mut self, filter: ContentFilter) -> Self {
+        self.content_filter = filter;
+        self
+    }
+
+    /// Whether robots.txt rules are enforced for this fetcher
+    pub fn respect_robots(&self) -> bool {
+        self.robots_cache.respects_robots()
+    }
+
+    /// Empty the robots.txt cache shared by this fetcher and its clones.
+    /// Returns the number of entries evicted.
+    pub async fn clear_caches(&self) -> usize {
+        let evicted = self.robots_cache.clear().await;
+        info!("Cleared {} cached robots.txt entries", evicted);
+        evicted
+    }
+
+    /// Counters updated by this fetcher and the tools created from it
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+}
+
+impl WebFetchOutput {
+    /// Cut `content` to at most `max_chars` characters at a natural boundary
+    fn truncate_content(&mut self, max_chars: usize) {
+        let kept = truncate_at_boundary(&self.content, max_chars).len();
+        if kept < self.content.len() {
+            self.content.truncate(kept);
+            self.truncated = true;
+        }
+    }
+}
+
+impl<L: LlmClient> WebFetch<L> {
+    /// Keep the paragraphs of `text` most relevant to `query`.
+    /// Falls back to the full text without an embedder or when embedding fails.
+    async fn focus(&self, text: String, query: &str, url: &str) -> String {
+        let Some(embedder) = &self.embedder else {
+            warn!("No embedder configured; returning full text of {}", url);
+            return text;
+        };
+        match focus_paragraphs(embedder.as_ref(), &text, query, FOCUS_TOP_K).await {
+            Ok(focused) => focused,
+            Err(e) => {
+                warn!("Failed to rank paragraphs of {}: {:#}", url, e);
+                text
+            }
         }
     }
+
+    /// Build the tool output for a fetched page, narrowing its text to `focus`
+    /// and summarizing it if requested
+    async fn build_output(
+        &self,
+        page: PageContent,
+        include_links: bool,
+        include_toc: bool,
+        summarize: bool,
+        focus: Option<&str>,
+    ) -> Result<WebFetchOutput, WebFetchError> {
+        let mut metadata = page.metadata;
+        let description = metadata
+            .remove("description")
+            .or_else(|| metadata.remove("og:description"));
+        let title = page.title.or_else(|| metadata.remove("og:title"));
+
+        let citation = Citation::now(&page.url, title.clone());
+
+        let text = match focus {
+            Some(query) => self.focus(page.text, query, &page.url).await,
+            None => page.text,
+        };
+
+        let content = match (&self.summarizer, summarize) {
+            (Some(llm), true) => {
+                info!("Summarizing {} ...", page.url);
+                llm.complete(&format!("{}{}", SUMMARIZE_PROMPT, text))
+                    .await
+                    .map_err(WebFetchError::SummarizeError)?
+            }
+            (None, true) => {
+                warn!(
+                    "No summarizer configured; returning full text of {}",
+                    page.url
+                );
+                text
+            }
+            (_, false) => text,
+        };
+
+        Ok(WebFetchOutput {
+            title,
+            description,
+            published_at: metadata.remove("article:published_time"),
+            detected_language: page.detected_language,
+            content,
+            truncated: false,
+            links: include_links.then_some(page.links),
+            toc: include_toc.then_some(page.headings),
+            raw_html: page.raw_html,
+            citation,
+        })
+    }
 }
 
-impl rig::tool::Tool for WebFetch {
+impl<L: LlmClient + Clone + Send + Sync + 'static> rig::tool::Tool for WebFetch<L> {
     const NAME: &'static str = "web_fetch";
     type Error = WebFetchError;
     type Args = WebFetchArgs;
@@ -63,6 +433,30 @@ impl rig::tool::Tool for WebFetch {
                     "url": {
                         "type": "string",
                         "description": "The URL to fetch"
+                    },
+                    "include_links": {
+                        "type": "boolean",
+                        "description": "Also return the links found on the page (default: false)"
+                    },
+                    "include_toc": {
+                        "type": "boolean",
+                        "description": "Also return the page's headings (level, text, anchor) as a table of contents, to navigate long documents (default: false)"
+                    },
+                    "summarize": {
+                        "type": "boolean",
+                        "description": "Return a short summary instead of the full page text (default: false)"
+                    },
+                    "include_raw": {
+                        "type": "boolean",
+                        "description": "Also return the raw HTML, only for debugging poor extraction (default: false)"
+                    },
+                    "max_chars": {
+                        "type": "integer",
+                        "description": "Cut the page text to at most this many characters, ending at a sentence or paragraph (default: no limit)"
+                    },
+                    "focus": {
+                        "type": "string",
+                        "description": "Return only the paragraphs most relevant to this query, for long pages (default: full text)"
                     }
                 },
                 "required": ["url"]
@@ -72,34 +466,458 @@ impl rig::tool::Tool for WebFetch {
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         info!("Fetching {} ...", args.url);
-        let page = fetch_url(&args.url, &self.robots_cache).await?;
-        Ok(WebFetchOutput {
-            title: page.title,
-            content: page.text,
-        })
+        let http = self.http.clone();
+        let robots_cache = self.robots_cache.clone();
+        let url = args.url.clone();
+        let include_raw = args.include_raw;
+        // Raw and plain fetches of a URL yield different pages, so they aren't shared
+        let key = if include_raw {
+            format!("raw:{}", args.url)
+        } else {
+            args.url.clone()
+        };
+        let page = self
+            .in_flight
+            .run(&key, async move {
+                fetch_url(&http, &url, &robots_cache, include_raw).await
+            })
+            .await;
+        // A page refused by the content filter counts as a failed fetch
+        let page = page.and_then(|page| {
+            self.content_filter
+                .apply_page(page)
+                .map_err(|e| Arc::new(e.into()))
+        });
+        self.metrics
+            .record_fetch(page.as_ref().err().map(|e| e.as_ref()));
+        let page = page.map_err(|e| WebFetchError::from_fetch(&e, &args.url))?;
+        let mut output = self
+            .build_output(
+                page,
+                args.include_links,
+                args.include_toc,
+                args.summarize,
+                args.focus.as_deref(),
+            )
+            .await?;
+        if let Some(max_chars) = args.max_chars {
+            output.truncate_content(max_chars);
+        }
+        Ok(output)
+    }
+}
+
+impl<L: LlmClient + Clone + Send + Sync + 'static> WebFetch<L> {
+    /// Fetch `url` as a document to attach to a completion request,
+    /// keyed by the URL, along with its citation
+    pub async fn fetch_document(&self, url: &str) -> Result<(Document, Citation), WebFetchError> {
+        let args = WebFetchArgs {
+            url: url.to_string(),
+            include_links: false,
+            include_toc: false,
+            summarize: false,
+            include_raw: false,
+            max_chars: None,
+            focus: None,
+        };
+        let output = rig::tool::Tool::call(self, args).await?;
+        let document = page_document(url, output.title.as_deref(), output.content);
+        Ok((document, output.citation))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent::relevance::tests::KeywordEmbedder;
     use rig::tool::Tool;
 
+    #[test]
+    fn test_from_config_uses_configured_content_filter() {
+        let config = Config::from_lookup(|key| match key {
+            "COPAL_API_TOKEN" => Some("token".to_string()),
+            "CONTENT_FILTER_MODE" => Some("block".to_string()),
+            "CONTENT_FILTER_WORDS" => Some("darn".to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        let fetch = WebFetch::from_config(&config);
+
+        assert_eq!(fetch.content_filter, config.content_filter);
+        assert!(fetch
+            .content_filter
+            .apply("well, darn".to_string())
+            .is_err());
+    }
+
     #[test]
     fn test_web_fetch_args_deserialize() {
         let json = r#"{"url": "https://example.com"}"#;
         let args: WebFetchArgs = serde_json::from_str(json).unwrap();
         assert_eq!(args.url, "https://example.com");
+        assert!(!args.include_links);
+        assert!(!args.include_toc);
+        assert!(!args.include_raw);
+        assert!(args.focus.is_none());
+    }
+
+    #[test]
+    fn test_web_fetch_args_deserialize_summarize() {
+        let json = r#"{"url": "https://example.com", "summarize": true}"#;
+        let args: WebFetchArgs = serde_json::from_str(json).unwrap();
+        assert!(args.summarize);
+        assert!(!args.include_links);
+    }
+
+    #[test]
+    fn test_web_fetch_args_deserialize_include_links() {
+        let json = r#"{"url": "https://example.com", "include_links": true}"#;
+        let args: WebFetchArgs = serde_json::from_str(json).unwrap();
+        assert!(args.include_links);
     }
 
     #[test]
     fn test_web_fetch_output_serialize() {
         let output = WebFetchOutput {
             title: Some("Test".to_string()),
+            description: None,
+            published_at: None,
+            detected_language: None,
             content: "Hello".to_string(),
+            truncated: false,
+            links: None,
+            toc: None,
+            raw_html: None,
+            citation: Citation::now("https://example.com", Some("Test".to_string())),
         };
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("Test"));
+        assert!(!json.contains("links"));
+        assert!(!json.contains("published_at"));
+        assert!(json.contains("fetched_at"));
+    }
+
+    #[test]
+    fn test_web_fetch_output_serialize_links() {
+        let output = WebFetchOutput {
+            title: None,
+            description: Some("About page".to_string()),
+            published_at: Some("2024-05-01".to_string()),
+            detected_language: Some("en".to_string()),
+            content: "Hello".to_string(),
+            truncated: false,
+            links: Some(vec![Link {
+                href: "https://example.com/about".to_string(),
+                text: "About".to_string(),
+            }]),
+            toc: None,
+            raw_html: None,
+            citation: Citation::now("https://example.com", None),
+        };
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(value["links"][0]["href"], "https://example.com/about");
+        assert_eq!(value["description"], "About page");
+        assert_eq!(value["published_at"], "2024-05-01");
+        assert_eq!(value["detected_language"], "en");
+    }
+
+    #[test]
+    fn test_with_respect_robots_overrides_default() {
+        let sut = WebFetch::new().with_respect_robots(false);
+        assert!(!sut.respect_robots());
+    }
+
+    fn make_page(url: &str) -> PageContent {
+        PageContent {
+            url: url.to_string(),
+            title: None,
+            text: "content".to_string(),
+            links: vec![],
+            headings: vec![],
+            metadata: HashMap::new(),
+            detected_language: None,
+            raw_html: None,
+            access_wall: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_fetches_share_identical_concurrent_fetch() {
+        let in_flight = InFlightFetches::default();
+        let client_hits = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let fetch = |hits: Arc<std::sync::atomic::AtomicUsize>| async move {
+            hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(make_page("https://example.com"))
+        };
+
+        let (first, second) = tokio::join!(
+            in_flight.run("https://example.com", fetch(client_hits.clone())),
+            in_flight.run("https://example.com", fetch(client_hits.clone())),
+        );
+
+        assert_eq!(first.unwrap().url, "https://example.com");
+        assert_eq!(second.unwrap().url, "https://example.com");
+        assert_eq!(client_hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(in_flight.pending.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_fetches_shares_errors() {
+        let in_flight = InFlightFetches::default();
+
+        let (first, second) = tokio::join!(
+            in_flight.run("https://example.com", async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Err(anyhow!("connection refused"))
+            }),
+            in_flight.run("https://example.com", async { Ok(make_page("unused")) }),
+        );
+
+        assert_eq!(first.unwrap_err().to_string(), "connection refused");
+        assert_eq!(second.unwrap_err().to_string(), "connection refused");
+    }
+
+    #[derive(Clone)]
+    struct MockLlmClient;
+
+    impl LlmClient for MockLlmClient {
+        async fn complete(&self, _prompt: &str) -> anyhow::Result<String> {
+            Ok("Fixed summary".to_string())
+        }
+
+        async fn complete_with_history(
+            &self,
+            _history: &[rig::completion::Message],
+            prompt: &str,
+        ) -> anyhow::Result<String> {
+            self.complete(prompt).await
+        }
+
+        async fn stream_complete(&self, _prompt: &str) -> anyhow::Result<crate::llm::TextStream> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_output_uses_summary_when_requested() {
+        let sut = WebFetch::new().with_summarizer(MockLlmClient);
+
+        let output = sut
+            .build_output(make_page("https://example.com"), false, false, true, None)
+            .await
+            .unwrap();
+
+        assert_eq!(output.content, "Fixed summary");
+    }
+
+    #[tokio::test]
+    async fn test_build_output_returns_full_text_by_default() {
+        let sut = WebFetch::new().with_summarizer(MockLlmClient);
+
+        let output = sut
+            .build_output(make_page("https://example.com"), false, false, false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(output.content, "content");
+    }
+
+    #[tokio::test]
+    async fn test_truncate_content_cuts_at_sentence_and_flags_output() {
+        let mut page = make_page("https://example.com");
+        page.text = "First sentence here. Second sentence is longer than the limit.".to_string();
+        let mut output = WebFetch::new()
+            .build_output(page, false, false, false, None)
+            .await
+            .unwrap();
+
+        output.truncate_content(30);
+
+        assert_eq!(output.content, "First sentence here.");
+        assert!(output.truncated);
+        assert_eq!(serde_json::to_value(&output).unwrap()["truncated"], true);
+    }
+
+    #[test]
+    fn test_from_fetch_maps_content_blocked_to_the_url() {
+        let err = WebFetchError::from_fetch(&ContentBlocked.into(), "https://example.com");
+
+        assert!(matches!(
+            err,
+            WebFetchError::ContentBlocked(url) if url == "https://example.com"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_build_output_includes_toc_only_when_requested() {
+        let mut page = make_page("https://example.com");
+        page.headings = vec![
+            Heading {
+                level: 1,
+                text: "Guide".to_string(),
+                anchor: Some("guide".to_string()),
+            },
+            Heading {
+                level: 2,
+                text: "Install".to_string(),
+                anchor: None,
+            },
+        ];
+        let sut = WebFetch::new();
+
+        let with_toc = sut
+            .build_output(page.clone(), false, true, false, None)
+            .await
+            .unwrap();
+        let without_toc = sut
+            .build_output(page, false, false, false, None)
+            .await
+            .unwrap();
+
+        let value = serde_json::to_value(&with_toc).unwrap();
+        assert_eq!(value["toc"][0]["level"], 1);
+        assert_eq!(value["toc"][0]["anchor"], "guide");
+        assert_eq!(value["toc"][1]["text"], "Install");
+        assert!(value["toc"][1].get("anchor").is_none());
+        assert!(serde_json::to_value(&without_toc)
+            .unwrap()
+            .get("toc")
+            .is_none());
+    }
+
+    fn long_page() -> PageContent {
+        let mut page = make_page("https://example.com");
+        page.text = "Rain expected.\n\nRecipe of the day.\n\nRust has a borrow checker.\n\n\
+            Weather report.\n\nMore rain.\n\nCargo builds Rust code.\n\nRecipe index."
+            .to_string();
+        page
+    }
+
+    #[tokio::test]
+    async fn test_build_output_focuses_on_relevant_paragraphs() {
+        let sut = WebFetch::new().with_embedder(KeywordEmbedder);
+
+        let output = sut
+            .build_output(long_page(), false, false, false, Some("rust borrow"))
+            .await
+            .unwrap();
+
+        assert!(output.content.contains("Rust has a borrow checker."));
+        assert!(output.content.contains("Cargo builds Rust code."));
+        assert_eq!(output.content.split("\n\n").count(), FOCUS_TOP_K);
+    }
+
+    #[tokio::test]
+    async fn test_focus_without_embedder_returns_full_text() {
+        let output = WebFetch::new()
+            .build_output(long_page(), false, false, false, Some("rust"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.content, long_page().text);
+    }
+
+    #[test]
+    fn test_from_fetch_maps_each_failure() {
+        let map = |failure: FetchFailure| {
+            WebFetchError::from_fetch(&anyhow::Error::new(failure), "https://example.com")
+        };
+
+        assert!(matches!(
+            map(FetchFailure::RobotsDisallowed("https://example.com".to_string())),
+            WebFetchError::RobotsDisallowed(url) if url == "https://example.com"
+        ));
+        assert!(matches!(map(FetchFailure::Timeout), WebFetchError::Timeout));
+        assert!(matches!(
+            map(FetchFailure::HttpStatus(404)),
+            WebFetchError::HttpStatus(404)
+        ));
+        assert!(matches!(
+            map(FetchFailure::UnsupportedContentType("application/pdf".to_string())),
+            WebFetchError::UnsupportedContentType(ct) if ct == "application/pdf"
+        ));
+        assert!(matches!(
+            map(FetchFailure::Network("connection refused".to_string())),
+            WebFetchError::Network(_)
+        ));
+        assert!(matches!(
+            map(FetchFailure::AccessWall(AccessWall::Paywall)),
+            WebFetchError::AccessWall(AccessWall::Paywall)
+        ));
+        assert!(matches!(
+            map(FetchFailure::TooLittleText(12)),
+            WebFetchError::TooLittleText(12)
+        ));
+    }
+
+    #[test]
+    fn test_from_fetch_keeps_unclassified_errors() {
+        let err = WebFetchError::from_fetch(
+            &anyhow!("response body exceeds 5MB limit"),
+            "https://example.com",
+        );
+
+        assert!(matches!(err, WebFetchError::FetchError(_)));
+        assert!(err.to_string().contains("exceeds 5MB"));
+    }
+
+    #[test]
+    fn test_clones_share_the_fetch_limit() {
+        let sut = WebFetch::new();
+        let injected = sut
+            .clone()
+            .with_http_client(Arc::new(reqwest::Client::new()));
+
+        assert!(sut
+            .clone()
+            .http
+            .limiter()
+            .shares_permits_with(sut.http.limiter()));
+        assert!(injected
+            .http
+            .limiter()
+            .shares_permits_with(sut.http.limiter()));
+    }
+
+    #[tokio::test]
+    async fn test_web_fetch_sends_requests_through_injected_client() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            let body = "<html><head><title>Local</title></head><body>\
+                        <p>Hi from a local server, with enough text to count as a page.</p>\
+                        </body></html>";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+        let client = reqwest::Client::builder()
+            .user_agent("custom-client/1.0")
+            .build()
+            .unwrap();
+        let sut = WebFetch::new()
+            .with_respect_robots(false)
+            .with_http_client(Arc::new(client));
+        let args: WebFetchArgs = serde_json::from_value(json!({ "url": url })).unwrap();
+
+        let output = sut.call(args).await.unwrap();
+
+        assert_eq!(output.title, Some("Local".to_string()));
+        assert!(server
+            .await
+            .unwrap()
+            .contains("user-agent: custom-client/1.0"));
     }
 
     #[tokio::test]
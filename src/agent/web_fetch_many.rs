@@ -0,0 +1,266 @@
+use std::sync::Arc;
+
+use log::info;
+use rig::completion::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::cite::Citation;
+use super::content_filter::ContentFilter;
+use super::web_fetch::WebFetchError;
+use super::WebFetch;
+use crate::collectors::robots::RobotsCache;
+use crate::collectors::web::{fetch_urls, ReqwestClient};
+use crate::metrics::Metrics;
+
+/// Arguments for the WebFetchMany tool
+#[derive(Deserialize)]
+pub struct WebFetchManyArgs {
+    urls: Vec<String>,
+}
+
+/// Outcome of one URL of a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchStatus {
+    Ok,
+    Error,
+}
+
+/// Result of fetching a single URL within a batch.
+/// `status` says which of `content` or `error` is set; never both.
+#[derive(Serialize)]
+pub struct WebFetchManyItem {
+    url: String,
+    status: FetchStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    citation: Option<Citation>,
+}
+
+/// Output from the WebFetchMany tool: one result per requested URL, in
+/// request order, with counts so a partly failed batch is obvious at a glance
+#[derive(Serialize)]
+pub struct WebFetchManyOutput {
+    succeeded: usize,
+    failed: usize,
+    results: Vec<WebFetchManyItem>,
+}
+
+impl WebFetchManyOutput {
+    fn new(results: Vec<WebFetchManyItem>) -> Self {
+        let succeeded = results
+            .iter()
+            .filter(|item| item.status == FetchStatus::Ok)
+            .count();
+        Self {
+            succeeded,
+            failed: results.len() - succeeded,
+            results,
+        }
+    }
+}
+
+/// Error type for WebFetchMany tool.
+/// Per-URL failures are reported in the output rather than as an error.
+#[derive(Debug, thiserror::Error)]
+pub enum WebFetchManyError {
+    #[error("No URLs provided")]
+    NoUrls,
+}
+
+/// Fetches several web pages concurrently.
+/// Shares the HTTP client, robots.txt cache, metrics and content filter
+/// with the `WebFetch` it was created from.
+#[derive(Clone)]
+pub struct WebFetchMany {
+    http: ReqwestClient,
+    robots_cache: RobotsCache,
+    metrics: Arc<Metrics>,
+    content_filter: ContentFilter,
+    concurrency: usize,
+}
+
+impl WebFetchMany {
+    /// Create a batch fetcher sharing `web_fetch`'s robots.txt cache, fetching
+    /// as many pages at once as it was configured with (see `FETCH_CONCURRENCY`).
+    pub fn new(web_fetch: &WebFetch) -> Self {
+        Self {
+            http: web_fetch.http.clone(),
+            robots_cache: web_fetch.robots_cache.clone(),
+            metrics: web_fetch.metrics.clone(),
+            content_filter: web_fetch.content_filter.clone(),
+            concurrency: web_fetch.many_concurrency,
+        }
+    }
+
+    /// Override the maximum number of pages fetched at the same time.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+}
+
+impl rig::tool::Tool for WebFetchMany {
+    const NAME: &'static str = "web_fetch_many";
+    type Error = WebFetchManyError;
+    type Args = WebFetchManyArgs;
+    type Output = WebFetchManyOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Fetches content from multiple web URLs in parallel. \
+                          Returns one result per URL with status \"ok\" (content) or \"error\" \
+                          (why it failed); a failed URL doesn't affect the others."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "urls": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The URLs to fetch"
+                    }
+                },
+                "required": ["urls"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.urls.is_empty() {
+            return Err(WebFetchManyError::NoUrls);
+        }
+
+        info!("Fetching {} URLs ...", args.urls.len());
+        let results = fetch_urls(&self.http, &args.urls, &self.robots_cache, self.concurrency)
+            .await
+            .into_iter()
+            .map(|(url, result)| {
+                // A page refused by the content filter counts as a failed fetch
+                let result = result.and_then(|page| Ok(self.content_filter.apply_page(page)?));
+                self.metrics.record_fetch(result.as_ref().err());
+                match result.map_err(|e| WebFetchError::from_fetch(&e, &url)) {
+                    Ok(page) => {
+                        let citation = Citation::now(&page.url, page.title.clone());
+                        WebFetchManyItem {
+                            url,
+                            status: FetchStatus::Ok,
+                            title: page.title,
+                            content: Some(page.text),
+                            error: None,
+                            citation: Some(citation),
+                        }
+                    }
+                    Err(e) => WebFetchManyItem {
+                        url,
+                        status: FetchStatus::Error,
+                        title: None,
+                        content: None,
+                        error: Some(e.to_string()),
+                        citation: None,
+                    },
+                }
+            })
+            .collect();
+        Ok(WebFetchManyOutput::new(results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::tool::Tool;
+
+    #[test]
+    fn test_web_fetch_many_args_deserialize() {
+        let json = r#"{"urls": ["https://example.com", "https://example.org"]}"#;
+        let args: WebFetchManyArgs = serde_json::from_str(json).unwrap();
+        assert_eq!(args.urls.len(), 2);
+    }
+
+    #[test]
+    fn test_web_fetch_many_output_omits_missing_fields() {
+        let output = WebFetchManyOutput::new(vec![WebFetchManyItem {
+            url: "https://example.com".to_string(),
+            status: FetchStatus::Error,
+            title: None,
+            content: None,
+            error: Some("boom".to_string()),
+            citation: None,
+        }]);
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(value["results"][0]["status"], "error");
+        assert_eq!(value["results"][0]["error"], "boom");
+        assert!(value["results"][0].get("content").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_web_fetch_many_reports_successes_and_failures_per_url() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let good = format!("http://{}/ok", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            _ = socket.read(&mut buf).await.unwrap();
+            let body = "<html><head><title>Good</title></head><body>\
+                        <p>Fine, and long enough to count as a page rather than a shell.</p>\
+                        </body></html>";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        let bad = "http://127.0.0.1:9/unreachable".to_string();
+        let sut = WebFetchMany::new(&WebFetch::new().with_respect_robots(false));
+
+        let output = sut
+            .call(WebFetchManyArgs {
+                urls: vec![bad.clone(), good.clone()],
+            })
+            .await
+            .unwrap();
+
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(value["succeeded"], 1);
+        assert_eq!(value["failed"], 1);
+        let results = value["results"].as_array().unwrap();
+        assert_eq!(results[0]["url"], bad);
+        assert_eq!(results[0]["status"], "error");
+        assert!(results[0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Network error"));
+        assert!(results[0].get("content").is_none());
+        assert_eq!(results[1]["url"], good);
+        assert_eq!(results[1]["status"], "ok");
+        assert_eq!(
+            results[1]["content"],
+            "Fine, and long enough to count as a page rather than a shell."
+        );
+        assert!(results[1].get("error").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_web_fetch_many_rejects_empty_urls() {
+        let sut = WebFetchMany::new(&WebFetch::new());
+        let result = sut.call(WebFetchManyArgs { urls: vec![] }).await;
+        assert!(matches!(result, Err(WebFetchManyError::NoUrls)));
+    }
+
+    #[test]
+    fn test_with_concurrency_overrides_default() {
+        let sut = WebFetchMany::new(&WebFetch::new()).with_concurrency(8);
+        assert_eq!(sut.concurrency, 8);
+    }
+}
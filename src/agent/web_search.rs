@@ -5,10 +5,19 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::env;
 
+/// Default maximum snippet length in characters
+const DEFAULT_SNIPPET_CHARS: usize = 300;
+
+/// Query words shorter than this are not used to locate the snippet
+const MIN_TERM_CHARS: usize = 3;
+
 /// Arguments for the WebSearch tool
 #[derive(Deserialize)]
 pub struct WebSearchArgs {
     pub query: String,
+    /// Maximum snippet length in characters (default: `DEFAULT_SNIPPET_CHARS`)
+    #[serde(default)]
+    pub snippet_chars: Option<usize>,
 }
 
 /// A single search result
@@ -53,6 +62,10 @@ impl rig::tool::Tool for WebSearch {
                     "query": {
                         "type": "string",
                         "description": "The search query"
+                    },
+                    "snippet_chars": {
+                        "type": "integer",
+                        "description": format!("Maximum length of each result snippet in characters (default: {DEFAULT_SNIPPET_CHARS})")
                     }
                 },
                 "required": ["query"]
@@ -87,6 +100,7 @@ impl rig::tool::Tool for WebSearch {
             }
         };
         let parsed_response: serde_json::Value = serde_json::from_str(&response_body).unwrap();
+        let snippet_chars = args.snippet_chars.unwrap_or(DEFAULT_SNIPPET_CHARS).max(1);
         let contents: Vec<SearchResult> = parsed_response["results"]
             .as_array()
             .unwrap_or(&vec![])
@@ -94,7 +108,11 @@ impl rig::tool::Tool for WebSearch {
             .map(|item| SearchResult {
                 title: item["title"].as_str().unwrap_or("").to_string(),
                 url: item["url"].as_str().unwrap_or("").to_string(),
-                snippet: item["content"].as_str().unwrap_or("").to_string(),
+                snippet: snippet(
+                    item["content"].as_str().unwrap_or(""),
+                    &args.query,
+                    snippet_chars,
+                ),
             })
             .collect();
 
@@ -102,6 +120,79 @@ impl rig::tool::Tool for WebSearch {
     }
 }
 
+/// Cut `text` down to at most `max_chars` characters (plus ellipses), centered
+/// on the first occurrence of a query term so the match keeps its context.
+///
+/// Matching is case-insensitive and ignores query words shorter than
+/// `MIN_TERM_CHARS`. Without a match the snippet is the leading text. Runs of
+/// whitespace are collapsed, and cuts are moved to word boundaries when possible.
+pub(crate) fn snippet(text: &str, query: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_chars {
+        return text;
+    }
+
+    // Lowercase char by char so indices line up with `chars`
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let first_match = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| term.chars().count() >= MIN_TERM_CHARS)
+        .filter_map(|term| {
+            let term: Vec<char> = term.chars().flat_map(char::to_lowercase).collect();
+            lower
+                .windows(term.len())
+                .position(|window| window == term.as_slice())
+                .map(|pos| (pos, term.len()))
+        })
+        .min();
+
+    let (mut start, mut end) = match first_match {
+        Some((pos, len)) => {
+            let start = pos.saturating_sub(max_chars.saturating_sub(len) / 2);
+            let end = (start + max_chars).min(chars.len());
+            (end.saturating_sub(max_chars), end)
+        }
+        None => (0, max_chars),
+    };
+    let keep_from = first_match.map_or(end, |(pos, _)| pos);
+    let keep_to = first_match.map_or(start, |(pos, len)| pos + len);
+
+    // Don't start or end in the middle of a word, unless that would cut the match
+    if start > 0 && !chars[start - 1].is_whitespace() {
+        if let Some(space) = chars[start..keep_from.max(start)]
+            .iter()
+            .position(|c| c.is_whitespace())
+        {
+            start += space + 1;
+        }
+    }
+    if end < chars.len() && !chars[end].is_whitespace() {
+        if let Some(space) = chars[keep_to.min(end)..end]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+        {
+            end = keep_to.min(end) + space;
+        }
+    }
+
+    let mut snippet: String = chars[start..end]
+        .iter()
+        .collect::<String>()
+        .trim()
+        .to_string();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
 #[cfg(test)]
 mod tests {
     use rig::tool::Tool;
@@ -113,6 +204,57 @@ mod tests {
         const JSON_ARGS: &str = r#"{"query": "How is the weather in Tokyo today?"}"#;
         let args: WebSearchArgs = serde_json::from_str(JSON_ARGS).unwrap();
         assert_eq!(args.query, "How is the weather in Tokyo today?");
+        assert_eq!(args.snippet_chars, None);
+    }
+
+    const SAMPLE: &str = "Rust is a general-purpose programming language. \
+        It emphasizes performance, type safety and concurrency. \
+        The borrow checker enforces memory safety at compile time, \
+        without a garbage collector. Cargo is its build system.";
+
+    #[test]
+    fn test_snippet_is_centered_on_first_query_term() {
+        let snippet = snippet(SAMPLE, "what does the Borrow checker do", 40);
+
+        assert!(snippet.starts_with('…'), "{snippet}");
+        assert!(snippet.ends_with('…'), "{snippet}");
+        assert!(snippet.contains("borrow checker"), "{snippet}");
+        assert!(snippet.chars().count() <= 42, "{snippet}");
+        // Cuts land on word boundaries
+        let inner = snippet.trim_matches('…');
+        assert!(SAMPLE.contains(&format!(" {inner} ")), "{snippet}");
+    }
+
+    #[test]
+    fn test_snippet_falls_back_to_leading_text_without_match() {
+        let snippet = snippet(SAMPLE, "python", 30);
+
+        assert_eq!(snippet, "Rust is a general-purpose…");
+    }
+
+    #[test]
+    fn test_snippet_ignores_short_query_words() {
+        // "is" appears early but is too short to count; "Cargo" is at the end
+        let snippet = snippet(SAMPLE, "is cargo", 40);
+
+        assert!(snippet.contains("Cargo"), "{snippet}");
+        assert!(snippet.starts_with('…'), "{snippet}");
+    }
+
+    #[test]
+    fn test_snippet_keeps_short_text_and_collapses_whitespace() {
+        assert_eq!(snippet("  Short\n text  ", "text", 100), "Short text");
+    }
+
+    #[test]
+    fn test_snippet_handles_multibyte_text() {
+        let text = "東京の天気は晴れです。明日の大阪は雨の予報です。週末は全国的に曇りでしょう。";
+
+        let snippet = snippet(text, "大阪は雨", 10);
+
+        assert!(snippet.chars().count() <= 12, "{snippet}");
+        assert!(snippet.starts_with('…'), "{snippet}");
+        assert!(snippet.contains("大阪は雨"), "{snippet}");
     }
 
     #[test]
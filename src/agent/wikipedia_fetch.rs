@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use log::info;
+use rig::completion::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::cite::Citation;
+use super::content_filter::ContentFilter;
+use super::WebFetch;
+use crate::collectors::web::ReqwestClient;
+use crate::collectors::wikipedia::fetch_wikipedia_article;
+use crate::metrics::Metrics;
+
+/// Arguments for the WikipediaFetch tool
+#[derive(Deserialize)]
+pub struct WikipediaFetchArgs {
+    url: String,
+}
+
+/// Output from the WikipediaFetch tool
+#[derive(Serialize)]
+pub struct WikipediaFetchOutput {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    content: String,
+    citation: Citation,
+}
+
+/// Error type for WikipediaFetch tool
+#[derive(Debug, thiserror::Error)]
+pub enum WikipediaFetchError {
+    #[error("Failed to fetch Wikipedia article: {0}")]
+    FetchError(#[from] anyhow::Error),
+}
+
+/// Fetches clean Wikipedia article text via the Wikipedia REST API,
+/// avoiding the edit links, references and navboxes of the HTML page.
+/// Shares the HTTP client, metrics and content filter with the `WebFetch` it was created from.
+#[derive(Clone)]
+pub struct WikipediaFetch {
+    http: ReqwestClient,
+    metrics: Arc<Metrics>,
+    content_filter: ContentFilter,
+}
+
+impl WikipediaFetch {
+    pub fn new(web_fetch: &WebFetch) -> Self {
+        Self {
+            http: web_fetch.http.clone(),
+            metrics: web_fetch.metrics.clone(),
+            content_filter: web_fetch.content_filter.clone(),
+        }
+    }
+}
+
+impl rig::tool::Tool for WikipediaFetch {
+    const NAME: &'static str = "wikipedia_fetch";
+    type Error = WikipediaFetchError;
+    type Args = WikipediaFetchArgs;
+    type Output = WikipediaFetchOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Fetches the clean text of a Wikipedia article. Prefer this over \
+                          web_fetch for any *.wikipedia.org/wiki/ URL"
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "The Wikipedia article URL (any language edition)"
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        info!("Fetching Wikipedia article {} ...", args.url);
+        // An article refused by the content filter counts as a failed fetch
+        let article = fetch_wikipedia_article(&self.http, &args.url)
+            .await
+            .and_then(|mut article| {
+                self.content_filter.apply_all(
+                    [&mut article.title, &mut article.extract]
+                        .into_iter()
+                        .chain(article.description.as_mut()),
+                )?;
+                Ok(article)
+            });
+        self.metrics.record_fetch(article.as_ref().err());
+        let article = article?;
+        let citation = Citation::now(&article.url, Some(article.title.clone()));
+        Ok(WikipediaFetchOutput {
+            title: article.title,
+            description: article.description,
+            content: article.extract,
+            citation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wikipedia_fetch_args_deserialize() {
+        let json = r#"{"url": "https://en.wikipedia.org/wiki/Rust"}"#;
+        let args: WikipediaFetchArgs = serde_json::from_str(json).unwrap();
+        assert_eq!(args.url, "https://en.wikipedia.org/wiki/Rust");
+    }
+
+    #[test]
+    fn test_wikipedia_fetch_output_includes_citation() {
+        let output = WikipediaFetchOutput {
+            title: "Rust".to_string(),
+            description: None,
+            content: "Rust is ...".to_string(),
+            citation: Citation::now("https://en.wikipedia.org/wiki/Rust", None),
+        };
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(
+            value["citation"]["url"],
+            "https://en.wikipedia.org/wiki/Rust"
+        );
+        assert!(value.get("description").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use std::path::PathBuf;
+
+/// How one-shot responses are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Markdown rendered for the terminal
+    #[default]
+    Text,
+    /// A single `{prompt, response, tokens}` JSON object, for scripting
+    Json,
+}
+
+/// Error returned for invalid command-line arguments
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum CliError {
+    #[error("{0} requires a value")]
+    MissingValue(&'static str),
+    #[error("unknown output format '{0}' (expected text or json)")]
+    UnknownFormat(String),
+    #[error("unknown option '{0}'")]
+    UnknownOption(String),
+    #[error("--format json requires a prompt (e.g. copal --format json \"question\")")]
+    JsonWithoutPrompt,
+    #[error("--record records interactive sessions and can't be used with a prompt")]
+    RecordWithPrompt,
+}
+
+/// Command-line arguments.
+///
+/// ```text
+/// copal                               # interactive REPL
+/// copal "question"                    # one-shot: answer and exit
+/// copal --format json "question"      # one-shot, printed as JSON
+/// copal --record notes.md             # REPL, appending each turn to notes.md
+/// copal --check                       # self-check
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Cli {
+    /// Run the self-check instead of chatting
+    pub check: bool,
+    pub format: OutputFormat,
+    /// Prompt for one-shot mode (positional arguments joined by spaces)
+    pub prompt: Option<String>,
+    /// Markdown file the REPL appends each turn to
+    pub record: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Parse the process arguments, exiting with a message on invalid input.
+    pub fn parse() -> Self {
+        Self::parse_from(std::env::args().skip(1)).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
+        })
+    }
+
+    /// Parse arguments (without the program name).
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut cli = Self::default();
+        let mut words = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let format = match arg.as_str() {
+                "--check" => {
+                    cli.check = true;
+                    continue;
+                }
+                "--record" => {
+                    let path = args.next().ok_or(CliError::MissingValue("--record"))?;
+                    cli.record = Some(PathBuf::from(path));
+                    continue;
+                }
+                "--format" => args.next().ok_or(CliError::MissingValue("--format"))?,
+                _ => {
+                    if let Some(path) = arg.strip_prefix("--record=") {
+                        cli.record = Some(PathBuf::from(path));
+                        continue;
+                    }
+                    match arg.strip_prefix("--format=") {
+                        Some(value) => value.to_string(),
+                        None if arg.starts_with("--") => return Err(CliError::UnknownOption(arg)),
+                        None => {
+                            words.push(arg);
+                            continue;
+                        }
+                    }
+                }
+            };
+            cli.format = match format.as_str() {
+                "text" => OutputFormat::Text,
+                "json" => OutputFormat::Json,
+                _ => return Err(CliError::UnknownFormat(format)),
+            };
+        }
+
+        cli.prompt = (!words.is_empty()).then(|| words.join(" "));
+        if cli.format == OutputFormat::Json && cli.prompt.is_none() && !cli.check {
+            return Err(CliError::JsonWithoutPrompt);
+        }
+        if cli.record.is_some() && cli.prompt.is_some() {
+            return Err(CliError::RecordWithPrompt);
+        }
+        Ok(cli)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, CliError> {
+        Cli::parse_from(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_no_arguments_starts_repl() {
+        assert_eq!(parse(&[]), Ok(Cli::default()));
+    }
+
+    #[test]
+    fn test_format_json_with_prompt() {
+        let expected = Cli {
+            check: false,
+            format: OutputFormat::Json,
+            prompt: Some("what is rust".to_string()),
+            record: None,
+        };
+
+        assert_eq!(
+            parse(&["--format", "json", "what", "is", "rust"]),
+            Ok(expected.clone())
+        );
+        assert_eq!(parse(&["what is rust", "--format=json"]), Ok(expected));
+    }
+
+    #[test]
+    fn test_format_json_requires_prompt() {
+        assert_eq!(
+            parse(&["--format", "json"]),
+            Err(CliError::JsonWithoutPrompt)
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments_are_errors() {
+        assert_eq!(
+            parse(&["--format"]),
+            Err(CliError::MissingValue("--format"))
+        );
+        assert_eq!(
+            parse(&["--format", "yaml", "hi"]),
+            Err(CliError::UnknownFormat("yaml".to_string()))
+        );
+        assert_eq!(
+            parse(&["--verbose"]),
+            Err(CliError::UnknownOption("--verbose".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_record_flag() {
+        let expected = Some(PathBuf::from("notes.md"));
+
+        assert_eq!(parse(&["--record", "notes.md"]).unwrap().record, expected);
+        assert_eq!(parse(&["--record=notes.md"]).unwrap().record, expected);
+        assert_eq!(
+            parse(&["--record"]),
+            Err(CliError::MissingValue("--record"))
+        );
+        assert_eq!(
+            parse(&["--record", "notes.md", "question"]),
+            Err(CliError::RecordWithPrompt)
+        );
+    }
+
+    #[test]
+    fn test_check_flag() {
+        assert!(parse(&["--check"]).unwrap().check);
+    }
+}
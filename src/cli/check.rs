@@ -0,0 +1,209 @@
+use crate::agent::{ConfigError, ProviderConfig};
+use crate::collectors::web::{HttpClient, ReqwestClient};
+use crate::llm::{LlmClient, RigClient};
+
+/// Page fetched to confirm outbound web access works
+const CHECK_URL: &str = "https://example.com/";
+
+/// Tiny prompt used to confirm the model answers
+const PING_PROMPT: &str = "Reply with the single word OK.";
+
+/// Outcome of one self-check: a detail message on success, the reason on failure
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CheckResult {
+    pub name: &'static str,
+    pub outcome: Result<String, String>,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, outcome: Result<String, String>) -> Self {
+        Self { name, outcome }
+    }
+}
+
+/// Run every self-check against the real provider and network, print a checklist
+/// and return whether all checks passed (used by `copal --check`).
+pub async fn run_self_check() -> bool {
+    let results = run_checks(
+        ProviderConfig::from_env(),
+        RigClient::from_config,
+        ReqwestClient::shared(),
+    )
+    .await;
+    println!("{}", format_report(&results));
+    results.iter().all(|r| r.outcome.is_ok())
+}
+
+/// Validate the provider configuration, ping the model built by `make_llm`
+/// and fetch a known-good page with `http`.
+/// The model ping is reported as failed without being attempted if the configuration is invalid.
+pub(crate) async fn run_checks<L, C>(
+    config: Result<ProviderConfig, ConfigError>,
+    make_llm: impl FnOnce(ProviderConfig) -> L,
+    http: &C,
+) -> Vec<CheckResult>
+where
+    L: LlmClient,
+    C: HttpClient,
+{
+    let mut results = Vec::new();
+
+    match config {
+        Ok(config) => {
+            let label = format!("{} ({})", config.provider_name(), config.model());
+            results.push(CheckResult::new("Provider configuration", Ok(label)));
+            let ping = match make_llm(config).complete(PING_PROMPT).await {
+                Ok(reply) if reply.trim().is_empty() => Err("model returned an empty reply".into()),
+                Ok(_) => Ok("model replied".to_string()),
+                Err(e) => Err(format!("{e:#}")),
+            };
+            results.push(CheckResult::new("Model ping", ping));
+        }
+        Err(e) => {
+            results.push(CheckResult::new(
+                "Provider configuration",
+                Err(e.to_string()),
+            ));
+            results.push(CheckResult::new(
+                "Model ping",
+                Err("skipped: provider configuration is invalid".to_string()),
+            ));
+        }
+    }
+
+    let fetch = http
+        .get_html(CHECK_URL)
+        .await
+        .map(|_| format!("fetched {CHECK_URL}"))
+        .map_err(|e| format!("{e:#}"));
+    results.push(CheckResult::new("Web fetch", fetch));
+
+    results
+}
+
+/// Render results as a pass/fail checklist, one line per check
+pub(crate) fn format_report(results: &[CheckResult]) -> String {
+    results
+        .iter()
+        .map(|r| match &r.outcome {
+            Ok(detail) => format!("[PASS] {}: {}", r.name, detail),
+            Err(reason) => format!("[FAIL] {}: {}", r.name, reason),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::TextStream;
+    use anyhow::Result;
+    use rig::completion::Message;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct MockLlmClient {
+        reply: Result<String, String>,
+    }
+
+    impl LlmClient for MockLlmClient {
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            self.reply.clone().map_err(|e| anyhow::anyhow!(e))
+        }
+
+        async fn complete_with_history(
+            &self,
+            _history: &[Message],
+            prompt: &str,
+        ) -> Result<String> {
+            self.complete(prompt).await
+        }
+
+        async fn stream_complete(&self, _prompt: &str) -> Result<TextStream> {
+            unimplemented!()
+        }
+    }
+
+    struct MockHttpClient {
+        ok: bool,
+    }
+
+    impl HttpClient for MockHttpClient {
+        async fn get(&self, _url: &str) -> Result<String> {
+            if self.ok {
+                Ok("<html></html>".to_string())
+            } else {
+                Err(anyhow::anyhow!("connection refused"))
+            }
+        }
+    }
+
+    fn ollama_config() -> Result<ProviderConfig, ConfigError> {
+        ProviderConfig::from_lookup(|_| None)
+    }
+
+    fn llm(reply: Result<&str, &str>) -> impl FnOnce(ProviderConfig) -> MockLlmClient {
+        let reply = reply.map(str::to_string).map_err(str::to_string);
+        move |_| MockLlmClient { reply }
+    }
+
+    #[tokio::test]
+    async fn test_all_checks_pass() {
+        let results =
+            run_checks(ollama_config(), llm(Ok("OK")), &MockHttpClient { ok: true }).await;
+
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.outcome.is_ok()), "{results:?}");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config_skips_model_ping() {
+        let config = ProviderConfig::from_lookup(|key| {
+            (key == "LLM_PROVIDER").then(|| "openai".to_string())
+        });
+        let pinged = AtomicBool::new(false);
+
+        let results = run_checks(
+            config,
+            |_| {
+                pinged.store(true, Ordering::SeqCst);
+                MockLlmClient {
+                    reply: Ok("OK".to_string()),
+                }
+            },
+            &MockHttpClient { ok: true },
+        )
+        .await;
+
+        assert!(!pinged.load(Ordering::SeqCst));
+        assert!(results[0]
+            .outcome
+            .as_ref()
+            .unwrap_err()
+            .contains("OPENAI_API_KEY"));
+        assert!(results[1].outcome.is_err());
+        assert!(results[2].outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_failing_model_and_fetch_are_reported() {
+        let results = run_checks(
+            ollama_config(),
+            llm(Err("model not found")),
+            &MockHttpClient { ok: false },
+        )
+        .await;
+
+        let report = format_report(&results);
+        assert!(report.contains("[PASS] Provider configuration: ollama (qwen3)"));
+        assert!(report.contains("[FAIL] Model ping: model not found"));
+        assert!(report.contains("[FAIL] Web fetch: connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_empty_model_reply_fails_ping() {
+        let results =
+            run_checks(ollama_config(), llm(Ok("  ")), &MockHttpClient { ok: true }).await;
+
+        assert!(results[1].outcome.is_err());
+    }
+}
@@ -1,4 +1,12 @@
+mod args;
+mod check;
+mod oneshot;
 mod render;
 mod repl;
+mod transcript;
 
+pub use args::{Cli, CliError, OutputFormat};
+pub use check::run_self_check;
+pub use oneshot::{run_once, OneShotOutput};
 pub use repl::run_interactive;
+pub use transcript::Transcript;
@@ -0,0 +1,142 @@
+use futures::StreamExt;
+use serde::Serialize;
+
+use super::args::OutputFormat;
+use super::render::render_markdown;
+use crate::agent::{
+    check_prompt_length, limit_response_chars, ChatAgent, ChatStreamEvent, TurnTiming,
+};
+
+/// A completed one-shot response, printed as JSON with `--format json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OneShotOutput {
+    pub prompt: String,
+    pub response: String,
+    /// Tokens reported by the provider (None if it didn't report usage)
+    pub tokens: Option<u64>,
+}
+
+/// Answer `prompt` once and print the result in `format`.
+/// Returns false if the prompt is over `max_prompt_chars` (see `MAX_PROMPT_CHARS`)
+/// or the agent reported an error (printed to stderr). The answer is cut off
+/// after `max_response_chars` (see `MAX_RESPONSE_CHARS`).
+pub async fn run_once(
+    agent: impl ChatAgent,
+    prompt: &str,
+    format: OutputFormat,
+    max_prompt_chars: Option<usize>,
+    max_response_chars: Option<usize>,
+) -> bool {
+    if let Err(e) = check_prompt_length(prompt, max_prompt_chars) {
+        eprintln!("Error: {e}");
+        return false;
+    }
+    match collect_response(&agent, prompt, max_response_chars).await {
+        Ok(output) => {
+            match format {
+                OutputFormat::Text => render_markdown(&output.response),
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string(&output).expect("output serialization cannot fail")
+                ),
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            false
+        }
+    }
+}
+
+/// Stream the agent's answer to `prompt` to completion, or to `max_chars` characters
+async fn collect_response(
+    agent: &impl ChatAgent,
+    prompt: &str,
+    max_chars: Option<usize>,
+) -> Result<OneShotOutput, String> {
+    let mut timing = TurnTiming::start();
+    let stream = agent.stream_chat(prompt, vec![]).await;
+    let mut stream = limit_response_chars(stream, max_chars);
+
+    let mut output = OneShotOutput {
+        prompt: prompt.to_string(),
+        response: String::new(),
+        tokens: None,
+    };
+    while let Some(event) = stream.next().await {
+        timing.observe(&event);
+        match event {
+            ChatStreamEvent::TextDelta(text) => output.response.push_str(&text),
+            ChatStreamEvent::Thinking(text) => log::debug!("Thinking: {}", text),
+            ChatStreamEvent::ToolCall { name } => log::info!("Tool call: {}", name),
+            ChatStreamEvent::ToolResult(output) => log::debug!("Tool result: {}", output),
+            ChatStreamEvent::Source(citation) => log::debug!("Source: {}", citation.url),
+            ChatStreamEvent::Warning(message) => log::warn!("{}", message),
+            ChatStreamEvent::Usage { total_tokens } => output.tokens = Some(total_tokens),
+            ChatStreamEvent::Truncated => {
+                log::warn!("The response was cut off at the output token limit")
+            }
+            ChatStreamEvent::Done => break,
+            ChatStreamEvent::Error(e) => {
+                log::info!("Chat turn: {}", timing.log_line());
+                return Err(e);
+            }
+        }
+    }
+    log::info!("Chat turn: {}", timing.log_line());
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::MockAgent;
+
+    #[tokio::test]
+    async fn test_collect_response_serializes_completed_response() {
+        let agent = MockAgent::new(vec![vec![
+            ChatStreamEvent::TextDelta("Rust is ".to_string()),
+            ChatStreamEvent::TextDelta("a language.".to_string()),
+            ChatStreamEvent::Usage { total_tokens: 57 },
+            ChatStreamEvent::Done,
+        ]]);
+
+        let output = collect_response(&agent, "what is rust", None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            serde_json::json!({
+                "prompt": "what is rust",
+                "response": "Rust is a language.",
+                "tokens": 57
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_collect_response_stops_at_max_chars() {
+        let agent = MockAgent::new(vec![vec![
+            ChatStreamEvent::TextDelta("Rust is ".to_string()),
+            ChatStreamEvent::TextDelta("a language.".to_string()),
+            ChatStreamEvent::Done,
+        ]]);
+
+        let output = collect_response(&agent, "what is rust", Some(4))
+            .await
+            .unwrap();
+
+        assert_eq!(output.response, "Rust");
+    }
+
+    #[tokio::test]
+    async fn test_collect_response_returns_agent_error() {
+        let agent = MockAgent::with_error("rate limited");
+
+        let result = collect_response(&agent, "hi", None).await;
+
+        assert_eq!(result, Err("rate limited".to_string()));
+    }
+}
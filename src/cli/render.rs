@@ -1,19 +1,171 @@
 use std::io::{self, Write};
 
+use crossterm::style::Stylize;
 use crossterm::terminal;
 use log::warn;
+use termimad::MadSkin;
+use unicode_width::UnicodeWidthChar;
+
+/// Print a dim "Sources" footer listing `urls`; prints nothing when empty
+pub(crate) fn render_sources(urls: &[String]) {
+    if urls.is_empty() {
+        return;
+    }
+    println!("{}", "Sources:".dim());
+    for url in urls {
+        println!("{}", format!("  - {url}").dim());
+    }
+}
+
+/// Print a fragment of the model's reasoning dimmed, so it reads as separate from the answer
+pub(crate) fn print_thinking(text: &str) {
+    print!("{}", text.dim());
+    if let Err(e) = io::stdout().flush() {
+        warn!("Failed to flush console {}", e);
+    }
+}
+
+/// Width used when the terminal size can't be determined
+const DEFAULT_RENDER_WIDTH: usize = 80;
 
 /// Render markdown text to the terminal using termimad.
+/// Paragraphs and tables are wrapped to `render_width()`.
 pub fn render_markdown(text: &str) {
-    // termimad::print_text renders markdown with formatting
+    // termimad renders markdown with formatting
     // (bold, headers, code blocks, lists, etc.)
-    termimad::print_text(text);
+    print!(
+        "{}",
+        format_markdown(&MadSkin::default(), text, render_width())
+    );
+}
+
+/// Width to wrap rendered markdown at.
+/// `COLUMNS` overrides the terminal width; falls back to 80 columns.
+pub(crate) fn render_width() -> usize {
+    width_from(
+        std::env::var("COLUMNS").ok().as_deref(),
+        terminal::size().ok().map(|(w, _)| w as usize),
+    )
+}
+
+/// The wrap width for a `COLUMNS` value and the terminal's width, when known
+fn width_from(columns: Option<&str>, terminal_width: Option<usize>) -> usize {
+    columns
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&w: &usize| w > 0)
+        .or(terminal_width)
+        .unwrap_or(DEFAULT_RENDER_WIDTH)
+}
+
+/// Format markdown with `skin`, wrapping paragraphs and tables to `width` columns.
+fn format_markdown(skin: &MadSkin, text: &str, width: usize) -> String {
+    skin.text(text, Some(width)).to_string()
+}
+
+/// Passes streamed Markdown through as it arrives, except tables: a table's rows
+/// are held back until the block ends (a blank line, a line that isn't a row, or
+/// the end of the stream), so a half-built table is never printed.
+///
+/// A row is a line whose first non-blank character is `|`.
+#[derive(Debug, Default)]
+pub(crate) struct TableAwareStream {
+    /// Complete rows of the table being received
+    table: String,
+    /// Start of the current line while it may still turn out to be a row
+    line: String,
+    /// The current line is plain text and is being passed through
+    line_is_text: bool,
+}
+
+impl TableAwareStream {
+    /// Take the next `chunk` of the stream; returns the text that is ready to print
+    pub(crate) fn push(&mut self, chunk: &str) -> String {
+        let mut ready = String::new();
+        for segment in chunk.split_inclusive('\n') {
+            let complete = segment.ends_with('\n');
+            if self.line_is_text {
+                ready.push_str(segment);
+                self.line_is_text = !complete;
+                continue;
+            }
+
+            self.line.push_str(segment);
+            let start = self.line.trim_start();
+            if complete {
+                if start.starts_with('|') {
+                    self.table.push_str(&self.line);
+                } else {
+                    // A blank or plain line ends the table before it
+                    ready.push_str(&self.table);
+                    ready.push_str(&self.line);
+                    self.table.clear();
+                }
+                self.line.clear();
+            } else if !start.is_empty() && !start.starts_with('|') {
+                ready.push_str(&self.table);
+                ready.push_str(&self.line);
+                self.table.clear();
+                self.line.clear();
+                self.line_is_text = true;
+            }
+        }
+        ready
+    }
+
+    /// End of the stream: returns everything still held back
+    pub(crate) fn finish(&mut self) -> String {
+        self.line_is_text = false;
+        let mut rest = std::mem::take(&mut self.table);
+        rest.push_str(&std::mem::take(&mut self.line));
+        rest
+    }
+}
+
+/// Shorten `text` to at most `max_chars` characters for display, ending it with
+/// an ellipsis and a "(+N chars)" marker counting what was left out.
+pub(crate) fn truncate_for_display(text: &str, max_chars: usize) -> String {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return text.to_string();
+    }
+    let shown: String = text.chars().take(max_chars).collect();
+    format!("{}… (+{} chars)", shown.trim_end(), total - max_chars)
+}
+
+/// Columns between tab stops
+const TAB_WIDTH: usize = 8;
+
+/// Number of terminal rows `text` occupies when printed from the first column
+/// of a terminal `terminal_width` columns wide. Characters are measured by
+/// display width: CJK and most emoji take two columns, combining marks and
+/// other zero-width codepoints none. A wide character that doesn't fit at the
+/// end of a row wraps whole to the next one, as terminals do.
+fn display_line_count(text: &str, terminal_width: usize) -> usize {
+    let terminal_width = terminal_width.max(1);
+    text.split('\n')
+        .map(|line| {
+            let mut rows = 1;
+            let mut column = 0;
+            for c in line.chars() {
+                let width = match c {
+                    '\t' => TAB_WIDTH - column % TAB_WIDTH,
+                    c => c.width().unwrap_or(0),
+                };
+                if column + width > terminal_width {
+                    rows += 1;
+                    column = 0;
+                }
+                column += width.min(terminal_width);
+            }
+            rows
+        })
+        .sum()
 }
 
 /// Try to clear the previously printed raw text from the terminal.
 ///
 /// Calculates how many terminal lines the text occupied (accounting for
-/// line wrapping at terminal width). If it fits within the terminal height,
+/// line wrapping at terminal width and the display width of wide characters). If it fits within the terminal height,
 /// moves the cursor up and clears. Returns `true` if cleared, `false` if
 /// the text was too long (scrolled off-screen).
 pub(crate) fn try_clear_lines(text: &str) -> bool {
@@ -25,10 +177,7 @@ pub(crate) fn try_clear_lines(text: &str) -> bool {
         }
     };
 
-    let total_lines: usize = text
-        .split('\n')
-        .map(|line| (line.len() / terminal_width) + 1)
-        .sum();
+    let total_lines = display_line_count(text, terminal_width);
 
     if total_lines >= terminal_height {
         return false;
@@ -45,3 +194,185 @@ pub(crate) fn try_clear_lines(text: &str) -> bool {
     }
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_line_count_ascii() {
+        assert_eq!(display_line_count("", 80), 1);
+        assert_eq!(display_line_count(&"a".repeat(80), 80), 1);
+        assert_eq!(display_line_count(&"a".repeat(81), 80), 2);
+        assert_eq!(display_line_count("one\ntwo\n", 80), 3);
+    }
+
+    #[test]
+    fn test_display_line_count_counts_cjk_as_two_columns() {
+        // 30 characters, 90 bytes, 60 columns
+        let japanese: String = "日本語のテキスト".repeat(4).chars().take(30).collect();
+
+        assert_eq!(display_line_count(&japanese, 80), 1);
+        assert_eq!(display_line_count(&japanese, 60), 1);
+        assert_eq!(display_line_count(&japanese, 59), 2);
+        assert_eq!(display_line_count(&japanese, 20), 3);
+    }
+
+    #[test]
+    fn test_display_line_count_wraps_wide_character_whole() {
+        // Two characters fill 4 of 5 columns; the third doesn't fit in the last one
+        assert_eq!(display_line_count("日本語", 5), 2);
+        assert_eq!(display_line_count("日本語", 6), 1);
+        assert_eq!(display_line_count("a日本語", 6), 2);
+    }
+
+    #[test]
+    fn test_display_line_count_emoji_and_zero_width() {
+        assert_eq!(display_line_count(&"😀".repeat(10), 20), 1);
+        assert_eq!(display_line_count(&"😀".repeat(10), 19), 2);
+        // "e" + combining acute accent takes one column
+        assert_eq!(display_line_count(&"e\u{301}".repeat(10), 10), 1);
+        assert_eq!(display_line_count("a\u{200b}b", 2), 1);
+    }
+
+    #[test]
+    fn test_display_line_count_mixed_lines_at_various_widths() {
+        let text = "Summary: 東京の天気は晴れ ☀️\n\n- 最高気温 25°C";
+
+        assert_eq!(display_line_count(text, 80), 3);
+        assert_eq!(display_line_count(text, 20), 4);
+        assert_eq!(display_line_count(text, 10), 6);
+    }
+
+    #[test]
+    fn test_truncate_for_display() {
+        let text = "日本語 and English";
+
+        assert_eq!(truncate_for_display(text, 100), text);
+        assert_eq!(truncate_for_display(text, 15), text);
+        assert_eq!(truncate_for_display(text, 8), "日本語 and… (+7 chars)");
+        // Whitespace before the cut is not shown
+        assert_eq!(truncate_for_display(text, 4), "日本語… (+11 chars)");
+        assert_eq!(truncate_for_display(text, 0), "… (+15 chars)");
+    }
+
+    const PARAGRAPH: &str = "Rust is a general-purpose programming language emphasizing \
+        performance, type safety, and concurrency. It enforces memory safety without a \
+        garbage collector.";
+
+    fn max_line_width(rendered: &str) -> usize {
+        rendered
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_format_markdown_wraps_paragraph_to_width() {
+        for width in [20, 40, 80] {
+            let rendered = format_markdown(&MadSkin::no_style(), PARAGRAPH, width);
+
+            assert!(
+                max_line_width(&rendered) <= width,
+                "width {width}: {rendered:?}"
+            );
+            let words: Vec<&str> = rendered.split_whitespace().collect();
+            assert_eq!(words, PARAGRAPH.split_whitespace().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_format_markdown_narrow_width_uses_more_lines() {
+        let narrow = format_markdown(&MadSkin::no_style(), PARAGRAPH, 20);
+        let wide = format_markdown(&MadSkin::no_style(), PARAGRAPH, 80);
+
+        assert!(narrow.lines().count() > wide.lines().count());
+    }
+
+    #[test]
+    fn test_format_markdown_fits_table_to_width() {
+        let table = "|Name|Description|\n|-|-|\n|copal|A research assistant that gathers and \
+                     summarizes information from the web|\n";
+
+        for width in [30, 50] {
+            let rendered = format_markdown(&MadSkin::no_style(), table, width);
+
+            assert!(
+                max_line_width(&rendered) <= width,
+                "width {width}: {rendered:?}"
+            );
+        }
+    }
+
+    /// Feed `chunks` one by one, collecting what each push makes printable
+    fn stream_chunks(chunks: &[&str]) -> (Vec<String>, String) {
+        let mut stream = TableAwareStream::default();
+        let printed = chunks.iter().map(|chunk| stream.push(chunk)).collect();
+        (printed, stream.finish())
+    }
+
+    #[test]
+    fn test_table_stream_passes_plain_text_through_immediately() {
+        let (printed, rest) = stream_chunks(&["Rust is ", "fast.\nIt is ", "safe."]);
+
+        assert_eq!(printed, ["Rust is ", "fast.\nIt is ", "safe."]);
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn test_table_stream_defers_partial_rows_until_block_ends() {
+        let (printed, rest) = stream_chunks(&[
+            "Results:\n",
+            "| Name | Sta",
+            "rs |\n|---|---|\n",
+            "| copal | 1",
+            "0 |\n",
+            "\nDone.",
+        ]);
+
+        assert_eq!(
+            printed,
+            [
+                "Results:\n",
+                "",
+                "",
+                "",
+                "",
+                "| Name | Stars |\n|---|---|\n| copal | 10 |\n\nDone."
+            ]
+        );
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn test_table_stream_releases_table_at_non_row_line() {
+        let (printed, _) = stream_chunks(&["| a |\n| - |\n", "Next para", "graph"]);
+
+        assert_eq!(printed, ["", "| a |\n| - |\nNext para", "graph"]);
+    }
+
+    #[test]
+    fn test_table_stream_flushes_unfinished_table_at_end() {
+        let (printed, rest) = stream_chunks(&["| a | b |\n", "| 1 | 2"]);
+
+        assert_eq!(printed, ["", ""]);
+        assert_eq!(rest, "| a | b |\n| 1 | 2");
+    }
+
+    #[test]
+    fn test_table_stream_waits_for_first_character_of_indented_line() {
+        let (printed, rest) = stream_chunks(&["  ", "| a |\n"]);
+
+        assert_eq!(printed, ["", ""]);
+        assert_eq!(rest, "  | a |\n");
+    }
+
+    #[test]
+    fn test_render_width_respects_columns_override() {
+        assert_eq!(width_from(Some("42"), Some(120)), 42);
+        assert_eq!(width_from(Some("0"), Some(120)), 120);
+        assert_eq!(width_from(Some("wide"), None), DEFAULT_RENDER_WIDTH);
+        assert_eq!(width_from(None, None), DEFAULT_RENDER_WIDTH);
+    }
+}
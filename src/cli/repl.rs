@@ -1,25 +1,187 @@
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
 use log::error;
 use rustyline::error::ReadlineError;
-use rustyline::DefaultEditor;
+use rustyline::{Config, DefaultEditor};
+use std::cell::Cell;
+use std::future::Future;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-use super::render::{render_markdown, try_clear_lines};
-use crate::agent::{ChatAgent, ChatStreamEvent};
+use super::render::{
+    print_thinking, render_markdown, render_sources, truncate_for_display, try_clear_lines,
+    TableAwareStream,
+};
+use super::transcript::Transcript;
+use crate::agent::{
+    check_prompt_length, limit_response_chars, ChatAgent, ChatStreamEvent, TurnTiming,
+};
 use crate::session::ConversationHistory;
 use crate::session::DEFAULT_MAX_HISTORY_TURNS;
 
 const PROMPT: &str = "> ";
-const HISTORY_FILE: &str = ".copal_history";
+/// History file in the working directory, used when no data directory is known
+const FALLBACK_HISTORY_FILE: &str = ".copal_history";
 
-pub async fn run_interactive(agent: impl ChatAgent) {
-    println!("Copal Interactive Mode");
-    println!("Type 'exit' or 'quit' to exit, Ctrl+D to quit\n");
+/// Default number of input lines kept in the history file
+const DEFAULT_HISTORY_SIZE: usize = 1000;
 
-    let mut rl = DefaultEditor::new().expect("Failed to create editor");
+/// Where REPL input history is stored: `COPAL_HISTORY_FILE` if set, otherwise
+/// `$XDG_DATA_HOME/copal/history`, then `~/.local/share/copal/history`, then
+/// `.copal_history` in the working directory. Blank variables count as unset.
+fn history_file(lookup: impl Fn(&str) -> Option<String>) -> PathBuf {
+    let var = |key: &str| lookup(key).filter(|v| !v.trim().is_empty());
+    if let Some(path) = var("COPAL_HISTORY_FILE") {
+        return PathBuf::from(path);
+    }
+    // The XDG spec says relative paths are invalid and must be ignored
+    let data_home = var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| var("HOME").map(|home| Path::new(&home).join(".local/share")));
+    match data_home {
+        Some(dir) => dir.join("copal").join("history"),
+        None => PathBuf::from(FALLBACK_HISTORY_FILE),
+    }
+}
+
+/// Number of input lines kept in the history file, read from
+/// `COPAL_HISTORY_SIZE` (default: `DEFAULT_HISTORY_SIZE`)
+fn history_size() -> usize {
+    std::env::var("COPAL_HISTORY_SIZE")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_HISTORY_SIZE)
+}
+
+/// Default number of characters of fetched content echoed per tool result
+const DEFAULT_FETCH_PREVIEW_CHARS: usize = 200;
+
+/// Characters of fetched content echoed per tool result, read from
+/// `CLI_FETCH_PREVIEW_CHARS` (0 hides tool results). Only affects what the
+/// terminal shows; the model always receives the full output.
+fn fetch_preview_chars() -> usize {
+    std::env::var("CLI_FETCH_PREVIEW_CHARS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_FETCH_PREVIEW_CHARS)
+}
+
+/// The readable part of a tool's output: the `content` field of a JSON
+/// result (e.g. web_fetch's page text), otherwise the output as-is
+fn tool_output_text(output: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(output)
+        .ok()
+        .and_then(|value| value.get("content")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| output.to_string())
+}
+
+/// How a streamed response ended
+#[derive(Debug, PartialEq)]
+enum StreamEnd {
+    /// The agent finished (or the stream ran out)
+    Completed,
+    /// The user pressed Ctrl+C
+    Cancelled,
+    /// The agent reported an error
+    Failed(String),
+}
+
+/// URLs the agent fetched during one turn, unique and in the order first seen
+#[derive(Debug, Default)]
+struct SourceCollector {
+    urls: Vec<String>,
+}
+
+impl SourceCollector {
+    /// Record `url` unless it was already recorded this turn
+    fn add(&mut self, url: &str) {
+        if !self.urls.iter().any(|seen| seen == url) {
+            self.urls.push(url.to_string());
+        }
+    }
+
+    fn urls(&self) -> &[String] {
+        &self.urls
+    }
+}
+
+/// Consume `stream` until it completes, fails, or `cancel` resolves, whichever is first.
+/// `on_text` is called with each text delta, `on_tool_result` with each tool output
+/// and `on_thinking` with each reasoning fragment; the text received so far is
+/// returned in every case.
+async fn consume_until_cancelled(
+    mut stream: impl Stream<Item = ChatStreamEvent> + Unpin,
+    cancel: impl Future<Output = ()>,
+    timing: &mut TurnTiming,
+    sources: &mut SourceCollector,
+    mut on_text: impl FnMut(&str),
+    mut on_tool_result: impl FnMut(&str),
+    mut on_thinking: impl FnMut(&str),
+) -> (String, StreamEnd) {
+    tokio::pin!(cancel);
+    let mut response_text = String::new();
+    loop {
+        let event = tokio::select! {
+            _ = &mut cancel => return (response_text, StreamEnd::Cancelled),
+            event = stream.next() => match event {
+                Some(event) => event,
+                None => return (response_text, StreamEnd::Completed),
+            },
+        };
+        timing.observe(&event);
+        match event {
+            ChatStreamEvent::TextDelta(text) => {
+                on_text(&text);
+                response_text.push_str(&text);
+            }
+            ChatStreamEvent::Thinking(text) => on_thinking(&text),
+            ChatStreamEvent::ToolCall { name } => {
+                log::info!("Tool call: {}", name);
+            }
+            ChatStreamEvent::ToolResult(output) => on_tool_result(&output),
+            ChatStreamEvent::Source(citation) => sources.add(&citation.url),
+            ChatStreamEvent::Warning(message) => log::warn!("{}", message),
+            ChatStreamEvent::Usage { total_tokens } => {
+                log::debug!("Tokens used: {}", total_tokens);
+            }
+            ChatStreamEvent::Truncated => {
+                log::warn!(
+                    "The response was cut off at the token limit; type \"continue\" for the rest"
+                )
+            }
+            ChatStreamEvent::Done => return (response_text, StreamEnd::Completed),
+            ChatStreamEvent::Error(e) => return (response_text, StreamEnd::Failed(e)),
+        }
+    }
+}
+
+/// Run the REPL, appending each turn to `transcript` if one is given.
+/// `name` is shown in the banner; messages over `max_prompt_chars` are refused
+/// and answers are cut off after `max_response_chars`.
+pub async fn run_interactive(
+    agent: impl ChatAgent,
+    mut transcript: Option<Transcript>,
+    name: &str,
+    max_prompt_chars: Option<usize>,
+    max_response_chars: Option<usize>,
+) {
+    println!("{} Interactive Mode", name);
+    if let Some(transcript) = &transcript {
+        println!("Recording to {}", transcript.path().display());
+    }
+    println!("Type 'exit' or 'quit' to exit, Ctrl+D to quit, Ctrl+C to stop a response\n");
+
+    let config = Config::builder()
+        .max_history_size(history_size())
+        .expect("Failed to configure editor")
+        .build();
+    let mut rl = DefaultEditor::with_config(config).expect("Failed to create editor");
+    let preview_chars = fetch_preview_chars();
 
     // Load history from previous sessions
-    _ = rl.load_history(HISTORY_FILE);
+    let history_path = history_file(|key| std::env::var(key).ok());
+    _ = rl.load_history(&history_path);
 
     // Conversation history for multi-turn context
     let mut conversation_history = ConversationHistory::new(DEFAULT_MAX_HISTORY_TURNS);
@@ -48,44 +210,328 @@ pub async fn run_interactive(agent: impl ChatAgent) {
         // Add input to history
         _ = rl.add_history_entry(&input);
 
+        // Too long to send: warn and keep it out of the conversation
+        if let Err(e) = check_prompt_length(&input, max_prompt_chars) {
+            log::warn!("{}", e);
+            continue;
+        }
+
         conversation_history.add_user(&input);
 
         // Stream with conversation history
-        let mut stream = agent
+        let mut timing = TurnTiming::start();
+        let mut sources = SourceCollector::default();
+        let stream = agent
             .stream_chat(&input, conversation_history.to_vec())
             .await;
+        let stream = limit_response_chars(stream, max_response_chars);
 
-        let mut response_text = String::new();
-
-        while let Some(event) = stream.next().await {
-            match event {
-                ChatStreamEvent::TextDelta(text) => {
-                    print!("{}", text);
-                    response_text.push_str(&text);
-                    io::stdout().flush().unwrap();
-                }
-                ChatStreamEvent::ToolCall { name } => {
-                    log::info!("Tool call: {}", name);
+        // Ctrl+C stops this response only; the stream is dropped, cancelling the request
+        let ctrl_c = async {
+            _ = tokio::signal::ctrl_c().await;
+        };
+        // Set while reasoning is being printed, so the answer starts on its own line
+        let thinking = Cell::new(false);
+        // Tables are printed once complete, not row fragment by row fragment
+        let mut tables = TableAwareStream::default();
+        let (response_text, end) = consume_until_cancelled(
+            stream,
+            ctrl_c,
+            &mut timing,
+            &mut sources,
+            |text| {
+                if thinking.replace(false) {
+                    println!("\n");
                 }
-                ChatStreamEvent::Done => break,
-                ChatStreamEvent::Error(e) => {
-                    error!("Stream error: {}", e);
-                    break;
+                print!("{}", tables.push(text));
+                io::stdout().flush().unwrap();
+            },
+            |output| {
+                if preview_chars > 0 {
+                    let text = tool_output_text(output).replace('\n', " ");
+                    println!("  ↳ {}", truncate_for_display(&text, preview_chars));
                 }
-            }
+            },
+            |text| {
+                thinking.set(true);
+                print_thinking(text);
+            },
+        )
+        .await;
+        print!("{}", tables.finish());
+        log::info!("Chat turn: {}", timing.log_line());
+
+        match &end {
+            StreamEnd::Completed => {}
+            StreamEnd::Cancelled => println!("\n[cancelled]"),
+            StreamEnd::Failed(e) => error!("Stream error: {}", e),
         }
 
         // Replace raw streamed text with rendered markdown
-        if !response_text.is_empty() {
+        if end != StreamEnd::Cancelled && !response_text.is_empty() {
             if !try_clear_lines(&response_text) {
                 // Text was too long to clear; add separator before rendered output
                 println!("\n─────────────────────────────────────────");
             }
             render_markdown(&response_text);
+            render_sources(sources.urls());
+        }
+        if let Some(transcript) = &mut transcript {
+            let recorded = match &end {
+                StreamEnd::Completed => response_text.clone(),
+                StreamEnd::Cancelled => format!("{}\n\n*[cancelled]*", response_text),
+                StreamEnd::Failed(e) => format!("{}\n\n*[error: {}]*", response_text, e),
+            };
+            if let Err(e) = transcript.record_turn(&input, &recorded) {
+                log::warn!(
+                    "Failed to record the turn to {}: {}",
+                    transcript.path().display(),
+                    e
+                );
+            }
         }
         conversation_history.add_assistant(&response_text);
     }
 
-    // Save history for next session
-    _ = rl.save_history(HISTORY_FILE);
+    // Save history for next session (capped at `history_size()` lines)
+    if let Some(dir) = history_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            log::warn!(
+                "Failed to create history directory {}: {}",
+                dir.display(),
+                e
+            );
+        }
+    }
+    if let Err(e) = rl.save_history(&history_path) {
+        log::warn!(
+            "Failed to save history to {}: {}",
+            history_path.display(),
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Citation;
+    use futures::stream;
+    use tokio::sync::oneshot;
+
+    fn lookup<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_history_file_prefers_explicit_path() {
+        let vars = [
+            ("COPAL_HISTORY_FILE", "/tmp/my_history"),
+            ("XDG_DATA_HOME", "/data"),
+            ("HOME", "/home/user"),
+        ];
+        assert_eq!(history_file(lookup(&vars)), Path::new("/tmp/my_history"));
+    }
+
+    #[test]
+    fn test_history_file_uses_xdg_data_home() {
+        let vars = [("XDG_DATA_HOME", "/data"), ("HOME", "/home/user")];
+        assert_eq!(
+            history_file(lookup(&vars)),
+            Path::new("/data/copal/history")
+        );
+    }
+
+    #[test]
+    fn test_history_file_falls_back_to_home_local_share() {
+        let home = Path::new("/home/user/.local/share/copal/history");
+        assert_eq!(history_file(lookup(&[("HOME", "/home/user")])), home);
+        // Blank and relative XDG_DATA_HOME values are ignored
+        let vars = [
+            ("COPAL_HISTORY_FILE", " "),
+            ("XDG_DATA_HOME", "relative/data"),
+            ("HOME", "/home/user"),
+        ];
+        assert_eq!(history_file(lookup(&vars)), home);
+    }
+
+    #[test]
+    fn test_history_file_without_home_uses_working_directory() {
+        assert_eq!(history_file(lookup(&[])), Path::new(".copal_history"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_keeps_text_received_so_far() {
+        // One chunk arrives, then the agent stalls until cancelled
+        let events = stream::iter(vec![ChatStreamEvent::TextDelta("partial ".to_string())])
+            .chain(stream::pending());
+        let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+        let mut cancel_tx = Some(cancel_tx);
+
+        let (text, end) = consume_until_cancelled(
+            events,
+            async {
+                _ = cancel_rx.await;
+            },
+            &mut TurnTiming::start(),
+            &mut SourceCollector::default(),
+            |_| {
+                // Press Ctrl+C as soon as the first chunk is shown
+                if let Some(tx) = cancel_tx.take() {
+                    _ = tx.send(());
+                }
+            },
+            |_| {},
+            |_| {},
+        )
+        .await;
+
+        assert_eq!(text, "partial ");
+        assert_eq!(end, StreamEnd::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_uncancelled_stream_runs_to_completion() {
+        let events = stream::iter(vec![
+            ChatStreamEvent::TextDelta("Hello, ".to_string()),
+            ChatStreamEvent::TextDelta("world".to_string()),
+            ChatStreamEvent::Done,
+        ]);
+
+        let (text, end) = consume_until_cancelled(
+            events,
+            std::future::pending(),
+            &mut TurnTiming::start(),
+            &mut SourceCollector::default(),
+            |_| {},
+            |_| {},
+            |_| {},
+        )
+        .await;
+
+        assert_eq!(text, "Hello, world");
+        assert_eq!(end, StreamEnd::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_error_ends_stream_with_failure() {
+        let events = stream::iter(vec![
+            ChatStreamEvent::TextDelta("Hel".to_string()),
+            ChatStreamEvent::Error("rate limited".to_string()),
+        ]);
+
+        let (text, end) = consume_until_cancelled(
+            events,
+            std::future::pending(),
+            &mut TurnTiming::start(),
+            &mut SourceCollector::default(),
+            |_| {},
+            |_| {},
+            |_| {},
+        )
+        .await;
+
+        assert_eq!(text, "Hel");
+        assert_eq!(end, StreamEnd::Failed("rate limited".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_tool_results_are_passed_to_callback_not_response() {
+        let events = stream::iter(vec![
+            ChatStreamEvent::ToolResult(r#"{"content":"page text"}"#.to_string()),
+            ChatStreamEvent::TextDelta("Answer".to_string()),
+            ChatStreamEvent::Done,
+        ]);
+        let mut previews = Vec::new();
+
+        let mut timing = TurnTiming::start();
+
+        let (text, _) = consume_until_cancelled(
+            events,
+            std::future::pending(),
+            &mut timing,
+            &mut SourceCollector::default(),
+            |_| {},
+            |output| previews.push(tool_output_text(output)),
+            |_| {},
+        )
+        .await;
+
+        assert_eq!(text, "Answer");
+        assert_eq!(previews, ["page text"]);
+        assert!(timing.ttft().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_thinking_is_passed_to_callback_not_response() {
+        let events = stream::iter(vec![
+            ChatStreamEvent::Thinking("Let me add ".to_string()),
+            ChatStreamEvent::Thinking("the numbers.".to_string()),
+            ChatStreamEvent::TextDelta("4".to_string()),
+            ChatStreamEvent::Done,
+        ]);
+        let mut thoughts = String::new();
+
+        let (text, _) = consume_until_cancelled(
+            events,
+            std::future::pending(),
+            &mut TurnTiming::start(),
+            &mut SourceCollector::default(),
+            |_| {},
+            |_| {},
+            |thought| thoughts.push_str(thought),
+        )
+        .await;
+
+        assert_eq!(text, "4");
+        assert_eq!(thoughts, "Let me add the numbers.");
+    }
+
+    #[test]
+    fn test_source_collector_keeps_unique_urls_in_call_order() {
+        let mut sources = SourceCollector::default();
+
+        sources.add("https://b.example/");
+        sources.add("https://a.example/");
+        sources.add("https://b.example/");
+
+        assert_eq!(sources.urls(), ["https://b.example/", "https://a.example/"]);
+    }
+
+    #[tokio::test]
+    async fn test_source_events_are_collected_for_the_turn() {
+        let events = stream::iter(vec![
+            ChatStreamEvent::Source(Citation::now("https://a.example/", None)),
+            ChatStreamEvent::Source(Citation::now("https://a.example/", None)),
+            ChatStreamEvent::TextDelta("Answer".to_string()),
+            ChatStreamEvent::Done,
+        ]);
+        let mut sources = SourceCollector::default();
+
+        consume_until_cancelled(
+            events,
+            std::future::pending(),
+            &mut TurnTiming::start(),
+            &mut sources,
+            |_| {},
+            |_| {},
+            |_| {},
+        )
+        .await;
+
+        assert_eq!(sources.urls(), ["https://a.example/"]);
+    }
+
+    #[test]
+    fn test_tool_output_text_falls_back_to_raw_output() {
+        assert_eq!(tool_output_text("plain result"), "plain result");
+        assert_eq!(tool_output_text(r#"{"title":"x"}"#), r#"{"title":"x"}"#);
+    }
 }
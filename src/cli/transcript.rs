@@ -0,0 +1,117 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+/// Timestamp format of session and turn headings
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Markdown record of a REPL session (`--record <file>`).
+/// Sessions are appended, so one file can collect many of them.
+pub struct Transcript {
+    path: PathBuf,
+    file: File,
+}
+
+impl Transcript {
+    /// Open `path` for appending, creating it if needed, and start a session in it
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut transcript = Self {
+            path: path.to_path_buf(),
+            file,
+        };
+        transcript.append(&format_session_heading(&Local::now()))?;
+        Ok(transcript)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one turn, stamped with the current time. Written through to the
+    /// file before returning, so a crash later in the session loses nothing.
+    pub fn record_turn(&mut self, prompt: &str, response: &str) -> io::Result<()> {
+        self.append(&format_turn(prompt, response, &Local::now()))
+    }
+
+    fn append(&mut self, text: &str) -> io::Result<()> {
+        self.file.write_all(text.as_bytes())?;
+        self.file.flush()
+    }
+}
+
+/// Heading opening a session's turns
+fn format_session_heading(at: &DateTime<Local>) -> String {
+    format!("# Session {}\n\n", at.format(TIMESTAMP_FORMAT))
+}
+
+/// One turn as Markdown: a timestamped heading, the prompt as a block quote
+/// and the response as plain Markdown, followed by a rule
+pub(crate) fn format_turn(prompt: &str, response: &str, at: &DateTime<Local>) -> String {
+    let quoted: Vec<String> = prompt
+        .lines()
+        .map(|line| format!("> {line}").trim_end().to_string())
+        .collect();
+    format!(
+        "## {}\n\n{}\n\n{}\n\n---\n\n",
+        at.format(TIMESTAMP_FORMAT),
+        quoted.join("\n"),
+        response.trim()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 14, 9, 26, 5).unwrap()
+    }
+
+    #[test]
+    fn test_format_turn() {
+        let turn = format_turn(
+            "What is Rust?",
+            "Rust is a **systems** language.\n\n- fast\n- safe\n",
+            &at(),
+        );
+
+        assert_eq!(
+            turn,
+            "## 2026-03-14 09:26:05\n\n\
+             > What is Rust?\n\n\
+             Rust is a **systems** language.\n\n- fast\n- safe\n\n\
+             ---\n\n"
+        );
+    }
+
+    #[test]
+    fn test_format_turn_quotes_every_prompt_line() {
+        let turn = format_turn("First line\n\nThird line", "Answer", &at());
+
+        assert!(turn.contains("> First line\n>\n> Third line\n\nAnswer"));
+    }
+
+    #[test]
+    fn test_record_turn_appends_to_file() {
+        let path = std::env::temp_dir().join(format!("copal-transcript-{}.md", std::process::id()));
+        _ = std::fs::remove_file(&path);
+
+        Transcript::open(&path)
+            .unwrap()
+            .record_turn("hi", "hello")
+            .unwrap();
+        Transcript::open(&path)
+            .unwrap()
+            .record_turn("again", "hello again")
+            .unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        _ = std::fs::remove_file(&path);
+        assert_eq!(written.matches("# Session ").count(), 2);
+        assert!(written.find("> hi").unwrap() < written.find("> again").unwrap());
+    }
+}
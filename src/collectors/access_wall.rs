@@ -0,0 +1,289 @@
+use std::fmt;
+
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+/// Pages whose visible text is longer than this are treated as content,
+/// whatever phrases they contain (articles often end with "Subscribe" boxes
+/// and carry cookie banners)
+const MAX_WALL_TEXT_CHARS: usize = 1500;
+
+/// Phrases of pages refusing the request outright, e.g. bot checks
+const ACCESS_DENIED_PHRASES: &[&str] = &[
+    "access denied",
+    "access to this page has been denied",
+    "you don't have permission to access",
+    "you do not have permission to access",
+    "request blocked",
+    "verify you are human",
+    "are you a robot",
+    "checking your browser",
+];
+
+/// Phrases of pages asking the reader to sign in first
+const LOGIN_PHRASES: &[&str] = &[
+    "sign in to continue",
+    "log in to continue",
+    "login to continue",
+    "please sign in",
+    "please log in",
+    "you must be logged in",
+    "you need to log in",
+    "login required",
+];
+
+/// Phrases of pages showing a teaser and a subscription offer
+const PAYWALL_PHRASES: &[&str] = &[
+    "subscribe to continue reading",
+    "subscribe to read",
+    "to continue reading",
+    "this article is for subscribers",
+    "this content is for subscribers",
+    "subscribers only",
+    "already a subscriber",
+    "become a subscriber",
+    "free article limit",
+    "free articles remaining",
+];
+
+/// Phrases of cookie/consent interstitials shown before the page
+const CONSENT_PHRASES: &[&str] = &[
+    "we value your privacy",
+    "before you continue",
+    "accept all cookies",
+    "manage cookie preferences",
+    "consent to the use of cookies",
+];
+
+/// Path segments of sign-in pages, for meta refresh targets. A segment
+/// matches with or without an extension (`login.php`), but not as part of a
+/// longer word (`/authors/`).
+const LOGIN_PATHS: &[&str] = &["login", "signin", "sign-in", "sign_in", "auth"];
+
+/// A page that was served instead of the requested content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessWall {
+    /// A teaser with a subscription offer
+    Paywall,
+    /// A sign-in form or a redirect to one
+    LoginWall,
+    /// A cookie/consent interstitial
+    ConsentWall,
+    /// An "access denied" or bot-check page
+    AccessDenied,
+}
+
+impl fmt::Display for AccessWall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AccessWall::Paywall => "paywall",
+            AccessWall::LoginWall => "login wall",
+            AccessWall::ConsentWall => "consent wall",
+            AccessWall::AccessDenied => "access-denied page",
+        })
+    }
+}
+
+/// Detect a page that stands in for the requested content. A meta refresh to
+/// a sign-in page is a login wall; otherwise the page's visible text must be
+/// short and contain one of the known phrases.
+pub(crate) fn detect_access_wall(document: &Html) -> Option<AccessWall> {
+    if refreshes_to_login(document) {
+        return Some(AccessWall::LoginWall);
+    }
+    // Walls are often built from `<div>`s rather than paragraphs, so both the
+    // length and the phrases are taken from all of the page's visible text
+    let visible = visible_text(document);
+    if visible.chars().count() > MAX_WALL_TEXT_CHARS {
+        return None;
+    }
+    let mentions = |phrases: &[&str]| phrases.iter().any(|phrase| visible.contains(phrase));
+    [
+        (ACCESS_DENIED_PHRASES, AccessWall::AccessDenied),
+        (LOGIN_PHRASES, AccessWall::LoginWall),
+        (PAYWALL_PHRASES, AccessWall::Paywall),
+        (CONSENT_PHRASES, AccessWall::ConsentWall),
+    ]
+    .into_iter()
+    .find(|(phrases, _)| mentions(phrases))
+    .map(|(_, wall)| wall)
+}
+
+/// Whether a `<meta http-equiv="refresh">` sends the reader to a sign-in page
+fn refreshes_to_login(document: &Html) -> bool {
+    let selector = Selector::parse("meta[http-equiv]").unwrap();
+    document
+        .select(&selector)
+        .filter(|meta| {
+            meta.value()
+                .attr("http-equiv")
+                .is_some_and(|v| v.eq_ignore_ascii_case("refresh"))
+        })
+        .filter_map(|meta| meta.value().attr("content"))
+        .filter_map(|content| {
+            let content = content.to_lowercase();
+            let (_, target) = content.split_once("url=")?;
+            Some(target.trim_matches(['\'', '"', ' ']).to_string())
+        })
+        .any(|target| path_segments(&target).any(is_login_segment))
+}
+
+/// The `/`-separated segments of a URL's path, without scheme, host, query or fragment
+fn path_segments(url: &str) -> impl Iterator<Item = &str> {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').map_or("", |(_, path)| path),
+        None => url,
+    };
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn is_login_segment(segment: &str) -> bool {
+    let stem = segment.split_once('.').map_or(segment, |(stem, _)| stem);
+    LOGIN_PATHS.contains(&stem)
+}
+
+/// Lowercased, whitespace-collapsed text of `<title>` and `<body>`,
+/// without scripts and styles
+fn visible_text(document: &Html) -> String {
+    text_outside_scripts(document, "title, body").to_lowercase()
+}
+
+/// Whitespace-collapsed text of the page's `<body>`, without scripts and styles
+pub(crate) fn body_text(document: &Html) -> String {
+    text_outside_scripts(document, "body")
+}
+
+fn text_outside_scripts(document: &Html, selectors: &str) -> String {
+    let selector = Selector::parse(selectors).unwrap();
+    let skipped = Selector::parse("script, style, noscript").unwrap();
+    let hidden: Vec<_> = document.select(&skipped).map(|e| e.id()).collect();
+    let mut words = Vec::new();
+    for element in document.select(&selector) {
+        for node in element.descendants() {
+            let Some(text) = node.value().as_text() else {
+                continue;
+            };
+            if node.ancestors().any(|a| hidden.contains(&a.id())) {
+                continue;
+            }
+            words.extend(text.split_whitespace());
+        }
+    }
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYWALL_PAGE: &str = r#"<html><head><title>Markets rally | The Daily Ledger</title></head>
+        <body>
+          <article>
+            <h1>Markets rally as rates fall</h1>
+            <p>Stocks climbed on Tuesday after the central bank signalled...</p>
+          </article>
+          <div class="paywall">
+            <h2>Subscribe to continue reading</h2>
+            <div>Get unlimited access for $1 a week. Already a subscriber? <a href="/login">Sign in</a></div>
+          </div>
+        </body></html>"#;
+
+    const LOGIN_REDIRECT_PAGE: &str = r#"<html><head>
+        <meta http-equiv="Refresh" content="0; URL='https://example.com/account/login?next=/report'">
+        </head><body><p>Redirecting…</p></body></html>"#;
+
+    const ACCESS_DENIED_PAGE: &str = r#"<html><head><title>Access Denied</title></head>
+        <body><h1>Access Denied</h1>
+        You don't have permission to access "http://www.example.com/report" on this server.
+        </body></html>"#;
+
+    const CONSENT_PAGE: &str = r#"<html><head><title>Before you continue</title></head>
+        <body><div>We use cookies and data to deliver our services.</div>
+        <button>Accept all cookies</button><button>Reject all</button></body></html>"#;
+
+    const ARTICLE_PAGE: &str = r#"<html><head><title>Ownership in Rust</title>
+        <script>var msg = "access denied";</script></head>
+        <body>
+          <p>Each value in Rust has an owner, and there can only be one owner at a time.</p>
+          <p>When the owner goes out of scope, the value is dropped.</p>
+        </body></html>"#;
+
+    fn detect(html: &str) -> Option<AccessWall> {
+        detect_access_wall(&Html::parse_document(html))
+    }
+
+    #[test]
+    fn test_detects_paywall() {
+        assert_eq!(detect(PAYWALL_PAGE), Some(AccessWall::Paywall));
+    }
+
+    #[test]
+    fn test_detects_meta_refresh_to_login() {
+        assert_eq!(detect(LOGIN_REDIRECT_PAGE), Some(AccessWall::LoginWall));
+    }
+
+    #[test]
+    fn test_detects_access_denied_page() {
+        assert_eq!(detect(ACCESS_DENIED_PAGE), Some(AccessWall::AccessDenied));
+    }
+
+    #[test]
+    fn test_detects_consent_wall() {
+        assert_eq!(detect(CONSENT_PAGE), Some(AccessWall::ConsentWall));
+    }
+
+    #[test]
+    fn test_normal_article_is_content() {
+        // Phrases inside scripts don't count
+        assert_eq!(detect(ARTICLE_PAGE), None);
+    }
+
+    #[test]
+    fn test_long_article_with_subscribe_box_is_content() {
+        let paragraphs = "<p>Rust's ownership rules are checked at compile time.</p>".repeat(40);
+        let html = format!(
+            "<html><body>{paragraphs}<div>Already a subscriber? Sign in.</div></body></html>"
+        );
+
+        assert_eq!(detect(&html), None);
+    }
+
+    #[test]
+    fn test_long_page_without_paragraphs_with_cookie_banner_is_content() {
+        let sections = "<div>Rust's ownership rules are checked at compile time.</div>".repeat(40);
+        let html = format!("<html><body><div>Accept all cookies</div>{sections}</body></html>");
+
+        assert_eq!(detect(&html), None);
+    }
+
+    #[test]
+    fn test_meta_refresh_matches_whole_path_segments() {
+        let refresh = |target: &str| {
+            format!(
+                r#"<html><head><meta http-equiv="refresh" content="0; url={target}"></head>
+                <body><p>Redirecting…</p></body></html>"#
+            )
+        };
+
+        for target in [
+            "/auth",
+            "/u/login.php?next=/",
+            "https://example.com/signin#top",
+        ] {
+            assert_eq!(
+                detect(&refresh(target)),
+                Some(AccessWall::LoginWall),
+                "{target}"
+            );
+        }
+        for target in [
+            "/authors/jane",
+            "https://login.example.com/blog",
+            "/catalogue",
+        ] {
+            assert_eq!(detect(&refresh(target)), None, "{target}");
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+
+use super::web::{HttpClient, ReqwestClient};
+
+/// arXiv API endpoint (returns an Atom feed)
+const ARXIV_API_URL: &str = "https://export.arxiv.org/api/query";
+
+/// Metadata of an arXiv paper as returned by the arXiv API
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArxivPaper {
+    /// arXiv identifier, including version (e.g. "2301.07041v2")
+    pub id: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub summary: String,
+    /// Submission date (RFC 3339)
+    pub published: Option<String>,
+    pub abs_url: String,
+    pub pdf_url: String,
+}
+
+/// Fetch a paper's metadata from the arXiv API.
+///
+/// `id_or_url` may be a bare identifier (`2301.07041`, `arXiv:2301.07041v2`,
+/// `hep-th/9901001`) or an arxiv.org abs/pdf URL.
+pub(crate) async fn fetch_arxiv_paper(
+    client: &ReqwestClient,
+    id_or_url: &str,
+) -> Result<ArxivPaper> {
+    fetch_arxiv_paper_with_client(client, id_or_url).await
+}
+
+async fn fetch_arxiv_paper_with_client<C: HttpClient>(
+    client: &C,
+    id_or_url: &str,
+) -> Result<ArxivPaper> {
+    let id = parse_arxiv_id(id_or_url)
+        .with_context(|| format!("Not an arXiv ID or URL: {}", id_or_url))?;
+    let api_url = format!("{}?id_list={}", ARXIV_API_URL, id);
+    let feed = client.get(&api_url).await?;
+    parse_arxiv_feed(&feed)
+}
+
+/// Extract the arXiv identifier from an ID or an arxiv.org URL.
+/// Returns None if the input doesn't look like an arXiv paper.
+pub(crate) fn parse_arxiv_id(id_or_url: &str) -> Option<String> {
+    let input = id_or_url.trim();
+    // "arXiv:1234.5678" also parses as a URL, so only treat http(s) input as one
+    let id = match Url::parse(input)
+        .ok()
+        .filter(|url| url.scheme().starts_with("http"))
+    {
+        Some(url) => {
+            let host = url.host_str()?;
+            if host != "arxiv.org" && !host.ends_with(".arxiv.org") {
+                return None;
+            }
+            let path = url.path();
+            let id = path
+                .strip_prefix("/abs/")
+                .or_else(|| path.strip_prefix("/pdf/"))?;
+            id.trim_end_matches(".pdf").to_string()
+        }
+        None => match input.get(..6) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("arxiv:") => input[6..].to_string(),
+            _ => input.to_string(),
+        },
+    };
+    is_arxiv_id(&id).then_some(id)
+}
+
+/// Whether `id` is a new-style (`2301.07041v2`) or old-style (`hep-th/9901001`) identifier
+fn is_arxiv_id(id: &str) -> bool {
+    // Drop an optional version suffix ("v2")
+    let number = match id.rsplit_once('v') {
+        Some((number, version))
+            if !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            number
+        }
+        _ => id,
+    };
+
+    let new_style = number.split_once('.').is_some_and(|(yymm, seq)| {
+        yymm.len() == 4
+            && (4..=5).contains(&seq.len())
+            && yymm.chars().chain(seq.chars()).all(|c| c.is_ascii_digit())
+    });
+    let old_style = number.split_once('/').is_some_and(|(archive, seq)| {
+        !archive.is_empty()
+            && archive
+                .chars()
+                .all(|c| c.is_ascii_alphabetic() || c == '-' || c == '.')
+            && seq.len() == 7
+            && seq.chars().all(|c| c.is_ascii_digit())
+    });
+    new_style || old_style
+}
+
+/// Parse the first `<entry>` of an arXiv API Atom feed
+fn parse_arxiv_feed(feed: &str) -> Result<ArxivPaper> {
+    let document = Html::parse_document(feed);
+    let entry_selector = Selector::parse("entry").unwrap();
+    let entry = document
+        .select(&entry_selector)
+        .next()
+        .context("arXiv API returned no paper")?;
+
+    let id_url = child_text(entry, "id").context("arXiv entry has no id")?;
+    // The API reports unknown or malformed IDs as an entry titled "Error"
+    if id_url.contains("/api/errors") {
+        bail!(
+            "arXiv API error: {}",
+            child_text(entry, "summary").unwrap_or_default()
+        );
+    }
+    let id = id_url
+        .rsplit_once("/abs/")
+        .map(|(_, id)| id.to_string())
+        .unwrap_or(id_url.clone());
+
+    let author_selector = Selector::parse("author > name").unwrap();
+    let authors = entry
+        .select(&author_selector)
+        .map(|name| collapse_whitespace(&name.text().collect::<String>()))
+        .collect();
+
+    let pdf_selector = Selector::parse(r#"link[title="pdf"]"#).unwrap();
+    let pdf_url = entry
+        .select(&pdf_selector)
+        .next()
+        .and_then(|link| link.value().attr("href"))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("https://arxiv.org/pdf/{}", id));
+
+    Ok(ArxivPaper {
+        title: child_text(entry, "title").unwrap_or_default(),
+        authors,
+        summary: child_text(entry, "summary").unwrap_or_default(),
+        published: child_text(entry, "published"),
+        abs_url: id_url,
+        pdf_url,
+        id,
+    })
+}
+
+/// Whitespace-normalized text of the first direct child named `name`
+fn child_text(entry: ElementRef, name: &str) -> Option<String> {
+    entry
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find(|child| child.value().name() == name)
+        .map(|child| collapse_whitespace(&child.text().collect::<String>()))
+}
+
+/// Collapse runs of whitespace (the API wraps titles and abstracts) into single spaces
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MockHttpClient {
+        responses: HashMap<String, String>,
+    }
+
+    impl MockHttpClient {
+        fn new() -> Self {
+            Self {
+                responses: HashMap::new(),
+            }
+        }
+
+        fn with_response(mut self, url: &str, body: &str) -> Self {
+            self.responses.insert(url.to_string(), body.to_string());
+            self
+        }
+    }
+
+    impl HttpClient for MockHttpClient {
+        async fn get(&self, url: &str) -> Result<String> {
+            self.responses
+                .get(url)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No mock response for {}", url))
+        }
+    }
+
+    const ARXIV_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <link href="http://arxiv.org/api/query?id_list=1706.03762" rel="self" type="application/atom+xml"/>
+  <title type="html">ArXiv Query: id_list=1706.03762</title>
+  <id>http://arxiv.org/api/abc</id>
+  <entry>
+    <id>http://arxiv.org/abs/1706.03762v7</id>
+    <updated>2023-08-02T00:41:18Z</updated>
+    <published>2017-06-12T17:57:34Z</published>
+    <title>Attention Is All You
+  Need</title>
+    <summary>  The dominant sequence transduction models are based on complex recurrent or
+convolutional neural networks.
+</summary>
+    <author>
+      <name>Ashish Vaswani</name>
+    </author>
+    <author>
+      <name>Noam Shazeer</name>
+    </author>
+    <link href="http://arxiv.org/abs/1706.03762v7" rel="alternate" type="text/html"/>
+    <link title="pdf" href="http://arxiv.org/pdf/1706.03762v7" rel="related" type="application/pdf"/>
+  </entry>
+</feed>"#;
+
+    const ARXIV_ERROR_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title type="html">ArXiv Query: id_list=9999.99999</title>
+  <entry>
+    <id>http://arxiv.org/api/errors#incorrect_id_format_for_9999.99999</id>
+    <title>Error</title>
+    <summary>incorrect id format for 9999.99999</summary>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn test_parse_arxiv_feed_extracts_paper() {
+        let paper = parse_arxiv_feed(ARXIV_FEED).unwrap();
+
+        assert_eq!(
+            paper,
+            ArxivPaper {
+                id: "1706.03762v7".to_string(),
+                title: "Attention Is All You Need".to_string(),
+                authors: vec!["Ashish Vaswani".to_string(), "Noam Shazeer".to_string()],
+                summary: "The dominant sequence transduction models are based on complex \
+                          recurrent or convolutional neural networks."
+                    .to_string(),
+                published: Some("2017-06-12T17:57:34Z".to_string()),
+                abs_url: "http://arxiv.org/abs/1706.03762v7".to_string(),
+                pdf_url: "http://arxiv.org/pdf/1706.03762v7".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_arxiv_feed_reports_api_error() {
+        let result = parse_arxiv_feed(ARXIV_ERROR_FEED);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("incorrect id format"));
+    }
+
+    #[test]
+    fn test_parse_arxiv_feed_errors_without_entry() {
+        let feed = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>empty</title></feed>"#;
+        assert!(parse_arxiv_feed(feed).is_err());
+    }
+
+    #[test]
+    fn test_parse_arxiv_id_accepts_ids_and_urls() {
+        for (input, expected) in [
+            ("1706.03762", "1706.03762"),
+            ("arXiv:1706.03762v7", "1706.03762v7"),
+            ("hep-th/9901001", "hep-th/9901001"),
+            ("https://arxiv.org/abs/1706.03762", "1706.03762"),
+            ("https://arxiv.org/pdf/1706.03762v7.pdf", "1706.03762v7"),
+            (
+                "https://export.arxiv.org/abs/hep-th/9901001v2",
+                "hep-th/9901001v2",
+            ),
+        ] {
+            assert_eq!(parse_arxiv_id(input).as_deref(), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_arxiv_id_rejects_other_input() {
+        for input in [
+            "https://example.com/abs/1706.03762",
+            "attention is all you need",
+            "1706.03762vx",
+            "https://arxiv.org/list/cs.AI/recent",
+        ] {
+            assert_eq!(parse_arxiv_id(input), None, "{input}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_arxiv_paper_queries_api_by_id() {
+        let client = MockHttpClient::new().with_response(
+            "https://export.arxiv.org/api/query?id_list=1706.03762",
+            ARXIV_FEED,
+        );
+
+        let paper = fetch_arxiv_paper_with_client(&client, "https://arxiv.org/abs/1706.03762")
+            .await
+            .unwrap();
+
+        assert_eq!(paper.title, "Attention Is All You Need");
+    }
+}
@@ -0,0 +1,91 @@
+use std::str::FromStr;
+
+use reqwest::Url;
+use scraper::Selector;
+
+use super::credentials::host_matches;
+
+/// Per-host CSS selectors for the container holding a page's main content.
+///
+/// Read from `CONTENT_SELECTORS` as `host=selector` entries separated by `;`
+/// (commas belong to selector lists, e.g. `article, main`). A host of
+/// `*.example.com` matches every subdomain of example.com; the first
+/// matching entry wins.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ContentSelectors {
+    entries: Vec<(String, Selector)>,
+}
+
+impl FromStr for ContentSelectors {
+    /// The first malformed entry
+    type Err = String;
+
+    /// Parse a `CONTENT_SELECTORS` value, rejecting entries without a host or
+    /// with an invalid selector
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let entries = spec
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (host, selector) = entry
+                    .split_once('=')
+                    .filter(|(host, _)| !host.trim().is_empty())
+                    .ok_or_else(|| entry.to_string())?;
+                let selector = Selector::parse(selector.trim()).map_err(|_| entry.to_string())?;
+                Ok((host.trim().to_ascii_lowercase(), selector))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { entries })
+    }
+}
+
+impl ContentSelectors {
+    /// The content selector configured for `url`'s host, if any
+    pub(crate) fn for_url(&self, url: &str) -> Option<&Selector> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        self.entries
+            .iter()
+            .find(|(pattern, _)| host_matches(pattern, &host))
+            .map(|(_, selector)| selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_url_matches_host_and_wildcard() {
+        let sut = "docs.rs=#main-content; *.example.com=article, main"
+            .parse::<ContentSelectors>()
+            .unwrap();
+
+        assert_eq!(
+            sut.for_url("https://DOCS.rs/serde"),
+            Some(&Selector::parse("#main-content").unwrap())
+        );
+        assert_eq!(
+            sut.for_url("https://blog.example.com/post"),
+            Some(&Selector::parse("article, main").unwrap())
+        );
+        assert!(sut.for_url("https://example.org/").is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_entries() {
+        assert_eq!(
+            "ok.example=main; no-host-here".parse::<ContentSelectors>(),
+            Err("no-host-here".to_string())
+        );
+        assert_eq!(
+            "bad.example=div[".parse::<ContentSelectors>(),
+            Err("bad.example=div[".to_string())
+        );
+        assert_eq!(
+            "=main".parse::<ContentSelectors>(),
+            Err("=main".to_string())
+        );
+    }
+}
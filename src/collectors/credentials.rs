@@ -0,0 +1,190 @@
+use std::fmt;
+use std::str::FromStr;
+
+use reqwest::{RequestBuilder, Url};
+
+/// Credentials sent in the `Authorization` header of requests to one host
+#[derive(Clone, PartialEq)]
+pub(crate) enum Credential {
+    Basic { username: String, password: String },
+    Bearer(String),
+}
+
+impl Credential {
+    /// Add this credential's `Authorization` header to `request`
+    pub(crate) fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credential::Basic { username, password } => {
+                request.basic_auth(username, Some(password))
+            }
+            Credential::Bearer(token) => request.bearer_auth(token),
+        }
+    }
+}
+
+/// Shows the scheme and username only, so credentials never end up in logs
+impl fmt::Debug for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credential::Basic { username, .. } => write!(f, "Basic({username}:[redacted])"),
+            Credential::Bearer(_) => write!(f, "Bearer([redacted])"),
+        }
+    }
+}
+
+/// Per-host credentials for fetching protected pages.
+///
+/// Read from `FETCH_CREDENTIALS` as comma-separated `host=basic:user:password`
+/// or `host=bearer:token` entries. A host of `*.example.com` matches every
+/// subdomain of example.com. Credentials are only sent over HTTPS.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct FetchCredentials {
+    entries: Vec<(String, Credential)>,
+}
+
+impl FromStr for FetchCredentials {
+    /// The host of the first malformed entry; the secret is left out so it
+    /// never ends up in error messages
+    type Err = String;
+
+    /// Parse a `FETCH_CREDENTIALS` value, rejecting entries without a host or
+    /// with an unknown scheme, a missing password or an empty token
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let entries = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (host, credential) = entry.split_once('=').unwrap_or((entry, ""));
+                let host = host.trim().to_ascii_lowercase();
+                let credential = match credential.split_once(':') {
+                    Some(("basic", user_pass)) => {
+                        user_pass
+                            .split_once(':')
+                            .map(|(username, password)| Credential::Basic {
+                                username: username.to_string(),
+                                password: password.to_string(),
+                            })
+                    }
+                    Some(("bearer", token)) if !token.is_empty() => {
+                        Some(Credential::Bearer(token.to_string()))
+                    }
+                    _ => None,
+                };
+                match credential {
+                    Some(credential) if !host.is_empty() => Ok((host, credential)),
+                    _ => Err(host),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
+    }
+}
+
+impl FetchCredentials {
+    /// The credential to send with a request to `url`, if its host has one
+    pub(crate) fn for_url(&self, url: &str) -> Option<&Credential> {
+        let url = Url::parse(url).ok()?;
+        if url.scheme() != "https" {
+            return None;
+        }
+        let host = url.host_str()?.to_ascii_lowercase();
+        self.entries
+            .iter()
+            .find(|(pattern, _)| host_matches(pattern, &host))
+            .map(|(_, credential)| credential)
+    }
+}
+
+/// Whether `host` is `pattern`, or a subdomain of it for `*.` patterns
+pub(crate) fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "docs.example.com=basic:alice:s3cr:et, *.corp.example=bearer:tok123";
+
+    #[test]
+    fn test_for_url_matches_exact_host_and_wildcard_subdomains() {
+        let sut = SPEC.parse::<FetchCredentials>().unwrap();
+
+        assert!(matches!(
+            sut.for_url("https://DOCS.example.com/guide"),
+            Some(Credential::Basic { password, .. }) if password == "s3cr:et"
+        ));
+        assert!(sut.for_url("https://wiki.corp.example/page").is_some());
+        assert!(sut.for_url("https://corp.example/").is_none());
+        assert!(sut.for_url("https://evilcorp.example/").is_none());
+        assert!(sut.for_url("https://other.example.com/").is_none());
+    }
+
+    #[test]
+    fn test_credentials_are_not_sent_over_plain_http() {
+        let sut = SPEC.parse::<FetchCredentials>().unwrap();
+
+        assert!(sut.for_url("http://docs.example.com/guide").is_none());
+    }
+
+    #[test]
+    fn test_apply_sets_authorization_header() {
+        let client = reqwest::Client::new();
+        let header = |credential: &Credential| {
+            credential
+                .apply(client.get("https://docs.example.com/"))
+                .build()
+                .unwrap()
+                .headers()[reqwest::header::AUTHORIZATION]
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let basic = Credential::Basic {
+            username: "alice".to_string(),
+            password: "secret".to_string(),
+        };
+        // base64("alice:secret")
+        assert_eq!(header(&basic), "Basic YWxpY2U6c2VjcmV0");
+        assert_eq!(
+            header(&Credential::Bearer("tok123".to_string())),
+            "Bearer tok123"
+        );
+    }
+
+    #[test]
+    fn test_debug_output_redacts_secrets() {
+        let debug = format!("{:?}", SPEC.parse::<FetchCredentials>().unwrap());
+
+        assert!(debug.contains("alice"));
+        assert!(!debug.contains("s3cr"));
+        assert!(!debug.contains("tok123"));
+    }
+
+    #[test]
+    fn test_malformed_entries_are_rejected_without_the_secret() {
+        for spec in [
+            "a.example=basic:nopassword",
+            "a.example=token",
+            "a.example",
+            "a.example=bearer:",
+        ] {
+            assert_eq!(
+                spec.parse::<FetchCredentials>(),
+                Err("a.example".to_string()),
+                "{spec}"
+            );
+        }
+        assert_eq!(
+            "=bearer:tok123".parse::<FetchCredentials>(),
+            Err(String::new())
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::{bail, Context, Result};
+use scraper::Html;
+use serde::Serialize;
+
+use super::robots::RobotsCache;
+use super::web::{HttpClient, ReqwestClient};
+
+/// Maximum number of entries returned from a feed (keeps tool output small)
+pub(crate) const MAX_FEED_ENTRIES: usize = 50;
+
+/// One item of an RSS or Atom feed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedEntry {
+    pub title: String,
+    /// Link to the item's page (empty if the feed gives none)
+    pub link: String,
+    /// Publication date (RFC 3339), falling back to the last update
+    pub published: Option<String>,
+    /// Summary as plain text, with any HTML markup removed
+    pub summary: Option<String>,
+}
+
+/// Fetch an RSS or Atom feed and return its newest `MAX_FEED_ENTRIES` entries,
+/// in feed order. Fails if robots.txt disallows the feed URL.
+pub(crate) async fn fetch_feed(
+    client: &ReqwestClient,
+    url: &str,
+    robots_cache: &RobotsCache,
+) -> Result<Vec<FeedEntry>> {
+    fetch_feed_with_client(client, robots_cache, url).await
+}
+
+async fn fetch_feed_with_client<C: HttpClient>(
+    client: &C,
+    robots_cache: &RobotsCache,
+    url: &str,
+) -> Result<Vec<FeedEntry>> {
+    if !robots_cache.is_allowed(client, url).await {
+        bail!("Access to {} is prohibited by robots.txt", url);
+    }
+    robots_cache.wait_for_turn(url).await;
+    let xml = client.get(url).await?;
+    let mut entries = parse_feed(&xml).with_context(|| format!("Not an RSS/Atom feed: {}", url))?;
+    entries.truncate(MAX_FEED_ENTRIES);
+    Ok(entries)
+}
+
+/// Parse an RSS (0.9x, 1.0, 2.0) or Atom document into entries
+fn parse_feed(xml: &str) -> Result<Vec<FeedEntry>> {
+    let feed = feed_rs::parser::parse(xml.as_bytes())?;
+    Ok(feed
+        .entries
+        .into_iter()
+        .map(|entry| FeedEntry {
+            title: entry
+                .title
+                .map(|t| html_to_text(&t.content))
+                .unwrap_or_default(),
+            link: entry
+                .links
+                .into_iter()
+                .next()
+                .map(|link| link.href)
+                .unwrap_or_default(),
+            published: entry
+                .published
+                .or(entry.updated)
+                .map(|date| date.to_rfc3339()),
+            summary: entry
+                .summary
+                .map(|s| html_to_text(&s.content))
+                .filter(|s| !s.is_empty()),
+        })
+        .collect())
+}
+
+/// Plain text of a feed field, which may contain (escaped) HTML
+fn html_to_text(content: &str) -> String {
+    let fragment = Html::parse_fragment(content);
+    let text: String = fragment.root_element().text().collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MockHttpClient {
+        responses: HashMap<String, String>,
+    }
+
+    impl HttpClient for MockHttpClient {
+        async fn get(&self, url: &str) -> Result<String> {
+            self.responses
+                .get(url)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No mock response for {}", url))
+        }
+    }
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <rss version="2.0">
+          <channel>
+            <title>Example News</title>
+            <link>https://news.example.com/</link>
+            <item>
+              <title>Rust 1.85 released</title>
+              <link>https://news.example.com/rust-1-85</link>
+              <pubDate>Thu, 20 Feb 2025 10:00:00 GMT</pubDate>
+              <description>&lt;p&gt;The &lt;b&gt;2024 edition&lt;/b&gt; is stable.&lt;/p&gt;</description>
+            </item>
+            <item>
+              <title>No date or summary</title>
+              <link>https://news.example.com/bare</link>
+            </item>
+          </channel>
+        </rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+        <feed xmlns="http://www.w3.org/2005/Atom">
+          <title>Example Blog</title>
+          <id>urn:example:blog</id>
+          <updated>2025-03-01T12:00:00Z</updated>
+          <entry>
+            <title>Async closures</title>
+            <id>urn:example:async-closures</id>
+            <link href="https://blog.example.com/async-closures"/>
+            <updated>2025-03-01T12:00:00Z</updated>
+            <summary>Async closures are now stable.</summary>
+          </entry>
+        </feed>"#;
+
+    #[test]
+    fn test_parse_rss_feed() {
+        let entries = parse_feed(RSS).unwrap();
+
+        assert_eq!(
+            entries,
+            [
+                FeedEntry {
+                    title: "Rust 1.85 released".to_string(),
+                    link: "https://news.example.com/rust-1-85".to_string(),
+                    published: Some("2025-02-20T10:00:00+00:00".to_string()),
+                    summary: Some("The 2024 edition is stable.".to_string()),
+                },
+                FeedEntry {
+                    title: "No date or summary".to_string(),
+                    link: "https://news.example.com/bare".to_string(),
+                    published: None,
+                    summary: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_atom_feed_falls_back_to_updated_date() {
+        let entries = parse_feed(ATOM).unwrap();
+
+        assert_eq!(
+            entries,
+            [FeedEntry {
+                title: "Async closures".to_string(),
+                link: "https://blog.example.com/async-closures".to_string(),
+                published: Some("2025-03-01T12:00:00+00:00".to_string()),
+                summary: Some("Async closures are now stable.".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_feed_rejects_html_page() {
+        assert!(parse_feed("<html><body>Not a feed</body></html>").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_feed_respects_robots_txt() {
+        let client = MockHttpClient {
+            responses: HashMap::from([
+                (
+                    "https://news.example.com/robots.txt".to_string(),
+                    "User-agent: *\nDisallow: /feeds/".to_string(),
+                ),
+                (
+                    "https://news.example.com/feeds/rss".to_string(),
+                    RSS.to_string(),
+                ),
+                ("https://news.example.com/rss".to_string(), RSS.to_string()),
+            ]),
+        };
+        let robots_cache = RobotsCache::new();
+
+        let disallowed =
+            fetch_feed_with_client(&client, &robots_cache, "https://news.example.com/feeds/rss")
+                .await;
+        let allowed =
+            fetch_feed_with_client(&client, &robots_cache, "https://news.example.com/rss").await;
+
+        assert!(disallowed.unwrap_err().to_string().contains("robots.txt"));
+        assert_eq!(allowed.unwrap().len(), 2);
+    }
+}
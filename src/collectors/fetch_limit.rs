@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Requests in flight at once when `MAX_CONCURRENT_FETCHES` is unset
+pub(crate) const DEFAULT_MAX_CONCURRENT_FETCHES: usize = 16;
+
+/// Bound on outbound fetches in flight at once, across every request and tool.
+///
+/// Unlike `DomainRateLimiter`, which spaces requests to one site, this caps
+/// the total number of connections the process opens, however many chats
+/// are running. Clones share the same permits.
+#[derive(Clone)]
+pub(crate) struct FetchLimiter {
+    /// `None` means unlimited
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl FetchLimiter {
+    /// Allow `max_concurrent` fetches at once; zero disables the limit
+    pub(crate) fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: (max_concurrent > 0).then(|| Arc::new(Semaphore::new(max_concurrent))),
+        }
+    }
+
+    /// A limiter that never waits
+    pub(crate) fn unlimited() -> Self {
+        Self::new(0)
+    }
+
+    /// Wait for a free slot. The fetch holds it until the permit is dropped;
+    /// `None` when unlimited.
+    pub(crate) async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let permits = Arc::clone(self.permits.as_ref()?);
+        // The semaphore is never closed
+        permits.acquire_owned().await.ok()
+    }
+
+    /// Whether `other` hands out the same permits
+    #[cfg(test)]
+    pub(crate) fn shares_permits_with(&self, other: &Self) -> bool {
+        match (&self.permits, &other.permits) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Run `fetches` simulated fetches through `limiter` at once and return
+    /// the most that were ever in flight together
+    async fn peak_concurrency(limiter: FetchLimiter, fetches: usize) -> usize {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..fetches)
+            .map(|_| {
+                let limiter = limiter.clone();
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire().await;
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        peak.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_limits_concurrent_fetches() {
+        assert_eq!(peak_concurrency(FetchLimiter::new(3), 12).await, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_zero_is_unlimited() {
+        assert_eq!(peak_concurrency(FetchLimiter::new(0), 12).await, 12);
+        assert!(FetchLimiter::unlimited().acquire().await.is_none());
+    }
+
+    #[test]
+    fn test_clones_share_permits() {
+        let limiter = FetchLimiter::new(2);
+
+        assert!(limiter.clone().shares_permits_with(&limiter));
+        assert!(!FetchLimiter::new(2).shares_permits_with(&limiter));
+    }
+}
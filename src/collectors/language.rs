@@ -0,0 +1,140 @@
+/// Minimum number of letters before detection is attempted
+const MIN_LETTERS: usize = 20;
+
+/// Share of letters a script must reach to be considered the text's script
+const SCRIPT_THRESHOLD: f64 = 0.5;
+
+/// Share of words that must be English stop words to call Latin-script text English
+const ENGLISH_STOPWORD_THRESHOLD: f64 = 0.15;
+
+/// Frequent English function words
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "the", "of", "and", "to", "a", "in", "is", "it", "that", "for", "was", "on", "are", "as",
+    "with", "be", "by", "this", "at", "from", "or", "an", "have", "not", "which", "but",
+];
+
+/// Detect the primary language of `text` as an ISO 639-1 code.
+///
+/// A lightweight heuristic: the dominant writing system decides most languages
+/// (kana → "ja", Hangul → "ko", Han without kana → "zh", Cyrillic → "ru", ...),
+/// and Latin-script text is called English when enough words are English stop words.
+/// Returns None when the text is too short or the result would be a guess.
+pub(crate) fn detect_language(text: &str) -> Option<&'static str> {
+    let mut counts = ScriptCounts::default();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        counts.add(c);
+    }
+    if counts.total < MIN_LETTERS {
+        return None;
+    }
+
+    let share = |n: usize| n as f64 / counts.total as f64;
+    // Japanese mixes kana with kanji, so any substantial kana share means Japanese
+    if share(counts.kana) >= 0.1 && share(counts.kana + counts.han) >= SCRIPT_THRESHOLD {
+        return Some("ja");
+    }
+    if share(counts.hangul) >= SCRIPT_THRESHOLD {
+        return Some("ko");
+    }
+    if share(counts.han) >= SCRIPT_THRESHOLD {
+        return Some("zh");
+    }
+    if share(counts.cyrillic) >= SCRIPT_THRESHOLD {
+        return Some("ru");
+    }
+    if share(counts.arabic) >= SCRIPT_THRESHOLD {
+        return Some("ar");
+    }
+    if share(counts.latin) >= SCRIPT_THRESHOLD && is_english(text) {
+        return Some("en");
+    }
+    None
+}
+
+/// Primary subtag of an HTML `lang` attribute, lowercased ("en-US" → "en")
+pub(crate) fn normalize_lang_attr(lang: &str) -> Option<String> {
+    let primary = lang.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+    (!primary.is_empty() && primary.chars().all(|c| c.is_ascii_alphabetic())).then_some(primary)
+}
+
+fn is_english(text: &str) -> bool {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return false;
+    }
+    let stopwords = words
+        .iter()
+        .filter(|w| ENGLISH_STOPWORDS.contains(&w.as_str()))
+        .count();
+    stopwords as f64 / words.len() as f64 >= ENGLISH_STOPWORD_THRESHOLD
+}
+
+/// Letter counts per writing system
+#[derive(Default)]
+struct ScriptCounts {
+    total: usize,
+    latin: usize,
+    kana: usize,
+    han: usize,
+    hangul: usize,
+    cyrillic: usize,
+    arabic: usize,
+}
+
+impl ScriptCounts {
+    fn add(&mut self, c: char) {
+        self.total += 1;
+        match c as u32 {
+            0x0041..=0x024F => self.latin += 1,
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9D => self.kana += 1,
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => self.han += 1,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF | 0x3130..=0x318F => self.hangul += 1,
+            0x0400..=0x04FF => self.cyrillic += 1,
+            0x0600..=0x06FF => self.arabic += 1,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGLISH: &str = "Rust is a general-purpose programming language. It is known for \
+                           the speed of its programs and for the safety of its memory model.";
+    const JAPANESE: &str = "Rustは、性能、メモリ安全性、安全な並行性を目指して設計された\
+                            マルチパラダイムのプログラミング言語である。";
+
+    #[test]
+    fn test_detects_english() {
+        assert_eq!(detect_language(ENGLISH), Some("en"));
+    }
+
+    #[test]
+    fn test_detects_japanese() {
+        assert_eq!(detect_language(JAPANESE), Some("ja"));
+    }
+
+    #[test]
+    fn test_short_text_is_undetermined() {
+        assert_eq!(detect_language("Hello"), None);
+    }
+
+    #[test]
+    fn test_non_english_latin_text_is_undetermined() {
+        let german = "Rust ist eine Programmiersprache, die für Sicherheit und \
+                      Geschwindigkeit entwickelt wurde und sich großer Beliebtheit erfreut.";
+        assert_eq!(detect_language(german), None);
+    }
+
+    #[test]
+    fn test_normalize_lang_attr() {
+        assert_eq!(normalize_lang_attr("en-US").as_deref(), Some("en"));
+        assert_eq!(normalize_lang_attr(" JA ").as_deref(), Some("ja"));
+        assert_eq!(normalize_lang_attr(""), None);
+    }
+}
@@ -1,3 +1,13 @@
+pub mod access_wall;
+pub mod arxiv;
+pub(crate) mod content_selectors;
+pub(crate) mod credentials;
+pub mod feed;
+pub(crate) mod fetch_limit;
+pub(crate) mod language;
 pub mod pdf;
+pub(crate) mod rate_limit;
 pub mod robots;
+pub mod sitemap;
 pub mod web;
+pub mod wikipedia;
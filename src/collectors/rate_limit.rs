@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::debug;
+use tokio::time::Instant;
+
+use super::robots::extract_origin;
+use crate::sync::lock;
+
+/// Requests per second sent to one origin when `FETCH_RATE_PER_SEC` is unset
+pub(crate) const DEFAULT_FETCH_RATE_PER_SEC: f64 = 1.0;
+
+/// Slowest rate a limited origin is held to: one request every 1000 seconds.
+/// Slower rates would space requests so far apart that fetches look hung.
+pub(crate) const MIN_FETCH_RATE_PER_SEC: f64 = 0.001;
+
+/// Per-origin token bucket that keeps fetches to one site politely spaced.
+///
+/// Each origin's bucket holds a single token, refilled at the configured rate,
+/// so requests to a site are at least `1 / rate` seconds apart while different
+/// sites are fetched without waiting on each other. Callers that find the
+/// bucket empty reserve the next token and sleep until it is due, so concurrent
+/// fetches to one origin queue up in order.
+/// Uses `Arc<Mutex<...>>` so clones share the same buckets, like `RobotsCache`.
+#[derive(Clone)]
+pub(crate) struct DomainRateLimiter {
+    /// When each origin's next token is due; origins whose token is
+    /// already available are dropped from the map
+    next_allowed: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Time to refill one token; `None` means unlimited
+    interval: Option<Duration>,
+}
+
+impl DomainRateLimiter {
+    /// Allow `requests_per_sec` requests per second to each origin.
+    /// Zero (or any non-positive or non-finite rate) disables the limit;
+    /// rates below `MIN_FETCH_RATE_PER_SEC` are raised to it.
+    pub(crate) fn new(requests_per_sec: f64) -> Self {
+        let interval = (requests_per_sec.is_finite() && requests_per_sec > 0.0)
+            .then(|| 1.0 / requests_per_sec.max(MIN_FETCH_RATE_PER_SEC))
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+        Self {
+            next_allowed: Arc::default(),
+            interval,
+        }
+    }
+
+    /// A limiter that never waits
+    pub(crate) fn unlimited() -> Self {
+        Self::new(0.0)
+    }
+
+    /// Wait until a request to `url`'s origin is allowed, then take the token.
+    /// URLs without a valid origin are not limited.
+    pub(crate) async fn acquire(&self, url: &str) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let Some(origin) = extract_origin(url) else {
+            return;
+        };
+        let start = {
+            let mut next_allowed = lock(&self.next_allowed);
+            let now = Instant::now();
+            next_allowed.retain(|_, due| *due > now);
+            let start = next_allowed.get(&origin).copied().unwrap_or(now).max(now);
+            next_allowed.insert(origin.clone(), start + interval);
+            start
+        };
+        if start > Instant::now() {
+            debug!(
+                "Waiting {:?} before fetching from {}",
+                start - Instant::now(),
+                origin
+            );
+            tokio::time::sleep_until(start).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_to_same_origin_are_spaced() {
+        let limiter = DomainRateLimiter::new(2.0);
+        let started = Instant::now();
+
+        limiter.acquire("https://example.com/a").await;
+        assert_eq!(started.elapsed(), Duration::ZERO);
+        limiter.acquire("https://example.com/b").await;
+        assert_eq!(started.elapsed(), Duration::from_millis(500));
+        limiter.acquire("https://example.com/c").await;
+        assert_eq!(started.elapsed(), Duration::from_millis(1000));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_other_origins_are_not_delayed() {
+        let limiter = DomainRateLimiter::new(1.0);
+        let started = Instant::now();
+
+        limiter.acquire("https://example.com/a").await;
+        limiter.acquire("https://example.org/a").await;
+        limiter.acquire("http://example.com/a").await;
+
+        assert_eq!(started.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_refills_after_idle_time() {
+        let limiter = DomainRateLimiter::new(1.0);
+
+        limiter.acquire("https://example.com/a").await;
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let started = Instant::now();
+        limiter.acquire("https://example.com/b").await;
+
+        assert_eq!(started.elapsed(), Duration::ZERO);
+        // The idle origin was dropped before the new token was taken
+        assert_eq!(limiter.next_allowed.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrent_requests_queue_up() {
+        let limiter = DomainRateLimiter::new(1.0);
+        let started = Instant::now();
+
+        let mut elapsed = futures::future::join_all((0..3).map(|i| {
+            let limiter = limiter.clone();
+            async move {
+                limiter.acquire(&format!("https://example.com/{i}")).await;
+                started.elapsed().as_secs()
+            }
+        }))
+        .await;
+
+        elapsed.sort();
+        assert_eq!(elapsed, [0, 1, 2]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_recovers_from_poisoned_lock() {
+        let limiter = DomainRateLimiter::new(1.0);
+        let buckets = Arc::clone(&limiter.next_allowed);
+        let result = std::thread::spawn(move || {
+            let _guard = buckets.lock().unwrap();
+            panic!("simulated panic");
+        })
+        .join();
+        assert!(result.is_err());
+
+        limiter.acquire("https://example.com/a").await;
+
+        assert!(!limiter.next_allowed.is_poisoned());
+        assert_eq!(limiter.next_allowed.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_zero_rate_is_unlimited() {
+        let limiter = DomainRateLimiter::new(0.0);
+        let started = Instant::now();
+
+        for _ in 0..3 {
+            limiter.acquire("https://example.com/").await;
+        }
+
+        assert_eq!(started.elapsed(), Duration::ZERO);
+        assert!(limiter.next_allowed.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tiny_rate_is_raised_to_the_minimum() {
+        for rate in [1e-300, f64::MIN_POSITIVE, 1e-9] {
+            let limiter = DomainRateLimiter::new(rate);
+
+            assert_eq!(
+                limiter.interval,
+                Some(Duration::from_secs_f64(1.0 / MIN_FETCH_RATE_PER_SEC))
+            );
+        }
+    }
+}
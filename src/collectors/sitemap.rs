@@ -0,0 +1,256 @@
+use std::collections::VecDeque;
+
+use anyhow::{Context, Result};
+use log::debug;
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+use super::robots::{extract_origin, RobotsCache};
+use super::web::{HttpClient, ReqwestClient};
+
+/// Maximum number of page URLs returned from sitemaps (keeps tool output small)
+pub(crate) const MAX_SITEMAP_URLS: usize = 200;
+
+/// How many levels of `<sitemapindex>` are followed below the starting sitemaps
+const MAX_SITEMAP_DEPTH: usize = 2;
+
+/// Maximum number of sitemap documents fetched for one call, indexes included
+const MAX_SITEMAP_FETCHES: usize = 20;
+
+/// The entries of one sitemap document
+#[derive(Debug, PartialEq)]
+enum SitemapEntries {
+    /// A `<urlset>`: the site's page URLs
+    Pages(Vec<String>),
+    /// A `<sitemapindex>`: URLs of further sitemaps
+    Index(Vec<String>),
+}
+
+/// Fetch the page URLs listed in a site's sitemap(s).
+///
+/// `url` may be a sitemap itself (ending in `.xml`) or any page on the site.
+/// For a page, sitemaps are discovered via `Sitemap:` lines in robots.txt,
+/// falling back to `{origin}/sitemap.xml`. Sitemap indexes are followed up to
+/// `MAX_SITEMAP_DEPTH` levels deep and `MAX_SITEMAP_FETCHES` documents in total.
+pub(crate) async fn fetch_sitemap(
+    client: &ReqwestClient,
+    url: &str,
+    robots_cache: &RobotsCache,
+) -> Result<Vec<String>> {
+    fetch_sitemap_with_client(client, robots_cache, url).await
+}
+
+async fn fetch_sitemap_with_client<C: HttpClient>(
+    client: &C,
+    robots_cache: &RobotsCache,
+    url: &str,
+) -> Result<Vec<String>> {
+    let parsed = Url::parse(url).with_context(|| format!("Invalid URL: {}", url))?;
+
+    let sitemap_urls = if parsed.path().ends_with(".xml") {
+        vec![url.to_string()]
+    } else {
+        let origin = extract_origin(url).with_context(|| format!("Invalid URL: {}", url))?;
+        // Populates the robots cache (and its sitemap list) for this origin
+        robots_cache.is_allowed(client, url).await;
+        let declared = robots_cache.sitemaps_for(&origin).await;
+        if declared.is_empty() {
+            vec![format!("{}/sitemap.xml", origin)]
+        } else {
+            declared
+        }
+    };
+
+    let mut queue: VecDeque<(String, usize)> =
+        sitemap_urls.into_iter().map(|url| (url, 0)).collect();
+    let mut page_urls = Vec::new();
+    let mut last_error = None;
+    let mut fetches = 0;
+    while let Some((sitemap_url, depth)) = queue.pop_front() {
+        if fetches >= MAX_SITEMAP_FETCHES || page_urls.len() >= MAX_SITEMAP_URLS {
+            break;
+        }
+        if !robots_cache.is_allowed(client, &sitemap_url).await {
+            debug!("Sitemap {} is prohibited by robots.txt", sitemap_url);
+            continue;
+        }
+        robots_cache.wait_for_turn(&sitemap_url).await;
+        fetches += 1;
+        match client
+            .get(&sitemap_url)
+            .await
+            .map(|xml| parse_sitemap(&xml))
+        {
+            Ok(SitemapEntries::Pages(urls)) => page_urls.extend(urls),
+            Ok(SitemapEntries::Index(children)) if depth < MAX_SITEMAP_DEPTH => {
+                queue.extend(children.into_iter().map(|url| (url, depth + 1)));
+            }
+            Ok(SitemapEntries::Index(_)) => {
+                debug!(
+                    "Sitemap index {} is nested too deeply; skipping",
+                    sitemap_url
+                );
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    if page_urls.is_empty() {
+        if let Some(e) = last_error {
+            return Err(e);
+        }
+    }
+    page_urls.truncate(MAX_SITEMAP_URLS);
+    Ok(page_urls)
+}
+
+/// Extract the `<loc>` entries of a sitemap or sitemap index XML document
+fn parse_sitemap(xml: &str) -> SitemapEntries {
+    let document = Html::parse_document(xml);
+    let loc_selector = Selector::parse("loc").unwrap();
+    let index_selector = Selector::parse("sitemapindex").unwrap();
+    let locs = document
+        .select(&loc_selector)
+        .map(|element| element.text().collect::<String>().trim().to_string())
+        .filter(|loc| !loc.is_empty())
+        .collect();
+    if document.select(&index_selector).next().is_some() {
+        SitemapEntries::Index(locs)
+    } else {
+        SitemapEntries::Pages(locs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MockHttpClient {
+        responses: HashMap<String, String>,
+    }
+
+    impl MockHttpClient {
+        fn new() -> Self {
+            Self {
+                responses: HashMap::new(),
+            }
+        }
+
+        fn with_response(mut self, url: &str, body: &str) -> Self {
+            self.responses.insert(url.to_string(), body.to_string());
+            self
+        }
+    }
+
+    impl HttpClient for MockHttpClient {
+        async fn get(&self, url: &str) -> Result<String> {
+            self.responses
+                .get(url)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No mock response for {}", url))
+        }
+    }
+
+    const SITEMAP_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <url><loc>https://example.com/a</loc></url>
+            <url><loc> https://example.com/b </loc></url>
+        </urlset>"#;
+
+    const INDEX_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <sitemap><loc>https://example.com/pages.xml</loc></sitemap>
+        </sitemapindex>"#;
+
+    /// A sitemap index whose only entry is itself
+    const SELF_INDEX_XML: &str = r#"<sitemapindex>
+            <sitemap><loc>https://example.com/loop.xml</loc></sitemap>
+        </sitemapindex>"#;
+
+    #[test]
+    fn test_parse_sitemap_extracts_locs() {
+        assert_eq!(
+            parse_sitemap(SITEMAP_XML),
+            SitemapEntries::Pages(vec![
+                "https://example.com/a".to_string(),
+                "https://example.com/b".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_sitemap_recognizes_sitemap_index() {
+        assert_eq!(
+            parse_sitemap(INDEX_XML),
+            SitemapEntries::Index(vec!["https://example.com/pages.xml".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sitemap_follows_sitemap_index() {
+        let client = MockHttpClient::new()
+            .with_response("https://example.com/sitemap.xml", INDEX_XML)
+            .with_response("https://example.com/pages.xml", SITEMAP_XML);
+        let cache = RobotsCache::new();
+
+        let result = fetch_sitemap_with_client(&client, &cache, "https://example.com/")
+            .await
+            .unwrap();
+
+        assert_eq!(result, ["https://example.com/a", "https://example.com/b"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sitemap_stops_following_nested_indexes() {
+        let client =
+            MockHttpClient::new().with_response("https://example.com/loop.xml", SELF_INDEX_XML);
+        let cache = RobotsCache::new();
+
+        let result = fetch_sitemap_with_client(&client, &cache, "https://example.com/loop.xml")
+            .await
+            .unwrap();
+
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sitemap_discovers_sitemap_via_robots_txt() {
+        let client = MockHttpClient::new()
+            .with_response(
+                "https://example.com/robots.txt",
+                "User-agent: *\nAllow: /\nSitemap: https://example.com/pages.xml",
+            )
+            .with_response("https://example.com/pages.xml", SITEMAP_XML);
+        let cache = RobotsCache::new();
+
+        let result = fetch_sitemap_with_client(&client, &cache, "https://example.com/")
+            .await
+            .unwrap();
+
+        assert_eq!(result, ["https://example.com/a", "https://example.com/b"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sitemap_falls_back_to_default_location() {
+        let client =
+            MockHttpClient::new().with_response("https://example.com/sitemap.xml", SITEMAP_XML);
+        let cache = RobotsCache::new();
+
+        let result = fetch_sitemap_with_client(&client, &cache, "https://example.com/page")
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sitemap_errors_when_no_sitemap_found() {
+        let client = MockHttpClient::new();
+        let cache = RobotsCache::new();
+
+        let result = fetch_sitemap_with_client(&client, &cache, "https://example.com/").await;
+
+        assert!(result.is_err());
+    }
+}
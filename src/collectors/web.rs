@@ -1,8 +1,19 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
 use anyhow::{bail, Ok, Result};
-use reqwest;
+use encoding_rs::{Encoding, UTF_8};
+use futures::stream::{self, StreamExt};
+use reqwest::{self, Url};
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
 
+use super::access_wall::{body_text, detect_access_wall, AccessWall};
+use super::content_selectors::ContentSelectors;
+use super::credentials::FetchCredentials;
+use super::fetch_limit::FetchLimiter;
+use super::language::{detect_language, normalize_lang_attr};
 use super::robots::RobotsCache;
 
 /// Represents parsed content from a web page
@@ -14,50 +25,565 @@ pub struct PageContent {
     pub title: Option<String>,
     /// The main text content of the page
     pub text: String,
+    /// Outbound links found on the page, resolved to absolute URLs
+    pub links: Vec<Link>,
+    /// `<h1>`-`<h3>` headings in document order, as a table of contents
+    #[serde(default)]
+    pub headings: Vec<Heading>,
+    /// Selected `<meta>` values keyed by name/property (see `METADATA_KEYS`)
+    pub metadata: HashMap<String, String>,
+    /// ISO 639-1 code of the text's language, falling back to `<html lang>`
+    /// when the text alone is inconclusive
+    #[serde(default)]
+    pub detected_language: Option<String>,
+    /// The HTML the page was parsed from; only kept when
+    /// requested (for debugging extraction), and bounded by `FETCH_MAX_BYTES`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_html: Option<String>,
+    /// Set when the page is a paywall, login or consent wall, or an
+    /// "access denied" page rather than the content that was asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_wall: Option<AccessWall>,
+}
+
+/// A hyperlink extracted from a web page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    /// Absolute URL the link points to
+    pub href: String,
+    /// The visible anchor text
+    pub text: String,
 }
 
-/// User-Agent string used for all HTTP requests
-pub(crate) const USER_AGENT: &str = "copal/0.1.0";
+/// A section heading extracted from a web page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heading {
+    /// Heading level: 1 for `<h1>` through 3 for `<h3>`
+    pub level: u8,
+    /// The heading text, whitespace-collapsed
+    pub text: String,
+    /// The heading's `id` (or that of an anchor inside it), usable as a `#fragment`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+}
+
+/// Default User-Agent string used for HTTP requests and robots.txt matching
+pub(crate) const DEFAULT_USER_AGENT: &str = "copal/0.1.0";
+
+/// `<meta name=...>` / `<meta property=...>` keys captured into `PageContent::metadata`
+const METADATA_KEYS: &[&str] = &[
+    "description",
+    "author",
+    "og:title",
+    "og:description",
+    "og:site_name",
+    "article:published_time",
+];
+
+/// Default number of pages fetched concurrently by `fetch_urls`
+pub(crate) const DEFAULT_FETCH_CONCURRENCY: usize = 4;
+
+/// Default timeout for a single HTTP request
+pub(crate) const DEFAULT_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed before a fetch is abandoned
+const MAX_REDIRECTS: usize = 10;
+
+/// Classified reason a fetch failed.
+///
+/// Collectors return these wrapped in `anyhow::Error`; recover the variant with
+/// `err.downcast_ref::<FetchFailure>()`. Failures that don't fit any variant
+/// (e.g. an oversized body) are plain `anyhow` errors.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum FetchFailure {
+    #[error("Access to {0} is prohibited by robots.txt")]
+    RobotsDisallowed(String),
+    #[error("request timed out")]
+    Timeout,
+    #[error("server responded with HTTP status {0}")]
+    HttpStatus(u16),
+    #[error("unsupported content type: {0}")]
+    UnsupportedContentType(String),
+    #[error("network error: {0}")]
+    Network(String),
+    #[error("the server returned a {0} instead of the page")]
+    AccessWall(AccessWall),
+    #[error(
+        "only {0} characters of text were extracted; the page is likely rendered with JavaScript"
+    )]
+    TooLittleText(usize),
+}
+
+impl From<reqwest::Error> for FetchFailure {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            FetchFailure::Timeout
+        } else {
+            FetchFailure::Network(e.to_string())
+        }
+    }
+}
+
+/// Default maximum response body size (5MB)
+pub(crate) const DEFAULT_FETCH_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+/// HTML pages with less visible body text than this are reported as
+/// `FetchFailure::TooLittleText` unless `FETCH_MIN_TEXT_CHARS` says otherwise
+pub(crate) const DEFAULT_FETCH_MIN_TEXT_CHARS: usize = 50;
+
+/// A fetched page body, by how it should be read
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Document {
+    /// An HTML page to be parsed
+    Html(String),
+    /// A plain-text or Markdown file to be used verbatim
+    PlainText(String),
+}
 
 /// Trait for HTTP client abstraction (enables mocking in tests)
 pub(crate) trait HttpClient {
     async fn get(&self, url: &str) -> Result<String>;
+
+    /// Fetch a page that will be parsed as HTML.
+    /// Implementations that know the content type should reject non-HTML responses.
+    async fn get_html(&self, url: &str) -> Result<String> {
+        self.get(url).await
+    }
+
+    /// Fetch a page as HTML or, for `.txt`/`.md` files, as plain text.
+    /// Implementations that know the content type should use it instead of the URL.
+    async fn get_document(&self, url: &str) -> Result<Document> {
+        if has_plain_text_extension(url) {
+            self.get(url).await.map(Document::PlainText)
+        } else {
+            self.get_html(url).await.map(Document::Html)
+        }
+    }
+
+    /// The selector for the container holding the main content of pages on
+    /// `url`'s host (see `CONTENT_SELECTORS`), if one is configured
+    fn content_selector(&self, _url: &str) -> Option<&Selector> {
+        None
+    }
+}
+
+/// Build the `reqwest::Client` used for page fetches.
+/// The User-Agent, timeout, redirect and compression settings all live here;
+/// proxies are taken from the standard `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY` variables.
+pub(crate) fn build_http_client(user_agent: &str, timeout: Duration) -> Result<reqwest::Client> {
+    // Advertise Accept-Encoding and transparently decompress bodies; some
+    // sites are slow or misbehave when asked for an uncompressed page
+    let client = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .build()?;
+    Ok(client)
+}
+
+/// `HttpClient` backed by a shared `reqwest::Client`.
+/// Clones share the client, so connections and DNS lookups are pooled,
+/// and the fetch limiter, so the bound on requests in flight is shared too.
+#[derive(Clone)]
+pub(crate) struct ReqwestClient {
+    /// Responses larger than this are aborted instead of buffered
+    max_bytes: usize,
+    /// HTML pages yielding fewer characters of text than this are treated as failed
+    min_text_chars: usize,
+    /// `Authorization` headers for protected hosts
+    credentials: FetchCredentials,
+    /// Main-content containers for hosts whose pages need one
+    content_selectors: ContentSelectors,
+    client: Arc<reqwest::Client>,
+    /// Bound on requests in flight at once (unlimited unless set)
+    limiter: FetchLimiter,
+}
+
+impl ReqwestClient {
+    /// Create a client with the default User-Agent (`DEFAULT_USER_AGENT`), timeout
+    /// (`DEFAULT_FETCH_TIMEOUT`), body limit and minimum page text, without
+    /// per-host credentials or content selectors.
+    pub(crate) fn new() -> Self {
+        Self::with_settings(DEFAULT_USER_AGENT, DEFAULT_FETCH_TIMEOUT)
+    }
+
+    /// Like `new`, sending `user_agent` (see `USER_AGENT`) and giving up on
+    /// requests after `timeout` (see `FETCH_TIMEOUT_SECS`)
+    pub(crate) fn with_settings(user_agent: &str, timeout: Duration) -> Self {
+        let client = build_http_client(user_agent, timeout).expect("Failed to build HTTP client");
+        Self::with_client(Arc::new(client))
+    }
+
+    /// Send requests through `client` instead of one built from the environment.
+    /// The body limit and minimum text length start at their defaults, with
+    /// no per-host credentials or content selectors.
+    pub(crate) fn with_client(client: Arc<reqwest::Client>) -> Self {
+        Self {
+            max_bytes: DEFAULT_FETCH_MAX_BYTES,
+            min_text_chars: DEFAULT_FETCH_MIN_TEXT_CHARS,
+            credentials: FetchCredentials::default(),
+            content_selectors: ContentSelectors::default(),
+            client,
+            limiter: FetchLimiter::unlimited(),
+        }
+    }
+
+    /// Abort responses larger than `bytes` instead of buffering them (see `FETCH_MAX_BYTES`)
+    pub(crate) fn with_max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Treat HTML pages with less visible text than `chars` as failed (see `FETCH_MIN_TEXT_CHARS`)
+    pub(crate) fn with_min_text_chars(mut self, chars: usize) -> Self {
+        self.min_text_chars = chars;
+        self
+    }
+
+    /// Send `credentials` to the hosts they are configured for (see `FETCH_CREDENTIALS`)
+    pub(crate) fn with_credentials(mut self, credentials: FetchCredentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Extract page text from the containers in `selectors` (see `CONTENT_SELECTORS`)
+    pub(crate) fn with_content_selectors(mut self, selectors: ContentSelectors) -> Self {
+        self.content_selectors = selectors;
+        self
+    }
+
+    /// Hold a slot of `limiter` for each request
+    pub(crate) fn with_limiter(mut self, limiter: FetchLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    pub(crate) fn limiter(&self) -> &FetchLimiter {
+        &self.limiter
+    }
+
+    /// Process-wide client with the default settings, built on first use.
+    /// Sharing it lets every fetch reuse the same connection pool.
+    pub(crate) fn shared() -> &'static Self {
+        static SHARED: OnceLock<ReqwestClient> = OnceLock::new();
+        SHARED.get_or_init(Self::new)
+    }
+
+    /// Fetch `url` and return the raw body with its `Content-Type` header.
+    /// Network failures, timeouts and non-2xx statuses are reported as `FetchFailure`.
+    /// Waits for a slot of the fetch limiter first and holds it until the body is read.
+    async fn fetch(&self, url: &str) -> Result<(Vec<u8>, Option<String>)> {
+        let _permit = self.limiter.acquire().await;
+        let mut request = self.client.get(url);
+        if let Some(credential) = self.credentials.for_url(url) {
+            request = credential.apply(request);
+        }
+        let mut response = request.send().await.map_err(FetchFailure::from)?;
+        if !response.status().is_success() {
+            return Err(FetchFailure::HttpStatus(response.status().as_u16()).into());
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        // Reject early when the server announces an oversized body
+        if let Some(len) = response.content_length() {
+            if len > self.max_bytes as u64 {
+                bail!(
+                    "response body exceeds {} limit",
+                    format_byte_size(self.max_bytes)
+                );
+            }
+        }
+
+        // Stream the body chunk by chunk so we never buffer more than the limit
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(FetchFailure::from)? {
+            append_limited(&mut body, &chunk, self.max_bytes)?;
+        }
+
+        Ok((body, content_type))
+    }
 }
 
-pub(crate) struct ReqwestClient;
+/// Whether a `Content-Type` header value denotes an HTML document
+fn is_html_content_type(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime == "text/html" || mime == "application/xhtml+xml"
+}
+
+/// Whether a `Content-Type` header value denotes a plain-text or Markdown file
+fn is_plain_text_content_type(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    matches!(
+        mime.as_str(),
+        "text/plain" | "text/markdown" | "text/x-markdown"
+    )
+}
+
+/// Whether the URL's path ends in `.txt`, `.md` or `.markdown`
+fn has_plain_text_extension(url: &str) -> bool {
+    let Some(path) = Url::parse(url).ok().map(|u| u.path().to_ascii_lowercase()) else {
+        return false;
+    };
+    [".txt", ".md", ".markdown"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
 
 impl HttpClient for ReqwestClient {
     async fn get(&self, url: &str) -> Result<String> {
-        let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
-        let response = client.get(url).send().await?;
-        let text = response.text().await?;
+        let (body, content_type) = self.fetch(url).await?;
+        Ok(decode_body(&body, content_type.as_deref()))
+    }
+
+    async fn get_html(&self, url: &str) -> Result<String> {
+        let (body, content_type) = self.fetch(url).await?;
+        // A missing Content-Type is given the benefit of the doubt
+        if let Some(ct) = content_type
+            .as_deref()
+            .filter(|ct| !is_html_content_type(ct))
+        {
+            return Err(FetchFailure::UnsupportedContentType(ct.to_string()).into());
+        }
+        Ok(decode_body(&body, content_type.as_deref()))
+    }
+
+    async fn get_document(&self, url: &str) -> Result<Document> {
+        let (body, content_type) = self.fetch(url).await?;
+        let text = decode_body(&body, content_type.as_deref());
+        match content_type.as_deref() {
+            Some(ct) if is_html_content_type(ct) => Ok(Document::Html(text)),
+            Some(ct) if is_plain_text_content_type(ct) => Ok(Document::PlainText(text)),
+            // Raw file hosts often serve .md files as e.g. application/octet-stream
+            _ if has_plain_text_extension(url) => Ok(Document::PlainText(text)),
+            // A missing Content-Type is given the benefit of the doubt
+            None => Ok(Document::Html(text)),
+            Some(ct) => Err(FetchFailure::UnsupportedContentType(ct.to_string()).into()),
+        }
+    }
+
+    fn content_selector(&self, url: &str) -> Option<&Selector> {
+        self.content_selectors.for_url(url)
+    }
+}
+
+/// Decode a response body to a String using the page's declared charset.
+///
+/// The charset is taken from the `Content-Type` header first, then from a
+/// `<meta charset>` / `<meta http-equiv="Content-Type">` tag near the top of
+/// the document. Unknown or missing charsets fall back to lossy UTF-8.
+fn decode_body(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(charset_from_content_type)
+        .or_else(|| charset_from_meta(body))
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+/// Extract the `charset=` parameter from a Content-Type value,
+/// e.g. "text/html; charset=Shift_JIS" -> "Shift_JIS"
+fn charset_from_content_type(content_type: &str) -> Option<String> {
+    content_type.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("charset") {
+            Some(
+                value
+                    .trim()
+                    .trim_matches(|c| c == '"' || c == '\'')
+                    .to_string(),
+            )
+        } else {
+            None
+        }
+    })
+}
 
-        Ok(text)
+/// Look for a charset declaration in the first 1024 bytes of an HTML document.
+/// Covers both `<meta charset="...">` and `<meta http-equiv=... content="...; charset=...">`.
+fn charset_from_meta(body: &[u8]) -> Option<String> {
+    let head = &body[..body.len().min(1024)];
+    // Charset declarations are ASCII, so a lossy view is safe to search
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+
+    let start = head.find("charset=")? + "charset=".len();
+    let value: String = head[start..]
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+        .collect();
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Append `chunk` to `body`, failing if the result would exceed `max_bytes`.
+fn append_limited(body: &mut Vec<u8>, chunk: &[u8], max_bytes: usize) -> Result<()> {
+    if body.len() + chunk.len() > max_bytes {
+        bail!(
+            "response body exceeds {} limit",
+            format_byte_size(max_bytes)
+        );
     }
+    body.extend_from_slice(chunk);
+    Ok(())
 }
 
-pub(crate) async fn fetch_url(url: &str, robots_cache: &RobotsCache) -> Result<PageContent> {
-    let request_client = ReqwestClient {};
-    let page_content = fetch_url_with_client(&request_client, robots_cache, url).await?;
+/// Format a byte count for error messages, e.g. 5242880 -> "5MB"
+fn format_byte_size(bytes: usize) -> String {
+    const MB: usize = 1024 * 1024;
+    if bytes >= MB && bytes.is_multiple_of(MB) {
+        format!("{}MB", bytes / MB)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+/// Fetch and parse `url` through `client`. With `include_raw`, the page's HTML is kept in `raw_html`.
+pub(crate) async fn fetch_url(
+    client: &ReqwestClient,
+    url: &str,
+    robots_cache: &RobotsCache,
+    include_raw: bool,
+) -> Result<PageContent> {
+    let page_content = fetch_url_with_client(
+        client,
+        robots_cache,
+        url,
+        include_raw,
+        client.min_text_chars,
+    )
+    .await?;
 
     Ok(page_content)
 }
 
-/// Fetch URL content using the provided HTTP client
+/// Fetch multiple URLs concurrently, at most `concurrency` at a time.
+///
+/// Each URL gets its own `Result`, so one failing page does not fail the batch.
+/// Results are returned in the same order as `urls`.
+pub(crate) async fn fetch_urls(
+    client: &ReqwestClient,
+    urls: &[String],
+    robots_cache: &RobotsCache,
+    concurrency: usize,
+) -> Vec<(String, Result<PageContent>)> {
+    fetch_urls_with_client(
+        client,
+        robots_cache,
+        urls,
+        concurrency,
+        client.min_text_chars,
+    )
+    .await
+}
+
+async fn fetch_urls_with_client<C: HttpClient>(
+    client: &C,
+    robots_cache: &RobotsCache,
+    urls: &[String],
+    concurrency: usize,
+    min_text_chars: usize,
+) -> Vec<(String, Result<PageContent>)> {
+    let mut results: Vec<(usize, String, Result<PageContent>)> =
+        stream::iter(urls.iter().cloned().enumerate())
+            .map(|(i, url)| async move {
+                let result =
+                    fetch_url_with_client(client, robots_cache, &url, false, min_text_chars).await;
+                (i, url, result)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+
+    results.sort_by_key(|(i, _, _)| *i);
+    results
+        .into_iter()
+        .map(|(_, url, result)| (url, result))
+        .collect()
+}
+
+/// Fetch URL content using the provided HTTP client.
+/// HTML pages whose visible body text (scripts and styles aside) is shorter
+/// than `min_text_chars` characters fail with `FetchFailure::TooLittleText`:
+/// the content is most likely filled in by JavaScript, which a static fetch
+/// never runs. With `include_raw` such a page is returned anyway, so its HTML
+/// can be inspected.
 async fn fetch_url_with_client<C: HttpClient>(
     client: &C,
     robots_cache: &RobotsCache,
     url: &str,
+    include_raw: bool,
+    min_text_chars: usize,
 ) -> Result<PageContent> {
     if !robots_cache.is_allowed(client, url).await {
-        bail!("Access to {} is prohibited by robots.txt", url);
+        return Err(FetchFailure::RobotsDisallowed(url.to_string()).into());
+    }
+    robots_cache.wait_for_turn(url).await;
+
+    match client.get_document(url).await? {
+        Document::Html(html) => {
+            let mut page = parse_html(url, &html, client.content_selector(url));
+            if let Some(wall) = page.access_wall {
+                return Err(FetchFailure::AccessWall(wall).into());
+            }
+            // Paragraph text is the cheap check; pages without enough of it
+            // may still carry their text in `<div>`s, lists or tables
+            if !include_raw && page.text.trim().chars().count() < min_text_chars {
+                let chars = body_text(&Html::parse_document(&html)).chars().count();
+                if chars < min_text_chars {
+                    return Err(FetchFailure::TooLittleText(chars).into());
+                }
+            }
+            page.raw_html = include_raw.then_some(html);
+            Ok(page)
+        }
+        Document::PlainText(text) => Ok(plain_text_page(url, text)),
     }
+}
 
-    let html = client.get(url).await?;
-    Ok(parse_html(url, &html))
+/// Wrap a plain-text or Markdown body as a page, keeping the text verbatim
+fn plain_text_page(url: &str, text: String) -> PageContent {
+    PageContent {
+        url: url.to_string(),
+        title: None,
+        detected_language: detect_language(&text).map(str::to_string),
+        text,
+        links: Vec::new(),
+        headings: Vec::new(),
+        metadata: HashMap::new(),
+        raw_html: None,
+        access_wall: None,
+    }
 }
 
-fn parse_html(url: &str, html: &str) -> PageContent {
+/// Parse an HTML page. The body text is the page's `<p>` elements; with a
+/// `content_selector`, only those inside the matching container(s), or the
+/// containers' whole text when they have no paragraphs. A selector that
+/// matches nothing falls back to the whole page. Pages standing in for the
+/// content (paywalls, login walls, ...) are flagged in `access_wall`.
+fn parse_html(url: &str, html: &str, content_selector: Option<&Selector>) -> PageContent {
     let document = Html::parse_document(html);
 
     // Extract title
@@ -69,22 +595,148 @@ fn parse_html(url: &str, html: &str) -> PageContent {
 
     // Extract <p> tag (body text)
     let p_selector = Selector::parse("p").unwrap();
-    let body = document
-        .select(&p_selector)
-        .map(|element| element.text().collect())
-        .collect::<Vec<String>>()
-        .join("\n\n");
+    let body = content_selector
+        .and_then(|selector| container_text(&document, selector, &p_selector))
+        .unwrap_or_else(|| {
+            document
+                .select(&p_selector)
+                .map(|element| element.text().collect())
+                .collect::<Vec<String>>()
+                .join("\n\n")
+        });
+
+    let html_selector = Selector::parse("html[lang]").unwrap();
+    let detected_language = detect_language(&body).map(str::to_string).or_else(|| {
+        document
+            .select(&html_selector)
+            .next()
+            .and_then(|html| normalize_lang_attr(html.value().attr("lang")?))
+    });
+    let access_wall = detect_access_wall(&document);
 
     PageContent {
         url: url.to_string(),
         title,
         text: body,
+        links: extract_links(url, &document),
+        headings: extract_headings(&document),
+        metadata: extract_metadata(&document),
+        detected_language,
+        raw_html: None,
+        access_wall,
+    }
+}
+
+/// Text of the elements matching `container`: their paragraphs, or their
+/// whitespace-collapsed text if they contain none. `None` if nothing matches.
+fn container_text(document: &Html, container: &Selector, p_selector: &Selector) -> Option<String> {
+    let containers: Vec<_> = document.select(container).collect();
+    if containers.is_empty() {
+        return None;
+    }
+    let paragraphs: Vec<String> = containers
+        .iter()
+        .flat_map(|element| element.select(p_selector))
+        .map(|element| element.text().collect())
+        .collect();
+    if !paragraphs.is_empty() {
+        return Some(paragraphs.join("\n\n"));
     }
+    let texts: Vec<String> = containers
+        .iter()
+        .map(|element| element.text().collect::<Vec<_>>().join(" "))
+        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    Some(texts.join("\n\n"))
+}
+
+/// Collect the `<meta>` values listed in `METADATA_KEYS`.
+/// Both `name` (e.g. description) and `property` (e.g. OpenGraph) attributes are matched.
+/// The first occurrence of a key wins.
+fn extract_metadata(document: &Html) -> HashMap<String, String> {
+    let meta_selector = Selector::parse("meta[content]").unwrap();
+    let mut metadata = HashMap::new();
+
+    for element in document.select(&meta_selector) {
+        let attrs = element.value();
+        let key = match attrs.attr("property").or_else(|| attrs.attr("name")) {
+            Some(k) => k.to_ascii_lowercase(),
+            None => continue,
+        };
+        if !METADATA_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let content = attrs.attr("content").unwrap_or_default().trim();
+        if !content.is_empty() {
+            metadata.entry(key).or_insert_with(|| content.to_string());
+        }
+    }
+
+    metadata
+}
+
+/// Collect `<a href>` links, resolving relative hrefs against `base_url`.
+/// Non-HTTP links (mailto:, javascript:, ...) are skipped.
+fn extract_links(base_url: &str, document: &Html) -> Vec<Link> {
+    let base = Url::parse(base_url).ok();
+    let a_selector = Selector::parse("a[href]").unwrap();
+
+    document
+        .select(&a_selector)
+        .filter_map(|element| {
+            let href = element.value().attr("href")?;
+            let resolved = match &base {
+                Some(b) => b.join(href).ok()?,
+                None => Url::parse(href).ok()?,
+            };
+            if !matches!(resolved.scheme(), "http" | "https") {
+                return None;
+            }
+            Some(Link {
+                href: resolved.to_string(),
+                text: element.text().collect::<String>().trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Collect `<h1>`-`<h3>` headings in document order. Headings without text
+/// (e.g. icon-only) are skipped.
+fn extract_headings(document: &Html) -> Vec<Heading> {
+    let heading_selector = Selector::parse("h1, h2, h3").unwrap();
+    let anchor_selector = Selector::parse("[id], a[name]").unwrap();
+
+    document
+        .select(&heading_selector)
+        .filter_map(|element| {
+            let text = element.text().collect::<Vec<_>>().join(" ");
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if text.is_empty() {
+                return None;
+            }
+            let level = element.value().name()[1..].parse().ok()?;
+            let anchor = element.value().id().map(str::to_string).or_else(|| {
+                element.select(&anchor_selector).find_map(|inner| {
+                    let inner = inner.value();
+                    inner
+                        .id()
+                        .or_else(|| inner.attr("name"))
+                        .map(str::to_string)
+                })
+            });
+            Some(Heading {
+                level,
+                text,
+                anchor,
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::collectors::rate_limit::DomainRateLimiter;
 
     #[test]
     fn test_parse_html_extracts_title() {
@@ -97,7 +749,7 @@ mod tests {
         "#;
 
         // Act
-        let result = parse_html("https://example.com", html);
+        let result = parse_html("https://example.com", html, None);
 
         // Assert
         assert_eq!(result.title, Some("Test Page".to_string()));
@@ -116,11 +768,270 @@ mod tests {
             </html>
         "#;
 
-        let result = parse_html("https://example.com", html);
+        let result = parse_html("https://example.com", html, None);
 
         assert_eq!(result.text, "First paragraph\n\nSecond paragraph");
     }
 
+    #[test]
+    fn test_parse_html_with_content_selector_extracts_only_that_region() {
+        let html = r#"
+            <html>
+                <body>
+                    <nav><p>Home | Docs | Blog</p></nav>
+                    <article class="post"><p>Main point.</p><p>Details.</p></article>
+                    <footer><p>Copyright</p></footer>
+                </body>
+            </html>
+        "#;
+        let selector = Selector::parse("article.post").unwrap();
+
+        let result = parse_html("https://example.com", html, Some(&selector));
+
+        assert_eq!(result.text, "Main point.\n\nDetails.");
+    }
+
+    #[test]
+    fn test_parse_html_content_selector_without_paragraphs_uses_container_text() {
+        let html = r#"<body><p>Sidebar</p><div id="content">
+            <h1>Install</h1>   <pre>cargo add copal</pre></div></body>"#;
+        let selector = Selector::parse("#content").unwrap();
+
+        let result = parse_html("https://example.com", html, Some(&selector));
+
+        assert_eq!(result.text, "Install cargo add copal");
+    }
+
+    #[test]
+    fn test_parse_html_unmatched_content_selector_falls_back_to_paragraphs() {
+        let html = "<body><p>First</p><p>Second</p></body>";
+        let selector = Selector::parse("main").unwrap();
+
+        let result = parse_html("https://example.com", html, Some(&selector));
+
+        assert_eq!(result.text, "First\n\nSecond");
+    }
+
+    #[test]
+    fn test_append_limited_passes_small_chunks_unchanged() {
+        let mut body = Vec::new();
+
+        append_limited(&mut body, b"hello ", 16).unwrap();
+        append_limited(&mut body, b"world", 16).unwrap();
+
+        assert_eq!(body, b"hello world");
+    }
+
+    #[test]
+    fn test_append_limited_fails_when_limit_exceeded() {
+        let mut body = Vec::new();
+        append_limited(&mut body, &[0u8; 1024 * 1024], DEFAULT_FETCH_MAX_BYTES).unwrap();
+
+        let result = append_limited(&mut body, &[0u8; 5 * 1024 * 1024], DEFAULT_FETCH_MAX_BYTES);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "response body exceeds 5MB limit"
+        );
+        // The oversized chunk must not be buffered
+        assert_eq!(body.len(), 1024 * 1024);
+    }
+
+    /// "日本語のページ" encoded as Shift_JIS
+    const SHIFT_JIS_TEXT: &[u8] = &[
+        0x93, 0xfa, 0x96, 0x7b, 0x8c, 0xea, 0x82, 0xcc, 0x83, 0x79, 0x81, 0x5b, 0x83, 0x57,
+    ];
+
+    #[test]
+    fn test_decode_body_uses_content_type_charset() {
+        let result = decode_body(SHIFT_JIS_TEXT, Some("text/html; charset=Shift_JIS"));
+
+        assert_eq!(result, "日本語のページ");
+    }
+
+    #[test]
+    fn test_decode_body_uses_meta_charset() {
+        let mut html = b"<html><head><meta charset=\"shift_jis\"></head><body><p>".to_vec();
+        html.extend_from_slice(SHIFT_JIS_TEXT);
+        html.extend_from_slice(b"</p></body></html>");
+
+        let result = decode_body(&html, Some("text/html"));
+
+        assert!(result.contains("日本語のページ"));
+    }
+
+    #[test]
+    fn test_decode_body_uses_meta_http_equiv_charset() {
+        let mut html =
+            b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=Shift_JIS\"><p>"
+                .to_vec();
+        html.extend_from_slice(SHIFT_JIS_TEXT);
+
+        let result = decode_body(&html, None);
+
+        assert!(result.contains("日本語のページ"));
+    }
+
+    #[test]
+    fn test_decode_body_decodes_latin1() {
+        // "café" in ISO-8859-1
+        let result = decode_body(b"caf\xe9", Some("text/html; charset=ISO-8859-1"));
+
+        assert_eq!(result, "café");
+    }
+
+    #[test]
+    fn test_decode_body_falls_back_to_lossy_utf8() {
+        let result = decode_body(b"ok \xff", Some("text/html; charset=unknown-charset"));
+
+        assert_eq!(result, "ok \u{fffd}");
+    }
+
+    #[tokio::test]
+    async fn test_configured_user_agent_flows_to_client_and_robots() {
+        let client = ReqwestClient::with_settings("copal-test-bot/1.0", DEFAULT_FETCH_TIMEOUT);
+        let robots_cache = RobotsCache::new().with_user_agent("copal-test-bot/1.0");
+
+        // Request UA
+        let (url, request) = capture_request().await;
+        client.get(&url).await.unwrap();
+        assert!(
+            request
+                .await
+                .unwrap()
+                .contains("user-agent: copal-test-bot/1.0"),
+            "request should carry the configured User-Agent"
+        );
+
+        // robots.txt rules are matched against the same UA
+        let mock_client = MockHttpClient::new().with_response(
+            "https://example.com/robots.txt",
+            "User-agent: copal-test-bot\nDisallow: /\n\nUser-agent: *\nAllow: /",
+        );
+        assert!(
+            !robots_cache
+                .is_allowed(&mock_client, "https://example.com/page")
+                .await
+        );
+    }
+
+    #[test]
+    fn test_format_byte_size() {
+        assert_eq!(format_byte_size(5 * 1024 * 1024), "5MB");
+        assert_eq!(format_byte_size(1500), "1500 bytes");
+    }
+
+    #[test]
+    fn test_parse_html_resolves_relative_and_absolute_links() {
+        let html = r#"
+            <html>
+                <body>
+                    <a href="/about">About</a>
+                    <a href="next.html"> Next </a>
+                    <a href="https://other.example.org/page">Other</a>
+                </body>
+            </html>
+        "#;
+
+        let result = parse_html("https://example.com/docs/index.html", html, None);
+
+        assert_eq!(
+            result.links,
+            vec![
+                Link {
+                    href: "https://example.com/about".to_string(),
+                    text: "About".to_string(),
+                },
+                Link {
+                    href: "https://example.com/docs/next.html".to_string(),
+                    text: "Next".to_string(),
+                },
+                Link {
+                    href: "https://other.example.org/page".to_string(),
+                    text: "Other".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_html_skips_non_http_links() {
+        let html = r#"
+            <a href="mailto:someone@example.com">Mail</a>
+            <a href="javascript:void(0)">Click</a>
+        "#;
+
+        let result = parse_html("https://example.com", html, None);
+
+        assert!(result.links.is_empty());
+    }
+
+    #[test]
+    fn test_parse_html_extracts_opengraph_metadata() {
+        let html = r#"
+            <html>
+                <head>
+                    <meta name="description" content="A page description">
+                    <meta property="og:title" content="OG Title">
+                    <meta property="og:description" content="OG Description">
+                    <meta property="article:published_time" content="2024-05-01T09:00:00Z">
+                    <meta name="viewport" content="width=device-width">
+                </head>
+                <body><p>Body</p></body>
+            </html>
+        "#;
+
+        let result = parse_html("https://example.com", html, None);
+
+        assert_eq!(result.metadata["description"], "A page description");
+        assert_eq!(result.metadata["og:title"], "OG Title");
+        assert_eq!(result.metadata["og:description"], "OG Description");
+        assert_eq!(
+            result.metadata["article:published_time"],
+            "2024-05-01T09:00:00Z"
+        );
+        assert!(!result.metadata.contains_key("viewport"));
+    }
+
+    #[test]
+    fn test_parse_html_without_metadata_yields_empty_map() {
+        let html = "<html><body><p>No meta here</p></body></html>";
+
+        let result = parse_html("https://example.com", html, None);
+
+        assert!(result.metadata.is_empty());
+    }
+
+    #[test]
+    fn test_parse_html_detects_language_from_text() {
+        let english = r#"<html lang="ja"><body><p>The quick brown fox jumps over the lazy dog,
+            and this is a page that is written in English.</p></body></html>"#;
+        let japanese = r#"<html><body><p>東京は日本の首都であり、世界有数の大都市である。
+            多くの人々がここで暮らしている。</p></body></html>"#;
+
+        assert_eq!(
+            parse_html("https://example.com", english, None)
+                .detected_language
+                .as_deref(),
+            Some("en")
+        );
+        assert_eq!(
+            parse_html("https://example.jp", japanese, None)
+                .detected_language
+                .as_deref(),
+            Some("ja")
+        );
+    }
+
+    #[test]
+    fn test_parse_html_falls_back_to_lang_attribute() {
+        let html = r#"<html lang="de-DE"><body><p>Kurzer Text</p></body></html>"#;
+
+        let result = parse_html("https://example.de", html, None);
+
+        assert_eq!(result.detected_language.as_deref(), Some("de"));
+    }
+
     #[test]
     fn test_parse_html_handles_missing_title() {
         // HTML without <title> tag should return None
@@ -130,11 +1041,53 @@ mod tests {
             </html>
         "#;
 
-        let result = parse_html("https://example.com", html);
+        let result = parse_html("https://example.com", html, None);
 
         assert_eq!(result.title, None);
     }
 
+    #[test]
+    fn test_parse_html_extracts_nested_headings_in_order() {
+        let html = r#"
+            <html><body>
+                <h1 id="guide">Rust <em>Guide</em></h1>
+                <section>
+                    <h2 id="install">Installing</h2>
+                    <h3>On  Linux</h3>
+                    <h4>Not part of the table of contents</h4>
+                    <h3><a name="macos"></a>On macOS</h3>
+                </section>
+                <h2 id="empty"><img src="icon.png"></h2>
+                <h2>Next steps</h2>
+            </body></html>
+        "#;
+
+        let result = parse_html("https://example.com", html, None);
+
+        let toc: Vec<(u8, &str, Option<&str>)> = result
+            .headings
+            .iter()
+            .map(|h| (h.level, h.text.as_str(), h.anchor.as_deref()))
+            .collect();
+        assert_eq!(
+            toc,
+            [
+                (1, "Rust Guide", Some("guide")),
+                (2, "Installing", Some("install")),
+                (3, "On Linux", None),
+                (3, "On macOS", Some("macos")),
+                (2, "Next steps", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_text_page_has_no_headings() {
+        let page = plain_text_page("https://example.com/notes.txt", "# Title".to_string());
+
+        assert!(page.headings.is_empty());
+    }
+
     /// Mock HTTP client for testing (supports URL-specific responses)
     struct MockHttpClient {
         responses: std::collections::HashMap<String, String>,
@@ -162,6 +1115,40 @@ mod tests {
         }
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_fetches_to_same_origin_are_rate_limited() {
+        let page = "<html><body><p>Content</p></body></html>";
+        let mock_client = MockHttpClient::new()
+            .with_response("https://example.com/robots.txt", "User-agent: *\nAllow: /")
+            .with_response("https://example.com/a", page)
+            .with_response("https://example.com/b", page);
+        let robots_cache = RobotsCache::new().with_rate_limiter(DomainRateLimiter::new(1.0));
+        let started = tokio::time::Instant::now();
+
+        fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/a",
+            false,
+            0,
+        )
+        .await
+        .unwrap();
+        let first = started.elapsed();
+        fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/b",
+            false,
+            0,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(first, Duration::ZERO);
+        assert_eq!(started.elapsed(), Duration::from_secs(1));
+    }
+
     #[tokio::test]
     async fn test_fetch_url_with_mock_client() {
         let mock_html =
@@ -171,14 +1158,201 @@ mod tests {
             .with_response("https://example.com", mock_html);
         let robots_cache = RobotsCache::new();
 
-        let result = fetch_url_with_client(&mock_client, &robots_cache, "https://example.com")
-            .await
-            .unwrap();
+        let result =
+            fetch_url_with_client(&mock_client, &robots_cache, "https://example.com", false, 0)
+                .await
+                .unwrap();
 
         assert_eq!(result.title, Some("Mock Page".to_string()));
         assert_eq!(result.text, "Mock content");
     }
 
+    #[tokio::test]
+    async fn test_fetch_paywalled_page_is_an_error() {
+        let paywall = r#"<html><head><title>Markets rally</title></head><body>
+            <p>Stocks climbed on Tuesday after...</p>
+            <div class="paywall">Subscribe to continue reading. Already a subscriber? Sign in</div>
+            </body></html>"#;
+        let mock_client = MockHttpClient::new()
+            .with_response("https://example.com/robots.txt", "User-agent: *\nAllow: /")
+            .with_response("https://example.com/markets", paywall);
+        let robots_cache = RobotsCache::new();
+
+        let err = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/markets",
+            false,
+            0,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<FetchFailure>(),
+            Some(FetchFailure::AccessWall(AccessWall::Paywall))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_script_rendered_page_is_an_error() {
+        let app_shell = r#"<html><head><title>Dashboard</title>
+            <script src="/static/app.js"></script></head>
+            <body><div id="root"></div><noscript>You need to enable JavaScript.</noscript></body></html>"#;
+        let mock_client = MockHttpClient::new()
+            .with_response("https://example.com/robots.txt", "User-agent: *\nAllow: /")
+            .with_response("https://example.com/app", app_shell);
+        let robots_cache = RobotsCache::new();
+
+        let err = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/app",
+            false,
+            DEFAULT_FETCH_MIN_TEXT_CHARS,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<FetchFailure>(),
+            Some(FetchFailure::TooLittleText(0))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_script_rendered_page_with_include_raw_returns_the_html() {
+        let app_shell =
+            r#"<html><body><div id="root"></div><script src="/app.js"></script></body></html>"#;
+        let mock_client = MockHttpClient::new()
+            .with_response("https://example.com/robots.txt", "User-agent: *\nAllow: /")
+            .with_response("https://example.com/app", app_shell);
+        let robots_cache = RobotsCache::new();
+
+        let page = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/app",
+            true,
+            DEFAULT_FETCH_MIN_TEXT_CHARS,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(page.raw_html.as_deref(), Some(app_shell));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_without_paragraphs_counts_its_visible_text() {
+        let html = r#"<html><head><title>Releases</title></head><body>
+            <ul><li>1.85: async closures and the 2024 edition</li>
+            <li>1.84: the MSRV-aware resolver and strict provenance APIs</li></ul>
+            <script>var padding = "</script>
+            </body></html>"#;
+        let mock_client = MockHttpClient::new()
+            .with_response("https://example.com/robots.txt", "User-agent: *\nAllow: /")
+            .with_response("https://example.com/releases", html);
+        let robots_cache = RobotsCache::new();
+
+        let result = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/releases",
+            false,
+            DEFAULT_FETCH_MIN_TEXT_CHARS,
+        )
+        .await;
+
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_with_enough_text_passes_min_length() {
+        let html = r#"<html><head><title>Ownership</title></head><body>
+            <p>Each value in Rust has an owner, and there can only be one owner at a time.</p>
+            </body></html>"#;
+        let mock_client = MockHttpClient::new()
+            .with_response("https://example.com/robots.txt", "User-agent: *\nAllow: /")
+            .with_response("https://example.com/ownership", html);
+        let robots_cache = RobotsCache::new();
+
+        let page = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/ownership",
+            false,
+            DEFAULT_FETCH_MIN_TEXT_CHARS,
+        )
+        .await
+        .unwrap();
+
+        assert!(page.text.starts_with("Each value in Rust"));
+    }
+
+    #[test]
+    fn test_parse_html_does_not_flag_normal_content() {
+        let html = r#"<html><head><title>Ownership</title></head><body>
+            <p>Each value in Rust has an owner.</p>
+            <p>When the owner goes out of scope, the value is dropped.</p>
+            </body></html>"#;
+
+        let page = parse_html("https://example.com/ownership", html, None);
+
+        assert_eq!(page.access_wall, None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_keeps_raw_html_only_when_requested() {
+        let html = r#"<html><body><div class="content">Not in a paragraph</div></body></html>"#;
+        let mock_client = MockHttpClient::new().with_response("https://example.com/page", html);
+        let robots_cache = RobotsCache::new();
+
+        let with_raw = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/page",
+            true,
+            0,
+        )
+        .await
+        .unwrap();
+        let without_raw = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/page",
+            false,
+            0,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(with_raw.raw_html.as_deref(), Some(html));
+        assert_eq!(without_raw.raw_html, None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_txt_url_returns_body_verbatim() {
+        let changelog = "# Changelog\n\n<b>1.0</b> - first release\n  * fixed <p> parsing\n";
+        let mock_client = MockHttpClient::new()
+            .with_response("https://example.com/robots.txt", "User-agent: *\nAllow: /")
+            .with_response("https://example.com/CHANGELOG.txt", changelog);
+        let robots_cache = RobotsCache::new();
+
+        let result = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/CHANGELOG.txt",
+            false,
+            0,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.text, changelog);
+        assert_eq!(result.title, None);
+        assert!(result.links.is_empty());
+    }
+
     #[tokio::test]
     async fn test_fetch_blocked_by_robots_txt() {
         let mock_client = MockHttpClient::new().with_response(
@@ -191,11 +1365,238 @@ mod tests {
             &mock_client,
             &robots_cache,
             "https://example.com/private/page",
+            false,
+            0,
         )
         .await;
 
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("robots.txt"));
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("robots.txt"));
+        assert_eq!(
+            err.downcast_ref::<FetchFailure>(),
+            Some(&FetchFailure::RobotsDisallowed(
+                "https://example.com/private/page".to_string()
+            ))
+        );
+    }
+
+    /// Serve `response` verbatim to the first connection on a local port
+    /// (or never reply when `response` is None). Returns the server's URL.
+    async fn serve_once(response: Option<&'static str>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            match response {
+                Some(response) => {
+                    let _ = socket.write_all(response.as_bytes()).await;
+                }
+                None => tokio::time::sleep(Duration::from_secs(5)).await,
+            }
+        });
+        format!("http://{}/", addr)
+    }
+
+    /// Serve one `ok` response and hand back the lowercased request it answered
+    async fn capture_request() -> (String, tokio::task::JoinHandle<String>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let request = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            let ok = "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nok";
+            socket.write_all(ok.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+        (url, request)
+    }
+
+    fn test_client() -> ReqwestClient {
+        let client = build_http_client(DEFAULT_USER_AGENT, Duration::from_millis(200)).unwrap();
+        ReqwestClient {
+            max_bytes: DEFAULT_FETCH_MAX_BYTES,
+            min_text_chars: 0,
+            credentials: FetchCredentials::default(),
+            content_selectors: ContentSelectors::default(),
+            client: Arc::new(client),
+            limiter: FetchLimiter::unlimited(),
+        }
+    }
+
+    fn failure_of(result: Result<String>) -> FetchFailure {
+        result
+            .unwrap_err()
+            .downcast_ref::<FetchFailure>()
+            .cloned()
+            .expect("error should be a FetchFailure")
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_client_reports_http_status() {
+        let url = serve_once(Some("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")).await;
+
+        let result = test_client().get_html(&url).await;
+
+        assert_eq!(failure_of(result), FetchFailure::HttpStatus(404));
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_client_reports_timeout() {
+        let url = serve_once(None).await;
+
+        let result = test_client().get_html(&url).await;
+
+        assert_eq!(failure_of(result), FetchFailure::Timeout);
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_client_rejects_non_html_page() {
+        let url = serve_once(Some(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/pdf\r\nContent-Length: 4\r\n\r\n%PDF",
+        ))
+        .await;
+
+        let result = test_client().get_html(&url).await;
+
+        assert_eq!(
+            failure_of(result),
+            FetchFailure::UnsupportedContentType("application/pdf".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_client_reads_markdown_as_plain_text() {
+        let url = serve_once(Some(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/markdown\r\nContent-Length: 7\r\n\r\n# <p>Hi",
+        ))
+        .await;
+
+        let result = test_client().get_document(&url).await.unwrap();
+
+        assert_eq!(result, Document::PlainText("# <p>Hi".to_string()));
+    }
+
+    /// `<html><body><p>Compressed page body</p></body></html>`, gzipped
+    const GZIPPED_PAGE: [u8; 64] = [
+        31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 179, 201, 40, 201, 205, 177, 179, 73, 202, 79, 169, 180,
+        179, 41, 176, 115, 206, 207, 45, 40, 74, 45, 46, 78, 77, 81, 40, 72, 76, 79, 85, 0, 137,
+        219, 232, 23, 216, 217, 232, 67, 84, 232, 131, 149, 3, 0, 170, 160, 154, 99, 53, 0, 0, 0,
+    ];
+
+    #[tokio::test]
+    async fn test_reqwest_client_requests_and_decompresses_gzip() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+                GZIPPED_PAGE.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(&GZIPPED_PAGE).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+
+        let html = test_client().get_html(&url).await.unwrap();
+
+        assert_eq!(
+            html,
+            "<html><body><p>Compressed page body</p></body></html>"
+        );
+        let request = server.await.unwrap();
+        let accept_encoding = request
+            .lines()
+            .find_map(|line| line.strip_prefix("accept-encoding:"))
+            .expect("request should advertise Accept-Encoding");
+        assert!(accept_encoding.contains("gzip"), "{accept_encoding}");
+        assert!(accept_encoding.contains("br"), "{accept_encoding}");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_reqwest_client_decompresses_real_gzip_endpoint() {
+        let client = ReqwestClient::new();
+
+        let body = client.get("https://httpbin.org/gzip").await.unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["gzipped"], true);
+    }
+
+    #[test]
+    fn test_has_plain_text_extension() {
+        assert!(has_plain_text_extension("https://example.com/README.md"));
+        assert!(has_plain_text_extension(
+            "https://example.com/notes.TXT?raw=1"
+        ));
+        assert!(!has_plain_text_extension("https://example.com/md"));
+        assert!(!has_plain_text_extension("https://example.com/index.html"));
+    }
+
+    #[test]
+    fn test_reqwest_client_clones_share_client() {
+        let client = test_client();
+        let clone = client.clone();
+
+        assert!(Arc::ptr_eq(&client.client, &clone.client));
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_client_uses_injected_client() {
+        let custom = reqwest::Client::builder()
+            .user_agent("injected-agent/2.0")
+            .build()
+            .unwrap();
+        let client = ReqwestClient::with_client(Arc::new(custom));
+        let (url, request) = capture_request().await;
+
+        assert_eq!(client.get(&url).await.unwrap(), "ok");
+
+        assert!(request
+            .await
+            .unwrap()
+            .contains("user-agent: injected-agent/2.0"));
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_client_get_accepts_any_content_type() {
+        let url = serve_once(Some(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nok",
+        ))
+        .await;
+
+        assert_eq!(test_client().get(&url).await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_client_reports_network_error() {
+        // Bind then drop a listener to get a port nothing listens on
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+
+        let result = test_client().get_html(&url).await;
+
+        assert!(matches!(failure_of(result), FetchFailure::Network(_)));
+    }
+
+    #[test]
+    fn test_is_html_content_type() {
+        assert!(is_html_content_type("text/html; charset=utf-8"));
+        assert!(is_html_content_type("application/xhtml+xml"));
+        assert!(!is_html_content_type("application/json"));
     }
 
     #[tokio::test]
@@ -207,10 +1608,57 @@ mod tests {
         );
         let robots_cache = RobotsCache::new();
 
-        let result = fetch_url_with_client(&mock_client, &robots_cache, "https://example.com/page")
-            .await
-            .unwrap();
+        let result = fetch_url_with_client(
+            &mock_client,
+            &robots_cache,
+            "https://example.com/page",
+            false,
+            0,
+        )
+        .await
+        .unwrap();
 
         assert_eq!(result.text, "Content");
     }
+
+    #[tokio::test]
+    async fn test_fetch_urls_returns_result_for_each_url() {
+        let mock_client = MockHttpClient::new()
+            .with_response("https://a.example.com/", "<p>A</p>")
+            .with_response("https://b.example.com/", "<p>B</p>")
+            .with_response("https://c.example.com/", "<p>C</p>");
+        let robots_cache = RobotsCache::new();
+        let urls = vec![
+            "https://a.example.com/".to_string(),
+            "https://b.example.com/".to_string(),
+            "https://c.example.com/".to_string(),
+        ];
+
+        let results = fetch_urls_with_client(&mock_client, &robots_cache, &urls, 2, 0).await;
+
+        assert_eq!(results.len(), 3);
+        let texts: Vec<String> = results.into_iter().map(|(_, r)| r.unwrap().text).collect();
+        assert_eq!(texts, ["A", "B", "C"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_urls_keeps_per_url_errors() {
+        let mock_client = MockHttpClient::new()
+            .with_response("https://a.example.com/", "<p>A</p>")
+            .with_response(
+                "https://b.example.com/robots.txt",
+                "User-agent: *\nDisallow: /",
+            );
+        let robots_cache = RobotsCache::new();
+        let urls = vec![
+            "https://a.example.com/".to_string(),
+            "https://b.example.com/".to_string(),
+        ];
+
+        let results = fetch_urls_with_client(&mock_client, &robots_cache, &urls, 4, 0).await;
+
+        assert!(results[0].1.is_ok());
+        assert_eq!(results[1].0, "https://b.example.com/");
+        assert!(results[1].1.is_err());
+    }
 }
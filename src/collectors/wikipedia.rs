@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde::Deserialize;
+
+use super::web::{HttpClient, ReqwestClient};
+
+/// A Wikipedia article as returned by the REST summary API
+#[derive(Debug, Clone, PartialEq)]
+pub struct WikipediaArticle {
+    pub title: String,
+    /// Short description (e.g. "Programming language"), when Wikipedia has one
+    pub description: Option<String>,
+    /// Plain-text lead section of the article, without markup or references
+    pub extract: String,
+    /// Canonical article URL
+    pub url: String,
+}
+
+/// Subset of the `/api/rest_v1/page/summary/{title}` response we use
+#[derive(Deserialize)]
+struct SummaryResponse {
+    title: String,
+    description: Option<String>,
+    extract: String,
+    content_urls: Option<ContentUrls>,
+}
+
+#[derive(Deserialize)]
+struct ContentUrls {
+    desktop: PageUrls,
+}
+
+#[derive(Deserialize)]
+struct PageUrls {
+    page: String,
+}
+
+/// Fetch a Wikipedia article's clean text via the REST summary API.
+///
+/// `url` must be an article URL such as `https://ja.wikipedia.org/wiki/Rust`;
+/// the API of the same language edition is queried.
+pub(crate) async fn fetch_wikipedia_article(
+    client: &ReqwestClient,
+    url: &str,
+) -> Result<WikipediaArticle> {
+    fetch_wikipedia_article_with_client(client, url).await
+}
+
+async fn fetch_wikipedia_article_with_client<C: HttpClient>(
+    client: &C,
+    url: &str,
+) -> Result<WikipediaArticle> {
+    let (lang, title) = parse_wikipedia_url(url)
+        .with_context(|| format!("Not a Wikipedia article URL: {}", url))?;
+    let api_url = format!(
+        "https://{}.wikipedia.org/api/rest_v1/page/summary/{}",
+        lang, title
+    );
+    let body = client.get(&api_url).await?;
+    let mut article = parse_summary(&body)
+        .with_context(|| format!("Unexpected Wikipedia API response for {}", url))?;
+    if article.url.is_empty() {
+        article.url = url.to_string();
+    }
+    Ok(article)
+}
+
+/// Split a Wikipedia article URL into its language code and (still percent-encoded) title.
+/// Mobile URLs (`{lang}.m.wikipedia.org`) are accepted too.
+pub(crate) fn parse_wikipedia_url(url: &str) -> Option<(String, String)> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    let lang = host
+        .strip_suffix(".wikipedia.org")?
+        .trim_end_matches(".m")
+        .to_string();
+    if lang.is_empty() || lang.contains('.') {
+        return None;
+    }
+    let title = parsed.path().strip_prefix("/wiki/")?;
+    if title.is_empty() {
+        return None;
+    }
+    Some((lang, title.to_string()))
+}
+
+/// Parse a REST summary API response body
+fn parse_summary(body: &str) -> Result<WikipediaArticle> {
+    let response: SummaryResponse = serde_json::from_str(body)?;
+    Ok(WikipediaArticle {
+        title: response.title,
+        description: response.description,
+        extract: response.extract,
+        url: response
+            .content_urls
+            .map(|urls| urls.desktop.page)
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MockHttpClient {
+        responses: HashMap<String, String>,
+    }
+
+    impl MockHttpClient {
+        fn new() -> Self {
+            Self {
+                responses: HashMap::new(),
+            }
+        }
+
+        fn with_response(mut self, url: &str, body: &str) -> Self {
+            self.responses.insert(url.to_string(), body.to_string());
+            self
+        }
+    }
+
+    impl HttpClient for MockHttpClient {
+        async fn get(&self, url: &str) -> Result<String> {
+            self.responses
+                .get(url)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No mock response for {}", url))
+        }
+    }
+
+    const SUMMARY_JSON: &str = r#"{
+        "type": "standard",
+        "title": "Rust (programming language)",
+        "displaytitle": "<span>Rust (programming language)</span>",
+        "description": "General-purpose programming language",
+        "extract": "Rust is a general-purpose programming language emphasizing performance, type safety, and concurrency.",
+        "content_urls": {
+            "desktop": { "page": "https://en.wikipedia.org/wiki/Rust_(programming_language)" },
+            "mobile": { "page": "https://en.m.wikipedia.org/wiki/Rust_(programming_language)" }
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_summary_extracts_article() {
+        let article = parse_summary(SUMMARY_JSON).unwrap();
+
+        assert_eq!(article.title, "Rust (programming language)");
+        assert_eq!(
+            article.description.as_deref(),
+            Some("General-purpose programming language")
+        );
+        assert!(article.extract.starts_with("Rust is a general-purpose"));
+        assert_eq!(
+            article.url,
+            "https://en.wikipedia.org/wiki/Rust_(programming_language)"
+        );
+    }
+
+    #[test]
+    fn test_parse_summary_rejects_error_response() {
+        let body = r#"{"type": "https://mediawiki.org/wiki/HyperSwitch/errors/not_found", "title": "Not found."}"#;
+        assert!(parse_summary(body).is_err());
+    }
+
+    #[test]
+    fn test_parse_wikipedia_url_keeps_language() {
+        assert_eq!(
+            parse_wikipedia_url("https://ja.wikipedia.org/wiki/%E6%9D%B1%E4%BA%AC"),
+            Some(("ja".to_string(), "%E6%9D%B1%E4%BA%AC".to_string()))
+        );
+        assert_eq!(
+            parse_wikipedia_url("https://de.m.wikipedia.org/wiki/Berlin"),
+            Some(("de".to_string(), "Berlin".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_wikipedia_url_rejects_other_urls() {
+        assert_eq!(parse_wikipedia_url("https://example.com/wiki/Rust"), None);
+        assert_eq!(parse_wikipedia_url("https://en.wikipedia.org/"), None);
+        assert_eq!(parse_wikipedia_url("https://wikipedia.org/wiki/Rust"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_wikipedia_article_queries_language_edition() {
+        let client = MockHttpClient::new().with_response(
+            "https://en.wikipedia.org/api/rest_v1/page/summary/Rust_(programming_language)",
+            SUMMARY_JSON,
+        );
+
+        let article = fetch_wikipedia_article_with_client(
+            &client,
+            "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(article.title, "Rust (programming language)");
+    }
+}
@@ -0,0 +1,772 @@
+//! Typed configuration, read from the environment once at startup.
+//!
+//! `Config::from_env` checks every variable it knows before returning, so a
+//! misconfigured deployment fails with all of its problems listed at once
+//! instead of the first one, or a value silently replaced by its default.
+
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::agent::{
+    AgentParams, AgentSettings, ConfigError, ContentFilter, ContentFilterMode, EnabledTools,
+    GeminiSafety, ModelAllowlist, ProviderConfig, ProviderSettings, ResponseStyle,
+    DEFAULT_MAX_PROMPT_CHARS, DEFAULT_MAX_TOOL_ROUNDS,
+};
+use crate::collectors::content_selectors::ContentSelectors;
+use crate::collectors::credentials::FetchCredentials;
+use crate::collectors::fetch_limit::DEFAULT_MAX_CONCURRENT_FETCHES;
+use crate::collectors::rate_limit::{DEFAULT_FETCH_RATE_PER_SEC, MIN_FETCH_RATE_PER_SEC};
+use crate::collectors::robots::{DEFAULT_ROBOTS_CACHE_TTL, DEFAULT_ROBOTS_FETCH_TIMEOUT};
+use crate::collectors::web::{
+    DEFAULT_FETCH_CONCURRENCY, DEFAULT_FETCH_MAX_BYTES, DEFAULT_FETCH_MIN_TEXT_CHARS,
+    DEFAULT_FETCH_TIMEOUT, DEFAULT_USER_AGENT,
+};
+use crate::session::{SessionEviction, SessionLimit, SessionTitleMode};
+
+/// Port the web server listens on when `PORT` is unset
+pub const DEFAULT_PORT: u16 = 3000;
+
+/// Largest chat request body accepted when `CHAT_MAX_BODY_BYTES` is unset
+pub const DEFAULT_CHAT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Application settings. Env names are unchanged; see `.env.example`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// `LLM_PROVIDER`, `LLM_MODEL` and the provider's API key or host
+    pub provider: ProviderConfig,
+    /// Every provider's API key or host, for per-request model overrides
+    pub provider_settings: ProviderSettings,
+    /// `CHAT_MODEL_ALLOWLIST`: `provider:model` pairs requests may switch to (default: none)
+    pub model_allowlist: ModelAllowlist,
+    /// `LLM_TEMPERATURE`, `LLM_TOP_P` and `LLM_MAX_TOKENS`
+    pub params: AgentParams,
+    /// `COPAL_API_TOKEN`; required when built with the "web" feature
+    pub api_token: Option<String>,
+    /// `PORT` (default: 3000)
+    pub port: u16,
+    /// `CHAT_MAX_BODY_BYTES` (default: 64KB)
+    pub chat_max_body_bytes: usize,
+    /// `FETCH_TIMEOUT_SECS` (default: 30s)
+    pub fetch_timeout: Duration,
+    /// `FETCH_MAX_BYTES` (default: 5MB)
+    pub fetch_max_bytes: usize,
+    /// `FETCH_MIN_TEXT_CHARS`: text an HTML page must yield to count as fetched (default: 50; `0` = no minimum)
+    pub fetch_min_text_chars: usize,
+    /// `FETCH_CONCURRENCY`: pages `web_fetch_many` fetches at once (default: 4)
+    pub fetch_concurrency: usize,
+    /// `FETCH_CREDENTIALS`: per-host `Authorization` credentials (default: none)
+    pub(crate) fetch_credentials: FetchCredentials,
+    /// `CONTENT_SELECTORS`: per-host main-content selectors (default: none)
+    pub(crate) content_selectors: ContentSelectors,
+    /// `CONTENT_FILTER_MODE` (`off`, `redact` or `block`; default: off) and the
+    /// comma-separated `CONTENT_FILTER_WORDS`, required unless the mode is off
+    pub content_filter: ContentFilter,
+    /// `USER_AGENT`: sent on fetches and matched against robots.txt (default: copal/0.1.0)
+    pub user_agent: String,
+    /// `RESPECT_ROBOTS` (default: true)
+    pub respect_robots: bool,
+    /// `ROBOTS_CACHE_TTL_SECS` (default: 24h)
+    pub robots_cache_ttl: Duration,
+    /// `ROBOTS_TIMEOUT_SECS` (default: 5s)
+    pub robots_timeout: Duration,
+    /// `FETCH_RATE_PER_SEC`: page requests per second to one origin (default: 1; `0` = unlimited)
+    pub fetch_rate_per_sec: f64,
+    /// `MAX_CONCURRENT_FETCHES`: requests in flight at once, process-wide (default: 16; `0` = unlimited)
+    pub max_concurrent_fetches: usize,
+    /// `MAX_TOOL_ROUNDS` (default: 8)
+    pub max_tool_rounds: usize,
+    /// `MAX_PROMPT_CHARS` (default: 32000; `0` = unlimited, read as `None`)
+    pub max_prompt_chars: Option<usize>,
+    /// `MAX_RESPONSE_CHARS` (default: `0` = unlimited, read as `None`)
+    pub max_response_chars: Option<usize>,
+    /// `MAX_CONTINUATIONS`: automatic continuations of a cut-off answer (default: 0)
+    pub max_continuations: usize,
+    /// `METRICS_ENABLED` (default: false)
+    pub metrics_enabled: bool,
+    /// `WARMUP` (default: false)
+    pub warmup: bool,
+    /// `ASSISTANT_NAME` (default: unset)
+    pub assistant_name: Option<String>,
+    /// `RESPONSE_STYLE`: `bullets`, `prose` or `steps` (default: prose)
+    pub response_style: ResponseStyle,
+    /// `GEMINI_SAFETY`: `category=threshold` pairs (default: Gemini's defaults)
+    pub gemini_safety: GeminiSafety,
+    /// `ENABLED_TOOLS`: comma-separated tool names or `none` (default: every tool)
+    pub enabled_tools: EnabledTools,
+    /// `SESSION_TITLE_MODE`: `first_message` or `llm` (default: first message)
+    pub session_title_mode: SessionTitleMode,
+    /// `MAX_SESSIONS` and `SESSION_EVICTION`: `lru` or `reject` (default: unlimited; reject)
+    pub session_limit: Option<SessionLimit>,
+}
+
+impl Config {
+    /// Read and check the configuration from environment variables.
+    ///
+    /// # Errors
+    /// Returns the single problem found, or `ConfigError::Multiple` listing all of them.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    /// Read and check the configuration using `lookup` to read variables.
+    pub(crate) fn from_lookup(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let mut vars = Vars {
+            lookup: &lookup,
+            problems: Vec::new(),
+        };
+
+        let provider = ProviderConfig::from_lookup(&lookup)
+            .map_err(|e| vars.problems.push(e))
+            .ok();
+        // A bad provider name was reported by `ProviderConfig` above
+        let provider_settings = ProviderSettings::from_lookup(&lookup).unwrap_or_default();
+        let enabled_tools = EnabledTools::from_lookup(&lookup)
+            .map_err(|e| vars.problems.push(e))
+            .unwrap_or_else(|()| EnabledTools::all());
+        let model_allowlist = vars
+            .parse(
+                "CHAT_MODEL_ALLOWLIST",
+                "comma-separated provider:model pairs",
+                |_| true,
+            )
+            .unwrap_or_default();
+        let params = AgentParams {
+            temperature: vars.parse("LLM_TEMPERATURE", "a number from 0 to 2", |t: &f64| {
+                (0.0..=2.0).contains(t)
+            }),
+            top_p: vars.parse("LLM_TOP_P", "a number from 0 to 1", |p: &f64| {
+                (0.0..=1.0).contains(p)
+            }),
+            max_tokens: vars.parse("LLM_MAX_TOKENS", "a positive integer", |&n: &u64| n > 0),
+        };
+        let api_token = vars.string("COPAL_API_TOKEN");
+        if cfg!(feature = "web") && api_token.is_none() {
+            vars.problems
+                .push(ConfigError::MissingVar("COPAL_API_TOKEN"));
+        }
+        let port = vars
+            .parse("PORT", "a port number from 1 to 65535", |&p: &u16| p > 0)
+            .unwrap_or(DEFAULT_PORT);
+        let chat_max_body_bytes = vars
+            .parse(
+                "CHAT_MAX_BODY_BYTES",
+                "a positive number of bytes",
+                |&n: &usize| n > 0,
+            )
+            .unwrap_or(DEFAULT_CHAT_MAX_BODY_BYTES);
+        let fetch_timeout = vars
+            .seconds("FETCH_TIMEOUT_SECS")
+            .unwrap_or(DEFAULT_FETCH_TIMEOUT);
+        let fetch_max_bytes = vars
+            .parse(
+                "FETCH_MAX_BYTES",
+                "a positive number of bytes",
+                |&n: &usize| n > 0,
+            )
+            .unwrap_or(DEFAULT_FETCH_MAX_BYTES);
+        let fetch_min_text_chars = vars
+            .parse(
+                "FETCH_MIN_TEXT_CHARS",
+                "a whole number of characters (0 = no minimum)",
+                |_: &usize| true,
+            )
+            .unwrap_or(DEFAULT_FETCH_MIN_TEXT_CHARS);
+        let fetch_concurrency = vars
+            .parse(
+                "FETCH_CONCURRENCY",
+                "a positive number of pages",
+                |&n: &usize| n > 0,
+            )
+            .unwrap_or(DEFAULT_FETCH_CONCURRENCY);
+        let fetch_credentials = vars.fetch_credentials();
+        let content_selectors = vars
+            .parse(
+                "CONTENT_SELECTORS",
+                "semicolon-separated host=selector entries with valid CSS selectors",
+                |_| true,
+            )
+            .unwrap_or_default();
+        let content_filter_mode = vars
+            .parse("CONTENT_FILTER_MODE", "off, redact or block", |_| true)
+            .unwrap_or_default();
+        let content_filter = ContentFilter::new(
+            content_filter_mode,
+            vars.string("CONTENT_FILTER_WORDS")
+                .map(|words| words.split(',').map(str::to_string).collect())
+                .unwrap_or_default(),
+        );
+        if content_filter_mode != ContentFilterMode::Off && content_filter.is_empty() {
+            let mode = vars.string("CONTENT_FILTER_MODE").unwrap_or_default();
+            vars.problems.push(ConfigError::EmptyContentFilter(mode));
+        }
+        let user_agent = vars
+            .string("USER_AGENT")
+            .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+        let respect_robots = vars.flag("RESPECT_ROBOTS").unwrap_or(true);
+        let robots_cache_ttl = vars
+            .seconds("ROBOTS_CACHE_TTL_SECS")
+            .unwrap_or(DEFAULT_ROBOTS_CACHE_TTL);
+        let robots_timeout = vars
+            .parse(
+                "ROBOTS_TIMEOUT_SECS",
+                "a positive number of seconds",
+                |&n: &u64| n > 0,
+            )
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_ROBOTS_FETCH_TIMEOUT);
+        let fetch_rate_per_sec = vars
+            .parse(
+                "FETCH_RATE_PER_SEC",
+                "0 (unlimited) or a number of requests per second of at least 0.001",
+                |&r: &f64| r == 0.0 || (r.is_finite() && r >= MIN_FETCH_RATE_PER_SEC),
+            )
+            .unwrap_or(DEFAULT_FETCH_RATE_PER_SEC);
+        let max_concurrent_fetches = vars
+            .parse(
+                "MAX_CONCURRENT_FETCHES",
+                "a whole number of fetches (0 = unlimited)",
+                |_: &usize| true,
+            )
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_FETCHES);
+        let max_tool_rounds = vars
+            .parse("MAX_TOOL_ROUNDS", "a positive integer", |&n: &usize| n > 0)
+            .unwrap_or(DEFAULT_MAX_TOOL_ROUNDS);
+        let max_prompt_chars = match vars.parse(
+            "MAX_PROMPT_CHARS",
+            "a whole number of characters (0 = unlimited)",
+            |_: &usize| true,
+        ) {
+            Some(0) => None,
+            Some(max) => Some(max),
+            None => Some(DEFAULT_MAX_PROMPT_CHARS),
+        };
+        let max_response_chars = vars
+            .parse(
+                "MAX_RESPONSE_CHARS",
+                "a whole number of characters (0 = unlimited)",
+                |_: &usize| true,
+            )
+            .filter(|&max| max > 0);
+        let max_continuations = vars
+            .parse(
+                "MAX_CONTINUATIONS",
+                "a whole number of continuations",
+                |_: &usize| true,
+            )
+            .unwrap_or(0);
+        let metrics_enabled = vars.flag("METRICS_ENABLED").unwrap_or(false);
+        let warmup = vars.flag("WARMUP").unwrap_or(false);
+        let assistant_name = vars.string("ASSISTANT_NAME");
+        let response_style = vars
+            .parse("RESPONSE_STYLE", "bullets, prose or steps", |_| true)
+            .unwrap_or_default();
+        let gemini_safety = vars
+            .parse(
+                "GEMINI_SAFETY",
+                "comma-separated category=threshold pairs with a threshold of \
+                 block_low_and_above, block_medium_and_above, block_only_high, block_none or off",
+                |_| true,
+            )
+            .unwrap_or_default();
+        let session_title_mode = vars
+            .parse("SESSION_TITLE_MODE", "first_message or llm", |_| true)
+            .unwrap_or_default();
+        let max_sessions = vars
+            .parse(
+                "MAX_SESSIONS",
+                "a whole number of sessions (0 = unlimited)",
+                |_: &usize| true,
+            )
+            .unwrap_or(0);
+        let eviction: SessionEviction = vars
+            .parse("SESSION_EVICTION", "reject or lru", |_| true)
+            .unwrap_or_default();
+        let session_limit = (max_sessions > 0).then_some(SessionLimit {
+            max_sessions,
+            eviction,
+        });
+
+        let mut problems = vars.problems;
+        match (provider, problems.len()) {
+            (Some(provider), 0) => Ok(Self {
+                provider,
+                provider_settings,
+                model_allowlist,
+                params,
+                api_token,
+                port,
+                chat_max_body_bytes,
+                fetch_timeout,
+                fetch_max_bytes,
+                fetch_min_text_chars,
+                fetch_concurrency,
+                fetch_credentials,
+                content_selectors,
+                content_filter,
+                user_agent,
+                respect_robots,
+                robots_cache_ttl,
+                robots_timeout,
+                fetch_rate_per_sec,
+                max_concurrent_fetches,
+                max_tool_rounds,
+                max_prompt_chars,
+                max_response_chars,
+                max_continuations,
+                metrics_enabled,
+                warmup,
+                assistant_name,
+                response_style,
+                gemini_safety,
+                enabled_tools,
+                session_title_mode,
+                session_limit,
+            }),
+            (_, 1) => Err(problems.remove(0)),
+            _ => Err(ConfigError::Multiple(problems)),
+        }
+    }
+}
+
+impl Config {
+    /// The settings the agent builders take
+    pub fn agent_settings(&self) -> AgentSettings {
+        AgentSettings {
+            params: self.params,
+            max_tool_rounds: self.max_tool_rounds,
+            assistant_name: self.assistant_name.clone(),
+            response_style: self.response_style,
+            gemini_safety: self.gemini_safety.clone(),
+            enabled_tools: self.enabled_tools.clone(),
+        }
+    }
+}
+
+/// Variable reader that collects problems instead of stopping at the first
+struct Vars<'a, F> {
+    lookup: &'a F,
+    problems: Vec<ConfigError>,
+}
+
+impl<F: Fn(&str) -> Option<String>> Vars<'_, F> {
+    /// The trimmed value of `var`; `None` when unset or blank
+    fn string(&self, var: &str) -> Option<String> {
+        (self.lookup)(var)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Parse `var`, recording a problem if it doesn't parse or isn't `valid`
+    fn parse<T: FromStr>(
+        &mut self,
+        var: &'static str,
+        expected: &'static str,
+        valid: impl Fn(&T) -> bool,
+    ) -> Option<T> {
+        let value = self.string(var)?;
+        match value.parse::<T>() {
+            Ok(parsed) if valid(&parsed) => Some(parsed),
+            _ => {
+                self.problems.push(ConfigError::InvalidValue {
+                    var,
+                    value,
+                    expected,
+                });
+                None
+            }
+        }
+    }
+
+    /// A duration given in whole seconds
+    fn seconds(&mut self, var: &'static str) -> Option<Duration> {
+        self.parse(var, "a whole number of seconds", |_: &u64| true)
+            .map(Duration::from_secs)
+    }
+
+    /// `FETCH_CREDENTIALS`, reported by host only so secrets stay out of the error
+    fn fetch_credentials(&mut self) -> FetchCredentials {
+        let Some(value) = self.string("FETCH_CREDENTIALS") else {
+            return FetchCredentials::default();
+        };
+        value.parse().unwrap_or_else(|host| {
+            self.problems.push(ConfigError::InvalidValue {
+                var: "FETCH_CREDENTIALS",
+                value: format!("{host}=[redacted]"),
+                expected: "comma-separated host=basic:user:password or host=bearer:token entries",
+            });
+            FetchCredentials::default()
+        })
+    }
+
+    /// A boolean: `true`/`false` or `1`/`0`
+    fn flag(&mut self, var: &'static str) -> Option<bool> {
+        let value = self.string(var)?;
+        match value.to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => {
+                self.problems.push(ConfigError::InvalidValue {
+                    var,
+                    value,
+                    expected: "true or false",
+                });
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::DEFAULT_OLLAMA_HOST;
+    use std::collections::HashMap;
+
+    fn lookup_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let mut vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        // Required in web builds; tests for its absence remove it explicitly
+        vars.entry("COPAL_API_TOKEN".to_string())
+            .or_insert_with(|| "token".to_string());
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::from_lookup(lookup_from(&[])).unwrap();
+
+        assert_eq!(
+            config.provider,
+            ProviderConfig::Ollama {
+                host: DEFAULT_OLLAMA_HOST.to_string(),
+                model: "qwen3".to_string()
+            }
+        );
+        assert_eq!(config.params, AgentParams::default());
+        assert_eq!(config.port, DEFAULT_PORT);
+        assert_eq!(config.chat_max_body_bytes, DEFAULT_CHAT_MAX_BODY_BYTES);
+        assert_eq!(config.fetch_timeout, DEFAULT_FETCH_TIMEOUT);
+        assert_eq!(config.fetch_min_text_chars, DEFAULT_FETCH_MIN_TEXT_CHARS);
+        assert_eq!(config.user_agent, DEFAULT_USER_AGENT);
+        assert!(config.respect_robots);
+        assert_eq!(config.fetch_rate_per_sec, DEFAULT_FETCH_RATE_PER_SEC);
+        assert_eq!(
+            config.max_concurrent_fetches,
+            DEFAULT_MAX_CONCURRENT_FETCHES
+        );
+        assert_eq!(config.max_tool_rounds, DEFAULT_MAX_TOOL_ROUNDS);
+        assert_eq!(config.max_prompt_chars, Some(DEFAULT_MAX_PROMPT_CHARS));
+        assert_eq!(config.max_continuations, 0);
+        assert!(!config.metrics_enabled);
+        assert_eq!(config.assistant_name, None);
+        assert_eq!(config.response_style, ResponseStyle::Prose);
+        assert!(config.gemini_safety.is_empty());
+        assert_eq!(config.session_title_mode, SessionTitleMode::FirstMessage);
+        assert_eq!(config.provider_settings.provider, "ollama");
+        assert_eq!(config.model_allowlist, ModelAllowlist::default());
+        assert_eq!(config.fetch_concurrency, DEFAULT_FETCH_CONCURRENCY);
+        assert_eq!(config.fetch_credentials, FetchCredentials::default());
+        assert_eq!(config.content_selectors, ContentSelectors::default());
+        assert_eq!(config.max_response_chars, None);
+        assert_eq!(config.session_limit, None);
+        assert_eq!(config.content_filter, ContentFilter::default());
+        assert_eq!(config.agent_settings().enabled_tools, EnabledTools::all());
+    }
+
+    #[test]
+    fn test_enabled_tools_are_passed_to_the_agent_settings() {
+        let config =
+            Config::from_lookup(lookup_from(&[("ENABLED_TOOLS", "web_search, calculator")]))
+                .unwrap();
+
+        assert_eq!(
+            config.agent_settings().enabled_tools,
+            EnabledTools::parse("web_search,calculator").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unknown_enabled_tool_is_reported() {
+        let result = Config::from_lookup(lookup_from(&[("ENABLED_TOOLS", "web_serch")]));
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::UnknownTool { name, .. }) if name == "web_serch"
+        ));
+    }
+
+    #[test]
+    fn test_valid_values() {
+        let config = Config::from_lookup(lookup_from(&[
+            ("LLM_PROVIDER", "openai"),
+            ("OPENAI_API_KEY", "sk-test"),
+            ("LLM_TEMPERATURE", "0.2"),
+            ("LLM_MAX_TOKENS", "512"),
+            ("PORT", "8080"),
+            ("CHAT_MAX_BODY_BYTES", "1024"),
+            ("FETCH_TIMEOUT_SECS", "10"),
+            ("FETCH_MIN_TEXT_CHARS", "0"),
+            ("USER_AGENT", " research-bot/2.0 "),
+            ("RESPECT_ROBOTS", "false"),
+            ("ROBOTS_CACHE_TTL_SECS", "60"),
+            ("FETCH_RATE_PER_SEC", "0.5"),
+            ("MAX_CONCURRENT_FETCHES", "4"),
+            ("MAX_TOOL_ROUNDS", "3"),
+            ("MAX_PROMPT_CHARS", "0"),
+            ("METRICS_ENABLED", "TRUE"),
+            ("ASSISTANT_NAME", "Acme"),
+            ("RESPONSE_STYLE", "bullets"),
+            ("GEMINI_SAFETY", "all=block_only_high"),
+            ("SESSION_TITLE_MODE", "llm"),
+            ("CHAT_MODEL_ALLOWLIST", "ollama:llama3, openai:gpt-4.1"),
+            ("FETCH_CONCURRENCY", "8"),
+            ("FETCH_CREDENTIALS", "docs.example.com=bearer:tok123"),
+            ("CONTENT_SELECTORS", "docs.rs=#main-content"),
+            ("MAX_RESPONSE_CHARS", "2000"),
+            ("MAX_SESSIONS", "100"),
+            ("SESSION_EVICTION", "LRU"),
+        ]))
+        .unwrap();
+
+        assert_eq!(config.provider.provider_name(), "openai");
+        assert_eq!(config.params.temperature, Some(0.2));
+        assert_eq!(config.params.max_tokens, Some(512));
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.chat_max_body_bytes, 1024);
+        assert_eq!(config.fetch_timeout, Duration::from_secs(10));
+        assert_eq!(config.fetch_min_text_chars, 0);
+        assert_eq!(config.user_agent, "research-bot/2.0");
+        assert!(!config.respect_robots);
+        assert_eq!(config.robots_cache_ttl, Duration::from_secs(60));
+        assert_eq!(config.fetch_rate_per_sec, 0.5);
+        assert_eq!(config.max_concurrent_fetches, 4);
+        assert_eq!(config.max_tool_rounds, 3);
+        assert_eq!(config.max_prompt_chars, None);
+        assert!(config.metrics_enabled);
+        assert_eq!(config.assistant_name.as_deref(), Some("Acme"));
+        assert_eq!(config.response_style, ResponseStyle::Bullets);
+        assert_eq!(config.gemini_safety, "all=block_only_high".parse().unwrap());
+        assert_eq!(config.session_title_mode, SessionTitleMode::Llm);
+        assert_eq!(config.agent_settings().max_tool_rounds, 3);
+        assert!(config.model_allowlist.allows("openai", "gpt-4.1"));
+        assert_eq!(config.fetch_concurrency, 8);
+        assert!(config
+            .fetch_credentials
+            .for_url("https://docs.example.com/")
+            .is_some());
+        assert!(config
+            .content_selectors
+            .for_url("https://docs.rs/serde")
+            .is_some());
+        assert_eq!(config.max_response_chars, Some(2000));
+        assert_eq!(
+            config.session_limit,
+            Some(SessionLimit {
+                max_sessions: 100,
+                eviction: SessionEviction::LeastRecentlyUsed
+            })
+        );
+    }
+
+    #[test]
+    fn test_zero_max_sessions_and_response_chars_mean_unlimited() {
+        let config = Config::from_lookup(lookup_from(&[
+            ("MAX_SESSIONS", "0"),
+            ("MAX_RESPONSE_CHARS", "0"),
+        ]))
+        .unwrap();
+
+        assert_eq!(config.session_limit, None);
+        assert_eq!(config.max_response_chars, None);
+    }
+
+    #[test]
+    fn test_malformed_values_are_reported() {
+        let cases = [
+            ("CHAT_MODEL_ALLOWLIST", "openai:gpt-4.1,bogus"),
+            ("MAX_SESSIONS", "abc"),
+            ("MAX_SESSIONS", "-1"),
+            ("SESSION_EVICTION", "oldest"),
+            ("FETCH_CONCURRENCY", "0"),
+            ("FETCH_CONCURRENCY", "many"),
+            ("MAX_RESPONSE_CHARS", "lots"),
+            ("FETCH_CREDENTIALS", "docs.example.com=token"),
+            ("CONTENT_SELECTORS", "docs.rs=div["),
+            ("CONTENT_SELECTORS", "main"),
+        ];
+
+        for (name, value) in cases {
+            let result = Config::from_lookup(lookup_from(&[(name, value)]));
+
+            assert!(
+                matches!(result, Err(ConfigError::InvalidValue { var, .. }) if var == name),
+                "{name}={value} gave {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_content_filter_mode_is_trimmed_and_case_insensitive() {
+        let config = Config::from_lookup(lookup_from(&[
+            ("CONTENT_FILTER_MODE", " Block "),
+            ("CONTENT_FILTER_WORDS", " darn , , heck "),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            config.content_filter,
+            ContentFilter::new(
+                ContentFilterMode::Block,
+                vec!["darn".to_string(), "heck".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_unknown_content_filter_mode_is_reported() {
+        let result = Config::from_lookup(lookup_from(&[
+            ("CONTENT_FILTER_MODE", "strict"),
+            ("CONTENT_FILTER_WORDS", "darn"),
+        ]));
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidValue {
+                var: "CONTENT_FILTER_MODE",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_content_filter_without_words_is_reported() {
+        for words in [None, Some(" , ")] {
+            let mut vars = vec![("CONTENT_FILTER_MODE", "redact")];
+            vars.extend(words.map(|w| ("CONTENT_FILTER_WORDS", w)));
+
+            let result = Config::from_lookup(lookup_from(&vars));
+
+            assert_eq!(
+                result,
+                Err(ConfigError::EmptyContentFilter("redact".to_string())),
+                "{words:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_malformed_fetch_credentials_error_hides_the_secret() {
+        let result = Config::from_lookup(lookup_from(&[(
+            "FETCH_CREDENTIALS",
+            "ok.example=bearer:tok123, docs.example.com=basic:alice",
+        )]));
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("docs.example.com"), "{message}");
+        assert!(!message.contains("alice"), "{message}");
+        assert!(!message.contains("tok123"), "{message}");
+    }
+
+    #[test]
+    fn test_zero_robots_timeout_is_reported() {
+        let result = Config::from_lookup(lookup_from(&[("ROBOTS_TIMEOUT_SECS", "0")]));
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidValue {
+                var: "ROBOTS_TIMEOUT_SECS",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_fetch_rate_below_minimum_is_reported() {
+        for rate in ["1e-300", "0.0001", "-1", "inf"] {
+            let result = Config::from_lookup(lookup_from(&[("FETCH_RATE_PER_SEC", rate)]));
+
+            assert!(
+                matches!(
+                    result,
+                    Err(ConfigError::InvalidValue {
+                        var: "FETCH_RATE_PER_SEC",
+                        ..
+                    })
+                ),
+                "{rate} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_gemini_safety_is_reported() {
+        let result = Config::from_lookup(lookup_from(&[(
+            "GEMINI_SAFETY",
+            "dangerous_content=block_only_high,violence=off",
+        )]));
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidValue {
+                var: "GEMINI_SAFETY",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_missing_api_key_is_reported() {
+        let result = Config::from_lookup(lookup_from(&[("LLM_PROVIDER", "gemini")]));
+
+        assert_eq!(
+            result,
+            Err(ConfigError::MissingApiKey {
+                provider: "gemini",
+                var: "GEMINI_API_KEY"
+            })
+        );
+    }
+
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_missing_api_token_is_reported_in_web_builds() {
+        let result = Config::from_lookup(|key| match key {
+            "COPAL_API_TOKEN" => Some(" ".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(result, Err(ConfigError::MissingVar("COPAL_API_TOKEN")));
+    }
+
+    #[test]
+    fn test_all_malformed_values_are_listed() {
+        let result = Config::from_lookup(lookup_from(&[
+            ("LLM_PROVIDER", "openai"),
+            ("PORT", "eighty"),
+            ("LLM_TEMPERATURE", "5"),
+            ("FETCH_TIMEOUT_SECS", "-1"),
+            ("METRICS_ENABLED", "yes"),
+        ]));
+
+        let Err(ConfigError::Multiple(problems)) = result else {
+            panic!("expected several problems, got {result:?}");
+        };
+        assert_eq!(problems.len(), 5);
+        let message = ConfigError::Multiple(problems).to_string();
+        for var in [
+            "OPENAI_API_KEY",
+            "PORT='eighty'",
+            "LLM_TEMPERATURE='5'",
+            "FETCH_TIMEOUT_SECS='-1'",
+            "METRICS_ENABLED='yes'",
+        ] {
+            assert!(message.contains(var), "{var} missing from: {message}");
+        }
+        assert!(message.starts_with("5 configuration problems: "));
+    }
+}
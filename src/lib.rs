@@ -2,7 +2,10 @@ pub mod agent;
 #[cfg(feature = "cli")]
 pub mod cli;
 pub mod collectors;
+pub mod config;
 pub mod llm;
+pub mod metrics;
 pub mod session;
+mod sync;
 #[cfg(feature = "web")]
 pub mod web;
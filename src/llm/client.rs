@@ -1,12 +1,33 @@
+use std::pin::Pin;
+
 use anyhow::Result;
+use futures::Stream;
+use rig::completion::Message;
+
+/// A boxed stream of text fragments produced by `LlmClient::stream_complete`
+pub type TextStream = Pin<Box<dyn Stream<Item = Result<String>> + Send>>;
 
 pub trait LlmClient {
     fn complete(&self, prompt: &str) -> impl std::future::Future<Output = Result<String>> + Send;
+
+    /// Complete `prompt` as the next user turn after the given conversation history.
+    fn complete_with_history(
+        &self,
+        history: &[Message],
+        prompt: &str,
+    ) -> impl std::future::Future<Output = Result<String>> + Send;
+
+    /// Stream the completion as text fragments instead of waiting for the full response.
+    fn stream_complete(
+        &self,
+        prompt: &str,
+    ) -> impl std::future::Future<Output = Result<TextStream>> + Send;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::StreamExt;
 
     struct MockLlmClient;
 
@@ -14,6 +35,19 @@ mod tests {
         async fn complete(&self, prompt: &str) -> Result<String> {
             Ok(format!("LLM Response: {}", prompt))
         }
+
+        async fn complete_with_history(&self, history: &[Message], prompt: &str) -> Result<String> {
+            Ok(format!(
+                "LLM Response ({} prior): {}",
+                history.len(),
+                prompt
+            ))
+        }
+
+        async fn stream_complete(&self, prompt: &str) -> Result<TextStream> {
+            let chunks = vec![Ok("LLM Response: ".to_string()), Ok(prompt.to_string())];
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
     }
 
     #[tokio::test]
@@ -23,4 +57,14 @@ mod tests {
 
         assert_eq!(response, "LLM Response: test message");
     }
+
+    #[tokio::test]
+    async fn test_stream_query_to_llm() {
+        let llm = MockLlmClient {};
+        let stream = llm.stream_complete("test message").await.unwrap();
+
+        let chunks: Vec<String> = stream.map(|c| c.unwrap()).collect().await;
+
+        assert_eq!(chunks.concat(), "LLM Response: test message");
+    }
 }
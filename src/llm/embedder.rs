@@ -0,0 +1,133 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use rig::{
+    client::{EmbeddingsClient, Nothing},
+    embeddings::EmbeddingModel,
+    providers::{gemini, ollama},
+};
+
+use crate::agent::{openai_client, ProviderConfig};
+
+/// Turns texts into embedding vectors, e.g. to rank page paragraphs by relevance.
+///
+/// `#[async_trait]` keeps the trait object-safe, so fetch tools can hold an
+/// `Arc<dyn Embedder>` without another type parameter.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embed each of `texts`, returning one vector per text in the same order
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>>;
+}
+
+/// Embedder backed by the embedding model of the configured provider
+#[derive(Clone)]
+pub struct RigEmbedder {
+    provider: ProviderConfig,
+    model: String,
+}
+
+impl RigEmbedder {
+    /// Embed with `model` (e.g. `nomic-embed-text`) on the host/API key of `provider`
+    pub fn new(provider: ProviderConfig, model: &str) -> Self {
+        Self {
+            provider,
+            model: model.to_string(),
+        }
+    }
+
+    /// Read the embedding model from `EMBEDDING_MODEL`; `None` when unset
+    pub fn from_env(provider: ProviderConfig) -> Option<Self> {
+        Self::from_lookup(provider, |key| std::env::var(key).ok())
+    }
+
+    fn from_lookup(
+        provider: ProviderConfig,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Option<Self> {
+        lookup("EMBEDDING_MODEL")
+            .map(|model| model.trim().to_string())
+            .filter(|model| !model.is_empty())
+            .map(|model| Self::new(provider, &model))
+    }
+}
+
+#[async_trait]
+impl Embedder for RigEmbedder {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        match &self.provider {
+            ProviderConfig::Ollama { host, .. } => {
+                let client: ollama::Client = ollama::Client::builder()
+                    .api_key(Nothing)
+                    .base_url(host)
+                    .build()
+                    .map_err(|e| anyhow::anyhow!("Failed to create Ollama client: {}", e))?;
+                run_embedding(client.embedding_model(&self.model), texts).await
+            }
+            ProviderConfig::Gemini { api_key, .. } => {
+                let client = gemini::Client::new(api_key)
+                    .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {}", e))?;
+                run_embedding(client.embedding_model(&self.model), texts).await
+            }
+            ProviderConfig::OpenAi { api_key, .. } => {
+                let client = openai_client(api_key, None)
+                    .map_err(|e| anyhow::anyhow!("Failed to create OpenAI client: {}", e))?;
+                run_embedding(client.embedding_model(&self.model), texts).await
+            }
+            ProviderConfig::OpenAiCompatible {
+                base_url, api_key, ..
+            } => {
+                // `/embeddings` is shared by both OpenAI APIs; rig only offers it on this client
+                let client = openai_client(api_key, Some(base_url)).map_err(|e| {
+                    anyhow::anyhow!("Failed to create OpenAI-compatible client: {}", e)
+                })?;
+                run_embedding(client.embedding_model(&self.model), texts).await
+            }
+        }
+    }
+}
+
+/// Embed `texts` with `model`, keeping the input order
+async fn run_embedding<M: EmbeddingModel>(model: M, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+    let embeddings = model.embed_texts(texts).await?;
+    Ok(embeddings.into_iter().map(|e| e.vec).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::DEFAULT_OLLAMA_HOST;
+
+    fn ollama() -> ProviderConfig {
+        ProviderConfig::Ollama {
+            host: DEFAULT_OLLAMA_HOST.to_string(),
+            model: "qwen3".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_from_lookup_reads_embedding_model() {
+        let embedder =
+            RigEmbedder::from_lookup(ollama(), |_| Some(" nomic-embed-text ".to_string())).unwrap();
+
+        assert_eq!(embedder.model, "nomic-embed-text");
+    }
+
+    #[test]
+    fn test_from_lookup_unset_or_blank_is_none() {
+        assert!(RigEmbedder::from_lookup(ollama(), |_| None).is_none());
+        assert!(RigEmbedder::from_lookup(ollama(), |_| Some("  ".to_string())).is_none());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_embed_with_local_ollama() {
+        let embedder = RigEmbedder::new(ollama(), "nomic-embed-text");
+
+        let vectors = embedder
+            .embed(vec!["rust".to_string(), "cargo".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(vectors.len(), 2);
+        assert!(!vectors[0].is_empty());
+    }
+}
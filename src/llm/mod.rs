@@ -1,5 +1,7 @@
 mod client;
+mod embedder;
 mod rig_client;
 
-pub use client::LlmClient;
+pub use client::{LlmClient, TextStream};
+pub use embedder::{Embedder, RigEmbedder};
 pub use rig_client::RigClient;
@@ -1,72 +1,231 @@
 use anyhow::Result;
+use futures::StreamExt;
 use rig::{
     client::{CompletionClient, Nothing},
-    completion::{message::AssistantContent, CompletionModel, CompletionRequest},
-    providers::ollama,
+    completion::{
+        message::AssistantContent, CompletionError, CompletionModel, CompletionRequest, Message,
+    },
+    providers::{gemini, ollama},
+    streaming::StreamedAssistantContent,
     OneOrMany,
 };
 
-use super::LlmClient;
+use super::{LlmClient, TextStream};
+use crate::agent::{
+    openai_client, openai_compatible_client, AgentParams, ConfigError, ProviderConfig,
+    DEFAULT_OLLAMA_HOST,
+};
+
+/// Default sampling temperature used by `RigClient::new`
+const DEFAULT_TEMPERATURE: f64 = 0.7;
 
-/// RigClient wraps Rig library to implement LlmClient trait
+/// RigClient wraps Rig library to implement LlmClient trait.
+///
+/// The provider (and its model/API key) is chosen the same way as for agents:
+/// either explicitly via `ProviderConfig` or from the environment with `from_env`.
+#[derive(Clone)]
 pub struct RigClient {
-    model: String,
+    provider: ProviderConfig,
+    temperature: Option<f64>,
+    max_tokens: Option<u64>,
 }
 
 impl RigClient {
+    /// Create an Ollama client with the default temperature (0.7) and no token cap
     pub fn new(model: &str) -> Self {
+        Self::with_params(model, Some(DEFAULT_TEMPERATURE), None)
+    }
+
+    /// Create an Ollama client with explicit sampling parameters.
+    /// `None` leaves the parameter to the provider's default.
+    pub fn with_params(model: &str, temperature: Option<f64>, max_tokens: Option<u64>) -> Self {
         Self {
-            model: model.to_string(),
+            provider: ProviderConfig::Ollama {
+                host: DEFAULT_OLLAMA_HOST.to_string(),
+                model: model.to_string(),
+            },
+            temperature,
+            max_tokens,
         }
     }
-}
 
-impl LlmClient for RigClient {
-    async fn complete(&self, prompt: &str) -> Result<String> {
-        // Create client using builder pattern (rig-core 0.28+)
-        let client: ollama::Client = ollama::Client::builder()
-            .api_key(Nothing)
-            .build()
-            .map_err(|e| anyhow::anyhow!("Failed to create Ollama client: {}", e))?;
+    /// Create a client for an explicit provider configuration
+    pub fn from_config(provider: ProviderConfig) -> Self {
+        Self {
+            provider,
+            temperature: Some(DEFAULT_TEMPERATURE),
+            max_tokens: None,
+        }
+    }
 
-        let comp_model = client.completion_model(&self.model);
+    /// Create the client that summarizes fetched pages for agents on `provider`,
+    /// sampling with the agents' `params`. Without a configured temperature the
+    /// default (0.7) is kept.
+    pub fn summarizer(provider: ProviderConfig, params: &AgentParams) -> Self {
+        let temperature = params.temperature.or(Some(DEFAULT_TEMPERATURE));
+        Self::from_config(provider).params(temperature, params.max_tokens)
+    }
+
+    /// Create a client from `LLM_PROVIDER` / `LLM_MODEL` and the provider's API key
+    ///
+    /// # Errors
+    /// Returns `ConfigError` if the selected provider's API key is missing.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self::from_config(ProviderConfig::from_env()?))
+    }
+
+    /// Override the sampling parameters
+    pub fn params(mut self, temperature: Option<f64>, max_tokens: Option<u64>) -> Self {
+        self.temperature = temperature;
+        self.max_tokens = max_tokens;
+        self
+    }
+}
 
+impl RigClient {
+    /// Build a request whose chat history is `history` followed by `prompt` as a user message
+    fn build_request(&self, history: &[Message], prompt: &str) -> CompletionRequest {
         // Build the user message
-        let user_message = rig::message::Message::User {
+        let user_message = Message::User {
             content: OneOrMany::one(rig::message::UserContent::text(prompt)),
         };
+        let mut messages = history.to_vec();
+        messages.push(user_message);
 
-        let req = CompletionRequest {
+        CompletionRequest {
             model: None,
             output_schema: None,
             preamble: None,
-            chat_history: OneOrMany::one(user_message),
+            chat_history: OneOrMany::many(messages)
+                .expect("chat history always contains the prompt"),
             documents: vec![],
             tools: vec![],
-            temperature: Some(0.7),
-            max_tokens: None,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
             tool_choice: None,
             additional_params: None,
-        };
+        }
+    }
+}
+
+impl LlmClient for RigClient {
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        self.complete_with_history(&[], prompt).await
+    }
 
-        // Parse response
-        let llm_response = comp_model.completion(req).await?;
-        let response_contents = llm_response
-            .choice
-            .iter()
-            .filter_map(|c| match c {
-                AssistantContent::Text(text) => Some(text.text.as_str()),
-                _ => None,
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-        Ok(response_contents)
+    async fn complete_with_history(&self, history: &[Message], prompt: &str) -> Result<String> {
+        let req = self.build_request(history, prompt);
+        match &self.provider {
+            ProviderConfig::Ollama { host, model } => {
+                let client: ollama::Client = ollama::Client::builder()
+                    .api_key(Nothing)
+                    .base_url(host)
+                    .build()
+                    .map_err(|e| anyhow::anyhow!("Failed to create Ollama client: {}", e))?;
+                run_completion(client.completion_model(model), req).await
+            }
+            ProviderConfig::Gemini { api_key, model } => {
+                let client = gemini::Client::new(api_key)
+                    .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {}", e))?;
+                run_completion(client.completion_model(model), req).await
+            }
+            ProviderConfig::OpenAi { api_key, model } => {
+                let client = openai_client(api_key, None)
+                    .map_err(|e| anyhow::anyhow!("Failed to create OpenAI client: {}", e))?;
+                run_completion(client.completion_model(model), req).await
+            }
+            ProviderConfig::OpenAiCompatible {
+                base_url,
+                api_key,
+                model,
+            } => {
+                let client = openai_compatible_client(api_key, base_url).map_err(|e| {
+                    anyhow::anyhow!("Failed to create OpenAI-compatible client: {}", e)
+                })?;
+                run_completion(client.completion_model(model), req).await
+            }
+        }
+    }
+
+    async fn stream_complete(&self, prompt: &str) -> Result<TextStream> {
+        let req = self.build_request(&[], prompt);
+        match &self.provider {
+            ProviderConfig::Ollama { host, model } => {
+                let client: ollama::Client = ollama::Client::builder()
+                    .api_key(Nothing)
+                    .base_url(host)
+                    .build()
+                    .map_err(|e| anyhow::anyhow!("Failed to create Ollama client: {}", e))?;
+                run_stream(client.completion_model(model), req).await
+            }
+            ProviderConfig::Gemini { api_key, model } => {
+                let client = gemini::Client::new(api_key)
+                    .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {}", e))?;
+                run_stream(client.completion_model(model), req).await
+            }
+            ProviderConfig::OpenAi { api_key, model } => {
+                let client = openai_client(api_key, None)
+                    .map_err(|e| anyhow::anyhow!("Failed to create OpenAI client: {}", e))?;
+                run_stream(client.completion_model(model), req).await
+            }
+            ProviderConfig::OpenAiCompatible {
+                base_url,
+                api_key,
+                model,
+            } => {
+                let client = openai_compatible_client(api_key, base_url).map_err(|e| {
+                    anyhow::anyhow!("Failed to create OpenAI-compatible client: {}", e)
+                })?;
+                run_stream(client.completion_model(model), req).await
+            }
+        }
+    }
+}
+
+/// Send a completion request and join the text parts of the response
+async fn run_completion<M: CompletionModel>(model: M, req: CompletionRequest) -> Result<String> {
+    // Parse response
+    let llm_response = model.completion(req).await?;
+    let response_contents = llm_response
+        .choice
+        .iter()
+        .filter_map(|c| match c {
+            AssistantContent::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(response_contents)
+}
+
+/// Send a streaming completion request and map it to a text stream
+async fn run_stream<M: CompletionModel>(model: M, req: CompletionRequest) -> Result<TextStream>
+where
+    M::StreamingResponse: Send + 'static,
+{
+    let stream = model.stream(req).await?;
+    Ok(Box::pin(
+        stream.filter_map(|item| async move { map_stream_item(item) }),
+    ))
+}
+
+/// Convert a raw rig streaming item into a text fragment.
+/// Only text content is forwarded; tool calls, reasoning and the final
+/// provider response are dropped. Errors are passed through.
+fn map_stream_item<R>(
+    item: Result<StreamedAssistantContent<R>, CompletionError>,
+) -> Option<Result<String>> {
+    match item {
+        Ok(StreamedAssistantContent::Text(text)) => Some(Ok(text.text)),
+        Ok(_) => None,
+        Err(e) => Some(Err(e.into())),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::session::{ConversationHistory, DEFAULT_MAX_HISTORY_TURNS};
 
     #[tokio::test]
     #[ignore] // Run with: cargo test -- --ignored
@@ -76,4 +235,155 @@ mod tests {
 
         assert!(!response.is_empty());
     }
+
+    #[tokio::test]
+    #[ignore] // Run with: cargo test -- --ignored
+    async fn test_rig_client_stream_with_ollama() {
+        let client = RigClient::new("llama3.2");
+        let stream = client.stream_complete("Say hello").await.unwrap();
+
+        let chunks: Vec<String> = stream.map(|c| c.unwrap()).collect().await;
+
+        assert!(!chunks.concat().is_empty());
+    }
+
+    #[test]
+    fn test_new_uses_default_params() {
+        let client = RigClient::new("qwen3");
+
+        let req = client.build_request(&[], "hello");
+
+        assert_eq!(req.temperature, Some(DEFAULT_TEMPERATURE));
+        assert_eq!(req.max_tokens, None);
+    }
+
+    #[test]
+    fn test_with_params_threads_params_into_request() {
+        let client = RigClient::with_params("qwen3", Some(0.0), Some(256));
+
+        let req = client.build_request(&[], "hello");
+
+        assert_eq!(req.temperature, Some(0.0));
+        assert_eq!(req.max_tokens, Some(256));
+    }
+
+    #[test]
+    fn test_new_selects_ollama() {
+        let client = RigClient::new("llama3.2");
+
+        assert_eq!(
+            client.provider,
+            ProviderConfig::Ollama {
+                host: DEFAULT_OLLAMA_HOST.to_string(),
+                model: "llama3.2".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_config_uses_selected_provider_and_default_model() {
+        let config = ProviderConfig::from_lookup(|key| match key {
+            "LLM_PROVIDER" => Some("gemini".to_string()),
+            "GEMINI_API_KEY" => Some("key".to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        let client = RigClient::from_config(config);
+
+        assert_eq!(
+            client.provider,
+            ProviderConfig::Gemini {
+                api_key: "key".to_string(),
+                model: crate::agent::default_model("gemini").to_string()
+            }
+        );
+        assert_eq!(client.temperature, Some(DEFAULT_TEMPERATURE));
+    }
+
+    #[test]
+    fn test_params_overrides_sampling_params() {
+        let client = RigClient::from_config(ProviderConfig::OpenAi {
+            api_key: "key".to_string(),
+            model: "gpt-4.1-mini".to_string(),
+        })
+        .params(Some(0.0), Some(64));
+
+        let req = client.build_request(&[], "hello");
+
+        assert_eq!(req.temperature, Some(0.0));
+        assert_eq!(req.max_tokens, Some(64));
+    }
+
+    #[test]
+    fn test_summarizer_requests_carry_the_configured_params() {
+        let provider = ProviderConfig::Ollama {
+            host: DEFAULT_OLLAMA_HOST.to_string(),
+            model: "qwen3".to_string(),
+        };
+        let params = AgentParams {
+            temperature: Some(0.2),
+            top_p: None,
+            max_tokens: Some(300),
+        };
+
+        let configured =
+            RigClient::summarizer(provider.clone(), &params).build_request(&[], "page");
+        let unset =
+            RigClient::summarizer(provider, &AgentParams::default()).build_request(&[], "page");
+
+        assert_eq!(configured.temperature, Some(0.2));
+        assert_eq!(configured.max_tokens, Some(300));
+        assert_eq!(unset.temperature, Some(DEFAULT_TEMPERATURE));
+        assert_eq!(unset.max_tokens, None);
+    }
+
+    #[test]
+    fn test_build_request_appends_prompt_after_history() {
+        let client = RigClient::new("qwen3");
+        let mut history = ConversationHistory::new(DEFAULT_MAX_HISTORY_TURNS);
+        history.add_user("first question");
+        history.add_assistant("first answer");
+
+        let req = client.build_request(history.as_slice(), "second question");
+
+        let messages: Vec<Message> = req.chat_history.into_iter().collect();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0], history.as_slice()[0]);
+        assert_eq!(messages[1], history.as_slice()[1]);
+        assert_eq!(
+            messages[2],
+            Message::User {
+                content: OneOrMany::one(rig::message::UserContent::text("second question")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_map_stream_item_forwards_text() {
+        let item: Result<StreamedAssistantContent<()>, CompletionError> =
+            Ok(StreamedAssistantContent::text("hello"));
+
+        let result = map_stream_item(item).unwrap().unwrap();
+
+        assert_eq!(result, "hello");
+    }
+
+    #[test]
+    fn test_map_stream_item_drops_final_response() {
+        let item: Result<StreamedAssistantContent<()>, CompletionError> =
+            Ok(StreamedAssistantContent::Final(()));
+
+        assert!(map_stream_item(item).is_none());
+    }
+
+    #[test]
+    fn test_map_stream_item_passes_errors_through() {
+        let item: Result<StreamedAssistantContent<()>, CompletionError> =
+            Err(CompletionError::ProviderError("boom".to_string()));
+
+        let result = map_stream_item(item).unwrap();
+
+        assert!(result.unwrap_err().to_string().contains("boom"));
+    }
 }
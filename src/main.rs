@@ -1,13 +1,17 @@
 use dotenvy::dotenv;
 
 #[cfg(all(feature = "cli", not(feature = "web")))]
-use copal::agent::RouterAgent;
+use copal::agent::{RouterAgent, WebFetch, DEFAULT_ASSISTANT_NAME};
+#[cfg(feature = "cli")]
+use copal::cli::Cli;
 #[cfg(all(feature = "cli", not(feature = "web")))]
-use copal::cli::run_interactive;
+use copal::cli::{run_interactive, run_once, Transcript};
+#[cfg(any(feature = "cli", feature = "web"))]
+use copal::config::Config;
 #[cfg(feature = "web")]
 use copal::{
-    agent::RouterAgent,
-    web::{build_router, AppState},
+    agent::{run_warmup, AnyAgent, RouterAgent, WebFetch},
+    web::{any_agent_factory, build_router, AppState},
 };
 #[cfg(feature = "web")]
 use std::sync::Arc;
@@ -19,23 +23,73 @@ async fn main() {
 
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
+    #[cfg(feature = "cli")]
+    let cli = Cli::parse();
+
+    // `copal --check`: verify provider, model and web access, then exit
+    #[cfg(feature = "cli")]
+    if cli.check {
+        let passed = copal::cli::run_self_check().await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    // Every setting is checked once here, so a bad value fails before anything starts
+    #[cfg(any(feature = "cli", feature = "web"))]
+    let config = match Config::from_env() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Configuration error: {e}");
+            std::process::exit(1);
+        }
+    };
+
     // Web server mode has priority (runs if web feature is enabled)
     #[cfg(feature = "web")]
     {
-        let api_token = std::env::var("COPAL_API_TOKEN")
-            .expect("COPAL_API_TOKEN environment variable is required");
-        assert!(!api_token.is_empty(), "COPAL_API_TOKEN must not be empty");
-        let agent = RouterAgent::from_env().await;
-        let app_state = AppState::new(Arc::new(agent), api_token);
+        // `Config::from_env` fails without a token in web builds
+        let api_token = config.api_token.clone().unwrap_or_default();
+        // Per-request model overrides share the default agent's fetch caches
+        let web_fetch = WebFetch::from_config(&config);
+        let settings = config.agent_settings();
+        let chat_only_agent = AnyAgent::chat_only_from_config(config.provider.clone(), &settings);
+        let agent = Arc::new(
+            RouterAgent::from_config(config.provider.clone(), &settings, web_fetch.clone()).await,
+        );
+        let metrics = web_fetch.metrics();
+        let mut app_state = AppState::new(agent.clone(), api_token)
+            .with_chat_only_agent(Arc::new(chat_only_agent))
+            .with_model_overrides(
+                any_agent_factory(web_fetch.clone(), settings),
+                config.model_allowlist.clone(),
+                config.provider_settings.clone(),
+            )
+            .with_web_fetch(web_fetch)
+            .with_chat_body_limit(config.chat_max_body_bytes)
+            .with_max_prompt_chars(config.max_prompt_chars)
+            .with_max_response_chars(config.max_response_chars)
+            .with_max_continuations(config.max_continuations)
+            .with_title_mode(config.session_title_mode);
+        if config.metrics_enabled {
+            app_state = app_state.with_metrics(metrics);
+        }
+        if let Some(name) = config.assistant_name.clone() {
+            app_state = app_state.with_assistant_name(name);
+        }
+        if let Some(limit) = config.session_limit {
+            app_state = app_state.with_session_limit(limit);
+        }
         let router = build_router(Arc::new(app_state));
 
-        // Read PORT from environment (Azure Container Apps injects this dynamically)
-        let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
-        let addr = format!("0.0.0.0:{}", port);
+        // PORT is injected dynamically by Azure Container Apps
+        let addr = format!("0.0.0.0:{}", config.port);
         println!("🚀 Server running on http://{}", addr);
         let listener = tokio::net::TcpListener::bind(&addr)
             .await
             .expect("Failed to create listener");
+        // Load the model in the background so startup isn't held up by a slow provider
+        if config.warmup {
+            tokio::spawn(async move { run_warmup(agent.as_ref()).await });
+        }
         axum::serve(listener, router)
             .await
             .expect("Failed to start server");
@@ -45,8 +99,42 @@ async fn main() {
     // CLI mode (only runs if web feature is disabled)
     #[cfg(all(feature = "cli", not(feature = "web")))]
     {
-        let agent = RouterAgent::from_env().await;
-        run_interactive(agent).await;
+        let web_fetch = WebFetch::from_config(&config);
+        let agent =
+            RouterAgent::from_config(config.provider.clone(), &config.agent_settings(), web_fetch)
+                .await;
+        match cli.prompt {
+            Some(prompt) => {
+                if !run_once(
+                    agent,
+                    &prompt,
+                    cli.format,
+                    config.max_prompt_chars,
+                    config.max_response_chars,
+                )
+                .await
+                {
+                    std::process::exit(1);
+                }
+            }
+            None => {
+                let transcript = cli.record.map(|path| {
+                    Transcript::open(&path).unwrap_or_else(|e| {
+                        eprintln!("Cannot record to {}: {e}", path.display());
+                        std::process::exit(1);
+                    })
+                });
+                let name = config.assistant_name.as_deref();
+                run_interactive(
+                    agent,
+                    transcript,
+                    name.unwrap_or(DEFAULT_ASSISTANT_NAME),
+                    config.max_prompt_chars,
+                    config.max_response_chars,
+                )
+                .await
+            }
+        }
     }
 
     #[cfg(not(any(feature = "cli", feature = "web")))]
@@ -0,0 +1,170 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::collectors::robots::RobotsStats;
+use crate::collectors::web::FetchFailure;
+
+/// Process-wide counters exposed in Prometheus text format.
+/// Shared via `Arc` between the web server and the fetch tools.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    chat_requests: AtomicU64,
+    tokens: AtomicU64,
+    web_fetches: AtomicU64,
+    fetch_errors: AtomicU64,
+    robots_blocks: AtomicU64,
+    /// Shared with the fetch tools' `RobotsCache`
+    robots: Arc<RobotsStats>,
+}
+
+impl Metrics {
+    /// Count an incoming chat request
+    pub fn record_chat_request(&self) {
+        self.chat_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add the tokens a provider reported for one response
+    pub fn record_tokens(&self, tokens: u64) {
+        self.tokens.fetch_add(tokens, Ordering::Relaxed);
+    }
+
+    /// Count a page fetch. Failures caused by robots.txt are also counted as robots blocks.
+    pub fn record_fetch(&self, error: Option<&anyhow::Error>) {
+        let Some(error) = error else {
+            self.web_fetches.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+        self.fetch_errors.fetch_add(1, Ordering::Relaxed);
+        if let Some(FetchFailure::RobotsDisallowed(_)) = error.downcast_ref::<FetchFailure>() {
+            self.robots_blocks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counters for a `RobotsCache` to update (see `RobotsCache::with_stats`)
+    pub(crate) fn robots_stats(&self) -> Arc<RobotsStats> {
+        Arc::clone(&self.robots)
+    }
+
+    /// Render all counters in the Prometheus text exposition format.
+    /// `active_sessions` is a gauge owned by the caller (the session store).
+    pub fn render(&self, active_sessions: usize) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            let _ = writeln!(out, "{name} {value}");
+        };
+        metric(
+            "copal_chat_requests_total",
+            "counter",
+            "Chat requests received.",
+            self.chat_requests.load(Ordering::Relaxed),
+        );
+        metric(
+            "copal_active_sessions",
+            "gauge",
+            "Sessions currently held in memory.",
+            active_sessions as u64,
+        );
+        metric(
+            "copal_tokens_total",
+            "counter",
+            "Tokens used by chat responses, as reported by the provider.",
+            self.tokens.load(Ordering::Relaxed),
+        );
+        metric(
+            "copal_web_fetches_total",
+            "counter",
+            "Pages fetched successfully.",
+            self.web_fetches.load(Ordering::Relaxed),
+        );
+        metric(
+            "copal_fetch_errors_total",
+            "counter",
+            "Page fetches that failed, including robots.txt blocks.",
+            self.fetch_errors.load(Ordering::Relaxed),
+        );
+        metric(
+            "copal_robots_blocks_total",
+            "counter",
+            "Page fetches refused by robots.txt.",
+            self.robots_blocks.load(Ordering::Relaxed),
+        );
+        metric(
+            "copal_robots_cache_hits_total",
+            "counter",
+            "robots.txt checks answered from the cache.",
+            self.robots.hits(),
+        );
+        metric(
+            "copal_robots_cache_misses_total",
+            "counter",
+            "robots.txt checks that had to fetch robots.txt.",
+            self.robots.misses(),
+        );
+        metric(
+            "copal_robots_fetch_failures_total",
+            "counter",
+            "robots.txt fetches that failed or didn't parse (treated as allow-all).",
+            self.robots.fetch_failures(),
+        );
+        metric(
+            "copal_robots_disallows_total",
+            "counter",
+            "robots.txt checks that refused the URL.",
+            self.robots.disallows(),
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Parse Prometheus text output into `name -> value`, failing on malformed lines
+    fn parse(text: &str) -> HashMap<String, u64> {
+        text.lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                let (name, value) = line.split_once(' ').expect("sample line");
+                (name.to_string(), value.parse().expect("integer value"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_reports_incremented_counters() {
+        let metrics = Metrics::default();
+        metrics.record_chat_request();
+        metrics.record_chat_request();
+        metrics.record_tokens(120);
+        metrics.record_fetch(None);
+        metrics.record_fetch(Some(&anyhow::anyhow!("connection reset")));
+        metrics.record_fetch(Some(
+            &FetchFailure::RobotsDisallowed("https://example.com/".to_string()).into(),
+        ));
+
+        let samples = parse(&metrics.render(3));
+
+        assert_eq!(samples["copal_chat_requests_total"], 2);
+        assert_eq!(samples["copal_active_sessions"], 3);
+        assert_eq!(samples["copal_tokens_total"], 120);
+        assert_eq!(samples["copal_web_fetches_total"], 1);
+        assert_eq!(samples["copal_fetch_errors_total"], 2);
+        assert_eq!(samples["copal_robots_blocks_total"], 1);
+        assert_eq!(samples["copal_robots_cache_hits_total"], 0);
+        assert_eq!(samples["copal_robots_cache_misses_total"], 0);
+    }
+
+    #[test]
+    fn test_render_declares_type_for_every_sample() {
+        let text = Metrics::default().render(0);
+
+        for name in parse(&text).keys() {
+            assert!(text.contains(&format!("# TYPE {name} ")), "{name}");
+        }
+    }
+}
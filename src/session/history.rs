@@ -6,19 +6,54 @@ use rig::OneOrMany;
 /// Default maximum number of conversation turns to keep
 pub const DEFAULT_MAX_HISTORY_TURNS: usize = 50;
 
+/// Heading of the summary turn kept by `TrimStrategy::Summarize`
+const SUMMARY_HEADING: &str = "Summary of the earlier conversation:";
+/// Assistant reply that closes the summary turn
+const SUMMARY_ACK: &str = "Noted.";
+/// Characters of each trimmed message kept in the summary
+const SUMMARY_EXCERPT_CHARS: usize = 200;
+/// Lines kept in the summary; the oldest are dropped beyond this
+const SUMMARY_MAX_LINES: usize = 20;
+
+/// How `ConversationHistory` makes room once it exceeds its max turns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrimStrategy {
+    /// Drop the oldest turn
+    #[default]
+    KeepRecent,
+    /// Always keep the first turn (the opening context) and drop the oldest turn after it
+    KeepFirstAndRecent,
+    /// Fold the oldest turns into a leading summary turn of short excerpts.
+    /// The summary is extractive; no model is called.
+    Summarize,
+}
+
 /// Manages conversation history for multi-turn dialogue
 #[derive(Clone)]
 pub struct ConversationHistory {
     messages: Vec<Message>,
     max_turns: usize,
+    strategy: TrimStrategy,
+    /// Excerpts of trimmed messages (`Summarize` only); when non-empty, the first
+    /// two messages are the summary turn
+    summary: Vec<String>,
 }
 
 impl ConversationHistory {
     /// Create a new conversation history with specified max turns
     pub fn new(max_turns: usize) -> Self {
+        Self::new_with_strategy(max_turns, TrimStrategy::KeepRecent)
+    }
+
+    /// Create a new conversation history that trims with `strategy`.
+    /// Strategies that pin a leading turn fall back to `KeepRecent` when
+    /// `max_turns` is below 2, since there'd be no room left for new turns.
+    pub fn new_with_strategy(max_turns: usize, strategy: TrimStrategy) -> Self {
         Self {
             messages: Vec::new(),
             max_turns: min(max_turns, DEFAULT_MAX_HISTORY_TURNS),
+            strategy,
+            summary: Vec::new(),
         }
     }
 
@@ -56,19 +91,145 @@ impl ConversationHistory {
         self.messages.is_empty()
     }
 
+    /// Remove all messages, keeping the configured max turns
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.summary.clear();
+    }
+
+    /// Keep only the first `len` messages (no-op if there are fewer)
+    pub fn truncate(&mut self, len: usize) {
+        self.messages.truncate(len);
+        if len < 2 {
+            self.summary.clear();
+        }
+    }
+
     /// Clone the conversation history as a Vec
     pub fn to_vec(&self) -> Vec<Message> {
         self.messages.clone()
     }
 
-    /// Trim old messages if history exceeds max turns
+    /// Trim old messages if history exceeds max turns, according to the strategy
     fn trim_if_needed(&mut self) {
-        if self.messages.len() > self.max_turns * 2 {
-            self.messages.drain(0..2);
+        let strategy = if self.max_turns < 2 {
+            TrimStrategy::KeepRecent
+        } else {
+            self.strategy
+        };
+        while self.messages.len() > self.max_turns * 2 {
+            match strategy {
+                TrimStrategy::KeepRecent => {
+                    self.messages.drain(0..2);
+                }
+                TrimStrategy::KeepFirstAndRecent => {
+                    self.messages.drain(2..4);
+                }
+                TrimStrategy::Summarize => self.summarize_oldest_turn(),
+            }
+        }
+    }
+
+    /// Move the oldest turn after the summary turn into the summary
+    fn summarize_oldest_turn(&mut self) {
+        let start = if self.summary.is_empty() { 0 } else { 2 };
+        let trimmed: Vec<Message> = self.messages.drain(start..start + 2).collect();
+        self.summary.extend(trimmed.iter().filter_map(summary_line));
+        let overflow = self.summary.len().saturating_sub(SUMMARY_MAX_LINES);
+        self.summary.drain(..overflow);
+
+        let summary_turn = summary_turn(&self.summary);
+        if start == 0 {
+            self.messages.splice(0..0, summary_turn);
+        } else {
+            self.messages.splice(0..2, summary_turn);
         }
     }
 }
 
+/// One summary line for a trimmed message: its role and a short excerpt.
+/// None if the message has no text.
+pub(crate) fn summary_line(msg: &Message) -> Option<String> {
+    let text = message_text(msg)?;
+    let role = match message_role(msg) {
+        MessageRole::User => "User",
+        MessageRole::Assistant => "Assistant",
+    };
+    let excerpt: String = text.chars().take(SUMMARY_EXCERPT_CHARS).collect();
+    let ellipsis = if excerpt.len() < text.len() {
+        "…"
+    } else {
+        ""
+    };
+    Some(format!("- {}: {}{}", role, excerpt, ellipsis))
+}
+
+/// The user/assistant turn that stands in for trimmed messages, built from
+/// their `summary_line`s (only the latest `SUMMARY_MAX_LINES` are kept)
+pub(crate) fn summary_turn(lines: &[String]) -> [Message; 2] {
+    let lines = &lines[lines.len().saturating_sub(SUMMARY_MAX_LINES)..];
+    [
+        Message::user(format!("{}\n{}", SUMMARY_HEADING, lines.join("\n"))),
+        Message::assistant(SUMMARY_ACK),
+    ]
+}
+
+/// Who sent a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageRole {
+    User,
+    Assistant,
+}
+
+/// The role of a message's sender
+pub fn message_role(msg: &Message) -> MessageRole {
+    match msg {
+        Message::User { .. } => MessageRole::User,
+        Message::Assistant { .. } => MessageRole::Assistant,
+    }
+}
+
+/// Extract the text of a message of either role.
+/// Multiple text parts are joined with newlines; other parts (images, tool calls,
+/// tool results, ...) are skipped. Returns None if the message has no text at all.
+pub fn message_text(msg: &Message) -> Option<String> {
+    let parts: Vec<&str> = match msg {
+        Message::User { content } => content
+            .iter()
+            .filter_map(|part| match part {
+                UserContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|part| match part {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect(),
+    };
+    (!parts.is_empty()).then(|| parts.join("\n"))
+}
+
+/// Extract the text of a User message.
+/// Returns None for assistant messages or non-text content.
+pub fn extract_user_text(msg: &Message) -> Option<String> {
+    match message_role(msg) {
+        MessageRole::User => message_text(msg),
+        MessageRole::Assistant => None,
+    }
+}
+
+/// Extract the text of an Assistant message.
+/// Returns None for user messages or non-text content.
+pub fn extract_assistant_text(msg: &Message) -> Option<String> {
+    match message_role(msg) {
+        MessageRole::Assistant => message_text(msg),
+        MessageRole::User => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,25 +318,134 @@ mod tests {
         );
     }
 
-    /// Helper to extract text from User message
-    fn extract_user_text(msg: &Message) -> Option<String> {
-        match msg {
-            Message::User { content } => match content.first_ref() {
-                UserContent::Text(text) => Some(text.text.clone()),
-                _ => None,
-            },
-            _ => None,
-        }
+    #[test]
+    fn test_truncate_keeps_prefix() {
+        let mut sut = ConversationHistory::new(2);
+        sut.add_user("user1");
+        sut.add_assistant("assistant1");
+        sut.add_user("user2");
+
+        sut.truncate(1);
+
+        assert_eq!(sut.len(), 1);
+        assert_eq!(
+            extract_user_text(&sut.as_slice()[0]),
+            Some("user1".to_string())
+        );
     }
 
-    /// Helper to extract text from Assistant message
-    fn extract_assistant_text(msg: &Message) -> Option<String> {
-        match msg {
-            Message::Assistant { content, .. } => match content.first_ref() {
-                AssistantContent::Text(text) => Some(text.text.clone()),
-                _ => None,
-            },
-            _ => None,
+    #[test]
+    fn test_clear_removes_all_messages() {
+        let mut sut = ConversationHistory::new(2);
+        sut.add_user("user1");
+        sut.add_assistant("assistant1");
+
+        sut.clear();
+
+        assert!(sut.is_empty());
+        assert_eq!(sut.max_turns, 2);
+    }
+
+    #[test]
+    fn test_message_text_joins_text_parts() {
+        let msg = Message::User {
+            content: OneOrMany::many(vec![
+                UserContent::text("first"),
+                UserContent::image_url("https://example.com/a.png", None, None),
+                UserContent::text("second"),
+            ])
+            .unwrap(),
+        };
+
+        assert_eq!(message_role(&msg), MessageRole::User);
+        assert_eq!(message_text(&msg).as_deref(), Some("first\nsecond"));
+        assert_eq!(extract_assistant_text(&msg), None);
+    }
+
+    #[test]
+    fn test_message_text_is_none_without_text_parts() {
+        let tool_call = Message::Assistant {
+            id: None,
+            content: OneOrMany::one(AssistantContent::tool_call(
+                "call_1",
+                "web_fetch",
+                serde_json::json!({ "url": "https://example.com" }),
+            )),
+        };
+        let image = Message::User {
+            content: OneOrMany::one(UserContent::image_url(
+                "https://example.com/a.png",
+                None,
+                None,
+            )),
+        };
+
+        assert_eq!(message_role(&tool_call), MessageRole::Assistant);
+        assert_eq!(message_text(&tool_call), None);
+        assert_eq!(message_text(&image), None);
+    }
+
+    /// Add `turns` user/assistant pairs named user1/assistant1, user2/assistant2, ...
+    fn add_turns(sut: &mut ConversationHistory, turns: usize) {
+        for i in 1..=turns {
+            sut.add_user(&format!("user{i}"));
+            sut.add_assistant(&format!("assistant{i}"));
         }
     }
+
+    fn texts(sut: &ConversationHistory) -> Vec<String> {
+        sut.as_slice().iter().filter_map(message_text).collect()
+    }
+
+    #[test]
+    fn test_keep_recent_strategy_retains_latest_turns() {
+        let mut sut = ConversationHistory::new_with_strategy(2, TrimStrategy::KeepRecent);
+        add_turns(&mut sut, 4);
+
+        assert_eq!(texts(&sut), ["user3", "assistant3", "user4", "assistant4"]);
+    }
+
+    #[test]
+    fn test_keep_first_and_recent_strategy_retains_first_turn() {
+        let mut sut = ConversationHistory::new_with_strategy(3, TrimStrategy::KeepFirstAndRecent);
+        add_turns(&mut sut, 5);
+
+        assert_eq!(
+            texts(&sut),
+            [
+                "user1",
+                "assistant1",
+                "user4",
+                "assistant4",
+                "user5",
+                "assistant5"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize_strategy_folds_trimmed_turns_into_summary() {
+        let mut sut = ConversationHistory::new_with_strategy(3, TrimStrategy::Summarize);
+        add_turns(&mut sut, 5);
+
+        let texts = texts(&sut);
+        assert_eq!(sut.len(), 6);
+        assert_eq!(
+            texts[0],
+            format!(
+                "{}\n- User: user1\n- Assistant: assistant1\n- User: user2\n- Assistant: assistant2\n- User: user3\n- Assistant: assistant3",
+                SUMMARY_HEADING
+            )
+        );
+        assert_eq!(texts[1], SUMMARY_ACK);
+        assert_eq!(texts[2..], ["user4", "assistant4", "user5", "assistant5"]);
+    }
+
+    #[test]
+    fn test_pinning_strategies_fall_back_to_keep_recent_with_one_turn() {
+        let mut sut = ConversationHistory::new_with_strategy(1, TrimStrategy::KeepFirstAndRecent);
+        add_turns(&mut sut, 2);
+
+        assert_eq!(texts(&sut), ["user2", "assistant2"]);
+    }
 }
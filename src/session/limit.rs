@@ -0,0 +1,30 @@
+use std::str::FromStr;
+
+/// What happens when a new session would exceed the session cap (`SESSION_EVICTION`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionEviction {
+    /// Refuse to create the session
+    #[default]
+    Reject,
+    /// Drop the least recently used session to make room
+    LeastRecentlyUsed,
+}
+
+impl FromStr for SessionEviction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "reject" => Ok(Self::Reject),
+            "lru" => Ok(Self::LeastRecentlyUsed),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Cap on the number of sessions held in memory (`MAX_SESSIONS`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionLimit {
+    pub max_sessions: usize,
+    pub eviction: SessionEviction,
+}
@@ -1,3 +1,7 @@
 pub mod history;
+pub mod limit;
+pub mod title;
 
 pub use history::*;
+pub use limit::*;
+pub use title::*;
@@ -0,0 +1,161 @@
+use std::str::FromStr;
+
+use futures::StreamExt;
+
+use crate::agent::{truncate_at_boundary, ChatAgent, ChatStreamEvent};
+
+/// Longest auto-generated session title, in characters (before the ellipsis)
+pub const MAX_TITLE_CHARS: usize = 60;
+
+/// Asks the model for a title; the first user message is appended
+const TITLE_PROMPT: &str = "Write a short title (at most 6 words) for a conversation that starts \
+with the message below. Reply with the title only, without quotes.\n\n";
+
+/// How sessions get their titles (`SESSION_TITLE_MODE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionTitleMode {
+    /// The first user message, shortened
+    #[default]
+    FirstMessage,
+    /// The first user message at first, then a title written by the model
+    /// once the first reply is done
+    Llm,
+}
+
+impl FromStr for SessionTitleMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "first_message" => Ok(Self::FirstMessage),
+            "llm" => Ok(Self::Llm),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Title for a session whose first user message is `message`: the message
+/// on one line, cut at a sentence or word boundary within `MAX_TITLE_CHARS`
+/// characters and marked with an ellipsis. Returns None for a blank message.
+pub fn title_from_message(message: &str) -> Option<String> {
+    let line = message.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.is_empty() {
+        return None;
+    }
+    let title = truncate_at_boundary(&line, MAX_TITLE_CHARS);
+    if title.len() == line.len() {
+        Some(line)
+    } else {
+        Some(format!("{}…", title))
+    }
+}
+
+/// Ask `agent` for a short title for a conversation opening with `message`.
+/// Returns None if the agent fails or replies with nothing usable.
+pub async fn generate_title(agent: &dyn ChatAgent, message: &str) -> Option<String> {
+    let prompt = format!("{}{}", TITLE_PROMPT, message);
+    let mut stream = agent.stream_chat(&prompt, vec![]).await;
+    let mut reply = String::new();
+    while let Some(event) = stream.next().await {
+        match event {
+            ChatStreamEvent::TextDelta(text) => reply.push_str(&text),
+            ChatStreamEvent::Done => break,
+            ChatStreamEvent::Error(_) => return None,
+            _ => {}
+        }
+    }
+    clean_generated_title(&reply)
+}
+
+/// Strip the decoration models tend to add around a title
+/// (a `Title:` label, quotes, Markdown emphasis) and keep its first line.
+fn clean_generated_title(reply: &str) -> Option<String> {
+    let line = reply.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let line = line.trim_matches(|c: char| c.is_whitespace() || "\"'`*#「」".contains(c));
+    title_from_message(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::MockAgent;
+
+    #[test]
+    fn test_title_mode_from_str() {
+        assert_eq!(" LLM ".parse(), Ok(SessionTitleMode::Llm));
+        assert_eq!("first_message".parse(), Ok(SessionTitleMode::FirstMessage));
+        assert_eq!("first".parse::<SessionTitleMode>(), Err(()));
+    }
+
+    #[test]
+    fn test_short_message_is_used_as_is() {
+        assert_eq!(
+            title_from_message("What's the weather in Kyoto?").as_deref(),
+            Some("What's the weather in Kyoto?")
+        );
+    }
+
+    #[test]
+    fn test_whitespace_is_collapsed_to_one_line() {
+        assert_eq!(
+            title_from_message("  Plan a trip\n\nto   Osaka  ").as_deref(),
+            Some("Plan a trip to Osaka")
+        );
+    }
+
+    #[test]
+    fn test_long_message_is_cut_between_words() {
+        let message = "Compare the memory models of Rust and C++ with examples of data races and \
+                       how each language prevents them";
+
+        let title = title_from_message(message).unwrap();
+
+        assert_eq!(
+            title,
+            "Compare the memory models of Rust and C++ with examples of…"
+        );
+        assert!(title.chars().count() <= MAX_TITLE_CHARS + 1);
+    }
+
+    #[test]
+    fn test_long_message_without_spaces_is_cut_at_the_limit() {
+        let message = "東".repeat(100);
+
+        let title = title_from_message(&message).unwrap();
+
+        assert_eq!(title, format!("{}…", "東".repeat(MAX_TITLE_CHARS)));
+    }
+
+    #[test]
+    fn test_blank_message_has_no_title() {
+        assert_eq!(title_from_message(" \n\t"), None);
+    }
+
+    #[test]
+    fn test_clean_generated_title_strips_decoration() {
+        assert_eq!(
+            clean_generated_title("Title: \"Kyoto Trip Plan\"\n").as_deref(),
+            Some("Kyoto Trip Plan")
+        );
+        assert_eq!(
+            clean_generated_title("\n**Rust vs C++**").as_deref(),
+            Some("Rust vs C++")
+        );
+        assert_eq!(clean_generated_title("  \n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_generate_title_uses_agent_reply() {
+        let agent = MockAgent::with_response("\"Weekend in Kyoto\"");
+
+        let title = generate_title(&agent, "Plan a weekend in Kyoto").await;
+
+        assert_eq!(title.as_deref(), Some("Weekend in Kyoto"));
+        // The title request is sent without the session's history
+        assert!(agent.received_histories()[0].is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+//! Lock helpers that recover from poisoning.
+//!
+//! The shared maps behind these locks (sessions, in-flight fetches, rate-limit
+//! buckets) are only changed by single inserts/removes, so one left behind by a
+//! panicking thread is still consistent. Recovering keeps one failed request
+//! from turning every later request that touches the map into a panic.
+
+use std::sync::{Mutex, MutexGuard};
+#[cfg(feature = "web")]
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+const POISONED_LOCK_WARNING: &str = "A lock was poisoned by a panicked thread; recovering";
+
+/// Lock `mutex`, recovering it if a thread panicked while holding it
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log::warn!("{POISONED_LOCK_WARNING}");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// Shared access to `rwlock`, recovering from poisoning like `lock`
+#[cfg(feature = "web")]
+pub(crate) fn read<T>(rwlock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    rwlock.read().unwrap_or_else(|poisoned| {
+        log::warn!("{POISONED_LOCK_WARNING}");
+        rwlock.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// Exclusive access to `rwlock`, recovering from poisoning like `lock`
+#[cfg(feature = "web")]
+pub(crate) fn write<T>(rwlock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    rwlock.write().unwrap_or_else(|poisoned| {
+        log::warn!("{POISONED_LOCK_WARNING}");
+        rwlock.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_lock_recovers_and_clears_poison() {
+        let mutex = Arc::new(Mutex::new(vec![1]));
+        let poisoner = Arc::clone(&mutex);
+        let result = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("simulated panic");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(mutex.is_poisoned());
+
+        lock(&mutex).push(2);
+
+        assert_eq!(*lock(&mutex), [1, 2]);
+        assert!(!mutex.is_poisoned());
+    }
+}
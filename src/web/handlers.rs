@@ -1,14 +1,34 @@
 use axum::{
-    extract::State,
-    response::sse::{Event, Sse},
+    body::{Body, Bytes},
+    extract::{rejection::JsonRejection, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, KeepAliveStream, Sse},
+        IntoResponse,
+    },
     Json,
 };
-use futures::{channel::mpsc, stream::Stream, SinkExt, StreamExt};
+use futures::{
+    stream::{BoxStream, Stream},
+    StreamExt,
+};
+use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::agent::{
+    check_prompt_length, continue_truncated, fetch_grounding_documents, limit_response_chars,
+    ChatAgent, ChatStreamEvent, TurnTiming,
+};
+use crate::web::idempotency::{fingerprint, IdempotencyKey, Lookup};
+use crate::web::{AppState, ExportFormat, SessionSummary, TranscriptMessage};
 
-use crate::agent::ChatStreamEvent;
-use crate::web::AppState;
+/// Idle time after which an SSE keep-alive comment is sent, so proxies don't
+/// drop the connection while the model is thinking before its first token
+const SSE_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
 
 /// Request body for the chat endpoint
 #[derive(Debug, Deserialize)]
@@ -17,71 +37,380 @@ pub struct ChatRequest {
     pub session_id: Option<String>,
     /// The user's message
     pub message: String,
+    /// Optional provider override (e.g. "openai"); must be in `CHAT_MODEL_ALLOWLIST`
+    pub provider: Option<String>,
+    /// Optional model override; must be in `CHAT_MODEL_ALLOWLIST`
+    pub model: Option<String>,
+    /// Whether the agent may use tools such as web fetching (default: true)
+    pub tools_enabled: Option<bool>,
+    /// Which events to stream back (default: `full`)
+    #[serde(default)]
+    pub verbosity: Verbosity,
+    /// Fetch the URLs in the message up front and attach them to the request
+    /// as documents to answer from (default: false)
+    #[serde(default)]
+    pub ground: bool,
+}
+
+/// How much of the agent's work a chat response streams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    /// Only the answer: `text`, `done` and `error` events
+    Answer,
+    /// Every event, including tool use, thinking, sources and warnings
+    #[default]
+    Full,
+}
+
+impl Verbosity {
+    /// Whether `data` is streamed at this verbosity
+    fn includes(self, data: &SseEventData) -> bool {
+        match self {
+            Verbosity::Full => true,
+            Verbosity::Answer => matches!(
+                data,
+                SseEventData::Text { .. } | SseEventData::Done { .. } | SseEventData::Error { .. }
+            ),
+        }
+    }
 }
 
+/// Request header naming a chat request, so a retry replays the first response
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
 /// SSE event data sent to the client
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum SseEventData {
     /// Text delta from the assistant
     Text { content: String },
-    /// Stream completed, includes session_id for future requests
-    Done { session_id: String },
-    /// Error occurred during processing
-    Error { message: String },
+    /// Fragment of the model's reasoning, for an optional "thinking" panel;
+    /// not part of the answer or the session history
+    Thinking { content: String },
+    /// Stream completed, includes session_id for future requests and the
+    /// time to first token in milliseconds (absent if no text was produced).
+    /// `truncated` is set when the answer was cut off at the model's output
+    /// token limit, so the client can offer to continue it.
+    Done {
+        session_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ttft_ms: Option<u64>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        truncated: bool,
+    },
+    /// Error occurred during processing; `code` tells the client whether retrying may help
+    Error { message: String, code: ErrorCode },
     /// The agent invoked a tool (e.g. web_search, web_fetch)
     ToolUse { tool_name: String },
+    /// The response continues, but something didn't go as planned
+    /// (e.g. the tool-round limit was reached)
+    Warning { message: String },
+    /// A page fetched by a tool that can be cited as a source
+    Source { url: String, title: Option<String> },
+}
+
+/// Machine-readable category of a stream error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The provider throttled the request; retrying after a delay may succeed
+    RateLimited,
+    /// The provider is down, overloaded or unreachable; retrying may succeed
+    ProviderUnavailable,
+    /// The conversation no longer fits the model's context window
+    ContextTooLong,
+    /// The API key is missing, invalid or lacks access
+    AuthenticationFailed,
+    /// The session was removed (e.g. evicted at the session limit) during the turn
+    SessionNotFound,
+    /// Anything else
+    Unknown,
+}
+
+/// Words that introduce an HTTP status in provider errors, as in
+/// `Invalid status code: 429`, `"code":429` or `HttpError: 503 Service Unavailable`
+const STATUS_MARKERS: &[&str] = &["status", "code", "http", "httperror"];
+
+/// The HTTP status a provider error reports: a whole three-digit number in
+/// 100..=599 that opens the message or directly follows a `STATUS_MARKERS` word.
+/// Numbers elsewhere (token counts, org IDs, ports) are ignored.
+fn http_status(message: &str) -> Option<u16> {
+    let mut previous: Option<&str> = None;
+    for token in message
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|t| !t.is_empty())
+    {
+        let after_marker = previous.is_none_or(|p| STATUS_MARKERS.contains(&p));
+        if after_marker && token.len() == 3 {
+            if let Some(status) = token.parse().ok().filter(|s| (100..=599).contains(s)) {
+                return Some(status);
+            }
+        }
+        previous = Some(token);
+    }
+    None
+}
+
+/// Classify a provider error message by the HTTP status and phrases
+/// OpenAI, Gemini and Ollama use in their error responses.
+pub(crate) fn classify_error(message: &str) -> ErrorCode {
+    let message = message.to_lowercase();
+    let mentions = |needles: &[&str]| needles.iter().any(|n| message.contains(n));
+
+    // Checked first: context errors are often plain 400s that mention "tokens"
+    if mentions(&[
+        "context length",
+        "context_length_exceeded",
+        "context window",
+        "maximum number of tokens",
+        "too many tokens",
+        "prompt is too long",
+    ]) {
+        return ErrorCode::ContextTooLong;
+    }
+    match http_status(&message) {
+        Some(429) => return ErrorCode::RateLimited,
+        Some(401 | 403) => return ErrorCode::AuthenticationFailed,
+        Some(500..=599) => return ErrorCode::ProviderUnavailable,
+        _ => {}
+    }
+    if mentions(&[
+        "rate limit",
+        "too many requests",
+        "resource_exhausted",
+        "quota",
+    ]) {
+        ErrorCode::RateLimited
+    } else if mentions(&[
+        "unauthorized",
+        "unauthenticated",
+        "permission_denied",
+        "api key",
+    ]) {
+        ErrorCode::AuthenticationFailed
+    } else if mentions(&[
+        "overloaded",
+        "unavailable",
+        "connection refused",
+        "error sending request",
+        "timed out",
+    ]) {
+        ErrorCode::ProviderUnavailable
+    } else {
+        ErrorCode::Unknown
+    }
+}
+
+/// Query parameters for the session export endpoint
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// `md` (default) or `json`
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Request body for the session truncate endpoint
+#[derive(Debug, Deserialize)]
+pub struct TruncateRequest {
+    /// 0-based index of the last message to keep
+    pub index: usize,
+}
+
+/// Request body for the session creation endpoint
+#[derive(Debug, Deserialize)]
+pub struct CreateSessionRequest {
+    /// Optional system preamble for this session's agent calls
+    pub preamble: Option<String>,
+}
+
+/// Request body for the session rename endpoint
+#[derive(Debug, Deserialize)]
+pub struct RenameSessionRequest {
+    pub title: String,
+}
+
+/// Response body for the session listing endpoint
+#[derive(Debug, Serialize)]
+pub struct SessionListResponse {
+    /// Sessions held in memory, most recently used first
+    pub sessions: Vec<SessionSummary>,
+}
+
+/// Response body for the health endpoint
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: &'static str,
+    /// Name the assistant goes by (`ASSISTANT_NAME`, default "Copal")
+    pub assistant_name: String,
+}
+
+/// Response body for the cache clearing endpoint
+#[derive(Debug, Serialize)]
+pub struct CacheClearResponse {
+    /// Cached entries dropped (robots.txt results, one per origin)
+    pub evicted: usize,
+}
+
+/// Response body for the session creation and import endpoints
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    /// ID of the newly created session
+    pub session_id: String,
+}
+
+/// Frame chat events as SSE events
+fn sse_events(
+    events: impl Stream<Item = SseEventData>,
+) -> impl Stream<Item = Result<Event, std::convert::Infallible>> {
+    events.map(|data| Ok(Event::default().json_data(data).unwrap()))
 }
 
-/// Internal function that returns a stream of SSE events
-/// Separated for testability - tests can consume this stream directly
-async fn chat_stream(
+/// Run one chat turn and stream its client-facing events, independent of framing
+/// (SSE or NDJSON). The assistant's reply is saved to the session on `Done`.
+/// With `ground`, the pages linked in `message` are fetched first and attached
+/// to the request as documents, and reported as sources (or warnings). If no
+/// page could be attached, the turn runs as an ordinary one with tools. A
+/// grounded answer cut off at the token limit is not continued, since the
+/// continuation request wouldn't carry the documents.
+///
+/// If the client disconnects (the returned stream is dropped), the spawned task
+/// stops immediately and drops the agent stream, cancelling the provider request.
+async fn chat_events(
     state: Arc<AppState>,
+    agent: Arc<dyn ChatAgent>,
     session_id: String,
     message: String,
-) -> impl Stream<Item = Result<Event, std::convert::Infallible>> {
-    let (mut tx, rx) = mpsc::channel::<Event>(100);
+    ground: bool,
+) -> impl Stream<Item = SseEventData> {
+    let (tx, rx) = mpsc::channel::<SseEventData>(100);
 
     tokio::spawn(async move {
         let prompt = message;
 
         let mut response_text = String::new();
-        let mut agent_stream = state
-            .agent
-            .stream_chat(&prompt, state.get_session(&session_id).unwrap().to_vec())
-            .await;
+        let mut truncated = false;
+        let mut timing = TurnTiming::start();
+        let Some(history) = state.agent_history(&session_id) else {
+            _ = tx.send(session_gone(&session_id)).await;
+            return;
+        };
+        // Only the message of this turn is in the history yet
+        let first_turn = state.get_session(&session_id).is_some_and(|h| h.len() == 1);
+        let (documents, fetch_events) = match state.web_fetch().filter(|_| ground) {
+            Some(web_fetch) => fetch_grounding_documents(web_fetch, &prompt).await,
+            None => (Vec::new(), Vec::new()),
+        };
+        let grounded = !documents.is_empty();
+        let answer = if grounded {
+            agent
+                .stream_chat_with_documents(&prompt, history.clone(), documents)
+                .await
+        } else {
+            agent.stream_chat(&prompt, history.clone()).await
+        };
+        let agent_stream = futures::stream::iter(fetch_events).chain(answer).boxed();
+        let continuations = if grounded {
+            0
+        } else {
+            state.max_continuations()
+        };
+        let agent_stream = continue_truncated(agent, &prompt, history, agent_stream, continuations);
+        let mut agent_stream = limit_response_chars(agent_stream, state.max_response_chars());
+
+        loop {
+            // Wait for the next agent event, but give up as soon as the client is gone
+            let event = tokio::select! {
+                _ = tx.closed() => {
+                    debug!("Client disconnected; cancelling stream for session {}", session_id);
+                    break;
+                }
+                event = agent_stream.next() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+            };
+            timing.observe(&event);
 
-        while let Some(event) = agent_stream.next().await {
-            let sse_event = match event {
+            let data = match event {
                 ChatStreamEvent::TextDelta(text) => {
                     response_text.push_str(&text);
-                    Event::default()
-                        .json_data(SseEventData::Text { content: text })
-                        .unwrap()
+                    SseEventData::Text { content: text }
+                }
+                ChatStreamEvent::Thinking(text) => SseEventData::Thinking { content: text },
+                ChatStreamEvent::ToolCall { name } => SseEventData::ToolUse { tool_name: name },
+                ChatStreamEvent::Source(citation) => {
+                    state.add_source(&session_id, citation.clone());
+                    SseEventData::Source {
+                        url: citation.url,
+                        title: citation.title,
+                    }
+                }
+                ChatStreamEvent::Warning(message) => SseEventData::Warning { message },
+                // Tool output is for the model; the client only sees tool names and sources
+                ChatStreamEvent::ToolResult(_) => continue,
+                ChatStreamEvent::Usage { total_tokens } => {
+                    if let Some(metrics) = state.metrics() {
+                        metrics.record_tokens(total_tokens);
+                    }
+                    continue;
+                }
+                ChatStreamEvent::Truncated => {
+                    truncated = true;
+                    continue;
                 }
-                ChatStreamEvent::ToolCall { name } => Event::default()
-                    .json_data(SseEventData::ToolUse { tool_name: name })
-                    .unwrap(),
                 ChatStreamEvent::Done => {
-                    state.add_assistant_message(&session_id, &response_text);
-                    Event::default()
-                        .json_data(SseEventData::Done {
-                            session_id: session_id.clone(),
-                        })
-                        .unwrap()
+                    if !state.add_assistant_message(&session_id, &response_text) {
+                        _ = tx.send(session_gone(&session_id)).await;
+                        break;
+                    }
+                    if first_turn {
+                        // Runs in the background; the title shows up in later session lists
+                        _ = state.generate_session_title(&session_id, &prompt);
+                    }
+                    SseEventData::Done {
+                        session_id: session_id.clone(),
+                        ttft_ms: timing.ttft().map(|d| d.as_millis() as u64),
+                        truncated,
+                    }
                 }
-                ChatStreamEvent::Error(e) => Event::default()
-                    .json_data(SseEventData::Error { message: e })
-                    .unwrap(),
+                ChatStreamEvent::Error(e) => SseEventData::Error {
+                    code: classify_error(&e),
+                    message: e,
+                },
             };
 
-            if tx.send(sse_event).await.is_err() {
+            if tx.send(data).await.is_err() {
                 break;
             }
         }
+        info!(
+            "Chat turn for session {}: {}",
+            session_id,
+            timing.log_line()
+        );
+        // Dropping the agent stream cancels the in-flight provider request
+        drop(agent_stream);
     });
 
-    rx.map(Ok)
+    ReceiverStream::new(rx)
+}
+
+/// Error ending a turn whose session was removed before the reply could be saved
+fn session_gone(session_id: &str) -> SseEventData {
+    SseEventData::Error {
+        message: format!("Session {session_id} no longer exists; start a new session"),
+        code: ErrorCode::SessionNotFound,
+    }
+}
+
+/// Wrap an event stream in an SSE response that sends a `:` comment after
+/// every `SSE_KEEP_ALIVE_INTERVAL` without events
+pub(crate) fn sse_with_keep_alive<S>(stream: S) -> Sse<KeepAliveStream<S>>
+where
+    S: Stream<Item = Result<Event, std::convert::Infallible>> + Send + 'static,
+{
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(SSE_KEEP_ALIVE_INTERVAL))
 }
 
 /// Verify handler that confirms a Bearer token is valid
@@ -93,39 +422,359 @@ pub async fn verify_handler() -> axum::http::StatusCode {
     axum::http::StatusCode::OK
 }
 
+/// Health handler for liveness probes; also reports the assistant's name
+pub async fn health_handler(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok",
+        assistant_name: state.assistant_name().to_string(),
+    })
+}
+
+/// Metrics handler that renders counters in the Prometheus text format
+///
+/// Returns 404 Not Found unless metrics are enabled (`METRICS_ENABLED=true`).
+pub async fn metrics_handler(State(state): State<Arc<AppState>>) -> Result<String, StatusCode> {
+    let metrics = state.metrics().ok_or(StatusCode::NOT_FOUND)?;
+    Ok(metrics.render(state.session_count()))
+}
+
+/// Export handler that returns a session transcript as Markdown or JSON
+///
+/// Returns 404 Not Found if the session doesn't exist.
+pub async fn export_handler(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let body = state
+        .export_session(&session_id, query.format)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(([(header::CONTENT_TYPE, query.format.content_type())], body))
+}
+
+/// Clear handler that wipes a session's history but keeps its ID
+///
+/// Returns 204 No Content on success, 404 Not Found if the session doesn't exist.
+pub async fn clear_handler(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+) -> StatusCode {
+    if state.clear_history(&session_id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// Session listing handler for a chat sidebar: every session with its title
+pub async fn list_sessions_handler(
+    State(state): State<Arc<AppState>>,
+) -> Json<SessionListResponse> {
+    Json(SessionListResponse {
+        sessions: state.list_sessions(),
+    })
+}
+
+/// Rename handler that replaces a session's title
+///
+/// Returns 204 No Content on success, 400 Bad Request for a blank title,
+/// 404 Not Found if the session doesn't exist.
+pub async fn rename_session_handler(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(req): Json<RenameSessionRequest>,
+) -> StatusCode {
+    if req.title.trim().is_empty() {
+        StatusCode::BAD_REQUEST
+    } else if state.rename_session(&session_id, &req.title) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// Cache clear handler that flushes the fetch caches without a restart
+///
+/// Returns the number of entries evicted; the next fetch per origin re-reads robots.txt.
+pub async fn clear_caches_handler(State(state): State<Arc<AppState>>) -> Json<CacheClearResponse> {
+    Json(CacheClearResponse {
+        evicted: state.clear_fetch_caches().await,
+    })
+}
+
+/// Truncate handler that drops every message after `index`, for edit-and-resend branching
+///
+/// Returns 204 No Content on success, 404 Not Found if the session doesn't exist.
+pub async fn truncate_handler(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(req): Json<TruncateRequest>,
+) -> StatusCode {
+    if state.truncate_after(&session_id, req.index) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// Import handler that seeds a new session from a JSON transcript
+///
+/// Accepts the format produced by the JSON export (an array of
+/// `{ "role": "user" | "assistant", "content": "..." }` objects).
+/// Returns 400 Bad Request if the body doesn't match that shape,
+/// 503 Service Unavailable if the session limit is reached.
+pub async fn import_handler(
+    State(state): State<Arc<AppState>>,
+    body: Bytes,
+) -> Result<Json<SessionResponse>, StatusCode> {
+    let messages: Vec<TranscriptMessage> =
+        serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let session_id = state
+        .import_session(messages)
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok(Json(SessionResponse { session_id }))
+}
+
+/// Create session handler that starts an empty session, optionally with a custom preamble
+///
+/// Returns 503 Service Unavailable if the session limit is reached.
+pub async fn create_session_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateSessionRequest>,
+) -> Result<Json<SessionResponse>, StatusCode> {
+    let session_id = state
+        .create_session_with_preamble(req.preamble)
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok(Json(SessionResponse { session_id }))
+}
+
 /// Chat handler that streams responses via Server-Sent Events (SSE)
 ///
 /// # Flow
-/// 1. Get or create session
-/// 2. Add user message to conversation history
-/// 3. Call chat_stream to get event stream
-/// 4. Return as SSE response
+/// 1. Select the agent (default, or a transient one for a provider/model override)
+/// 2. Get or create session
+/// 3. Add user message to conversation history
+/// 4. Call chat_stream to get event stream
+/// 5. Return as SSE response
+///
+/// A request with an `Idempotency-Key` header already answered within
+/// `IDEMPOTENCY_TTL` gets the stored events again instead of a new turn.
+///
+/// Returns 400 Bad Request if the message is over `AppState::max_prompt_chars` or
+/// the override is not allowed or can't be configured,
+/// 404 Not Found if `session_id` names a session that doesn't exist,
+/// 413 Payload Too Large if the body is over `AppState::chat_body_limit`,
+/// 503 Service Unavailable if a new session is needed but the session limit is reached.
 pub async fn chat_handler(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ChatRequest>,
-) -> Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>> {
-    // Save user message to history
-    let session_id = match req.session_id {
-        Some(i) => i,
-        None => state.create_session(),
+    headers: HeaderMap,
+    payload: Result<Json<ChatRequest>, JsonRejection>,
+) -> Result<
+    Sse<KeepAliveStream<impl Stream<Item = Result<Event, std::convert::Infallible>>>>,
+    (StatusCode, String),
+> {
+    let req = chat_request(&state, payload)?;
+    let events = chat_turn(state, &headers, req).await?;
+    Ok(sse_with_keep_alive(sse_events(events)))
+}
+
+/// Chat handler that streams the same events as `chat_handler`, one JSON
+/// object per line (`application/x-ndjson`), for clients that don't speak SSE.
+///
+/// Errors are the same as for `chat_handler`.
+pub async fn chat_ndjson_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    payload: Result<Json<ChatRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let req = chat_request(&state, payload)?;
+
+    let lines = chat_turn(state, &headers, req).await?.map(|data| {
+        let mut line = serde_json::to_string(&data).unwrap();
+        line.push('\n');
+        Ok::<_, std::convert::Infallible>(line)
+    });
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    ))
+}
+
+/// The chat request body, or the rejection as a response. An oversized body
+/// (see `AppState::chat_body_limit`) gets a message naming the limit.
+fn chat_request(
+    state: &AppState,
+    payload: Result<Json<ChatRequest>, JsonRejection>,
+) -> Result<ChatRequest, (StatusCode, String)> {
+    match payload {
+        Ok(Json(req)) => Ok(req),
+        Err(rejection) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "Request body exceeds the {} byte limit; send a shorter message",
+                state.chat_body_limit()
+            ),
+        )),
+        Err(rejection) => Err((rejection.status(), rejection.body_text())),
+    }
+}
+
+/// The events answering `req`, limited to those its `verbosity` asks for
+async fn chat_turn(
+    state: Arc<AppState>,
+    headers: &HeaderMap,
+    req: ChatRequest,
+) -> Result<BoxStream<'static, SseEventData>, (StatusCode, String)> {
+    let verbosity = req.verbosity;
+    let events = idempotent_chat_turn(state, headers, req).await?;
+    Ok(events
+        .filter(move |data| std::future::ready(verbosity.includes(data)))
+        .boxed())
+}
+
+/// All events answering `req`: a replay of the response stored under its
+/// `Idempotency-Key`, or a new turn whose response is stored under the key once done.
+/// The full response is stored, so a retry may ask for a different verbosity.
+///
+/// Keys are scoped to the request's credential and session. A key is reserved
+/// before the turn starts: a retry while it is still streaming gets 409 Conflict,
+/// and reusing a key with a different request body gets 422 Unprocessable Entity.
+async fn idempotent_chat_turn(
+    state: Arc<AppState>,
+    headers: &HeaderMap,
+    req: ChatRequest,
+) -> Result<BoxStream<'static, SseEventData>, (StatusCode, String)> {
+    let key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+    let reservation = match key {
+        None => None,
+        Some(key) => {
+            let credential = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            let idempotency_key = IdempotencyKey::new(credential, req.session_id.as_deref(), key);
+            match state
+                .idempotency()
+                .begin(idempotency_key, request_fingerprint(&req))
+            {
+                Lookup::Reserved(reservation) => Some(reservation),
+                Lookup::Replay(events) => {
+                    info!("Replaying the stored response for Idempotency-Key {}", key);
+                    return Ok(futures::stream::iter(events).boxed());
+                }
+                Lookup::InProgress => {
+                    return Err((
+                        StatusCode::CONFLICT,
+                        format!("A request with Idempotency-Key {key} is still in progress"),
+                    ))
+                }
+                Lookup::Mismatch => {
+                    return Err((
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("Idempotency-Key {key} was already used with a different request"),
+                    ))
+                }
+            }
+        }
+    };
+
+    // A failure here drops the reservation, freeing the key for a retry
+    let (agent, session_id) = start_chat_turn(&state, &req)?;
+    let events = chat_events(state, agent, session_id, req.message, req.ground).await;
+    let Some(reservation) = reservation else {
+        return Ok(events.boxed());
     };
-    state.add_user_message(&session_id, &req.message);
+    // Only a response that reached `Done` is stored; if the stream is dropped
+    // before that, the reservation goes with it and a retry runs the turn again
+    let mut reservation = Some(reservation);
+    let mut recorded = Vec::new();
+    Ok(events
+        .inspect(move |data| {
+            recorded.push(data.clone());
+            if matches!(data, SseEventData::Done { .. }) {
+                if let Some(reservation) = reservation.take() {
+                    reservation.complete(std::mem::take(&mut recorded));
+                }
+            }
+        })
+        .boxed())
+}
+
+/// Hash of the parts of `req` that decide its answer, to tell a retry from a
+/// different request reusing an `Idempotency-Key`. `verbosity` only filters
+/// the stored events, so it is left out.
+fn request_fingerprint(req: &ChatRequest) -> u64 {
+    fingerprint((
+        &req.session_id,
+        &req.message,
+        &req.provider,
+        &req.model,
+        req.tools_enabled,
+        req.ground,
+    ))
+}
+
+/// Steps 1-3 of a chat request: pick the agent, resolve the session and record
+/// the user's message. Returns the agent and session ID to stream the reply with.
+fn start_chat_turn(
+    state: &AppState,
+    req: &ChatRequest,
+) -> Result<(Arc<dyn ChatAgent>, String), (StatusCode, String)> {
+    check_prompt_length(&req.message, state.max_prompt_chars())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let agent = state
+        .agent_for(
+            req.provider.as_deref(),
+            req.model.as_deref(),
+            req.tools_enabled.unwrap_or(true),
+        )
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    if let Some(metrics) = state.metrics() {
+        metrics.record_chat_request();
+    }
 
-    // Get stream and wrap in SSE response
-    let stream = chat_stream(state, session_id, req.message).await;
-    Sse::new(stream)
+    // Save user message to history; unknown sessions are not created here,
+    // so every new session goes through the session limit
+    let session_id = match &req.session_id {
+        Some(i) => i.clone(),
+        None => state
+            .try_create_session()
+            .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e.to_string()))?,
+    };
+    if !state.add_user_message(&session_id, &req.message) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Session {session_id} does not exist; omit session_id to start a new one"),
+        ));
+    }
+    Ok((agent, session_id))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::agent::{ChatStreamEvent, MockAgent};
-    use crate::web::AppState;
+    use crate::agent::{ChatStreamEvent, Citation, MockAgent};
+    use crate::metrics::Metrics;
+    use crate::web::{AppState, SessionEviction, SessionLimit, SessionTitleMode};
 
     fn make_state(agent: MockAgent) -> Arc<AppState> {
         Arc::new(AppState::new(Arc::new(agent), "test-token".to_string()))
     }
 
+    /// One chat turn as SSE events, without a request or idempotency key
+    async fn chat_stream(
+        state: Arc<AppState>,
+        agent: Arc<dyn ChatAgent>,
+        session_id: String,
+        message: String,
+    ) -> impl Stream<Item = Result<Event, std::convert::Infallible>> {
+        sse_events(chat_events(state, agent, session_id, message, false).await)
+    }
+
     #[tokio::test]
     async fn test_chat_saves_assistant_response_to_history() {
         let state = make_state(MockAgent::with_response("Hello from mock!"));
@@ -135,6 +784,7 @@ mod tests {
 
         let mut stream = chat_stream(
             state.clone(),
+            state.agent.clone(),
             session_id.clone(),
             "test message".to_string(),
         )
@@ -174,6 +824,7 @@ mod tests {
         state.add_user_message(&session_id, "first message");
         let mut s1 = chat_stream(
             state.clone(),
+            state.agent.clone(),
             session_id.clone(),
             "first message".to_string(),
         )
@@ -184,6 +835,7 @@ mod tests {
         state.add_user_message(&session_id, "second message");
         let mut s2 = chat_stream(
             state.clone(),
+            state.agent.clone(),
             session_id.clone(),
             "second message".to_string(),
         )
@@ -209,6 +861,7 @@ mod tests {
 
         let mut stream = chat_stream(
             state.clone(),
+            state.agent.clone(),
             session_id.clone(),
             "test message".to_string(),
         )
@@ -227,6 +880,105 @@ mod tests {
         assert!(found_error, "Should have received an error SSE event");
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_keep_alive_comments_precede_slow_first_token() {
+        // The first token arrives after 40s of "thinking"
+        let events = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_secs(40)).await;
+            Ok(Event::default().data("first token"))
+        });
+        let body = sse_with_keep_alive(events).into_response().into_body();
+
+        let frames: Vec<String> = body
+            .into_data_stream()
+            .map(|frame| String::from_utf8(frame.unwrap().to_vec()).unwrap())
+            .collect()
+            .await;
+
+        let first_data = frames.iter().position(|f| f.starts_with("data:")).unwrap();
+        assert_eq!(first_data, 2, "{frames:?}");
+        assert!(frames[..first_data].iter().all(|f| f == ":\n\n"));
+    }
+
+    #[tokio::test]
+    async fn test_error_event_includes_code() {
+        let state = make_state(MockAgent::with_error(
+            "CompletionError: HttpError: 429 Too Many Requests",
+        ));
+        let session_id = state.create_session();
+
+        let mut stream = chat_stream(
+            state.clone(),
+            state.agent.clone(),
+            session_id,
+            "hi".to_string(),
+        )
+        .await;
+
+        let event = stream.next().await.unwrap().unwrap();
+        assert!(format!("{:?}", event).contains("rate_limited"));
+    }
+
+    #[test]
+    fn test_classify_error() {
+        let cases = [
+            (
+                "ProviderError: Rate limit reached for gpt-4o in organization org-x on tokens per min",
+                ErrorCode::RateLimited,
+            ),
+            (
+                r#"ProviderError: {"error":{"code":429,"status":"RESOURCE_EXHAUSTED"}}"#,
+                ErrorCode::RateLimited,
+            ),
+            (
+                "ProviderError: This model's maximum context length is 128000 tokens.                  However, your messages resulted in 130512 tokens.",
+                ErrorCode::ContextTooLong,
+            ),
+            (
+                "ProviderError: The input token count (1200000) exceeds the maximum number of tokens allowed (1048576).",
+                ErrorCode::ContextTooLong,
+            ),
+            (
+                "HttpError: 503 Service Unavailable: The model is overloaded.",
+                ErrorCode::ProviderUnavailable,
+            ),
+            (
+                "HttpError: error sending request for url (http://localhost:11434/api/chat)",
+                ErrorCode::ProviderUnavailable,
+            ),
+            (
+                "ProviderError: Incorrect API key provided: sk-abc.",
+                ErrorCode::AuthenticationFailed,
+            ),
+            (
+                "HttpError: Invalid status code 401 Unauthorized with message: bad key",
+                ErrorCode::AuthenticationFailed,
+            ),
+            (
+                "ProviderError: status: 502, upstream reset",
+                ErrorCode::ProviderUnavailable,
+            ),
+            (
+                "ProviderError: model not found in org-500-team after 1429 ms (port 4290)",
+                ErrorCode::Unknown,
+            ),
+            ("llm exploded", ErrorCode::Unknown),
+        ];
+
+        for (message, expected) in cases {
+            assert_eq!(classify_error(message), expected, "{message}");
+        }
+    }
+
+    #[test]
+    fn test_http_status_needs_a_marker_or_the_start_of_the_message() {
+        assert_eq!(http_status("503 service unavailable"), Some(503));
+        assert_eq!(http_status("invalid status code: 429"), Some(429));
+        assert_eq!(http_status(r#"{"error":{"code":403}}"#), Some(403));
+        assert_eq!(http_status("request 1 of 500 failed"), None);
+        assert_eq!(http_status("status: 4290"), None);
+    }
+
     #[tokio::test]
     async fn test_tool_call_event_emits_tool_use_sse_event() {
         let state = make_state(MockAgent::new(vec![vec![
@@ -239,7 +991,13 @@ mod tests {
 
         state.add_user_message(&session_id, "test");
 
-        let mut stream = chat_stream(state.clone(), session_id.clone(), "test".to_string()).await;
+        let mut stream = chat_stream(
+            state.clone(),
+            state.agent.clone(),
+            session_id.clone(),
+            "test".to_string(),
+        )
+        .await;
 
         let mut found_tool_use = false;
         while let Some(Ok(event)) = stream.next().await {
@@ -251,4 +1009,1013 @@ mod tests {
 
         assert!(found_tool_use, "Should have emitted a tool_use SSE event");
     }
+
+    #[tokio::test]
+    async fn test_done_event_reports_ttft_only_after_text() {
+        let state = make_state(MockAgent::new(vec![
+            vec![
+                ChatStreamEvent::TextDelta("hi".to_string()),
+                ChatStreamEvent::Done,
+            ],
+            vec![ChatStreamEvent::Done],
+        ]));
+        let session_id = state.create_session();
+
+        let mut done_events = Vec::new();
+        for _ in 0..2 {
+            let stream = chat_stream(
+                state.clone(),
+                state.agent.clone(),
+                session_id.clone(),
+                "test".to_string(),
+            )
+            .await;
+            let events: Vec<_> = stream.collect().await;
+            let done = format!("{:?}", events.last().unwrap().as_ref().unwrap());
+            done_events.push(done);
+        }
+
+        assert!(done_events[0].contains("ttft_ms"), "{}", done_events[0]);
+        assert!(!done_events[1].contains("ttft_ms"), "{}", done_events[1]);
+    }
+
+    #[tokio::test]
+    async fn test_done_event_flags_truncated_response() {
+        let state = make_state(MockAgent::new(vec![vec![
+            ChatStreamEvent::TextDelta("The first half".to_string()),
+            ChatStreamEvent::Truncated,
+            ChatStreamEvent::Done,
+        ]]));
+        let session_id = state.create_session();
+
+        let events: Vec<SseEventData> = chat_events(
+            state.clone(),
+            state.agent.clone(),
+            session_id,
+            "test".to_string(),
+            false,
+        )
+        .await
+        .collect()
+        .await;
+
+        let done = serde_json::to_value(events.last().unwrap()).unwrap();
+        assert_eq!(done["type"], "done");
+        assert_eq!(done["truncated"], true);
+    }
+
+    /// Agent reporting whether it was asked to answer from documents
+    #[derive(Default)]
+    struct GroundingProbe {
+        documents: std::sync::Mutex<Option<usize>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ChatAgent for GroundingProbe {
+        async fn stream_chat(
+            &self,
+            _prompt: &str,
+            _history: Vec<rig::completion::Message>,
+        ) -> std::pin::Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+            Box::pin(futures::stream::iter(vec![
+                ChatStreamEvent::TextDelta("from tools".into()),
+                ChatStreamEvent::Done,
+            ]))
+        }
+
+        async fn stream_chat_with_documents(
+            &self,
+            _prompt: &str,
+            _history: Vec<rig::completion::Message>,
+            documents: Vec<rig::completion::Document>,
+        ) -> std::pin::Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+            *self.documents.lock().unwrap() = Some(documents.len());
+            Box::pin(futures::stream::iter(vec![
+                ChatStreamEvent::TextDelta("from documents".into()),
+                ChatStreamEvent::Done,
+            ]))
+        }
+    }
+
+    /// Serve one request with `status` and an HTML `body` on a local port; returns its URL
+    async fn serve_page(status: &'static str, body: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            _ = socket.read(&mut buf).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        url
+    }
+
+    async fn grounded_turn(agent: Arc<GroundingProbe>, url: &str) -> Vec<serde_json::Value> {
+        let state = Arc::new(
+            AppState::new(agent.clone(), "token".to_string())
+                .with_web_fetch(crate::agent::WebFetch::new().with_respect_robots(false)),
+        );
+        let session_id = state.create_session();
+
+        let events: Vec<SseEventData> = chat_events(
+            state,
+            agent,
+            session_id,
+            format!("Summarize {url} please"),
+            true,
+        )
+        .await
+        .collect()
+        .await;
+        events
+            .iter()
+            .map(|e| serde_json::to_value(e).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_grounded_turn_answers_from_fetched_pages() {
+        let url = serve_page(
+            "200 OK",
+            "<html><head><title>Page</title></head><body>\
+             <p>Ownership rules are checked at compile time by the borrow checker.</p>\
+             </body></html>",
+        )
+        .await;
+        let agent = Arc::new(GroundingProbe::default());
+
+        let events = grounded_turn(agent.clone(), &url).await;
+
+        assert_eq!(*agent.documents.lock().unwrap(), Some(1));
+        assert_eq!(events[0]["type"], "source");
+        assert_eq!(events[1]["content"], "from documents");
+    }
+
+    #[tokio::test]
+    async fn test_grounded_turn_without_documents_falls_back_to_tools() {
+        let url = serve_page("404 Not Found", "").await;
+        let agent = Arc::new(GroundingProbe::default());
+
+        let events = grounded_turn(agent.clone(), &url).await;
+
+        assert_eq!(*agent.documents.lock().unwrap(), None);
+        assert_eq!(events[0]["type"], "warning");
+        assert!(events[0]["message"].as_str().unwrap().contains(&url));
+        assert_eq!(events[1]["content"], "from tools");
+        assert_eq!(events.last().unwrap()["type"], "done");
+    }
+
+    #[tokio::test]
+    async fn test_source_events_are_recorded_for_session() {
+        let state = make_state(MockAgent::new(vec![vec![
+            ChatStreamEvent::Source(Citation::now("https://a.example", None)),
+            ChatStreamEvent::Source(Citation::now("https://b.example", Some("B".to_string()))),
+            ChatStreamEvent::Done,
+        ]]));
+        let session_id = state.create_session();
+
+        state.add_user_message(&session_id, "test");
+
+        let mut stream = chat_stream(
+            state.clone(),
+            state.agent.clone(),
+            session_id.clone(),
+            "test".to_string(),
+        )
+        .await;
+
+        let mut source_events = 0;
+        while let Some(Ok(event)) = stream.next().await {
+            if format!("{:?}", event).contains(r#"\"type\":\"source\""#) {
+                source_events += 1;
+            }
+        }
+
+        assert_eq!(source_events, 2);
+        let urls: Vec<String> = state
+            .get_sources(&session_id)
+            .into_iter()
+            .map(|c| c.url)
+            .collect();
+        assert_eq!(urls, vec!["https://a.example", "https://b.example"]);
+    }
+
+    #[tokio::test]
+    async fn test_session_preamble_is_passed_to_agent() {
+        let agent = Arc::new(MockAgent::with_response("ok"));
+        let state = Arc::new(AppState::new(agent.clone(), "test-token".to_string()));
+        let Json(SessionResponse { session_id }) = create_session_handler(
+            State(state.clone()),
+            Json(CreateSessionRequest {
+                preamble: Some("You are a travel planner.".to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+        state.add_user_message(&session_id, "Plan a trip to Kyoto");
+
+        let mut stream = chat_stream(
+            state.clone(),
+            state.agent.clone(),
+            session_id,
+            "Plan a trip to Kyoto".to_string(),
+        )
+        .await;
+        while stream.next().await.is_some() {}
+
+        let histories = agent.received_histories();
+        let first = crate::session::extract_user_text(&histories[0][0]).unwrap();
+        assert!(first.ends_with("You are a travel planner."));
+        assert_eq!(
+            crate::session::extract_user_text(histories[0].last().unwrap()).as_deref(),
+            Some("Plan a trip to Kyoto")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_session_handler_returns_503_at_session_limit() {
+        let state = Arc::new(
+            AppState::new(
+                Arc::new(MockAgent::with_response("ok")),
+                "test-token".to_string(),
+            )
+            .with_session_limit(SessionLimit {
+                max_sessions: 1,
+                eviction: SessionEviction::Reject,
+            }),
+        );
+        let request = || Json(CreateSessionRequest { preamble: None });
+
+        assert!(create_session_handler(State(state.clone()), request())
+            .await
+            .is_ok());
+        assert_eq!(
+            create_session_handler(State(state), request())
+                .await
+                .unwrap_err(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_events_are_counted_but_not_forwarded() {
+        let agent = MockAgent::new(vec![vec![
+            ChatStreamEvent::TextDelta("hi".to_string()),
+            ChatStreamEvent::Usage { total_tokens: 42 },
+            ChatStreamEvent::Done,
+        ]]);
+        let state = Arc::new(
+            AppState::new(Arc::new(agent), "test-token".to_string())
+                .with_metrics(Arc::new(Metrics::default())),
+        );
+        let session_id = state.create_session();
+
+        let mut stream = chat_stream(
+            state.clone(),
+            state.agent.clone(),
+            session_id.clone(),
+            "test".to_string(),
+        )
+        .await;
+
+        let mut events = 0;
+        while let Some(Ok(_)) = stream.next().await {
+            events += 1;
+        }
+
+        assert_eq!(events, 2, "only text and done should reach the client");
+        let body = metrics_handler(State(state)).await.unwrap();
+        assert!(body.contains("copal_tokens_total 42"));
+        assert!(body.contains("copal_active_sessions 1"));
+    }
+
+    #[tokio::test]
+    async fn test_health_handler_reports_assistant_name() {
+        let state = make_state(MockAgent::with_response(""));
+        let Json(default) = health_handler(State(state)).await;
+        assert_eq!(default.status, "ok");
+        assert_eq!(default.assistant_name, "Copal");
+
+        let state = Arc::new(
+            AppState::new(Arc::new(MockAgent::with_response("")), "token".to_string())
+                .with_assistant_name("Acme Scout".to_string()),
+        );
+        let Json(named) = health_handler(State(state)).await;
+        assert_eq!(named.assistant_name, "Acme Scout");
+    }
+
+    #[tokio::test]
+    async fn test_clear_caches_handler_without_fetcher_evicts_nothing() {
+        let state = make_state(MockAgent::with_response(""));
+
+        let Json(response) = clear_caches_handler(State(state)).await;
+
+        assert_eq!(response.evicted, 0);
+    }
+
+    #[tokio::test]
+    async fn test_clear_caches_handler_reports_evicted_entries() {
+        let web_fetch = crate::agent::WebFetch::new();
+        let client = crate::collectors::web::ReqwestClient::new();
+        // An unreachable origin still gets a (failed) cache entry
+        web_fetch
+            .robots_cache
+            .is_allowed(&client, "http://127.0.0.1:9/page")
+            .await;
+        let state = Arc::new(
+            AppState::new(Arc::new(MockAgent::with_response("")), "token".to_string())
+                .with_web_fetch(web_fetch),
+        );
+
+        let Json(first) = clear_caches_handler(State(Arc::clone(&state))).await;
+        let Json(second) = clear_caches_handler(State(state)).await;
+
+        assert_eq!(first.evicted, 1);
+        assert_eq!(second.evicted, 0);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_handler_is_not_found_when_disabled() {
+        let state = make_state(MockAgent::with_response(""));
+
+        let result = metrics_handler(State(state)).await;
+
+        assert_eq!(result.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_handler_streams_one_event_per_line() {
+        let state = make_state(MockAgent::new(vec![vec![
+            ChatStreamEvent::TextDelta("Hel".to_string()),
+            ChatStreamEvent::TextDelta("lo".to_string()),
+            ChatStreamEvent::Done,
+        ]]));
+        let request = ChatRequest {
+            session_id: None,
+            message: "hi".to_string(),
+            provider: None,
+            model: None,
+            tools_enabled: None,
+            verbosity: Verbosity::Full,
+            ground: false,
+        };
+
+        let response =
+            chat_ndjson_handler(State(state.clone()), HeaderMap::new(), Ok(Json(request)))
+                .await
+                .unwrap()
+                .into_response();
+
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let events: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["text", "text", "done"]);
+
+        // The reply is saved to the session like in the SSE handler
+        let session_id = events[2]["session_id"].as_str().unwrap();
+        assert_eq!(state.get_session(session_id).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_answer_verbosity_suppresses_tool_events() {
+        let state = make_state(MockAgent::new(vec![vec![
+            ChatStreamEvent::Thinking("Let me search".to_string()),
+            ChatStreamEvent::ToolCall {
+                name: "web_search".to_string(),
+            },
+            ChatStreamEvent::Warning("Tool-round limit reached".to_string()),
+            ChatStreamEvent::TextDelta("Answer".to_string()),
+            ChatStreamEvent::Done,
+        ]]));
+        let request: ChatRequest =
+            serde_json::from_str(r#"{"message": "hi", "verbosity": "answer"}"#).unwrap();
+
+        let response = chat_handler(State(state), HeaderMap::new(), Ok(Json(request)))
+            .await
+            .unwrap()
+            .into_response();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let types: Vec<String> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| {
+                let event: serde_json::Value = serde_json::from_str(data).unwrap();
+                event["type"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(types, ["text", "done"]);
+    }
+
+    #[test]
+    fn test_verbosity_defaults_to_full() {
+        let request: ChatRequest = serde_json::from_str(r#"{"message": "hi"}"#).unwrap();
+
+        assert_eq!(request.verbosity, Verbosity::Full);
+    }
+
+    #[tokio::test]
+    async fn test_export_handler_sets_content_type() {
+        let state = make_state(MockAgent::with_response(""));
+        let session_id = state.create_session();
+        state.add_user_message(&session_id, "hello");
+
+        let response = export_handler(
+            State(state),
+            Path(session_id),
+            Query(ExportQuery {
+                format: ExportFormat::Json,
+            }),
+        )
+        .await
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    }
+
+    #[tokio::test]
+    async fn test_export_handler_returns_404_for_unknown_session() {
+        let state = make_state(MockAgent::with_response(""));
+
+        let response = export_handler(
+            State(state),
+            Path("nonexistent_session_id".to_string()),
+            Query(ExportQuery {
+                format: ExportFormat::Markdown,
+            }),
+        )
+        .await
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_import_handler_creates_session_from_transcript() {
+        let state = make_state(MockAgent::with_response(""));
+        let body = Bytes::from(
+            r#"[{"role":"user","content":"hi"},{"role":"assistant","content":"hello"}]"#,
+        );
+
+        let Json(response) = import_handler(State(state.clone()), body).await.unwrap();
+
+        assert_eq!(state.get_session(&response.session_id).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_import_handler_rejects_malformed_transcript() {
+        let state = make_state(MockAgent::with_response(""));
+
+        for body in [
+            "not json",
+            r#"{"role":"user","content":"hi"}"#,
+            r#"[{"role":"system","content":"hi"}]"#,
+            r#"[{"role":"user"}]"#,
+            r#"[{"role":"user","content":42}]"#,
+        ] {
+            let result = import_handler(State(state.clone()), Bytes::from(body)).await;
+            assert_eq!(result.err(), Some(StatusCode::BAD_REQUEST), "body: {body}");
+        }
+    }
+
+    /// Agent whose stream emits one text delta and then never ends,
+    /// flagging when the stream is dropped.
+    struct EndlessAgent {
+        dropped: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    struct DropFlag(Arc<std::sync::atomic::AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ChatAgent for EndlessAgent {
+        async fn stream_chat(
+            &self,
+            _prompt: &str,
+            _history: Vec<rig::completion::Message>,
+        ) -> std::pin::Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+            let flag = DropFlag(self.dropped.clone());
+            let first = futures::stream::iter(vec![ChatStreamEvent::TextDelta("partial".into())]);
+            let endless = futures::stream::pending().map(move |event| {
+                let _keep_alive = &flag;
+                event
+            });
+            Box::pin(first.chain(endless))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_cancels_agent_stream() {
+        let dropped = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let agent: Arc<dyn ChatAgent> = Arc::new(EndlessAgent {
+            dropped: dropped.clone(),
+        });
+        let state = make_state(MockAgent::with_response(""));
+        let session_id = state.create_session();
+        state.add_user_message(&session_id, "test");
+
+        let mut stream = chat_stream(state.clone(), agent, session_id, "test".to_string()).await;
+        assert!(stream.next().await.is_some());
+
+        // Simulate the client going away mid-response
+        drop(stream);
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        assert!(
+            dropped.load(std::sync::atomic::Ordering::SeqCst),
+            "Agent stream should be dropped after the client disconnects"
+        );
+    }
+
+    /// Agent whose stream emits one text delta, then waits for `release`
+    /// before finishing
+    struct GatedAgent {
+        release: Arc<tokio::sync::Notify>,
+    }
+
+    #[async_trait::async_trait]
+    impl ChatAgent for GatedAgent {
+        async fn stream_chat(
+            &self,
+            _prompt: &str,
+            _history: Vec<rig::completion::Message>,
+        ) -> std::pin::Pin<Box<dyn Stream<Item = ChatStreamEvent> + Send>> {
+            let release = self.release.clone();
+            let first = futures::stream::iter(vec![ChatStreamEvent::TextDelta("partial".into())]);
+            let done = futures::stream::once(async move {
+                release.notified().await;
+                ChatStreamEvent::Done
+            });
+            Box::pin(first.chain(done))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_evicted_mid_reply_ends_with_error_event() {
+        let release = Arc::new(tokio::sync::Notify::new());
+        let agent: Arc<dyn ChatAgent> = Arc::new(GatedAgent {
+            release: release.clone(),
+        });
+        let state = Arc::new(
+            AppState::new(agent.clone(), "test-token".to_string()).with_session_limit(
+                SessionLimit {
+                    max_sessions: 1,
+                    eviction: SessionEviction::LeastRecentlyUsed,
+                },
+            ),
+        );
+        let session_id = state.try_create_session().unwrap();
+        state.add_user_message(&session_id, "test");
+
+        let mut stream = chat_events(
+            state.clone(),
+            agent,
+            session_id.clone(),
+            "test".into(),
+            false,
+        )
+        .await
+        .boxed();
+        assert!(matches!(
+            stream.next().await,
+            Some(SseEventData::Text { .. })
+        ));
+        // Another client's session takes the only slot while the reply streams
+        state.try_create_session().unwrap();
+        release.notify_one();
+
+        let events: Vec<SseEventData> = stream.collect().await;
+        assert!(
+            matches!(
+                events.as_slice(),
+                [SseEventData::Error {
+                    code: ErrorCode::SessionNotFound,
+                    ..
+                }]
+            ),
+            "{events:?}"
+        );
+        assert!(state.get_session(&session_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_session_ends_with_error_event() {
+        let state = make_state(MockAgent::with_response("unused"));
+
+        let events: Vec<SseEventData> = chat_events(
+            state.clone(),
+            state.agent.clone(),
+            "gone".to_string(),
+            "hi".to_string(),
+            false,
+        )
+        .await
+        .collect()
+        .await;
+
+        assert!(matches!(
+            events.as_slice(),
+            [SseEventData::Error {
+                code: ErrorCode::SessionNotFound,
+                ..
+            }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_chat_with_unknown_session_id_is_404_and_creates_nothing() {
+        let state = Arc::new(
+            AppState::new(
+                Arc::new(MockAgent::with_response("unused")),
+                "test-token".to_string(),
+            )
+            .with_session_limit(SessionLimit {
+                max_sessions: 1,
+                eviction: SessionEviction::Reject,
+            }),
+        );
+        state.try_create_session().unwrap();
+        let request = ChatRequest {
+            session_id: Some("made-up-by-the-client".to_string()),
+            message: "hi".to_string(),
+            provider: None,
+            model: None,
+            tools_enabled: None,
+            verbosity: Verbosity::Full,
+            ground: false,
+        };
+
+        let result =
+            chat_ndjson_handler(State(state.clone()), HeaderMap::new(), Ok(Json(request))).await;
+
+        let Err((status, _)) = result else {
+            panic!("an unknown session should be rejected");
+        };
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(state.session_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_clear_handler_status_codes() {
+        let state = make_state(MockAgent::with_response(""));
+        let session_id = state.create_session();
+        state.add_user_message(&session_id, "hello");
+
+        let cleared = clear_handler(State(state.clone()), Path(session_id.clone())).await;
+        let missing = clear_handler(State(state.clone()), Path("nonexistent".to_string())).await;
+
+        assert_eq!(cleared, StatusCode::NO_CONTENT);
+        assert_eq!(missing, StatusCode::NOT_FOUND);
+        assert!(state.get_session(&session_id).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_truncate_handler_keeps_prefix() {
+        let state = make_state(MockAgent::with_response(""));
+        let session_id = state.create_session();
+        state.add_user_message(&session_id, "first");
+        state.add_assistant_message(&session_id, "reply");
+        state.add_user_message(&session_id, "second");
+
+        let truncated = truncate_handler(
+            State(state.clone()),
+            Path(session_id.clone()),
+            Json(TruncateRequest { index: 0 }),
+        )
+        .await;
+        let missing = truncate_handler(
+            State(state.clone()),
+            Path("nonexistent".to_string()),
+            Json(TruncateRequest { index: 0 }),
+        )
+        .await;
+
+        assert_eq!(truncated, StatusCode::NO_CONTENT);
+        assert_eq!(missing, StatusCode::NOT_FOUND);
+        let history = state.get_session(&session_id).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            crate::session::extract_user_text(&history.as_slice()[0]).as_deref(),
+            Some("first")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_session_handler_status_codes() {
+        let state = make_state(MockAgent::with_response(""));
+        let session_id = state.create_session();
+        let rename = |id: &str, title: &str| {
+            rename_session_handler(
+                State(state.clone()),
+                Path(id.to_string()),
+                Json(RenameSessionRequest {
+                    title: title.to_string(),
+                }),
+            )
+        };
+
+        assert_eq!(
+            rename(&session_id, " Trip notes ").await,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(rename(&session_id, "  ").await, StatusCode::BAD_REQUEST);
+        assert_eq!(rename("nonexistent", "x").await, StatusCode::NOT_FOUND);
+        assert_eq!(
+            state.session_title(&session_id).as_deref(),
+            Some("Trip notes")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_handler_includes_titles() {
+        let state = make_state(MockAgent::with_response(""));
+        let session_id = state.create_session();
+        state.add_user_message(&session_id, "Plan a weekend in Kyoto");
+
+        let Json(response) = list_sessions_handler(State(state)).await;
+
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            serde_json::json!({
+                "sessions": [{
+                    "session_id": session_id,
+                    "title": "Plan a weekend in Kyoto",
+                    "message_count": 1
+                }]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_first_turn_asks_model_for_title_in_llm_mode() {
+        let agent = MockAgent::new(vec![
+            vec![
+                ChatStreamEvent::TextDelta("Here is a plan...".to_string()),
+                ChatStreamEvent::Done,
+            ],
+            vec![
+                ChatStreamEvent::TextDelta("Title: Kyoto Weekend".to_string()),
+                ChatStreamEvent::Done,
+            ],
+        ]);
+        let state = Arc::new(
+            AppState::new(Arc::new(agent), "test-token".to_string())
+                .with_title_mode(SessionTitleMode::Llm),
+        );
+        let session_id = state.create_session();
+        state.add_user_message(&session_id, "Plan a weekend in Kyoto please");
+
+        let stream = chat_stream(
+            state.clone(),
+            state.agent.clone(),
+            session_id.clone(),
+            "Plan a weekend in Kyoto please".to_string(),
+        )
+        .await;
+        stream.collect::<Vec<_>>().await;
+
+        // The title is generated in the background; wait for it, but not forever
+        let title = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match state.session_title(&session_id) {
+                    Some(title) if title == "Kyoto Weekend" => break title,
+                    _ => tokio::task::yield_now().await,
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(title.as_deref(), Ok("Kyoto Weekend"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_rejects_message_over_prompt_limit() {
+        let state = Arc::new(
+            AppState::new(
+                Arc::new(MockAgent::with_response("ok")),
+                "test-token".to_string(),
+            )
+            .with_max_prompt_chars(Some(5)),
+        );
+        let request = |message: &str| ChatRequest {
+            session_id: None,
+            message: message.to_string(),
+            provider: None,
+            model: None,
+            tools_enabled: None,
+            verbosity: Verbosity::Full,
+            ground: false,
+        };
+
+        let too_long = chat_ndjson_handler(
+            State(state.clone()),
+            HeaderMap::new(),
+            Ok(Json(request("abcdef"))),
+        )
+        .await;
+        let at_limit = chat_ndjson_handler(
+            State(state.clone()),
+            HeaderMap::new(),
+            Ok(Json(request("abcde"))),
+        )
+        .await;
+
+        let Err((status, message)) = too_long else {
+            panic!("a 6-character message should be rejected");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            message.contains("split it into smaller messages"),
+            "{message}"
+        );
+        assert!(at_limit.is_ok());
+        // The rejected message didn't create a session
+        assert_eq!(state.session_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_chat_rejects_oversized_body_with_413() {
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let state = AppState::new(
+            Arc::new(MockAgent::with_response("unused")),
+            "test-token".to_string(),
+        )
+        .with_chat_body_limit(1024);
+        let body = serde_json::json!({ "message": "a".repeat(2048) }).to_string();
+
+        let response = crate::web::build_router(Arc::new(state))
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/chat")
+                    .header("authorization", "Bearer test-token")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let message = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(message.contains("1024 byte limit"), "{message}");
+    }
+
+    async fn send_chat(state: &Arc<AppState>, key: &str, body: &str) -> axum::response::Response {
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        crate::web::build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/chat/ndjson")
+                    .header("authorization", "Bearer test-token")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(IDEMPOTENCY_KEY_HEADER, key)
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn post_chat(state: &Arc<AppState>, key: &str, body: &str) -> String {
+        let response = send_chat(state, key, body).await;
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    fn two_replies() -> MockAgent {
+        MockAgent::new(vec![
+            vec![
+                ChatStreamEvent::TextDelta("first reply".to_string()),
+                ChatStreamEvent::Done,
+            ],
+            vec![
+                ChatStreamEvent::TextDelta("second reply".to_string()),
+                ChatStreamEvent::Done,
+            ],
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_repeated_idempotency_key_replays_the_response() {
+        let state = make_state(two_replies());
+        let session_id = state.create_session();
+        let body = serde_json::json!({ "session_id": session_id, "message": "hi" }).to_string();
+
+        let first = post_chat(&state, "retry-1", &body).await;
+        let retry = post_chat(&state, "retry-1", &body).await;
+
+        assert!(first.contains("first reply"), "{first}");
+        assert_eq!(retry, first);
+        // The retry didn't add another turn to the session
+        assert_eq!(state.get_session(&session_id).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_new_idempotency_key_runs_a_new_turn() {
+        let state = make_state(two_replies());
+        let session_id = state.create_session();
+        let body = serde_json::json!({ "session_id": session_id, "message": "hi" }).to_string();
+
+        let first = post_chat(&state, "key-a", &body).await;
+        let second = post_chat(&state, "key-b", &body).await;
+
+        assert!(first.contains("first reply"), "{first}");
+        assert!(second.contains("second reply"), "{second}");
+        assert_eq!(state.get_session(&session_id).unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_expired_idempotency_key_runs_a_new_turn() {
+        let state = Arc::new(
+            AppState::new(Arc::new(two_replies()), "test-token".to_string())
+                .with_idempotency_ttl(Duration::ZERO),
+        );
+        let body = serde_json::json!({ "message": "hi" }).to_string();
+
+        post_chat(&state, "retry-1", &body).await;
+        let retry = post_chat(&state, "retry-1", &body).await;
+
+        assert!(retry.contains("second reply"), "{retry}");
+    }
+
+    #[tokio::test]
+    async fn test_retry_while_first_request_streams_gets_conflict() {
+        let release = Arc::new(tokio::sync::Notify::new());
+        let state = Arc::new(AppState::new(
+            Arc::new(GatedAgent {
+                release: release.clone(),
+            }),
+            "test-token".to_string(),
+        ));
+        let body = serde_json::json!({ "message": "hi" }).to_string();
+
+        let first = send_chat(&state, "retry-1", &body).await;
+        let retry = send_chat(&state, "retry-1", &body).await;
+        assert_eq!(retry.status(), StatusCode::CONFLICT);
+
+        release.notify_one();
+        let bytes = axum::body::to_bytes(first.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let first = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(first.contains("partial"), "{first}");
+        // Once the first request is done, the retry replays it
+        assert_eq!(post_chat(&state, "retry-1", &body).await, first);
+    }
+
+    #[tokio::test]
+    async fn test_reused_idempotency_key_with_different_body_is_rejected() {
+        let state = make_state(two_replies());
+
+        post_chat(
+            &state,
+            "retry-1",
+            &serde_json::json!({ "message": "hi" }).to_string(),
+        )
+        .await;
+        let response = send_chat(
+            &state,
+            "retry-1",
+            &serde_json::json!({ "message": "something else" }).to_string(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
 }
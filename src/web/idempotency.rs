@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::handlers::SseEventData;
+use crate::sync::lock;
+
+/// How long a chat response is replayed for a repeated `Idempotency-Key`
+pub const IDEMPOTENCY_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// What an `Idempotency-Key` names: the key itself, scoped to the credential
+/// the request was made with and the session it continues (`None` for
+/// requests that start a new session)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct IdempotencyKey {
+    /// Hash of the credential, so the token itself isn't kept
+    client: u64,
+    session_id: Option<String>,
+    key: String,
+}
+
+impl IdempotencyKey {
+    pub(crate) fn new(credential: &str, session_id: Option<&str>, key: &str) -> Self {
+        Self {
+            client: fingerprint(credential),
+            session_id: session_id.map(str::to_string),
+            key: key.to_string(),
+        }
+    }
+}
+
+/// Hash of a value, to compare requests without keeping them
+pub(crate) fn fingerprint(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+enum Entry {
+    /// The first request with the key is still being answered
+    InFlight { request: u64 },
+    /// The complete response, for replay until `stored_at + ttl`
+    Done {
+        request: u64,
+        stored_at: Instant,
+        events: Vec<SseEventData>,
+    },
+}
+
+impl Entry {
+    fn request(&self) -> u64 {
+        match self {
+            Entry::InFlight { request } | Entry::Done { request, .. } => *request,
+        }
+    }
+}
+
+type Entries = Arc<Mutex<HashMap<IdempotencyKey, Entry>>>;
+
+/// Outcome of presenting an `Idempotency-Key`
+pub(crate) enum Lookup {
+    /// First use of the key: run the turn and `complete` the reservation with its events
+    Reserved(Reservation),
+    /// The key was answered before: replay these events
+    Replay(Vec<SseEventData>),
+    /// The first request with the key is still being answered
+    InProgress,
+    /// The key was first used with a different request body
+    Mismatch,
+}
+
+/// Chat responses by idempotency key. Clones share the entries.
+#[derive(Clone)]
+pub(crate) struct IdempotencyStore {
+    entries: Entries,
+    ttl: Duration,
+}
+
+impl IdempotencyStore {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// Look up `key` for a request whose body hashes to `request`, reserving
+    /// the key if it is unused. A key is reserved before the turn starts, so
+    /// a retry arriving while the first request is still streaming doesn't
+    /// run the turn a second time.
+    pub(crate) fn begin(&self, key: IdempotencyKey, request: u64) -> Lookup {
+        let mut entries = lock(&self.entries);
+        entries.retain(|_, entry| match entry {
+            Entry::InFlight { .. } => true,
+            Entry::Done { stored_at, .. } => stored_at.elapsed() < self.ttl,
+        });
+        match entries.get(&key) {
+            Some(entry) if entry.request() != request => Lookup::Mismatch,
+            Some(Entry::InFlight { .. }) => Lookup::InProgress,
+            Some(Entry::Done { events, .. }) => Lookup::Replay(events.clone()),
+            None => {
+                entries.insert(key.clone(), Entry::InFlight { request });
+                Lookup::Reserved(Reservation {
+                    entries: Arc::clone(&self.entries),
+                    key,
+                    request,
+                    completed: false,
+                })
+            }
+        }
+    }
+}
+
+/// A key held for a request being answered. Dropping it without `complete`
+/// (an error, or the client going away) frees the key, so a retry runs the turn.
+pub(crate) struct Reservation {
+    entries: Entries,
+    key: IdempotencyKey,
+    request: u64,
+    completed: bool,
+}
+
+impl Reservation {
+    /// Keep the complete response's `events` for replay
+    pub(crate) fn complete(mut self, events: Vec<SseEventData>) {
+        self.completed = true;
+        lock(&self.entries).insert(
+            self.key.clone(),
+            Entry::Done {
+                request: self.request,
+                stored_at: Instant::now(),
+                events,
+            },
+        );
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if !self.completed {
+            lock(&self.entries).remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(credential: &str, session_id: Option<&str>) -> IdempotencyKey {
+        IdempotencyKey::new(credential, session_id, "retry-1")
+    }
+
+    fn done() -> Vec<SseEventData> {
+        vec![SseEventData::Text {
+            content: "answer".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_key_is_in_progress_until_completed() {
+        let store = IdempotencyStore::new(IDEMPOTENCY_TTL);
+        let Lookup::Reserved(reservation) = store.begin(key("token", None), 1) else {
+            panic!("a new key should be reserved");
+        };
+
+        assert!(matches!(
+            store.begin(key("token", None), 1),
+            Lookup::InProgress
+        ));
+        reservation.complete(done());
+        assert!(matches!(
+            store.begin(key("token", None), 1),
+            Lookup::Replay(events) if events.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_dropped_reservation_frees_the_key() {
+        let store = IdempotencyStore::new(IDEMPOTENCY_TTL);
+        drop(store.begin(key("token", None), 1));
+
+        assert!(matches!(
+            store.begin(key("token", None), 1),
+            Lookup::Reserved(_)
+        ));
+    }
+
+    #[test]
+    fn test_different_body_is_a_mismatch() {
+        let store = IdempotencyStore::new(IDEMPOTENCY_TTL);
+        let Lookup::Reserved(reservation) = store.begin(key("token", None), 1) else {
+            panic!("a new key should be reserved");
+        };
+
+        assert!(matches!(
+            store.begin(key("token", None), 2),
+            Lookup::Mismatch
+        ));
+        reservation.complete(done());
+        assert!(matches!(
+            store.begin(key("token", None), 2),
+            Lookup::Mismatch
+        ));
+    }
+
+    #[test]
+    fn test_keys_are_scoped_to_credential_and_session() {
+        let store = IdempotencyStore::new(IDEMPOTENCY_TTL);
+        let Lookup::Reserved(reservation) = store.begin(key("token-a", None), 1) else {
+            panic!("a new key should be reserved");
+        };
+        reservation.complete(done());
+
+        assert!(matches!(
+            store.begin(key("token-b", None), 1),
+            Lookup::Reserved(_)
+        ));
+        assert!(matches!(
+            store.begin(key("token-a", Some("session")), 1),
+            Lookup::Reserved(_)
+        ));
+    }
+
+    #[test]
+    fn test_expired_response_is_not_replayed() {
+        let store = IdempotencyStore::new(Duration::ZERO);
+        let Lookup::Reserved(reservation) = store.begin(key("token", None), 1) else {
+            panic!("a new key should be reserved");
+        };
+        reservation.complete(done());
+
+        assert!(matches!(
+            store.begin(key("token", None), 1),
+            Lookup::Reserved(_)
+        ));
+    }
+}
@@ -1,7 +1,14 @@
 pub mod auth;
 pub mod handlers;
+mod idempotency;
+pub mod openai;
 pub mod router;
 mod state;
 
+pub use crate::agent::ModelAllowlist;
+pub use crate::session::{SessionEviction, SessionLimit, SessionTitleMode};
 pub use router::build_router;
-pub use state::AppState;
+pub use state::{
+    any_agent_factory, AgentFactory, AppState, ExportFormat, ModelOverrideError,
+    SessionLimitReached, SessionSummary, TranscriptMessage, TranscriptRole,
+};
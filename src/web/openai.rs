@@ -0,0 +1,575 @@
+//! OpenAI-compatible chat endpoint, so tools that speak the OpenAI chat API
+//! can use copal as a drop-in backend.
+//!
+//! The endpoint is stateless like the OpenAI API: every request carries the
+//! whole conversation, and nothing is stored in a session.
+
+use axum::{extract::State, http::StatusCode, response::sse::Event, response::IntoResponse, Json};
+use futures::{stream, StreamExt};
+use rig::completion::Message;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::agent::{continue_truncated, limit_response_chars, ChatStreamEvent};
+use crate::web::handlers::{classify_error, sse_with_keep_alive, ErrorCode};
+use crate::web::state::preamble_turn;
+use crate::web::AppState;
+
+/// Model name reported in responses when the request doesn't name one
+const DEFAULT_MODEL_NAME: &str = "copal";
+
+/// Request body of `POST /v1/chat/completions`. Fields of the OpenAI request
+/// that copal has no use for (temperature, tools, ...) are ignored.
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    /// Echoed back in the response; the configured agent answers regardless
+    pub model: Option<String>,
+    pub messages: Vec<ChatCompletionMessage>,
+    /// Stream `chat.completion.chunk` events over SSE instead of one JSON response
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// One message of the conversation in an OpenAI request
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionMessage {
+    pub role: String,
+    pub content: Option<MessageContent>,
+}
+
+/// Message content: a plain string, or an array of content parts
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+/// A content part; only text parts are used
+#[derive(Debug, Deserialize)]
+pub struct ContentPart {
+    pub text: Option<String>,
+}
+
+impl MessageContent {
+    fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| part.text.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// Non-streaming response (`object: "chat.completion"`)
+#[derive(Debug, Serialize)]
+pub struct ChatCompletion {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<CompletionChoice>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionChoice {
+    pub index: u32,
+    pub message: AssistantMessage,
+    pub finish_reason: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssistantMessage {
+    pub role: &'static str,
+    pub content: String,
+}
+
+/// Streaming response event (`object: "chat.completion.chunk"`)
+#[derive(Debug, Serialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkChoice {
+    pub index: u32,
+    pub delta: ChunkDelta,
+    /// `null` until the final chunk
+    pub finish_reason: Option<&'static str>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ChunkDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// Error body in the OpenAI shape: `{"error": {"message": ..., "type": ...}}`
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorDetail {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub kind: ErrorCode,
+}
+
+type ErrorResponse = (StatusCode, Json<ErrorBody>);
+
+fn error_response(status: StatusCode, kind: ErrorCode, message: String) -> ErrorResponse {
+    (
+        status,
+        Json(ErrorBody {
+            error: ErrorDetail { message, kind },
+        }),
+    )
+}
+
+/// Split OpenAI messages into the prompt (the final user message) and the
+/// history passed to the agent.
+///
+/// System and developer messages become a preamble turn at the start of the
+/// history, the same way a session's custom preamble is sent.
+///
+/// # Errors
+/// Returns a message for the client if the last message isn't from the user
+/// or a role other than system/developer/user/assistant is used.
+pub(crate) fn map_messages(
+    messages: &[ChatCompletionMessage],
+) -> Result<(String, Vec<Message>), String> {
+    let (last, earlier) = messages
+        .split_last()
+        .ok_or_else(|| "messages must not be empty".to_string())?;
+    if last.role != "user" {
+        return Err(format!(
+            "the last message must have role \"user\", got \"{}\"",
+            last.role
+        ));
+    }
+
+    let mut instructions = Vec::new();
+    let mut history = Vec::new();
+    for message in earlier {
+        let text = message
+            .content
+            .as_ref()
+            .map(MessageContent::text)
+            .unwrap_or_default();
+        match message.role.as_str() {
+            "system" | "developer" => instructions.push(text),
+            "user" => history.push(Message::user(text)),
+            "assistant" => history.push(Message::assistant(text)),
+            other => return Err(format!("unsupported message role \"{other}\"")),
+        }
+    }
+    if !instructions.is_empty() {
+        let mut with_preamble = preamble_turn(&instructions.join("\n\n")).to_vec();
+        with_preamble.append(&mut history);
+        history = with_preamble;
+    }
+
+    let prompt = last
+        .content
+        .as_ref()
+        .map(MessageContent::text)
+        .unwrap_or_default();
+    Ok((prompt, history))
+}
+
+/// `POST /v1/chat/completions` - OpenAI-compatible chat using the default agent
+///
+/// With `stream: true` the reply is sent as `chat.completion.chunk` SSE events
+/// ending with `data: [DONE]`; otherwise as a single `chat.completion` object.
+///
+/// Returns 400 Bad Request for messages that can't be mapped onto a chat turn,
+/// and 502 Bad Gateway if the agent fails before a non-streaming reply completes.
+pub async fn chat_completions_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ChatCompletionRequest>,
+) -> Result<axum::response::Response, ErrorResponse> {
+    let (prompt, history) = map_messages(&req.messages)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, ErrorCode::Unknown, e))?;
+    if let Some(metrics) = state.metrics() {
+        metrics.record_chat_request();
+    }
+
+    let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+    let created = chrono::Utc::now().timestamp();
+    let model = req
+        .model
+        .clone()
+        .unwrap_or_else(|| DEFAULT_MODEL_NAME.to_string());
+
+    let agent_stream = state.agent.stream_chat(&prompt, history.clone()).await;
+    let agent_stream = continue_truncated(
+        state.agent.clone(),
+        &prompt,
+        history,
+        agent_stream,
+        state.max_continuations(),
+    );
+    let agent_stream = limit_response_chars(agent_stream, state.max_response_chars());
+
+    if !req.stream {
+        let (content, truncated) = collect_reply(&state, agent_stream).await?;
+        return Ok(Json(ChatCompletion {
+            id,
+            object: "chat.completion",
+            created,
+            model,
+            choices: vec![CompletionChoice {
+                index: 0,
+                message: AssistantMessage {
+                    role: "assistant",
+                    content,
+                },
+                finish_reason: finish_reason(truncated),
+            }],
+        })
+        .into_response());
+    }
+
+    let chunk = move |delta: ChunkDelta, finish_reason: Option<&'static str>| {
+        let data = ChatCompletionChunk {
+            id: id.clone(),
+            object: "chat.completion.chunk",
+            created,
+            model: model.clone(),
+            choices: vec![ChunkChoice {
+                index: 0,
+                delta,
+                finish_reason,
+            }],
+        };
+        Event::default().json_data(data).unwrap()
+    };
+    let role = chunk(
+        ChunkDelta {
+            role: Some("assistant"),
+            content: None,
+        },
+        None,
+    );
+    let mut truncated = false;
+    let events = agent_stream.filter_map(move |event| {
+        let event = match event {
+            ChatStreamEvent::TextDelta(text) => Some(chunk(
+                ChunkDelta {
+                    role: None,
+                    content: Some(text),
+                },
+                None,
+            )),
+            ChatStreamEvent::Truncated => {
+                truncated = true;
+                None
+            }
+            ChatStreamEvent::Done => {
+                Some(chunk(ChunkDelta::default(), Some(finish_reason(truncated))))
+            }
+            ChatStreamEvent::Error(e) => {
+                let (_, Json(body)) =
+                    error_response(StatusCode::BAD_GATEWAY, classify_error(&e), e);
+                Some(Event::default().json_data(body).unwrap())
+            }
+            ChatStreamEvent::Usage { total_tokens } => {
+                if let Some(metrics) = state.metrics() {
+                    metrics.record_tokens(total_tokens);
+                }
+                None
+            }
+            // Tool activity and warnings have no counterpart in the chunk format
+            ChatStreamEvent::Thinking(_)
+            | ChatStreamEvent::ToolCall { .. }
+            | ChatStreamEvent::ToolResult(_)
+            | ChatStreamEvent::Source(_)
+            | ChatStreamEvent::Warning(_) => None,
+        };
+        futures::future::ready(event)
+    });
+    let events = stream::once(futures::future::ready(role))
+        .chain(events)
+        .chain(stream::once(futures::future::ready(
+            Event::default().data("[DONE]"),
+        )))
+        .map(Ok::<_, std::convert::Infallible>);
+    Ok(sse_with_keep_alive(events).into_response())
+}
+
+/// OpenAI's `finish_reason` for a reply: `length` when it was cut off at the token limit
+fn finish_reason(truncated: bool) -> &'static str {
+    if truncated {
+        "length"
+    } else {
+        "stop"
+    }
+}
+
+/// Drain the agent stream into the full reply text and whether it was cut off
+async fn collect_reply(
+    state: &AppState,
+    mut agent_stream: impl futures::Stream<Item = ChatStreamEvent> + Unpin,
+) -> Result<(String, bool), ErrorResponse> {
+    let mut content = String::new();
+    let mut truncated = false;
+    while let Some(event) = agent_stream.next().await {
+        match event {
+            ChatStreamEvent::TextDelta(text) => content.push_str(&text),
+            ChatStreamEvent::Usage { total_tokens } => {
+                if let Some(metrics) = state.metrics() {
+                    metrics.record_tokens(total_tokens);
+                }
+            }
+            ChatStreamEvent::Error(e) => {
+                return Err(error_response(
+                    StatusCode::BAD_GATEWAY,
+                    classify_error(&e),
+                    e,
+                ))
+            }
+            ChatStreamEvent::Truncated => truncated = true,
+            ChatStreamEvent::Done => break,
+            ChatStreamEvent::Thinking(_)
+            | ChatStreamEvent::ToolCall { .. }
+            | ChatStreamEvent::ToolResult(_)
+            | ChatStreamEvent::Source(_)
+            | ChatStreamEvent::Warning(_) => {}
+        }
+    }
+    Ok((content, truncated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::MockAgent;
+    use rig::message::{AssistantContent, UserContent};
+
+    fn message(role: &str, content: &str) -> ChatCompletionMessage {
+        ChatCompletionMessage {
+            role: role.to_string(),
+            content: Some(MessageContent::Text(content.to_string())),
+        }
+    }
+
+    fn text_of(message: &Message) -> String {
+        match message {
+            Message::User { content } => match content.first() {
+                UserContent::Text(text) => text.text,
+                other => panic!("unexpected user content: {other:?}"),
+            },
+            Message::Assistant { content, .. } => match content.first() {
+                AssistantContent::Text(text) => text.text,
+                other => panic!("unexpected assistant content: {other:?}"),
+            },
+        }
+    }
+
+    fn request(messages: Vec<ChatCompletionMessage>, stream: bool) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: Some("gpt-4o".to_string()),
+            messages,
+            stream,
+        }
+    }
+
+    #[test]
+    fn test_map_messages_splits_prompt_from_history() {
+        let messages = vec![
+            message("user", "Hi"),
+            message("assistant", "Hello!"),
+            message("user", "What is Rust?"),
+        ];
+
+        let (prompt, history) = map_messages(&messages).unwrap();
+
+        assert_eq!(prompt, "What is Rust?");
+        let texts: Vec<String> = history.iter().map(text_of).collect();
+        assert_eq!(texts, ["Hi", "Hello!"]);
+        assert!(matches!(history[1], Message::Assistant { .. }));
+    }
+
+    #[test]
+    fn test_map_messages_sends_system_messages_as_preamble_turn() {
+        let messages = vec![
+            message("system", "Be brief."),
+            message("user", "Hi"),
+            message("assistant", "Hello!"),
+            message("user", "Bye"),
+        ];
+
+        let (_, history) = map_messages(&messages).unwrap();
+
+        assert_eq!(history.len(), 4);
+        assert!(text_of(&history[0]).ends_with("Be brief."));
+        assert_eq!(text_of(&history[2]), "Hi");
+    }
+
+    #[test]
+    fn test_map_messages_joins_text_content_parts() {
+        let messages = vec![ChatCompletionMessage {
+            role: "user".to_string(),
+            content: Some(MessageContent::Parts(vec![
+                ContentPart {
+                    text: Some("first".to_string()),
+                },
+                ContentPart { text: None },
+                ContentPart {
+                    text: Some("second".to_string()),
+                },
+            ])),
+        }];
+
+        let (prompt, _) = map_messages(&messages).unwrap();
+
+        assert_eq!(prompt, "first\nsecond");
+    }
+
+    #[test]
+    fn test_map_messages_rejects_bad_conversations() {
+        assert!(map_messages(&[]).is_err());
+        assert!(map_messages(&[message("user", "Hi"), message("assistant", "Hello")]).is_err());
+        assert!(map_messages(&[message("tool", "{}"), message("user", "Hi")]).is_err());
+    }
+
+    #[test]
+    fn test_request_deserializes_openai_shape() {
+        let req: ChatCompletionRequest = serde_json::from_str(
+            r#"{"model":"gpt-4o","temperature":0.2,"messages":[
+                {"role":"user","content":[{"type":"text","text":"Hi"}]}]}"#,
+        )
+        .unwrap();
+
+        assert!(!req.stream);
+        assert_eq!(map_messages(&req.messages).unwrap().0, "Hi");
+    }
+
+    #[tokio::test]
+    async fn test_non_streaming_response_shape() {
+        let agent = MockAgent::new(vec![vec![
+            ChatStreamEvent::TextDelta("Hel".to_string()),
+            ChatStreamEvent::ToolCall {
+                name: "web_search".to_string(),
+            },
+            ChatStreamEvent::TextDelta("lo".to_string()),
+            ChatStreamEvent::Done,
+        ]]);
+        let state = Arc::new(AppState::new(Arc::new(agent), "test-token".to_string()));
+
+        let response = chat_completions_handler(
+            State(state),
+            Json(request(vec![message("user", "Hi")], false)),
+        )
+        .await
+        .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["object"], "chat.completion");
+        assert_eq!(body["model"], "gpt-4o");
+        assert!(body["id"].as_str().unwrap().starts_with("chatcmpl-"));
+        assert!(body["created"].as_i64().unwrap() > 0);
+        assert_eq!(body["choices"][0]["index"], 0);
+        assert_eq!(body["choices"][0]["message"]["role"], "assistant");
+        assert_eq!(body["choices"][0]["message"]["content"], "Hello");
+        assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_reply_finishes_with_length() {
+        let agent = MockAgent::new(vec![vec![
+            ChatStreamEvent::TextDelta("The first half".to_string()),
+            ChatStreamEvent::Truncated,
+            ChatStreamEvent::Done,
+        ]]);
+        let state = Arc::new(AppState::new(Arc::new(agent), "test-token".to_string()));
+
+        let response = chat_completions_handler(
+            State(state),
+            Json(request(vec![message("user", "Hi")], false)),
+        )
+        .await
+        .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["choices"][0]["finish_reason"], "length");
+    }
+
+    #[tokio::test]
+    async fn test_non_streaming_agent_error_is_bad_gateway() {
+        let state = Arc::new(AppState::new(
+            Arc::new(MockAgent::with_error("429 Too Many Requests")),
+            "test-token".to_string(),
+        ));
+
+        let (status, Json(body)) = chat_completions_handler(
+            State(state),
+            Json(request(vec![message("user", "Hi")], false)),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(body.error.message, "429 Too Many Requests");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_response_sends_chunks_then_done_marker() {
+        let agent = MockAgent::new(vec![vec![
+            ChatStreamEvent::TextDelta("Hel".to_string()),
+            ChatStreamEvent::TextDelta("lo".to_string()),
+            ChatStreamEvent::Done,
+        ]]);
+        let state = Arc::new(AppState::new(Arc::new(agent), "test-token".to_string()));
+
+        let response = chat_completions_handler(
+            State(state),
+            Json(request(vec![message("user", "Hi")], true)),
+        )
+        .await
+        .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let data: Vec<&str> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .collect();
+        assert_eq!(data.last(), Some(&"[DONE]"));
+        let chunks: Vec<serde_json::Value> = data[..data.len() - 1]
+            .iter()
+            .map(|d| serde_json::from_str(d).unwrap())
+            .collect();
+        assert!(chunks
+            .iter()
+            .all(|c| c["object"] == "chat.completion.chunk"));
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        let content: String = chunks
+            .iter()
+            .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+            .collect();
+        assert_eq!(content, "Hello");
+        let last = &chunks[chunks.len() - 1]["choices"][0];
+        assert_eq!(last["finish_reason"], "stop");
+        assert!(chunks[0]["choices"][0]["finish_reason"].is_null());
+    }
+}
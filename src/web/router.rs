@@ -1,11 +1,18 @@
 use crate::web::{
     auth::require_bearer_token,
-    handlers::{chat_handler, verify_handler},
+    handlers::{
+        chat_handler, chat_ndjson_handler, clear_caches_handler, clear_handler,
+        create_session_handler, export_handler, health_handler, import_handler,
+        list_sessions_handler, metrics_handler, rename_session_handler, truncate_handler,
+        verify_handler,
+    },
+    openai::chat_completions_handler,
     AppState,
 };
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
-    routing::{get, post},
+    routing::{get, post, put},
     Router,
 };
 use std::sync::Arc;
@@ -16,7 +23,19 @@ use tower_http::services::ServeDir;
 ///
 /// # Routes
 /// - GET /api/verify - Token validation endpoint (Bearer token required)
-/// - POST /api/chat - SSE streaming chat endpoint (Bearer token required)
+/// - POST /api/chat - SSE streaming chat endpoint (Bearer token required; 413 over the body limit)
+/// - POST /api/chat/ndjson - The same chat events as newline-delimited JSON (Bearer token required)
+/// - GET /api/sessions - List sessions with their titles, most recently used first (Bearer token required)
+/// - POST /api/sessions - Create a session, optionally with a custom `preamble` (Bearer token required)
+/// - PUT /api/sessions/{id}/title - Rename a session (Bearer token required)
+/// - GET /api/sessions/{id}/export?format=md|json - Session transcript export (Bearer token required)
+/// - POST /api/sessions/import - Create a session from a JSON transcript (Bearer token required)
+/// - POST /api/sessions/{id}/clear - Empty a session's history, keeping its ID (Bearer token required)
+/// - POST /api/admin/cache/clear - Flush the robots.txt cache, returning the entries evicted (Bearer token required)
+/// - POST /v1/chat/completions - OpenAI-compatible chat, streaming or not (Bearer token required)
+/// - GET /metrics - Prometheus metrics; 404 unless enabled (no auth required)
+/// - GET /health - Liveness check with the assistant's name (no auth required)
+/// - POST /api/sessions/{id}/truncate - Drop messages after `{index}` to branch the conversation (Bearer token required)
 /// - GET / - Serve static files from frontend/dist (no auth required)
 ///
 /// # Middleware
@@ -27,14 +46,34 @@ use tower_http::services::ServeDir;
 /// `.layer()` wraps the entire router including the ServeDir fallback, which would
 /// require a token just to load `index.html`. `.route_layer()` applies only to
 /// explicitly registered routes (`/api/chat`), leaving static file serving open.
+/// `/metrics` and `/health` are registered after the layer, so they are not authenticated either.
 pub fn build_router(state: Arc<AppState>) -> Router {
+    // Applied per route: the session import endpoint takes whole transcripts
+    let chat_body_limit = DefaultBodyLimit::max(state.chat_body_limit());
     Router::new()
         .route("/api/verify", get(verify_handler))
-        .route("/api/chat", post(chat_handler))
+        .route("/api/chat", post(chat_handler).layer(chat_body_limit))
+        .route(
+            "/api/chat/ndjson",
+            post(chat_ndjson_handler).layer(chat_body_limit),
+        )
+        .route(
+            "/api/sessions",
+            get(list_sessions_handler).post(create_session_handler),
+        )
+        .route("/api/sessions/{id}/title", put(rename_session_handler))
+        .route("/api/sessions/{id}/export", get(export_handler))
+        .route("/api/sessions/import", post(import_handler))
+        .route("/api/sessions/{id}/clear", post(clear_handler))
+        .route("/api/sessions/{id}/truncate", post(truncate_handler))
+        .route("/api/admin/cache/clear", post(clear_caches_handler))
+        .route("/v1/chat/completions", post(chat_completions_handler))
         .route_layer(middleware::from_fn_with_state(
             Arc::clone(&state),
             require_bearer_token,
         ))
+        .route("/metrics", get(metrics_handler))
+        .route("/health", get(health_handler))
         .fallback_service(ServeDir::new("frontend/dist"))
         .with_state(state)
         .layer(CorsLayer::permissive())
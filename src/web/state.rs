@@ -1,8 +1,129 @@
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
-use crate::agent::ChatAgent;
-use crate::session::{ConversationHistory, DEFAULT_MAX_HISTORY_TURNS};
+use rig::completion::Message;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::agent::{
+    default_model, AgentSettings, AnyAgent, ChatAgent, Citation, ConfigError, ModelAllowlist,
+    ProviderConfig, ProviderSettings, WebFetch, DEFAULT_ASSISTANT_NAME, DEFAULT_MAX_PROMPT_CHARS,
+};
+use crate::config::DEFAULT_CHAT_MAX_BODY_BYTES;
+use crate::llm::{RigClient, RigEmbedder};
+use crate::metrics::Metrics;
+use crate::session::{
+    extract_assistant_text, extract_user_text, generate_title, title_from_message,
+    ConversationHistory, SessionEviction, SessionLimit, SessionTitleMode,
+    DEFAULT_MAX_HISTORY_TURNS,
+};
+use crate::sync::{lock, read, write};
+use crate::web::idempotency::{IdempotencyStore, IDEMPOTENCY_TTL};
+
+/// Output format for `AppState::export_session`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum ExportFormat {
+    /// Markdown document with `## User` / `## Assistant` sections
+    #[default]
+    #[serde(rename = "md")]
+    Markdown,
+    /// JSON array of `{ "role", "content" }` objects
+    #[serde(rename = "json")]
+    Json,
+}
+
+impl ExportFormat {
+    /// Content-Type header value for this format
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+}
+
+/// Role of a message in an exported transcript
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptRole {
+    User,
+    Assistant,
+}
+
+/// One message of an exported transcript (the JSON export format)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    pub role: TranscriptRole,
+    pub content: String,
+}
+
+/// Introduces a session's custom preamble in the history sent to the agent
+const SESSION_PREAMBLE_PREFIX: &str =
+    "Follow these instructions for the rest of this conversation:\n\n";
+/// Assistant reply that closes the preamble turn
+const SESSION_PREAMBLE_ACK: &str = "Understood.";
+
+/// Turn that gives the agent custom instructions: providers only accept
+/// user/assistant turns in the history, so the preamble is sent as a user
+/// message acknowledged by the assistant.
+pub(crate) fn preamble_turn(preamble: &str) -> [Message; 2] {
+    [
+        Message::user(format!("{}{}", SESSION_PREAMBLE_PREFIX, preamble)),
+        Message::assistant(SESSION_PREAMBLE_ACK),
+    ]
+}
+
+/// Builds a transient agent for a chat request that overrides the provider/model.
+/// The flag tells whether the agent should have tools.
+pub type AgentFactory = Arc<dyn Fn(ProviderConfig, bool) -> Arc<dyn ChatAgent> + Send + Sync>;
+
+/// Factory building a plain `AnyAgent` (no MCP tools) with `settings` that
+/// shares `web_fetch`'s robots.txt cache and source log with the default agent.
+pub fn any_agent_factory(web_fetch: WebFetch, settings: AgentSettings) -> AgentFactory {
+    Arc::new(move |config: ProviderConfig, tools_enabled: bool| {
+        if !tools_enabled {
+            return Arc::new(AnyAgent::chat_only_from_config(config, &settings))
+                as Arc<dyn ChatAgent>;
+        }
+        let mut web_fetch = web_fetch
+            .clone()
+            .with_summarizer(RigClient::summarizer(config.clone(), &settings.params));
+        if let Some(embedder) = RigEmbedder::from_env(config.clone()) {
+            web_fetch = web_fetch.with_embedder(embedder);
+        }
+        Arc::new(AnyAgent::from_config(config, &settings, web_fetch, vec![])) as Arc<dyn ChatAgent>
+    })
+}
+
+/// Error returned when a chat request's provider/model override can't be honored
+#[derive(Debug, thiserror::Error)]
+pub enum ModelOverrideError {
+    #[error("model overrides are not enabled on this server")]
+    Disabled,
+    #[error("{provider}:{model} is not in CHAT_MODEL_ALLOWLIST")]
+    NotAllowed { provider: String, model: String },
+    #[error("{0} has no default model; name the model to use")]
+    ModelRequired(String),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+/// One entry of the session listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    /// Short human-readable title (None until the first message)
+    pub title: Option<String>,
+    /// Messages in the session's history
+    pub message_count: usize,
+}
+
+/// Returned when a session can't be created because the cap is reached
+#[derive(Debug, thiserror::Error)]
+#[error("session limit of {0} reached")]
+pub struct SessionLimitReached(pub usize);
 
 /// Shared application state for the web server.
 /// Cloned across all request handlers via Axum's State extractor.
@@ -10,10 +131,48 @@ use crate::session::{ConversationHistory, DEFAULT_MAX_HISTORY_TURNS};
 pub struct AppState {
     /// The LLM agent (provider-agnostic, behind a trait object)
     pub agent: Arc<dyn ChatAgent>,
+    /// Agent without tools for requests that disable them (None = always use `agent`)
+    chat_only_agent: Option<Arc<dyn ChatAgent>>,
     /// Bearer token required for API access
     pub(crate) api_token: String,
-    /// In-memory session store (session_id -> conversation history)
-    sessions: Arc<Mutex<HashMap<String, ConversationHistory>>>,
+    /// In-memory session store (session_id -> conversation history).
+    /// An `RwLock` so that concurrent reads (e.g. `get_session`) don't serialize
+    sessions: Arc<RwLock<HashMap<String, ConversationHistory>>>,
+    /// Sources cited during each session (session_id -> citations, one per URL)
+    sources: Arc<Mutex<HashMap<String, Vec<Citation>>>>,
+    /// Custom system preambles of sessions created with one (session_id -> preamble)
+    preambles: Arc<Mutex<HashMap<String, String>>>,
+    /// Titles of sessions that have one (session_id -> title)
+    titles: Arc<Mutex<HashMap<String, String>>>,
+    /// How new sessions are titled
+    title_mode: SessionTitleMode,
+    /// Builds agents for requests that override the provider/model (None = overrides disabled)
+    agent_factory: Option<AgentFactory>,
+    /// Provider/model pairs requests may override to
+    model_allowlist: ModelAllowlist,
+    /// Endpoints and API keys the overriding agents are built with
+    provider_settings: ProviderSettings,
+    /// Counters served at `/metrics` (None = endpoint disabled)
+    metrics: Option<Arc<Metrics>>,
+    /// Cap on concurrent sessions (None = unlimited)
+    session_limit: Option<SessionLimit>,
+    /// When each session was last used (session_id -> tick of `use_clock`), for LRU eviction
+    last_used: Arc<Mutex<HashMap<String, u64>>>,
+    use_clock: Arc<AtomicU64>,
+    /// Name the assistant goes by, reported by `/health`
+    assistant_name: String,
+    /// Fetcher whose caches the admin endpoint clears (None = nothing to clear)
+    web_fetch: Option<WebFetch>,
+    /// Largest chat request body accepted, in bytes
+    chat_body_limit: usize,
+    /// Chat responses kept for replay to repeated idempotency keys
+    idempotency: IdempotencyStore,
+    /// Longest chat message accepted, in characters (None = unlimited)
+    max_prompt_chars: Option<usize>,
+    /// Longest answer streamed back, in characters (None = unlimited)
+    max_response_chars: Option<usize>,
+    /// Times a cut-off answer is continued automatically
+    max_continuations: usize,
 }
 
 impl AppState {
@@ -21,27 +180,314 @@ impl AppState {
     pub fn new(agent: Arc<dyn ChatAgent>, api_token: String) -> Self {
         Self {
             agent,
+            chat_only_agent: None,
             api_token,
-            sessions: Arc::new(Mutex::new(HashMap::new())),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            sources: Arc::new(Mutex::new(HashMap::new())),
+            preambles: Arc::new(Mutex::new(HashMap::new())),
+            titles: Arc::new(Mutex::new(HashMap::new())),
+            title_mode: SessionTitleMode::default(),
+            agent_factory: None,
+            model_allowlist: ModelAllowlist::default(),
+            provider_settings: ProviderSettings::default(),
+            metrics: None,
+            session_limit: None,
+            last_used: Arc::new(Mutex::new(HashMap::new())),
+            use_clock: Arc::new(AtomicU64::new(0)),
+            assistant_name: DEFAULT_ASSISTANT_NAME.to_string(),
+            web_fetch: None,
+            chat_body_limit: DEFAULT_CHAT_MAX_BODY_BYTES,
+            idempotency: IdempotencyStore::new(IDEMPOTENCY_TTL),
+            max_prompt_chars: Some(DEFAULT_MAX_PROMPT_CHARS),
+            max_response_chars: None,
+            max_continuations: 0,
+        }
+    }
+
+    /// Reject chat messages longer than `max_chars` characters with 400
+    /// (see `MAX_PROMPT_CHARS`); `None` accepts any length
+    pub fn with_max_prompt_chars(mut self, max_chars: Option<usize>) -> Self {
+        self.max_prompt_chars = max_chars;
+        self
+    }
+
+    /// Longest chat message accepted, in characters (None = unlimited)
+    pub fn max_prompt_chars(&self) -> Option<usize> {
+        self.max_prompt_chars
+    }
+
+    /// Cut answers off after `max_chars` characters (see `MAX_RESPONSE_CHARS`)
+    pub fn with_max_response_chars(mut self, max_chars: Option<usize>) -> Self {
+        self.max_response_chars = max_chars;
+        self
+    }
+
+    /// Longest answer streamed back, in characters (None = unlimited)
+    pub fn max_response_chars(&self) -> Option<usize> {
+        self.max_response_chars
+    }
+
+    /// Continue answers cut off at the token limit up to `max` times (see
+    /// `MAX_CONTINUATIONS`); with 0, the client is told the answer was cut off
+    pub fn with_max_continuations(mut self, max: usize) -> Self {
+        self.max_continuations = max;
+        self
+    }
+
+    /// Times a cut-off answer is continued automatically
+    pub fn max_continuations(&self) -> usize {
+        self.max_continuations
+    }
+
+    /// Replay responses for a repeated `Idempotency-Key` for `ttl` (default: `IDEMPOTENCY_TTL`)
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency = IdempotencyStore::new(ttl);
+        self
+    }
+
+    /// Chat responses by `Idempotency-Key`
+    pub(crate) fn idempotency(&self) -> &IdempotencyStore {
+        &self.idempotency
+    }
+
+    /// Reject chat requests whose body is over `bytes` with 413 (see `CHAT_MAX_BODY_BYTES`)
+    pub fn with_chat_body_limit(mut self, bytes: usize) -> Self {
+        self.chat_body_limit = bytes;
+        self
+    }
+
+    /// Largest chat request body accepted, in bytes
+    pub fn chat_body_limit(&self) -> usize {
+        self.chat_body_limit
+    }
+
+    /// Let `POST /api/admin/cache/clear` empty `web_fetch`'s caches, and let
+    /// grounded chat requests fetch their pages with it.
+    /// Pass a clone of the agents' fetcher so the caches they use are cleared.
+    pub fn with_web_fetch(mut self, web_fetch: WebFetch) -> Self {
+        self.web_fetch = Some(web_fetch);
+        self
+    }
+
+    /// Fetcher for grounded chat requests, if one was configured
+    pub fn web_fetch(&self) -> Option<&WebFetch> {
+        self.web_fetch.as_ref()
+    }
+
+    /// Empty the fetch caches; returns the number of entries evicted
+    pub async fn clear_fetch_caches(&self) -> usize {
+        match &self.web_fetch {
+            Some(web_fetch) => web_fetch.clear_caches().await,
+            None => 0,
+        }
+    }
+
+    /// Report `name` as the assistant's name (see `ASSISTANT_NAME`)
+    pub fn with_assistant_name(mut self, name: String) -> Self {
+        self.assistant_name = name;
+        self
+    }
+
+    /// Name the assistant goes by
+    pub fn assistant_name(&self) -> &str {
+        &self.assistant_name
+    }
+
+    /// Title new sessions according to `mode` (see `SESSION_TITLE_MODE`)
+    pub fn with_title_mode(mut self, mode: SessionTitleMode) -> Self {
+        self.title_mode = mode;
+        self
+    }
+
+    /// Cap the number of sessions; see `try_create_session`.
+    pub fn with_session_limit(mut self, limit: SessionLimit) -> Self {
+        self.session_limit = Some(limit);
+        self
+    }
+
+    /// Allow chat requests to pick a provider/model from `allowlist`.
+    /// `factory` builds a transient agent for each such request from the
+    /// provider configuration resolved with `providers`.
+    pub fn with_model_overrides(
+        mut self,
+        factory: AgentFactory,
+        allowlist: ModelAllowlist,
+        providers: ProviderSettings,
+    ) -> Self {
+        self.agent_factory = Some(factory);
+        self.model_allowlist = allowlist;
+        self.provider_settings = providers;
+        self
+    }
+
+    /// Count requests in `metrics` and serve them at `/metrics`.
+    /// Pass the fetcher's metrics so fetch counts show up too.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The metrics, if the endpoint is enabled
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_deref()
+    }
+
+    /// Number of sessions currently held in memory
+    pub fn session_count(&self) -> usize {
+        read(&self.sessions).len()
+    }
+
+    /// Use `agent` for chat requests that disable tools.
+    pub fn with_chat_only_agent(mut self, agent: Arc<dyn ChatAgent>) -> Self {
+        self.chat_only_agent = Some(agent);
+        self
+    }
+
+    /// Get the agent for a chat request.
+    ///
+    /// Without an override this is the shared default agent, or the tool-less agent
+    /// when `tools_enabled` is false and one is configured. A request that names
+    /// only a provider uses that provider's default model; one that names only a
+    /// model keeps the configured `LLM_PROVIDER` (or `LLM_DEFAULT_PROVIDER`).
+    pub fn agent_for(
+        &self,
+        provider: Option<&str>,
+        model: Option<&str>,
+        tools_enabled: bool,
+    ) -> Result<Arc<dyn ChatAgent>, ModelOverrideError> {
+        if provider.is_none() && model.is_none() {
+            return Ok(match (&self.chat_only_agent, tools_enabled) {
+                (Some(chat_only_agent), false) => Arc::clone(chat_only_agent),
+                _ => Arc::clone(&self.agent),
+            });
+        }
+        let factory = self
+            .agent_factory
+            .as_ref()
+            .ok_or(ModelOverrideError::Disabled)?;
+
+        let provider = provider
+            .map(str::to_string)
+            .unwrap_or_else(|| self.provider_settings.provider.clone());
+        let model = match model {
+            Some(model) => model.to_string(),
+            // An OpenAI-compatible server hosts whatever was loaded into it, so there is no default
+            None if provider == "openai_compat" => {
+                return Err(ModelOverrideError::ModelRequired(provider))
+            }
+            None => default_model(&provider).to_string(),
+        };
+        if !self.model_allowlist.allows(&provider, &model) {
+            return Err(ModelOverrideError::NotAllowed { provider, model });
         }
+
+        let config = self.provider_settings.provider_config(&provider, &model)?;
+        Ok(factory(config, tools_enabled))
     }
 
     /// Create a new session and return its ID.
     /// The session is initialized with empty conversation history.
+    /// Ignores the session limit; request handlers use `try_create_session`.
     pub fn create_session(&self) -> String {
         let id = uuid::Uuid::new_v4().to_string();
         let new_history = ConversationHistory::new(DEFAULT_MAX_HISTORY_TURNS);
         {
-            let mut locked = self.sessions.lock().unwrap();
+            let mut locked = write(&self.sessions);
             locked.insert(id.clone(), new_history);
         }
+        self.touch(&id);
         id
     }
 
+    /// Create a new session and return its ID, respecting the session limit.
+    /// At the cap, either the least recently used session is evicted or
+    /// `SessionLimitReached` is returned, depending on the configured eviction.
+    pub fn try_create_session(&self) -> Result<String, SessionLimitReached> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.insert_session(
+            id.clone(),
+            ConversationHistory::new(DEFAULT_MAX_HISTORY_TURNS),
+        )?;
+        Ok(id)
+    }
+
+    /// Create a new session whose agent calls are prefixed with `preamble`
+    /// (e.g. a front-end "mode" such as coding helper or travel planner).
+    /// A missing or blank preamble creates a plain session.
+    pub fn create_session_with_preamble(
+        &self,
+        preamble: Option<String>,
+    ) -> Result<String, SessionLimitReached> {
+        let id = self.try_create_session()?;
+        if let Some(preamble) = preamble.filter(|p| !p.trim().is_empty()) {
+            lock(&self.preambles).insert(id.clone(), preamble);
+        }
+        Ok(id)
+    }
+
+    /// Store a new session, making room for it (or refusing it) at the session limit
+    fn insert_session(
+        &self,
+        id: String,
+        history: ConversationHistory,
+    ) -> Result<(), SessionLimitReached> {
+        {
+            let mut sessions = write(&self.sessions);
+            if let Some(limit) = self.session_limit {
+                if sessions.len() >= limit.max_sessions {
+                    if limit.eviction == SessionEviction::Reject {
+                        return Err(SessionLimitReached(limit.max_sessions));
+                    }
+                    let oldest = {
+                        let last_used = lock(&self.last_used);
+                        sessions
+                            .keys()
+                            .min_by_key(|id| last_used.get(*id).copied().unwrap_or(0))
+                            .cloned()
+                    };
+                    if let Some(oldest) = oldest {
+                        sessions.remove(&oldest);
+                        self.forget_session(&oldest);
+                    }
+                }
+            }
+            sessions.insert(id.clone(), history);
+        }
+        self.touch(&id);
+        Ok(())
+    }
+
+    /// Mark a session as just used
+    fn touch(&self, session_id: &str) {
+        let tick = self.use_clock.fetch_add(1, Ordering::Relaxed);
+        lock(&self.last_used).insert(session_id.to_string(), tick);
+    }
+
+    /// Drop everything kept alongside an evicted session's history
+    fn forget_session(&self, session_id: &str) {
+        lock(&self.last_used).remove(session_id);
+        lock(&self.sources).remove(session_id);
+        lock(&self.preambles).remove(session_id);
+        lock(&self.titles).remove(session_id);
+    }
+
+    /// Get the messages to pass to the agent for a session: the session's
+    /// preamble (if any) followed by its conversation history.
+    /// Returns None if the session doesn't exist.
+    pub fn agent_history(&self, session_id: &str) -> Option<Vec<Message>> {
+        let history = self.get_session(session_id)?;
+        let mut messages = match lock(&self.preambles).get(session_id) {
+            Some(preamble) => preamble_turn(preamble).to_vec(),
+            None => Vec::new(),
+        };
+        messages.extend(history.to_vec());
+        Some(messages)
+    }
+
     /// Get a copy of the conversation history for a session.
     /// Returns None if the session doesn't exist.
     pub fn get_session(&self, session_id: &str) -> Option<ConversationHistory> {
-        let locked = self.sessions.lock().unwrap();
+        let locked = read(&self.sessions);
         let history = match locked.get(session_id) {
             Some(h) => h,
             None => {
@@ -52,55 +498,248 @@ impl AppState {
     }
 
     /// Add a user message to a session's conversation history.
-    /// Creates the session if it doesn't exist (fallback for flexibility).
+    /// Returns false if the session doesn't exist: sessions are only created
+    /// through `try_create_session` and friends, so the session limit holds.
     ///
     /// # Typical Usage Flow
     /// ```ignore
     /// // In chat handler:
     /// let session_id = match request.session_id {
-    ///     Some(id) => id,                    // Existing session (continued conversation)
-    ///     None => state.create_session(),    // New session (recommended: explicit creation)
+    ///     Some(id) => id,                         // Existing session (continued conversation)
+    ///     None => state.try_create_session()?,    // New session
     /// };
-    /// state.add_user_message(&session_id, &request.message);
+    /// if !state.add_user_message(&session_id, &request.message) {
+    ///     // Unknown or evicted session: 404
+    /// }
     /// ```
-    ///
-    /// The auto-create behavior provides flexibility for clients that generate their own UUIDs,
-    /// but explicit `create_session()` is recommended for clearer lifecycle management.
-    pub fn add_user_message(&self, session_id: &str, message: &str) {
-        let mut locked = self.sessions.lock().unwrap();
-        let history = locked
-            .entry(session_id.to_string())
-            .or_insert_with(|| ConversationHistory::new(DEFAULT_MAX_HISTORY_TURNS));
-        history.add_user(message);
+    pub fn add_user_message(&self, session_id: &str, message: &str) -> bool {
+        {
+            let mut locked = write(&self.sessions);
+            let Some(history) = locked.get_mut(session_id) else {
+                return false;
+            };
+            history.add_user(message);
+            if let Some(title) = title_from_message(message) {
+                lock(&self.titles)
+                    .entry(session_id.to_string())
+                    .or_insert(title);
+            }
+        }
+        self.touch(session_id);
+        true
     }
 
     /// Add an assistant message to a session's conversation history.
-    /// Assumes the session already exists (panics otherwise).
+    /// Returns false if the session doesn't exist (any more).
     ///
     /// # Expected Call Sequence
     /// ```ignore
-    /// state.add_user_message(&session_id, "Hello");       // 1. User message (creates if needed)
-    /// let history = state.get_session(&session_id).unwrap().to_vec();
+    /// state.add_user_message(&session_id, "Hello");       // 1. User message
+    /// let history = state.agent_history(&session_id)?;
     /// let stream = agent.stream_chat("Hello", history).await;  // 2. Get response
     /// // ... collect full response from stream ...
     /// state.add_assistant_message(&session_id, &response);     // 3. Save response
     /// ```
     ///
-    /// This method does NOT auto-create because it's always called after `add_user_message`,
-    /// which ensures the session exists. Missing session indicates a logic error.
-    pub fn add_assistant_message(&self, session_id: &str, message: &str) {
-        let mut locked = self.sessions.lock().unwrap();
-        let history = locked
-            .get_mut(session_id)
-            .expect("session id does not exist");
-        history.add_assistant(message);
+    /// The session can disappear between steps 1 and 3, e.g. when it is evicted
+    /// at the session limit while its reply is streaming.
+    pub fn add_assistant_message(&self, session_id: &str, message: &str) -> bool {
+        let mut locked = write(&self.sessions);
+        match locked.get_mut(session_id) {
+            Some(history) => {
+                history.add_assistant(message);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Empty a session's conversation history (and recorded sources) while keeping
+    /// the session itself. Returns false if the session doesn't exist.
+    pub fn clear_history(&self, session_id: &str) -> bool {
+        {
+            let mut locked = write(&self.sessions);
+            match locked.get_mut(session_id) {
+                Some(history) => history.clear(),
+                None => return false,
+            }
+        }
+        lock(&self.sources).remove(session_id);
+        true
+    }
+
+    /// Drop every message after the one at `index` (0-based), keeping `index` itself.
+    /// Lets a client branch the conversation: truncate after the message preceding
+    /// the one being edited, then send the edited message. An index past the end
+    /// leaves the history unchanged. Returns false if the session doesn't exist.
+    pub fn truncate_after(&self, session_id: &str, index: usize) -> bool {
+        let mut locked = write(&self.sessions);
+        match locked.get_mut(session_id) {
+            Some(history) => {
+                history.truncate(index.saturating_add(1));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Export a session's conversation history as Markdown or JSON.
+    /// Returns None if the session doesn't exist.
+    pub fn export_session(&self, session_id: &str, format: ExportFormat) -> Option<String> {
+        let history = self.get_session(session_id)?;
+        let transcript: Vec<TranscriptMessage> = history
+            .as_slice()
+            .iter()
+            .filter_map(|msg| {
+                extract_user_text(msg)
+                    .map(|content| TranscriptMessage {
+                        role: TranscriptRole::User,
+                        content,
+                    })
+                    .or_else(|| {
+                        extract_assistant_text(msg).map(|content| TranscriptMessage {
+                            role: TranscriptRole::Assistant,
+                            content,
+                        })
+                    })
+            })
+            .collect();
+
+        let exported = match format {
+            ExportFormat::Markdown => transcript
+                .iter()
+                .map(|m| {
+                    let heading = match m.role {
+                        TranscriptRole::User => "User",
+                        TranscriptRole::Assistant => "Assistant",
+                    };
+                    format!("## {}\n\n{}\n", heading, m.content)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ExportFormat::Json => serde_json::to_string_pretty(&transcript)
+                .expect("transcript serialization cannot fail"),
+        };
+        Some(exported)
+    }
+
+    /// Create a new session pre-populated with the given transcript and return its ID.
+    /// Used to resume a conversation exported with `export_session`.
+    pub fn import_session(
+        &self,
+        messages: Vec<TranscriptMessage>,
+    ) -> Result<String, SessionLimitReached> {
+        let mut history = ConversationHistory::new(DEFAULT_MAX_HISTORY_TURNS);
+        let mut title = None;
+        for message in messages {
+            match message.role {
+                TranscriptRole::User => {
+                    if title.is_none() {
+                        title = title_from_message(&message.content);
+                    }
+                    history.add_user(&message.content)
+                }
+                TranscriptRole::Assistant => history.add_assistant(&message.content),
+            }
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        self.insert_session(id.clone(), history)?;
+        if let Some(title) = title {
+            lock(&self.titles).insert(id.clone(), title);
+        }
+        Ok(id)
+    }
+
+    /// A session's title, if it has one
+    pub fn session_title(&self, session_id: &str) -> Option<String> {
+        lock(&self.titles).get(session_id).cloned()
+    }
+
+    /// Give a session a new title, trimmed of surrounding whitespace.
+    /// Returns false if the session doesn't exist.
+    pub fn rename_session(&self, session_id: &str, title: &str) -> bool {
+        // Held while writing the title, so the session can't be evicted in between
+        let sessions = read(&self.sessions);
+        if !sessions.contains_key(session_id) {
+            return false;
+        }
+        lock(&self.titles).insert(session_id.to_string(), title.trim().to_string());
+        true
+    }
+
+    /// Every session with its title, most recently used first
+    pub fn list_sessions(&self) -> Vec<SessionSummary> {
+        let mut sessions: Vec<(u64, SessionSummary)> = {
+            let sessions = read(&self.sessions);
+            let titles = lock(&self.titles);
+            let last_used = lock(&self.last_used);
+            sessions
+                .iter()
+                .map(|(id, history)| {
+                    let summary = SessionSummary {
+                        session_id: id.clone(),
+                        title: titles.get(id).cloned(),
+                        message_count: history.len(),
+                    };
+                    (last_used.get(id).copied().unwrap_or(0), summary)
+                })
+                .collect()
+        };
+        sessions.sort_by(|(a, _), (b, _)| b.cmp(a));
+        sessions.into_iter().map(|(_, summary)| summary).collect()
+    }
+
+    /// With `SessionTitleMode::Llm`, ask the model for a better title for a
+    /// session opened with `first_message`, in the background. The title from the
+    /// first message is only replaced if nobody renamed the session meanwhile.
+    /// Returns the background task, or None when titles aren't generated.
+    pub fn generate_session_title(
+        &self,
+        session_id: &str,
+        first_message: &str,
+    ) -> Option<JoinHandle<()>> {
+        if self.title_mode != SessionTitleMode::Llm {
+            return None;
+        }
+        let agent = Arc::clone(self.chat_only_agent.as_ref().unwrap_or(&self.agent));
+        let titles = Arc::clone(&self.titles);
+        let session_id = session_id.to_string();
+        let first_message = first_message.to_string();
+        Some(tokio::spawn(async move {
+            let Some(generated) = generate_title(agent.as_ref(), &first_message).await else {
+                log::warn!("Could not generate a title for session {}", session_id);
+                return;
+            };
+            let mut titles = lock(&titles);
+            if titles.get(&session_id) == title_from_message(&first_message).as_ref() {
+                titles.insert(session_id, generated);
+            }
+        }))
+    }
+
+    /// Record a source fetched during a session.
+    /// A URL already recorded for the session is replaced by the newer citation.
+    pub fn add_source(&self, session_id: &str, citation: Citation) {
+        let mut locked = lock(&self.sources);
+        let sources = locked.entry(session_id.to_string()).or_default();
+        match sources.iter_mut().find(|c| c.url == citation.url) {
+            Some(existing) => *existing = citation,
+            None => sources.push(citation),
+        }
+    }
+
+    /// Get the sources recorded for a session, in the order they were first fetched.
+    pub fn get_sources(&self, session_id: &str) -> Vec<Citation> {
+        let locked = lock(&self.sources);
+        locked.get(session_id).cloned().unwrap_or_default()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::agent::MockAgent;
+    use crate::agent::{ChatStreamEvent, MockAgent};
 
     fn make_state() -> AppState {
         AppState::new(
@@ -119,6 +758,175 @@ mod tests {
         assert!(history.is_empty());
     }
 
+    #[test]
+    fn test_agent_history_prepends_session_preamble() {
+        let state = make_state();
+        let session_id = state
+            .create_session_with_preamble(Some("Answer in haiku.".to_string()))
+            .unwrap();
+        state.add_user_message(&session_id, "hello");
+
+        let messages = state.agent_history(&session_id).unwrap();
+
+        assert_eq!(messages.len(), 3);
+        assert!(extract_user_text(&messages[0])
+            .unwrap()
+            .ends_with("Answer in haiku."));
+        assert_eq!(extract_user_text(&messages[2]).as_deref(), Some("hello"));
+        // The preamble is not part of the stored conversation
+        assert_eq!(state.get_session(&session_id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_blank_preamble_creates_plain_session() {
+        let state = make_state();
+        let session_id = state
+            .create_session_with_preamble(Some("  ".to_string()))
+            .unwrap();
+
+        assert_eq!(state.agent_history(&session_id), Some(vec![]));
+    }
+
+    fn limited_state(eviction: SessionEviction) -> AppState {
+        make_state().with_session_limit(SessionLimit {
+            max_sessions: 2,
+            eviction,
+        })
+    }
+
+    #[test]
+    fn test_session_limit_rejects_sessions_over_the_cap() {
+        let state = limited_state(SessionEviction::Reject);
+        let first = state.try_create_session().unwrap();
+        state.try_create_session().unwrap();
+
+        assert!(state.try_create_session().is_err());
+        assert_eq!(state.session_count(), 2);
+        assert!(state.get_session(&first).is_some());
+    }
+
+    #[test]
+    fn test_session_limit_evicts_least_recently_used_session() {
+        let state = limited_state(SessionEviction::LeastRecentlyUsed);
+        let first = state.try_create_session().unwrap();
+        let second = state.try_create_session().unwrap();
+        state.add_source(&second, Citation::now("https://a.example", None));
+        // Using the first session makes the second the least recently used
+        state.add_user_message(&first, "still here");
+
+        let third = state.try_create_session().unwrap();
+
+        assert_eq!(state.session_count(), 2);
+        assert!(state.get_session(&first).is_some());
+        assert!(state.get_session(&second).is_none());
+        assert!(state.get_sources(&second).is_empty());
+        assert!(state.get_session(&third).is_some());
+    }
+
+    #[test]
+    fn test_first_user_message_titles_session() {
+        let state = make_state();
+        let session_id = state.create_session();
+        assert_eq!(state.session_title(&session_id), None);
+
+        state.add_user_message(&session_id, "What is\nborrow checking?");
+        state.add_user_message(&session_id, "And lifetimes?");
+
+        assert_eq!(
+            state.session_title(&session_id).as_deref(),
+            Some("What is borrow checking?")
+        );
+    }
+
+    #[test]
+    fn test_renamed_title_is_kept_and_listed() {
+        let state = make_state();
+        let older = state.create_session();
+        let newer = state.create_session();
+        state.add_user_message(&older, "hello");
+
+        assert!(state.rename_session(&older, "  Greetings "));
+        assert!(!state.rename_session("nonexistent", "x"));
+        state.add_user_message(&newer, "second session");
+
+        let listed = state.list_sessions();
+        assert_eq!(
+            listed,
+            vec![
+                SessionSummary {
+                    session_id: newer,
+                    title: Some("second session".to_string()),
+                    message_count: 1,
+                },
+                SessionSummary {
+                    session_id: older,
+                    title: Some("Greetings".to_string()),
+                    message_count: 1,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generated_title_does_not_replace_a_rename() {
+        let reply = || {
+            vec![
+                ChatStreamEvent::TextDelta("Title: Rust Borrowing".to_string()),
+                ChatStreamEvent::Done,
+            ]
+        };
+        let state = AppState::new(
+            Arc::new(MockAgent::new(vec![reply(), reply()])),
+            "test-token".to_string(),
+        )
+        .with_title_mode(SessionTitleMode::Llm);
+        let renamed = state.create_session();
+        let untouched = state.create_session();
+        state.add_user_message(&renamed, "What is borrow checking?");
+        state.add_user_message(&untouched, "What is borrow checking?");
+
+        let renamed_task = state
+            .generate_session_title(&renamed, "What is borrow checking?")
+            .unwrap();
+        assert!(state.rename_session(&renamed, "Mine"));
+        let untouched_task = state
+            .generate_session_title(&untouched, "What is borrow checking?")
+            .unwrap();
+        renamed_task.await.unwrap();
+        untouched_task.await.unwrap();
+
+        assert_eq!(state.session_title(&renamed).as_deref(), Some("Mine"));
+        assert_eq!(
+            state.session_title(&untouched).as_deref(),
+            Some("Rust Borrowing")
+        );
+        assert!(make_state()
+            .generate_session_title(&untouched, "hello")
+            .is_none());
+    }
+
+    #[test]
+    fn test_imported_session_is_titled_from_first_user_message() {
+        let state = make_state();
+        let session_id = state
+            .import_session(vec![
+                TranscriptMessage {
+                    role: TranscriptRole::Assistant,
+                    content: "Welcome!".to_string(),
+                },
+                TranscriptMessage {
+                    role: TranscriptRole::User,
+                    content: "Compare Rust and Go".to_string(),
+                },
+            ])
+            .unwrap();
+
+        assert_eq!(
+            state.session_title(&session_id).as_deref(),
+            Some("Compare Rust and Go")
+        );
+    }
+
     #[tokio::test]
     async fn test_add_multiple_user_messages() {
         let state = make_state();
@@ -127,7 +935,7 @@ mod tests {
         state.add_user_message(&session_id, "hello1");
         state.add_user_message(&session_id, "hello2");
 
-        let locked = state.sessions.lock().unwrap();
+        let locked = state.sessions.read().unwrap();
         assert_eq!(locked.get(&session_id).unwrap().len(), 2);
     }
 
@@ -139,27 +947,25 @@ mod tests {
         state.add_assistant_message(&session_id, "hello1");
         state.add_assistant_message(&session_id, "hello2");
 
-        let locked = state.sessions.lock().unwrap();
+        let locked = state.sessions.read().unwrap();
         assert_eq!(locked.get(&session_id).unwrap().len(), 2);
     }
 
     #[tokio::test]
-    async fn test_new_history_is_created_when_add_user_message_is_called_with_new_session_id() {
+    async fn test_add_user_message_does_not_create_unknown_session() {
         let state = make_state();
 
-        state.add_user_message("nonexistent_session_id", "hello1");
+        assert!(!state.add_user_message("nonexistent_session_id", "hello1"));
 
-        let locked = state.sessions.lock().unwrap();
-        assert_eq!(locked.get("nonexistent_session_id").unwrap().len(), 1);
+        assert_eq!(state.session_count(), 0);
+        assert_eq!(state.session_title("nonexistent_session_id"), None);
     }
 
     #[tokio::test]
-    #[should_panic(expected = "session id does not exist")]
-    async fn test_add_assistant_message_panics_when_session_does_not_exist() {
+    async fn test_add_assistant_message_reports_missing_session() {
         let state = make_state();
 
-        // This should panic because the session doesn't exist
-        state.add_assistant_message("nonexistent_session_id", "hello");
+        assert!(!state.add_assistant_message("nonexistent_session_id", "hello"));
     }
 
     #[tokio::test]
@@ -170,7 +976,7 @@ mod tests {
         // Step 1: User sends a message
         state.add_user_message(&session_id, "What is Rust?");
         {
-            let locked = state.sessions.lock().unwrap();
+            let locked = state.sessions.read().unwrap();
             assert_eq!(locked.get(&session_id).unwrap().len(), 1);
         }
 
@@ -182,7 +988,7 @@ mod tests {
         // Step 3: Add assistant response after getting LLM output
         state.add_assistant_message(&session_id, "Rust is a systems programming language...");
         {
-            let locked = state.sessions.lock().unwrap();
+            let locked = state.sessions.read().unwrap();
             assert_eq!(locked.get(&session_id).unwrap().len(), 2);
         }
     }
@@ -200,7 +1006,7 @@ mod tests {
         history_copy.add_user("This should not affect the original");
 
         // Verify the original session is unchanged
-        let locked = state.sessions.lock().unwrap();
+        let locked = state.sessions.read().unwrap();
         assert_eq!(locked.get(&session_id).unwrap().len(), 1);
     }
 
@@ -216,16 +1022,349 @@ mod tests {
         state.add_user_message(&session2, "Session 2 message 1");
 
         {
-            let locked = state.sessions.lock().unwrap();
+            let locked = state.sessions.read().unwrap();
             assert_eq!(locked.get(&session1).unwrap().len(), 2);
             assert_eq!(locked.get(&session2).unwrap().len(), 1);
         }
 
         state.add_assistant_message(&session2, "Session 2 response");
         {
-            let locked = state.sessions.lock().unwrap();
+            let locked = state.sessions.read().unwrap();
             assert_eq!(locked.get(&session1).unwrap().len(), 2);
             assert_eq!(locked.get(&session2).unwrap().len(), 2);
         }
     }
+
+    #[test]
+    fn test_add_source_deduplicates_by_url() {
+        let state = make_state();
+        let session_id = state.create_session();
+
+        state.add_source(&session_id, Citation::now("https://a.example", None));
+        state.add_source(&session_id, Citation::now("https://b.example", None));
+        state.add_source(
+            &session_id,
+            Citation::now("https://a.example", Some("A".to_string())),
+        );
+
+        let sources = state.get_sources(&session_id);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].url, "https://a.example");
+        assert_eq!(sources[0].title.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn test_get_sources_is_empty_for_unknown_session() {
+        let state = make_state();
+        assert!(state.get_sources("nonexistent_session_id").is_empty());
+    }
+
+    fn make_two_turn_session(state: &AppState) -> String {
+        let session_id = state.create_session();
+        state.add_user_message(&session_id, "What is Rust?");
+        state.add_assistant_message(&session_id, "A systems language.");
+        state.add_user_message(&session_id, "Who made it?");
+        state.add_assistant_message(&session_id, "Mozilla.");
+        session_id
+    }
+
+    #[test]
+    fn test_export_session_as_markdown() {
+        let state = make_state();
+        let session_id = make_two_turn_session(&state);
+
+        let exported = state
+            .export_session(&session_id, ExportFormat::Markdown)
+            .unwrap();
+
+        assert_eq!(
+            exported,
+            "## User\n\nWhat is Rust?\n\n\
+             ## Assistant\n\nA systems language.\n\n\
+             ## User\n\nWho made it?\n\n\
+             ## Assistant\n\nMozilla.\n"
+        );
+    }
+
+    #[test]
+    fn test_export_session_as_json() {
+        let state = make_state();
+        let session_id = make_two_turn_session(&state);
+
+        let exported = state
+            .export_session(&session_id, ExportFormat::Json)
+            .unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&exported).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([
+                { "role": "user", "content": "What is Rust?" },
+                { "role": "assistant", "content": "A systems language." },
+                { "role": "user", "content": "Who made it?" },
+                { "role": "assistant", "content": "Mozilla." },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_export_session_returns_none_for_unknown_session() {
+        let state = make_state();
+        assert!(state
+            .export_session("nonexistent_session_id", ExportFormat::Json)
+            .is_none());
+    }
+
+    #[test]
+    fn test_export_then_import_round_trips_history() {
+        let state = make_state();
+        let session_id = make_two_turn_session(&state);
+        let exported = state
+            .export_session(&session_id, ExportFormat::Json)
+            .unwrap();
+
+        let messages: Vec<TranscriptMessage> = serde_json::from_str(&exported).unwrap();
+        let imported_id = state.import_session(messages).unwrap();
+
+        assert_ne!(imported_id, session_id);
+        assert_eq!(state.get_session(&imported_id).unwrap().len(), 4);
+        assert_eq!(
+            state.export_session(&imported_id, ExportFormat::Json),
+            Some(exported)
+        );
+    }
+
+    fn make_override_state(allowlist: &str) -> AppState {
+        make_override_state_with(allowlist, ProviderSettings::default())
+    }
+
+    fn make_override_state_with(allowlist: &str, providers: ProviderSettings) -> AppState {
+        let factory: AgentFactory = Arc::new(|config: ProviderConfig, tools_enabled: bool| {
+            let mut label = format!("{}:{}", config.provider_name(), config.model());
+            if !tools_enabled {
+                label.push_str(" (no tools)");
+            }
+            Arc::new(MockAgent::with_response(&label)) as Arc<dyn ChatAgent>
+        });
+        AppState::new(
+            Arc::new(MockAgent::with_response("default")),
+            "test-token".to_string(),
+        )
+        .with_model_overrides(factory, allowlist.parse().unwrap(), providers)
+    }
+
+    async fn response_text(agent: Arc<dyn ChatAgent>) -> String {
+        use futures::StreamExt;
+
+        let mut stream = agent.stream_chat("hi", vec![]).await;
+        let mut text = String::new();
+        while let Some(event) = stream.next().await {
+            if let crate::agent::ChatStreamEvent::TextDelta(delta) = event {
+                text.push_str(&delta);
+            }
+        }
+        text
+    }
+
+    #[tokio::test]
+    async fn test_agent_for_without_override_uses_default_agent() {
+        let state = make_override_state("ollama:llama3");
+
+        let agent = state.agent_for(None, None, true).unwrap();
+
+        assert_eq!(response_text(agent).await, "default");
+    }
+
+    #[tokio::test]
+    async fn test_agent_for_override_selects_requested_provider() {
+        let state = make_override_state("ollama:llama3, ollama:qwen3");
+
+        let agent = state
+            .agent_for(Some("ollama"), Some("llama3"), true)
+            .unwrap();
+
+        assert_eq!(response_text(agent).await, "ollama:llama3");
+    }
+
+    #[tokio::test]
+    async fn test_agent_for_model_only_uses_configured_provider_and_key() {
+        let providers = ProviderSettings::from_lookup(|key| match key {
+            "LLM_PROVIDER" => Some("openai".to_string()),
+            "OPENAI_API_KEY" => Some("sk-test".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let state = make_override_state_with("openai:gpt-4.1", providers);
+
+        let agent = state.agent_for(None, Some("gpt-4.1"), true).unwrap();
+
+        assert_eq!(response_text(agent).await, "openai:gpt-4.1");
+    }
+
+    #[tokio::test]
+    async fn test_agent_for_tools_disabled_uses_chat_only_agent() {
+        let state = make_override_state("ollama:llama3")
+            .with_chat_only_agent(Arc::new(MockAgent::with_response("chat only")));
+
+        let agent = state.agent_for(None, None, false).unwrap();
+
+        assert_eq!(response_text(agent).await, "chat only");
+    }
+
+    #[tokio::test]
+    async fn test_agent_for_tools_disabled_override_builds_tool_less_agent() {
+        let state = make_override_state("ollama:llama3");
+
+        let agent = state
+            .agent_for(Some("ollama"), Some("llama3"), false)
+            .unwrap();
+
+        assert_eq!(response_text(agent).await, "ollama:llama3 (no tools)");
+    }
+
+    #[test]
+    fn test_agent_for_rejects_model_outside_allowlist() {
+        let state = make_override_state("ollama:llama3");
+
+        let result = state.agent_for(Some("ollama"), Some("deepseek-r1"), true);
+
+        assert!(matches!(result, Err(ModelOverrideError::NotAllowed { .. })));
+    }
+
+    #[test]
+    fn test_agent_for_requires_model_for_openai_compat() {
+        let state = make_override_state("openai_compat:qwen3");
+
+        let result = state.agent_for(Some("openai_compat"), None, true);
+
+        assert!(
+            matches!(result, Err(ModelOverrideError::ModelRequired(p)) if p == "openai_compat")
+        );
+    }
+
+    #[test]
+    fn test_agent_for_rejects_override_when_disabled() {
+        let state = make_state();
+
+        let result = state.agent_for(Some("ollama"), Some("llama3"), true);
+
+        assert!(matches!(result, Err(ModelOverrideError::Disabled)));
+    }
+
+    #[test]
+    fn test_clear_history_keeps_session_alive() {
+        let state = make_state();
+        let session_id = make_two_turn_session(&state);
+        state.add_source(&session_id, Citation::now("https://a.example", None));
+
+        assert!(state.clear_history(&session_id));
+
+        let history = state.get_session(&session_id);
+        assert!(history.is_some(), "Session should still exist");
+        assert!(history.unwrap().is_empty());
+        assert!(state.get_sources(&session_id).is_empty());
+    }
+
+    #[test]
+    fn test_truncate_after_keeps_messages_up_to_index() {
+        let state = make_state();
+        let session_id = make_two_turn_session(&state);
+
+        assert!(state.truncate_after(&session_id, 1));
+
+        let messages: Vec<TranscriptMessage> = serde_json::from_str(
+            &state
+                .export_session(&session_id, ExportFormat::Json)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            messages,
+            vec![
+                TranscriptMessage {
+                    role: TranscriptRole::User,
+                    content: "What is Rust?".to_string(),
+                },
+                TranscriptMessage {
+                    role: TranscriptRole::Assistant,
+                    content: "A systems language.".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncate_after_past_end_keeps_history() {
+        let state = make_state();
+        let session_id = make_two_turn_session(&state);
+
+        assert!(state.truncate_after(&session_id, 10));
+        assert!(state.truncate_after(&session_id, usize::MAX));
+
+        assert_eq!(state.get_session(&session_id).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_truncate_after_returns_false_for_unknown_session() {
+        let state = make_state();
+        assert!(!state.truncate_after("nonexistent_session_id", 0));
+    }
+
+    #[test]
+    fn test_clear_history_returns_false_for_unknown_session() {
+        let state = make_state();
+        assert!(!state.clear_history("nonexistent_session_id"));
+        assert!(state.get_session("nonexistent_session_id").is_none());
+    }
+
+    #[test]
+    fn test_session_store_recovers_from_poisoned_lock() {
+        let state = make_state();
+        let session_id = state.create_session();
+        state.add_user_message(&session_id, "before");
+
+        // A thread panicking while holding the lock poisons it
+        let sessions = Arc::clone(&state.sessions);
+        let result = std::thread::spawn(move || {
+            let _guard = sessions.write().unwrap();
+            panic!("simulated handler panic");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(state.sessions.is_poisoned());
+
+        state.add_user_message(&session_id, "after");
+        assert_eq!(state.get_session(&session_id).unwrap().len(), 2);
+        let other = state.create_session();
+        assert!(state.get_session(&other).is_some());
+        assert_eq!(state.session_count(), 2);
+        assert!(!state.sessions.is_poisoned());
+    }
+
+    #[test]
+    fn test_concurrent_readers_alongside_a_writer() {
+        const READERS: usize = 16;
+        const WRITES: usize = 50;
+        let state = make_state();
+        let session_id = state.create_session();
+
+        std::thread::scope(|scope| {
+            for _ in 0..READERS {
+                scope.spawn(|| {
+                    for _ in 0..WRITES {
+                        let len = state.get_session(&session_id).unwrap().len();
+                        assert!(len <= WRITES);
+                        state.session_count();
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for i in 0..WRITES {
+                    state.add_user_message(&session_id, &format!("message {i}"));
+                }
+            });
+        });
+
+        assert_eq!(state.get_session(&session_id).unwrap().len(), WRITES);
+    }
 }